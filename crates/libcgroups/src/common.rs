@@ -1,16 +1,19 @@
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf, StripPrefixError};
+use std::str::FromStr;
 use std::time::Duration;
 
 use nix::sys::statfs::{CGROUP2_SUPER_MAGIC, TMPFS_MAGIC, statfs};
-use nix::unistd::Pid;
+use nix::unistd::{Gid, Pid, Uid};
 use oci_spec::runtime::LinuxResources;
-#[cfg(any(feature = "cgroupsv2_devices", feature = "v1"))]
+#[cfg(any(feature = "cgroupsv2_devices", feature = "v1", feature = "systemd"))]
 use oci_spec::runtime::{
     LinuxDevice, LinuxDeviceBuilder, LinuxDeviceCgroup, LinuxDeviceCgroupBuilder, LinuxDeviceType,
 };
+use serde::{Deserialize, Serialize};
 
 use super::stats::Stats;
 use super::{systemd, v1, v2};
@@ -18,7 +21,6 @@ use super::{systemd, v1, v2};
 pub const CGROUP_PROCS: &str = "cgroup.procs";
 pub const DEFAULT_CGROUP_ROOT: &str = "/sys/fs/cgroup";
 
-#[cfg(feature = "systemd")]
 #[inline]
 fn is_true_root() -> Result<bool, WrappedIoError> {
     if !nix::unistd::geteuid().is_root() {
@@ -122,7 +124,8 @@ impl CgroupManager for AnyCgroupManager {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CgroupSetup {
     Hybrid,
     Legacy,
@@ -318,6 +321,8 @@ pub enum CreateCgroupSetupError {
     NonDefault,
     #[error("failed to detect cgroup setup")]
     FailedToDetect,
+    #[error("cgroup delegation is only supported with cgroup v2")]
+    DelegationNotSupported,
     #[error("v1 error: {0}")]
     V1(#[from] v1::manager::V1ManagerError),
     #[error("v2 error: {0}")]
@@ -331,6 +336,103 @@ pub struct CgroupConfig {
     pub cgroup_path: PathBuf,
     pub systemd_cgroup: bool,
     pub container_name: String,
+    /// Additional properties for the transient unit created by the systemd
+    /// manager, e.g. CollectMode='inactive-or-failed'. Ignored by other managers.
+    pub systemd_properties: HashMap<String, String>,
+    /// Delegates the container cgroup to the container, e.g. so that it can
+    /// run systemd as its init. Only supported with cgroup v2.
+    pub delegation: Option<CgroupDelegation>,
+}
+
+/// Delegation of a cgroup v2 subtree to the container. The container process is
+/// placed in an `init` leaf below the container cgroup, which is handed to the
+/// container following the rules in
+/// https://docs.kernel.org/admin-guide/cgroup-v2.html#delegation, while the
+/// resource limits stay on the container cgroup out of the container's reach.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CgroupDelegation {
+    /// User and group the delegated files are handed to, usually the host ids
+    /// of root in the user namespace of the container. If None, they keep the
+    /// owner of the caller.
+    pub owner: Option<(Uid, Gid)>,
+}
+
+/// Policy for choosing between the systemd and the cgroupfs manager
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CgroupDriver {
+    /// systemd manages the cgroups if it is requested and can be reached over
+    /// D-Bus, otherwise the cgroupfs manager is used
+    #[default]
+    Auto,
+    /// systemd always manages the cgroups
+    Systemd,
+    /// The cgroups are always managed through the cgroup filesystem
+    Cgroupfs,
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("unknown cgroup driver {0}, expected auto, systemd or cgroupfs")]
+pub struct InvalidCgroupDriver(String);
+
+impl FromStr for CgroupDriver {
+    type Err = InvalidCgroupDriver;
+
+    fn from_str(driver: &str) -> Result<Self, Self::Err> {
+        match driver {
+            "auto" => Ok(Self::Auto),
+            "systemd" => Ok(Self::Systemd),
+            "cgroupfs" => Ok(Self::Cgroupfs),
+            _ => Err(InvalidCgroupDriver(driver.to_owned())),
+        }
+    }
+}
+
+impl Display for CgroupDriver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let print = match self {
+            Self::Auto => "auto",
+            Self::Systemd => "systemd",
+            Self::Cgroupfs => "cgroupfs",
+        };
+
+        write!(f, "{print}")
+    }
+}
+
+/// Decides if the systemd manager is used for a container, given if it asks
+/// for it. The result is meant to be passed as `systemd_cgroup` of the
+/// [`CgroupConfig`] of the container, so that all commands use the same manager.
+pub fn use_systemd_driver(driver: CgroupDriver, systemd_cgroup: bool) -> bool {
+    match driver {
+        CgroupDriver::Systemd => true,
+        CgroupDriver::Cgroupfs => false,
+        CgroupDriver::Auto if !systemd_cgroup => false,
+        CgroupDriver::Auto => {
+            let use_system = is_true_root().unwrap_or_default();
+            if !systemd::booted() || !systemd::bus_available(use_system) {
+                tracing::warn!("systemd can't be reached, falling back to the cgroupfs manager");
+                return false;
+            }
+            if !use_system
+                && matches!(get_cgroup_setup(), Ok(CgroupSetup::Unified))
+                && !user_delegated(Path::new(DEFAULT_CGROUP_ROOT), nix::unistd::geteuid())
+            {
+                tracing::warn!(
+                    "no controllers are delegated to the user, resource limits can't be applied"
+                );
+            }
+            true
+        }
+    }
+}
+
+/// Checks if systemd delegated any controllers to the instance of the user,
+/// which rootless containers need for resource limits
+fn user_delegated(root_path: &Path, uid: Uid) -> bool {
+    let subtree_control = root_path
+        .join(format!("user.slice/user-{uid}.slice/user@{uid}.service"))
+        .join("cgroup.subtree_control");
+    fs::read_to_string(subtree_control).is_ok_and(|controllers| !controllers.trim().is_empty())
 }
 
 // Create any cgroup manager with customize root path. If root_path provided
@@ -339,31 +441,142 @@ pub fn create_cgroup_manager_with_root(
     root_path: Option<&Path>,
     config: CgroupConfig,
 ) -> Result<AnyCgroupManager, CreateCgroupSetupError> {
-    let root = match root_path {
-        Some(p) => p,
-        None => Path::new(DEFAULT_CGROUP_ROOT),
+    let root = root_path.unwrap_or(Path::new(DEFAULT_CGROUP_ROOT));
+    create_cgroup_manager_for_setup(root, detect_cgroup_setup(root)?, &config)
+}
+
+/// Kind of manager which handles the cgroups of a container
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CgroupManagerKind {
+    Cgroupfs,
+    Systemd,
+}
+
+/// Describes the cgroup manager of a container once it was created, so that it
+/// can be created again by [`create_cgroup_manager_from_info`] without detecting
+/// the cgroup setup of the host or asking systemd for the cgroup of the unit.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CgroupManagerInfo {
+    pub setup: CgroupSetup,
+    pub kind: CgroupManagerKind,
+    /// Mount point of the cgroup filesystem, e.g. /sys/fs/cgroup
+    pub root_path: PathBuf,
+    /// Absolute path of the cgroup of the container in the hierarchy, e.g.
+    /// /system.slice/youki-569d5ce3afe1074769f67.scope
+    pub cgroup_path: PathBuf,
+    /// cgroupsPath the systemd unit was named after, e.g. system.slice:youki:569d5ce3afe1074769f67
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub systemd_cgroups_path: Option<PathBuf>,
+    /// If the unit is managed by the system instance of systemd
+    #[serde(default)]
+    pub systemd_system: bool,
+}
+
+/// Creates the cgroup manager for a container like [`create_cgroup_manager_with_root`]
+/// and describes it, so that later commands can create it again with
+/// [`create_cgroup_manager_from_info`].
+pub fn create_cgroup_manager_with_info(
+    root_path: Option<&Path>,
+    config: CgroupConfig,
+) -> Result<(AnyCgroupManager, CgroupManagerInfo), CreateCgroupSetupError> {
+    let root = root_path.unwrap_or(Path::new(DEFAULT_CGROUP_ROOT));
+    let setup = detect_cgroup_setup(root)?;
+    let manager = create_cgroup_manager_for_setup(root, setup, &config)?;
+
+    let info = match &manager {
+        AnyCgroupManager::Systemd(m) => CgroupManagerInfo {
+            setup,
+            kind: CgroupManagerKind::Systemd,
+            root_path: root.to_path_buf(),
+            cgroup_path: m.cgroups_path().to_path_buf(),
+            systemd_cgroups_path: Some(config.cgroup_path),
+            systemd_system: m.uses_system(),
+        },
+        AnyCgroupManager::V1(_) | AnyCgroupManager::V2(_) => CgroupManagerInfo {
+            setup,
+            kind: CgroupManagerKind::Cgroupfs,
+            root_path: root.to_path_buf(),
+            cgroup_path: Path::new("/").join(&config.cgroup_path),
+            systemd_cgroups_path: None,
+            systemd_system: false,
+        },
     };
+    Ok((manager, info))
+}
+
+/// Creates the cgroup manager of an existing container from the description
+/// [`create_cgroup_manager_with_info`] returned when it was created
+pub fn create_cgroup_manager_from_info(
+    info: &CgroupManagerInfo,
+) -> Result<AnyCgroupManager, CreateCgroupSetupError> {
+    match (info.kind, info.setup) {
+        (CgroupManagerKind::Systemd, setup) => {
+            Ok(restore_systemd_cgroup_manager(info, !matches!(setup, CgroupSetup::Unified))?.any())
+        }
+        (CgroupManagerKind::Cgroupfs, CgroupSetup::Unified) => {
+            Ok(create_v2_cgroup_manager(&info.root_path, &info.cgroup_path, None)?.any())
+        }
+        (CgroupManagerKind::Cgroupfs, CgroupSetup::Legacy | CgroupSetup::Hybrid) => {
+            Ok(create_v1_cgroup_manager(&info.cgroup_path)?.any())
+        }
+    }
+}
 
-    let cgroup_setup = get_cgroup_setup_with_root(root).map_err(|err| match err {
+fn detect_cgroup_setup(root: &Path) -> Result<CgroupSetup, CreateCgroupSetupError> {
+    get_cgroup_setup_with_root(root).map_err(|err| match err {
         GetCgroupSetupError::WrappedIo(err) => CreateCgroupSetupError::WrappedIo(err),
         GetCgroupSetupError::NonDefault => CreateCgroupSetupError::NonDefault,
         GetCgroupSetupError::FailedToDetect => CreateCgroupSetupError::FailedToDetect,
-    })?;
+    })
+}
+
+fn create_cgroup_manager_for_setup(
+    root: &Path,
+    cgroup_setup: CgroupSetup,
+    config: &CgroupConfig,
+) -> Result<AnyCgroupManager, CreateCgroupSetupError> {
     let cgroup_path = config.cgroup_path.as_path();
 
     match cgroup_setup {
+        CgroupSetup::Legacy | CgroupSetup::Hybrid if config.delegation.is_some() => {
+            Err(CreateCgroupSetupError::DelegationNotSupported)
+        }
         CgroupSetup::Legacy | CgroupSetup::Hybrid => {
+            // systemd can only delegate cgroup v1 controllers to root, so rootless
+            // containers, as well as hosts not booted with systemd, keep using the
+            // cgroupfs manager as before
+            if !cgroup_path.is_absolute()
+                && config.systemd_cgroup
+                && systemd::booted()
+                && is_true_root()?
+            {
+                return Ok(create_systemd_legacy_cgroup_manager(
+                    root,
+                    cgroup_path,
+                    config.container_name.as_str(),
+                    &config.systemd_properties,
+                )?
+                .any());
+            }
             Ok(create_v1_cgroup_manager(cgroup_path)?.any())
         }
         CgroupSetup::Unified => {
             // ref https://github.com/opencontainers/runtime-spec/blob/main/config-linux.md#cgroups-path
             if cgroup_path.is_absolute() || !config.systemd_cgroup {
-                return Ok(create_v2_cgroup_manager(root, cgroup_path)?.any());
+                return Ok(
+                    create_v2_cgroup_manager(root, cgroup_path, config.delegation.clone())?.any(),
+                );
             }
-            Ok(
-                create_systemd_cgroup_manager(root, cgroup_path, config.container_name.as_str())?
-                    .any(),
-            )
+            Ok(create_systemd_cgroup_manager(
+                root,
+                cgroup_path,
+                config.container_name.as_str(),
+                &config.systemd_properties,
+                config.delegation.clone(),
+            )?
+            .any())
         }
     }
 }
@@ -393,15 +606,21 @@ fn create_v1_cgroup_manager(
 fn create_v2_cgroup_manager(
     root_path: &Path,
     cgroup_path: &Path,
+    delegation: Option<CgroupDelegation>,
 ) -> Result<v2::manager::Manager, v2::manager::V2ManagerError> {
     tracing::info!("cgroup manager V2 will be used");
-    v2::manager::Manager::new(root_path.to_path_buf(), cgroup_path.to_owned())
+    let manager = v2::manager::Manager::new(root_path.to_path_buf(), cgroup_path.to_owned())?;
+    Ok(match delegation {
+        Some(delegation) => manager.with_delegation(delegation),
+        None => manager,
+    })
 }
 
 #[cfg(not(feature = "v2"))]
 fn create_v2_cgroup_manager(
     _root_path: &Path,
     _cgroup_path: &Path,
+    _delegation: Option<CgroupDelegation>,
 ) -> Result<v2::manager::Manager, v2::manager::V2ManagerError> {
     Err(v2::manager::V2ManagerError::NotEnabled)
 }
@@ -411,13 +630,13 @@ fn create_systemd_cgroup_manager(
     root_path: &Path,
     cgroup_path: &Path,
     container_name: &str,
+    properties: &HashMap<String, String>,
+    delegation: Option<CgroupDelegation>,
 ) -> Result<systemd::manager::Manager, systemd::manager::SystemdManagerError> {
     use crate::systemd::manager::PROCESS_IN_CGROUP_TIMEOUT_DURATION;
 
     if !systemd::booted() {
-        panic!(
-            "systemd cgroup flag passed, but systemd support for managing cgroups is not available"
-        );
+        return Err(systemd::manager::SystemdManagerError::NotBooted);
     }
 
     let use_system = is_true_root().map_err(systemd::manager::SystemdManagerError::WrappedIo)?;
@@ -432,14 +651,78 @@ fn create_systemd_cgroup_manager(
         container_name.into(),
         use_system,
         PROCESS_IN_CGROUP_TIMEOUT_DURATION,
+    )?
+    .with_unit_properties(properties)
+    .map(|manager| match delegation {
+        Some(delegation) => manager.with_delegation(delegation),
+        None => manager,
+    })
+}
+
+#[cfg(feature = "systemd")]
+fn create_systemd_legacy_cgroup_manager(
+    root_path: &Path,
+    cgroup_path: &Path,
+    container_name: &str,
+    properties: &HashMap<String, String>,
+) -> Result<systemd::manager::Manager, systemd::manager::SystemdManagerError> {
+    use crate::systemd::manager::PROCESS_IN_CGROUP_TIMEOUT_DURATION;
+
+    if !systemd::booted() {
+        return Err(systemd::manager::SystemdManagerError::NotBooted);
+    }
+
+    tracing::info!("systemd cgroup manager on cgroup v1 will be used");
+    systemd::manager::Manager::new_legacy(
+        root_path.into(),
+        cgroup_path.to_owned(),
+        container_name.into(),
+        PROCESS_IN_CGROUP_TIMEOUT_DURATION,
+    )?
+    .with_unit_properties(properties)
+}
+
+#[cfg(feature = "systemd")]
+fn restore_systemd_cgroup_manager(
+    info: &CgroupManagerInfo,
+    legacy: bool,
+) -> Result<systemd::manager::Manager, systemd::manager::SystemdManagerError> {
+    systemd::manager::Manager::restore(
+        info.root_path.clone(),
+        info.cgroup_path.clone(),
+        info.systemd_cgroups_path
+            .as_deref()
+            .unwrap_or(Path::new("")),
+        info.systemd_system,
+        legacy,
     )
 }
 
+#[cfg(not(feature = "systemd"))]
+fn restore_systemd_cgroup_manager(
+    _info: &CgroupManagerInfo,
+    _legacy: bool,
+) -> Result<systemd::manager::Manager, systemd::manager::SystemdManagerError> {
+    Err(systemd::manager::SystemdManagerError::NotEnabled)
+}
+
+#[cfg(not(feature = "systemd"))]
+fn create_systemd_legacy_cgroup_manager(
+    _root_path: &Path,
+    _cgroup_path: &Path,
+    _container_name: &str,
+    _properties: &HashMap<String, String>,
+) -> Result<systemd::manager::Manager, systemd::manager::SystemdManagerError> {
+    Err(systemd::manager::SystemdManagerError::NotEnabled)
+}
+
 #[cfg(not(feature = "systemd"))]
 fn create_systemd_cgroup_manager(
     _root_path: &Path,
     _cgroup_path: &Path,
     _container_name: &str,
+    _properties: &HashMap<String, String>,
+    _delegation: Option<CgroupDelegation>,
 ) -> Result<systemd::manager::Manager, systemd::manager::SystemdManagerError> {
     Err(systemd::manager::SystemdManagerError::NotEnabled)
 }
@@ -511,7 +794,7 @@ impl PathBufExt for PathBuf {
     }
 }
 
-#[cfg(any(feature = "cgroupsv2_devices", feature = "v1"))]
+#[cfg(any(feature = "cgroupsv2_devices", feature = "v1", feature = "systemd"))]
 pub(crate) fn default_allow_devices() -> Vec<LinuxDeviceCgroup> {
     vec![
         LinuxDeviceCgroupBuilder::default()
@@ -563,7 +846,7 @@ pub(crate) fn default_allow_devices() -> Vec<LinuxDeviceCgroup> {
     ]
 }
 
-#[cfg(any(feature = "cgroupsv2_devices", feature = "v1"))]
+#[cfg(any(feature = "cgroupsv2_devices", feature = "v1", feature = "systemd"))]
 pub(crate) fn default_devices() -> Vec<LinuxDevice> {
     vec![
         LinuxDeviceBuilder::default()
@@ -731,3 +1014,57 @@ impl Display for MustBePowerOfTwo {
         f.write_str("page size must be in the format of 2^(integer)")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test::set_fixture;
+
+    #[test]
+    fn test_cgroup_driver() {
+        for driver in [
+            CgroupDriver::Auto,
+            CgroupDriver::Systemd,
+            CgroupDriver::Cgroupfs,
+        ] {
+            assert_eq!(driver.to_string().parse::<CgroupDriver>().unwrap(), driver);
+        }
+        assert!("cgroupv2".parse::<CgroupDriver>().is_err());
+
+        assert!(use_systemd_driver(CgroupDriver::Systemd, false));
+        assert!(!use_systemd_driver(CgroupDriver::Cgroupfs, true));
+        assert!(!use_systemd_driver(CgroupDriver::Auto, false));
+    }
+
+    #[test]
+    fn test_user_delegated() {
+        let tmp = tempfile::tempdir().unwrap();
+        let uid = Uid::from_raw(1000);
+        assert!(!user_delegated(tmp.path(), uid));
+
+        let service = tmp
+            .path()
+            .join("user.slice/user-1000.slice/user@1000.service");
+        fs::create_dir_all(&service).unwrap();
+        set_fixture(&service, "cgroup.subtree_control", "\n").unwrap();
+        assert!(!user_delegated(tmp.path(), uid));
+
+        set_fixture(&service, "cgroup.subtree_control", "cpu memory pids\n").unwrap();
+        assert!(user_delegated(tmp.path(), uid));
+    }
+
+    #[test]
+    fn test_cgroup_manager_from_info() {
+        let info: CgroupManagerInfo = serde_json::from_str(
+            r#"{"setup":"unified","kind":"cgroupfs","rootPath":"/sys/fs/cgroup","cgroupPath":"/youki/test"}"#,
+        )
+        .unwrap();
+        assert_eq!(info.setup, CgroupSetup::Unified);
+        assert_eq!(info.kind, CgroupManagerKind::Cgroupfs);
+        assert_eq!(info.systemd_cgroups_path, None);
+        assert!(!info.systemd_system);
+
+        let manager = create_cgroup_manager_from_info(&info).unwrap();
+        assert!(matches!(manager, AnyCgroupManager::V2(_)));
+    }
+}
@@ -12,6 +12,14 @@ impl Manager {
     pub fn any(self) -> AnyCgroupManager {
         AnyCgroupManager::Systemd(Box::new(self))
     }
+
+    pub fn cgroups_path(&self) -> &std::path::Path {
+        std::path::Path::new("")
+    }
+
+    pub fn uses_system(&self) -> bool {
+        false
+    }
 }
 
 impl CgroupManager for Manager {
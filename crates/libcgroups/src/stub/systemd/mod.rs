@@ -1 +1,9 @@
 pub mod manager;
+
+pub fn booted() -> bool {
+    false
+}
+
+pub fn bus_available(_use_system: bool) -> bool {
+    false
+}
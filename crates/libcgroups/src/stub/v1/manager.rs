@@ -9,6 +9,10 @@ pub enum V1ManagerError {
 pub struct Manager {}
 
 impl Manager {
+    pub fn new(_cgroup_path: &std::path::Path) -> Result<Self, V1ManagerError> {
+        Err(V1ManagerError::NotEnabled)
+    }
+
     pub fn any(self) -> AnyCgroupManager {
         crate::common::AnyCgroupManager::V1(self)
     }
@@ -150,6 +150,23 @@ impl DbusConnection {
         Ok(dbus)
     }
 
+    /// Checks that the bus accepts connections, without authenticating or
+    /// asking for the uid of the session bus, so it costs a connect only
+    pub fn probe(system: bool) -> Result<()> {
+        let addr = match system {
+            true => get_system_bus_address()?,
+            false => get_session_bus_address()?,
+        };
+        let socket = socket::socket(
+            socket::AddressFamily::Unix,
+            socket::SockType::Stream,
+            socket::SockFlag::SOCK_CLOEXEC,
+            None,
+        )?;
+        socket::connect(socket.as_raw_fd(), &socket::UnixAddr::new(addr.as_str())?)?;
+        Ok(())
+    }
+
     pub fn new_system() -> Result<Self> {
         let addr = get_system_bus_address()?;
         Self::new(&addr, 0, true)
@@ -384,20 +401,14 @@ impl SystemdClient for DbusConnection {
         pid: u32,
         parent: &str,
         unit_name: &str,
+        extra_properties: &HashMap<String, Variant>,
     ) -> Result<()> {
         // To view and introspect the methods under the 'org.freedesktop.systemd1' destination
         // and object path under it use the following command:
         // `gdbus introspect --system --dest org.freedesktop.systemd1 --object-path /org/freedesktop/systemd1`
         let proxy = self.create_proxy();
 
-        // To align with runc, youki will always add the following properties to its container units:
-        // - CPUAccounting=true
-        // - IOAccounting=true (BlockIOAccounting for cgroup v1)
-        // - MemoryAccounting=true
-        // - TasksAccounting=true
-        // see https://github.com/opencontainers/runc/blob/6023d635d725a74c6eaa11ab7f3c870c073badd2/docs/systemd.md#systemd-cgroup-driver
-        // for more details.
-        let mut properties: Vec<(&str, Variant)> = Vec::with_capacity(6);
+        let mut properties: Vec<(&str, Variant)> = Vec::with_capacity(5 + extra_properties.len());
         properties.push((
             "Description",
             Variant::String(format!("youki container {container_name}")),
@@ -412,14 +423,15 @@ impl SystemdClient for DbusConnection {
             properties.push(("Delegate", Variant::Bool(true)));
         }
 
-        properties.push(("MemoryAccounting", Variant::Bool(true)));
-        properties.push(("CPUAccounting", Variant::Bool(true)));
-        properties.push(("IOAccounting", Variant::Bool(true)));
-        properties.push(("TasksAccounting", Variant::Bool(true)));
-
         properties.push(("DefaultDependencies", Variant::Bool(false)));
         properties.push(("PIDs", Variant::ArrayU32(vec![pid])));
 
+        // the resource accounting and user provided properties come last,
+        // so they can override the defaults above
+        for (name, value) in extra_properties {
+            properties.push((name.as_str(), value.clone()));
+        }
+
         tracing::debug!("Starting transient unit: {:?}", properties);
         let props = properties
             .into_iter()
@@ -0,0 +1,416 @@
+use std::collections::HashMap;
+use std::fs;
+
+use oci_spec::runtime::{LinuxDeviceCgroup, LinuxDeviceType};
+
+use super::controller::Controller;
+use super::dbus_native::serialize::{Structure, Variant};
+use crate::common::{
+    ControllerOpt, WrapIoResult, WrappedIoError, default_allow_devices, default_devices,
+};
+
+pub const DEVICE_POLICY: &str = "DevicePolicy";
+pub const DEVICE_ALLOW: &str = "DeviceAllow";
+
+const PROC_DEVICES: &str = "/proc/devices";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SystemdDevicesError {
+    #[error("failed to look up device group: {0}")]
+    ProcDevices(#[from] WrappedIoError),
+}
+
+pub struct Devices {}
+
+impl Controller for Devices {
+    type Error = SystemdDevicesError;
+
+    fn apply(
+        options: &ControllerOpt,
+        _: u32,
+        properties: &mut HashMap<&str, Variant>,
+    ) -> Result<(), Self::Error> {
+        tracing::debug!("applying device restrictions");
+
+        // always needs to be applied due to default devices, the same as the
+        // cgroupfs managers do
+        let rules: Vec<LinuxDeviceCgroup> = [
+            options.resources.devices().clone().unwrap_or_default(),
+            default_devices().iter().map(|d| d.into()).collect(),
+            default_allow_devices(),
+        ]
+        .concat();
+
+        let proc_devices = fs::read_to_string(PROC_DEVICES).wrap_read(PROC_DEVICES)?;
+        Self::apply(&rules, &proc_devices, properties);
+
+        Ok(())
+    }
+}
+
+impl Devices {
+    // systemd can only express an allow list on top of a default policy, so the
+    // rules are first reduced in the same way the v2 device emulator does: a rule
+    // of type 'a' discards everything before it and switches the default, deny
+    // rules revoke access from the previously allowed devices they cover. Deny
+    // rules which only cover part of an allowed wildcard cannot be expressed this
+    // way, they are ignored with a warning. Even though the
+    // cgroupfs managers configure the same rules, they must be given to systemd
+    // as well, otherwise systemd resets them on the next daemon-reload.
+    fn apply(
+        rules: &[LinuxDeviceCgroup],
+        proc_devices: &str,
+        properties: &mut HashMap<&str, Variant>,
+    ) {
+        let mut default_allow = false;
+        let mut allowed: Vec<LinuxDeviceCgroup> = Vec::new();
+
+        for rule in rules {
+            let typ = rule.typ().unwrap_or_default();
+            if typ == LinuxDeviceType::A {
+                default_allow = rule.allow();
+                allowed.clear();
+                continue;
+            }
+
+            let access = match rule.access() {
+                Some(access) if !access.is_empty() => access,
+                _ => continue,
+            };
+
+            if rule.allow() {
+                if !default_allow {
+                    allowed.push(rule.clone());
+                }
+                continue;
+            }
+
+            if default_allow {
+                tracing::warn!(
+                    ?rule,
+                    "systemd does not support deny rules on top of an allow all policy, ignoring"
+                );
+                continue;
+            }
+
+            let mut partial = false;
+            for entry in allowed.iter_mut() {
+                if entry.typ() != rule.typ() {
+                    continue;
+                }
+                if !covers(rule, entry) {
+                    partial |= overlaps(rule, entry);
+                    continue;
+                }
+                let remaining: String = entry
+                    .access()
+                    .as_deref()
+                    .unwrap_or_default()
+                    .chars()
+                    .filter(|c| !access.contains(*c))
+                    .collect();
+                entry.set_access(Some(remaining));
+            }
+            allowed.retain(|entry| entry.access().as_deref().is_some_and(|a| !a.is_empty()));
+
+            if partial {
+                tracing::warn!(
+                    ?rule,
+                    "systemd does not support deny rules for part of an allowed wildcard, ignoring"
+                );
+            }
+        }
+
+        if default_allow {
+            // with an empty allow list and the auto policy, systemd does
+            // not install any device filter for the unit
+            properties.insert(DEVICE_POLICY, Variant::String("auto".to_owned()));
+            properties.insert(DEVICE_ALLOW, Variant::ArrayStructString(Vec::new()));
+            return;
+        }
+
+        let mut entries: Vec<Structure<String>> = Vec::new();
+        for rule in &allowed {
+            let Some(name) = Self::device_allow_name(rule, proc_devices) else {
+                continue;
+            };
+            let access: String = rule
+                .access()
+                .as_deref()
+                .unwrap_or_default()
+                .chars()
+                .filter(|c| matches!(c, 'r' | 'w' | 'm'))
+                .collect();
+            if access.is_empty() {
+                continue;
+            }
+
+            let entry = Structure::new(name, access);
+            if !entries.contains(&entry) {
+                entries.push(entry);
+            }
+        }
+
+        properties.insert(DEVICE_POLICY, Variant::String("strict".to_owned()));
+        properties.insert(DEVICE_ALLOW, Variant::ArrayStructString(entries));
+    }
+
+    /// Returns the name systemd uses to refer to the devices matched by the rule,
+    /// e.g. /dev/char/1:3 for a single device, char-pts for all minors of a major
+    /// and char-* for all character devices. A minor of any major can't be named.
+    fn device_allow_name(rule: &LinuxDeviceCgroup, proc_devices: &str) -> Option<String> {
+        let (kind, proc_section) = match rule.typ().unwrap_or_default() {
+            LinuxDeviceType::C | LinuxDeviceType::U => ("char", "Character devices:"),
+            LinuxDeviceType::B => ("block", "Block devices:"),
+            typ => {
+                tracing::warn!(?typ, "device type is not supported by systemd, ignoring");
+                return None;
+            }
+        };
+
+        match (rule.major(), rule.minor()) {
+            (None, None) => Some(format!("{kind}-*")),
+            (None, Some(minor)) => {
+                tracing::warn!(
+                    minor,
+                    "systemd does not support {kind} device rules for a minor of any major, ignoring"
+                );
+                None
+            }
+            // paths of the form /dev/{char,block}/major:minor are supported by
+            // systemd even if the device node does not exist on the host
+            (Some(major), Some(minor)) => Some(format!("/dev/{kind}/{major}:{minor}")),
+            (Some(major), None) => {
+                let group = find_device_group(proc_devices, proc_section, major);
+                if group.is_none() {
+                    tracing::warn!(
+                        major,
+                        "could not find a {kind} device group for major, ignoring"
+                    );
+                }
+                group.map(|name| format!("{kind}-{name}"))
+            }
+        }
+    }
+}
+
+// Returns if every device matched by other is matched by rule as well, where a
+// missing major or minor number is a wildcard
+fn covers(rule: &LinuxDeviceCgroup, other: &LinuxDeviceCgroup) -> bool {
+    let covers = |num: Option<i64>, other: Option<i64>| num.is_none() || num == other;
+    covers(rule.major(), other.major()) && covers(rule.minor(), other.minor())
+}
+
+// Returns if there is a device matched by both rules
+fn overlaps(rule: &LinuxDeviceCgroup, other: &LinuxDeviceCgroup) -> bool {
+    let overlaps =
+        |num: Option<i64>, other: Option<i64>| num.is_none() || other.is_none() || num == other;
+    overlaps(rule.major(), other.major()) && overlaps(rule.minor(), other.minor())
+}
+
+/// Finds the name of the device driver registered for the given major number in the
+/// specified section of /proc/devices.
+fn find_device_group(proc_devices: &str, section: &str, major: i64) -> Option<String> {
+    proc_devices
+        .lines()
+        .skip_while(|line| line.trim() != section)
+        .skip(1)
+        .take_while(|line| !line.trim().is_empty())
+        .find_map(|line| {
+            let (num, name) = line.trim().split_once(char::is_whitespace)?;
+            (num.parse::<i64>().ok()? == major).then(|| name.trim().to_owned())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use oci_spec::runtime::LinuxDeviceCgroupBuilder;
+
+    use super::*;
+
+    const PROC_DEVICES_CONTENT: &str = "Character devices:
+  1 mem
+  4 /dev/vc/0
+  5 /dev/tty
+136 pts
+226 drm
+
+Block devices:
+  7 loop
+  8 sd
+";
+
+    fn rule(
+        allow: bool,
+        typ: LinuxDeviceType,
+        major: Option<i64>,
+        minor: Option<i64>,
+        access: &str,
+    ) -> Result<LinuxDeviceCgroup> {
+        let mut builder = LinuxDeviceCgroupBuilder::default()
+            .allow(allow)
+            .typ(typ)
+            .access(access);
+        if let Some(major) = major {
+            builder = builder.major(major);
+        }
+        if let Some(minor) = minor {
+            builder = builder.minor(minor);
+        }
+        Ok(builder.build()?)
+    }
+
+    fn device_allow(properties: &HashMap<&str, Variant>) -> Vec<Structure<String>> {
+        match properties.get(DEVICE_ALLOW) {
+            Some(Variant::ArrayStructString(entries)) => entries.clone(),
+            other => panic!("unexpected DeviceAllow value {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_find_device_group() {
+        assert_eq!(
+            find_device_group(PROC_DEVICES_CONTENT, "Character devices:", 136),
+            Some("pts".to_owned())
+        );
+        assert_eq!(
+            find_device_group(PROC_DEVICES_CONTENT, "Block devices:", 8),
+            Some("sd".to_owned())
+        );
+        assert_eq!(
+            find_device_group(PROC_DEVICES_CONTENT, "Block devices:", 136),
+            None
+        );
+    }
+
+    #[test]
+    fn test_deny_all_then_allow() -> Result<()> {
+        let rules = vec![
+            rule(false, LinuxDeviceType::A, None, None, "rwm")?,
+            rule(true, LinuxDeviceType::C, Some(1), Some(3), "rwm")?,
+            rule(true, LinuxDeviceType::C, Some(136), None, "rw")?,
+            rule(true, LinuxDeviceType::B, None, None, "m")?,
+        ];
+        let mut properties = HashMap::new();
+
+        Devices::apply(&rules, PROC_DEVICES_CONTENT, &mut properties);
+
+        assert_eq!(
+            properties.get(DEVICE_POLICY),
+            Some(&Variant::String("strict".to_owned()))
+        );
+        assert_eq!(
+            device_allow(&properties),
+            vec![
+                Structure::new("/dev/char/1:3".to_owned(), "rwm".to_owned()),
+                Structure::new("char-pts".to_owned(), "rw".to_owned()),
+                Structure::new("block-*".to_owned(), "m".to_owned()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deny_revokes_allowed_access() -> Result<()> {
+        let rules = vec![
+            rule(true, LinuxDeviceType::C, Some(1), Some(3), "rwm")?,
+            rule(true, LinuxDeviceType::C, Some(1), Some(5), "rwm")?,
+            rule(false, LinuxDeviceType::C, Some(1), Some(3), "w")?,
+            rule(false, LinuxDeviceType::C, Some(1), Some(5), "rwm")?,
+        ];
+        let mut properties = HashMap::new();
+
+        Devices::apply(&rules, PROC_DEVICES_CONTENT, &mut properties);
+
+        assert_eq!(
+            device_allow(&properties),
+            vec![Structure::new("/dev/char/1:3".to_owned(), "rm".to_owned())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deny_covering_allowed_devices() -> Result<()> {
+        let rules = vec![
+            rule(true, LinuxDeviceType::C, Some(1), Some(3), "rwm")?,
+            rule(true, LinuxDeviceType::C, Some(1), Some(5), "rwm")?,
+            rule(true, LinuxDeviceType::B, Some(1), Some(3), "rwm")?,
+            rule(false, LinuxDeviceType::C, Some(1), None, "w")?,
+        ];
+        let mut properties = HashMap::new();
+
+        Devices::apply(&rules, PROC_DEVICES_CONTENT, &mut properties);
+
+        assert_eq!(
+            device_allow(&properties),
+            vec![
+                Structure::new("/dev/char/1:3".to_owned(), "rm".to_owned()),
+                Structure::new("/dev/char/1:5".to_owned(), "rm".to_owned()),
+                Structure::new("/dev/block/1:3".to_owned(), "rwm".to_owned()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_minor_of_any_major_is_skipped() -> Result<()> {
+        let rules = vec![
+            rule(true, LinuxDeviceType::C, None, Some(3), "rwm")?,
+            rule(true, LinuxDeviceType::C, Some(1), Some(3), "rwm")?,
+        ];
+        let mut properties = HashMap::new();
+
+        Devices::apply(&rules, PROC_DEVICES_CONTENT, &mut properties);
+
+        assert_eq!(
+            device_allow(&properties),
+            vec![Structure::new("/dev/char/1:3".to_owned(), "rwm".to_owned())]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_allow_all() -> Result<()> {
+        let rules = vec![
+            rule(false, LinuxDeviceType::A, None, None, "rwm")?,
+            rule(true, LinuxDeviceType::C, Some(1), Some(3), "rwm")?,
+            rule(true, LinuxDeviceType::A, None, None, "rwm")?,
+            rule(false, LinuxDeviceType::C, Some(1), Some(5), "rwm")?,
+        ];
+        let mut properties = HashMap::new();
+
+        Devices::apply(&rules, PROC_DEVICES_CONTENT, &mut properties);
+
+        assert_eq!(
+            properties.get(DEVICE_POLICY),
+            Some(&Variant::String("auto".to_owned()))
+        );
+        assert!(device_allow(&properties).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_major_is_skipped() -> Result<()> {
+        let rules = vec![
+            rule(true, LinuxDeviceType::C, Some(511), None, "rwm")?,
+            rule(true, LinuxDeviceType::C, Some(5), Some(1), "rwm")?,
+            rule(true, LinuxDeviceType::C, Some(5), Some(1), "rwm")?,
+        ];
+        let mut properties = HashMap::new();
+
+        Devices::apply(&rules, PROC_DEVICES_CONTENT, &mut properties);
+
+        assert_eq!(
+            device_allow(&properties),
+            vec![Structure::new("/dev/char/5:1".to_owned(), "rwm".to_owned())]
+        );
+
+        Ok(())
+    }
+}
@@ -12,6 +12,7 @@ pub const IO_READ_BANDWIDTH_MAX: &str = "IOReadBandwidthMax";
 pub const IO_WRITE_BANDWIDTH_MAX: &str = "IOWriteBandwidthMax";
 pub const IO_READ_IOPS_MAX: &str = "IOReadIOPSMax";
 pub const IO_WRITE_IOPS_MAX: &str = "IOWriteIOPSMax";
+pub const IO_WEIGHT: &str = "IOWeight";
 
 #[derive(thiserror::Error, Debug)]
 pub enum SystemdIoError {
@@ -37,6 +38,13 @@ impl Io {
         blkio: &LinuxBlockIo,
         properties: &mut HashMap<&str, Variant>,
     ) -> Result<(), SystemdIoError> {
+        if let Some(weight) = blkio.weight() {
+            let weight = convert_blkio_weight_to_io_weight(weight);
+            if weight != 0 {
+                properties.insert(IO_WEIGHT, Variant::U64(weight));
+            }
+        }
+
         let mut apply_limits = |devices: &[LinuxThrottleDevice],
                                 key|
          -> Result<(), SystemdIoError> {
@@ -75,6 +83,16 @@ impl Io {
     }
 }
 
+// The blkio weight of the runtime spec is given in the cgroup v1 range of [10-1000],
+// while IOWeight uses the cgroup v2 range of [1-10000], so it is converted linearly
+// with y = (1 + (x - 10) * 9999 / 990) in the same way the v2 io controller does.
+fn convert_blkio_weight_to_io_weight(weight: u16) -> u64 {
+    if weight == 0 {
+        return 0;
+    }
+    1 + (weight.saturating_sub(10) as u64) * 9999 / 990
+}
+
 fn dev_path_from_major_minor(major: i64, minor: i64) -> Option<String> {
     // Try block devices first: /sys/dev/block/<major>:<minor> -> .../block/<name>[/<part>]
     let block_path = format!("/sys/dev/block/{}:{}", major, minor);
@@ -190,6 +208,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_io_weight() {
+        let blkio = LinuxBlockIoBuilder::default()
+            .weight(500u16)
+            .build()
+            .unwrap();
+        let mut props: HashMap<&str, Variant> = HashMap::new();
+        Io::apply(&blkio, &mut props).expect("apply blkio weight");
+
+        assert_eq!(props.get(IO_WEIGHT), Some(&Variant::U64(4950)));
+        assert_eq!(convert_blkio_weight_to_io_weight(10), 1);
+        assert_eq!(convert_blkio_weight_to_io_weight(1000), 10000);
+    }
+
     #[test]
     fn test_io_apply() {
         let st = stat("/dev/null").expect("stat /dev/null");
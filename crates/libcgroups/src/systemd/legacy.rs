@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use oci_spec::runtime::{LinuxBlockIo, LinuxCpu, LinuxMemory};
+
+use super::controller::Controller;
+use super::cpu;
+use super::dbus_native::serialize::Variant;
+use super::devices::{Devices, SystemdDevicesError};
+use super::pids::Pids;
+use crate::common::ControllerOpt;
+
+pub const CPU_SHARES: &str = "CPUShares";
+pub const MEMORY_LIMIT: &str = "MemoryLimit";
+pub const BLOCK_IO_WEIGHT: &str = "BlockIOWeight";
+const MICROSECS_PER_SEC: u64 = 1_000_000;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SystemdLegacyError {
+    #[error("invalid memory limit value: {0}")]
+    MemoryLimit(i64),
+    #[error("in devices controller: {0}")]
+    Devices(#[from] SystemdDevicesError),
+}
+
+/// Translates resources into the unit properties systemd understands on a cgroup v1
+/// (legacy or hybrid) hierarchy. Older versions of systemd, which are common on such
+/// hosts, only know the cgroup v1 names for most of the properties (e.g. CPUShares
+/// instead of CPUWeight). Everything which cannot be expressed through properties is
+/// applied by the v1 cgroupfs manager.
+pub struct Legacy {}
+
+impl Controller for Legacy {
+    type Error = SystemdLegacyError;
+
+    fn apply(
+        options: &ControllerOpt,
+        systemd_version: u32,
+        properties: &mut HashMap<&str, Variant>,
+    ) -> Result<(), Self::Error> {
+        tracing::debug!("applying legacy resource restrictions");
+
+        if let Some(cpu) = options.resources.cpu() {
+            Self::apply_cpu(cpu, systemd_version, properties);
+        }
+
+        if let Some(memory) = options.resources.memory() {
+            Self::apply_memory(memory, properties)?;
+        }
+
+        if let Some(blkio) = options.resources.block_io() {
+            Self::apply_blkio(blkio, properties);
+        }
+
+        // TasksMax and the device properties have the same names on both hierarchies
+        if systemd_version >= 227 {
+            let Ok(()) = Pids::apply(options, systemd_version, properties);
+        }
+        Devices::apply(options, systemd_version, properties)?;
+
+        Ok(())
+    }
+}
+
+impl Legacy {
+    fn apply_cpu(cpu: &LinuxCpu, systemd_version: u32, properties: &mut HashMap<&str, Variant>) {
+        if let Some(shares) = cpu.shares() {
+            if shares != 0 {
+                properties.insert(CPU_SHARES, Variant::U64(shares));
+            }
+        }
+
+        let period = cpu.period().filter(|p| *p > 0).unwrap_or(100_000);
+        if let Some(quota) = cpu.quota() {
+            let quota = if quota > 0 {
+                quota as u64 * MICROSECS_PER_SEC / period
+            } else {
+                u64::MAX
+            };
+            properties.insert(cpu::CPU_QUOTA, Variant::U64(quota));
+        }
+
+        // the quota period can only be configured with systemd 242 or newer
+        if cpu.period().is_some() && systemd_version >= 242 {
+            properties.insert(cpu::CPU_PERIOD, Variant::U64(period));
+        }
+    }
+
+    fn apply_memory(
+        memory: &LinuxMemory,
+        properties: &mut HashMap<&str, Variant>,
+    ) -> Result<(), SystemdLegacyError> {
+        if let Some(limit) = memory.limit() {
+            match limit {
+                1..=i64::MAX => {
+                    properties.insert(MEMORY_LIMIT, Variant::U64(limit as u64));
+                }
+                -1 => {
+                    properties.insert(MEMORY_LIMIT, Variant::U64(u64::MAX));
+                }
+                _ => return Err(SystemdLegacyError::MemoryLimit(limit)),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn apply_blkio(blkio: &LinuxBlockIo, properties: &mut HashMap<&str, Variant>) {
+        if let Some(weight) = blkio.weight() {
+            if weight != 0 {
+                properties.insert(BLOCK_IO_WEIGHT, Variant::U64(weight as u64));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use oci_spec::runtime::{
+        LinuxBlockIoBuilder, LinuxCpuBuilder, LinuxMemoryBuilder, LinuxPidsBuilder,
+        LinuxResourcesBuilder,
+    };
+
+    use super::super::{devices, pids};
+    use super::*;
+
+    #[test]
+    fn test_legacy_properties() -> Result<()> {
+        let resources = LinuxResourcesBuilder::default()
+            .cpu(
+                LinuxCpuBuilder::default()
+                    .shares(2048u64)
+                    .quota(50_000i64)
+                    .period(100_000u64)
+                    .build()?,
+            )
+            .memory(LinuxMemoryBuilder::default().limit(1_048_576i64).build()?)
+            .block_io(LinuxBlockIoBuilder::default().weight(500u16).build()?)
+            .pids(LinuxPidsBuilder::default().limit(100i64).build()?)
+            .build()?;
+        let options = ControllerOpt {
+            resources: &resources,
+            disable_oom_killer: false,
+            oom_score_adj: None,
+            freezer_state: None,
+        };
+        let mut properties = HashMap::new();
+
+        Legacy::apply(&options, 219, &mut properties)?;
+
+        assert_eq!(properties.get(CPU_SHARES), Some(&Variant::U64(2048)));
+        assert_eq!(properties.get(cpu::CPU_QUOTA), Some(&Variant::U64(500_000)));
+        assert!(!properties.contains_key(cpu::CPU_PERIOD));
+        assert!(!properties.contains_key(cpu::CPU_WEIGHT));
+        assert_eq!(properties.get(MEMORY_LIMIT), Some(&Variant::U64(1_048_576)));
+        assert_eq!(properties.get(BLOCK_IO_WEIGHT), Some(&Variant::U64(500)));
+        assert!(!properties.contains_key(pids::TASKS_MAX));
+        assert_eq!(
+            properties.get(devices::DEVICE_POLICY),
+            Some(&Variant::String("strict".to_owned()))
+        );
+
+        let mut properties = HashMap::new();
+        Legacy::apply(&options, 245, &mut properties)?;
+
+        assert_eq!(
+            properties.get(cpu::CPU_PERIOD),
+            Some(&Variant::U64(100_000))
+        );
+        assert_eq!(properties.get(pids::TASKS_MAX), Some(&Variant::U64(100)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_memory_limit() -> Result<()> {
+        let memory = LinuxMemoryBuilder::default().limit(-2i64).build()?;
+        let mut properties = HashMap::new();
+
+        let result = Legacy::apply_memory(&memory, &mut properties);
+
+        assert!(matches!(result, Err(SystemdLegacyError::MemoryLimit(-2))));
+        Ok(())
+    }
+}
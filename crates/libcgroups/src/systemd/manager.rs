@@ -1,3 +1,4 @@
+use std::cell::OnceCell;
 use std::collections::HashMap;
 use std::convert::Infallible;
 use std::fmt::{Debug, Display};
@@ -16,20 +17,26 @@ use super::cpuset::CpuSet;
 use super::dbus_native::client::SystemdClient;
 use super::dbus_native::dbus::DbusConnection;
 use super::dbus_native::utils::SystemdClientError;
+use super::devices::Devices;
+use super::legacy::Legacy;
 use super::memory::Memory;
 use super::pids::Pids;
+use super::property::{self, SystemdPropertyError};
 use crate::common::{
-    self, AnyCgroupManager, CgroupManager, ControllerOpt, FreezerState, JoinSafelyError,
-    PathBufExt, WrapIoResult, WrappedIoError,
+    self, AnyCgroupManager, CgroupDelegation, CgroupManager, ControllerOpt, FreezerState,
+    JoinSafelyError, PathBufExt, WrapIoResult, WrappedIoError,
 };
 use crate::stats::Stats;
 use crate::systemd::dbus_native::serialize::Variant;
 use crate::systemd::io::Io;
 use crate::systemd::unified::Unified;
-use crate::v2::manager::{Manager as FsManager, V2ManagerError};
+use crate::v1::manager::{Manager as V1FsManager, V1ManagerError};
+use crate::v2::manager::{DELEGATED_INIT_CGROUP, Manager as V2FsManager, V2ManagerError};
 
 const CGROUP_CONTROLLERS: &str = "cgroup.controllers";
 const CGROUP_SUBTREE_CONTROL: &str = "cgroup.subtree_control";
+/// Named hierarchy which systemd uses to track processes on cgroup v1 hosts
+const SYSTEMD_V1_HIERARCHY: &str = "systemd";
 pub const PROCESS_IN_CGROUP_TIMEOUT_DURATION: Duration = Duration::from_secs(5);
 
 pub struct Manager {
@@ -38,7 +45,8 @@ pub struct Manager {
     /// Path relative to the root path e.g. /system.slice/youki-569d5ce3afe1074769f67.scope for rootfull containers
     /// and e.g. /user.slice/user-1000/user@1000.service/youki-569d5ce3afe1074769f67.scope for rootless containers
     cgroups_path: PathBuf,
-    /// Combination of root path and cgroups path
+    /// Combination of root path and cgroups path. On cgroup v1 hosts this is the path
+    /// in the hierarchy systemd uses to track processes, e.g. /sys/fs/cgroup/systemd/...
     full_path: PathBuf,
     /// Destructured cgroups path as specified in the runtime spec e.g. system.slice:youki:569d5ce3afe1074769f67
     destructured_path: CgroupsPath,
@@ -46,14 +54,55 @@ pub struct Manager {
     container_name: String,
     /// Name of the systemd unit e.g. youki-569d5ce3afe1074769f67.scope
     unit_name: String,
-    /// Client for communicating with systemd
-    client: DbusConnection,
+    /// Client for communicating with systemd, connected on first use
+    client: OnceCell<DbusConnection>,
+    /// If the unit is managed by the system instance of systemd rather than the one of the user
+    use_system: bool,
     /// Cgroup manager for the created transient unit
     fs_manager: FsManager,
     /// Last control group which is managed by systemd, e.g. /user.slice/user-1000/user@1000.service
     delegation_boundary: PathBuf,
     /// Duration to wait for a specific PID to be added to a cgroup
     cgroup_wait_timeout_duration: Duration,
+    /// Additional properties that are set when the transient unit is started
+    unit_properties: HashMap<String, Variant>,
+}
+
+/// Cgroupfs manager for the hierarchy the transient unit is placed in
+enum FsManager {
+    Unified(V2FsManager),
+    Legacy(V1FsManager),
+}
+
+impl FsManager {
+    fn is_legacy(&self) -> bool {
+        matches!(self, FsManager::Legacy(_))
+    }
+
+    fn is_delegated(&self) -> bool {
+        matches!(self, FsManager::Unified(m) if m.is_delegated())
+    }
+
+    fn apply(&self, controller_opt: &ControllerOpt) -> Result<(), SystemdManagerError> {
+        match self {
+            FsManager::Unified(m) => Ok(m.apply(controller_opt)?),
+            FsManager::Legacy(m) => Ok(m.apply(controller_opt)?),
+        }
+    }
+
+    fn freeze(&self, state: FreezerState) -> Result<(), SystemdManagerError> {
+        match self {
+            FsManager::Unified(m) => Ok(m.freeze(state)?),
+            FsManager::Legacy(m) => Ok(m.freeze(state)?),
+        }
+    }
+
+    fn stats(&self) -> Result<Stats, SystemdManagerError> {
+        match self {
+            FsManager::Unified(m) => Ok(m.stats()?),
+            FsManager::Legacy(m) => Ok(m.stats()?),
+        }
+    }
 }
 
 /// Represents the systemd cgroups path:
@@ -138,6 +187,7 @@ impl Debug for Manager {
             .field("destructured_path", &self.destructured_path)
             .field("container_name", &self.container_name)
             .field("unit_name", &self.unit_name)
+            .field("unit_properties", &self.unit_properties)
             .finish()
     }
 }
@@ -160,14 +210,24 @@ pub enum SystemdManagerError {
     BadDelegationBoundary { boundary: PathBuf, cgroup: PathBuf },
     #[error("in v2 manager: {0}")]
     V2Manager(#[from] V2ManagerError),
+    #[error("in v1 manager: {0}")]
+    V1Manager(#[from] V1ManagerError),
+    #[error("systemd cgroup driver is only supported for root on cgroup v1")]
+    RootlessLegacy,
+    #[error("systemd cgroup flag passed, but the host was not booted with systemd")]
+    NotBooted,
 
     #[error("Timeout waiting for pid {0} to be added to cgroup")]
     WaitForProcessInCgroupTimeout(String),
+    #[error("invalid unit property: {0}")]
+    UnitProperty(#[from] SystemdPropertyError),
 
     #[error("in cpu controller: {0}")]
     Cpu(#[from] super::cpu::SystemdCpuError),
     #[error("in cpuset controller: {0}")]
     CpuSet(#[from] super::cpuset::SystemdCpuSetError),
+    #[error("in devices controller: {0}")]
+    Devices(#[from] super::devices::SystemdDevicesError),
     #[error("in io controller: {0}")]
     Io(#[from] super::io::SystemdIoError),
     #[error("in memory controller: {0}")]
@@ -176,9 +236,12 @@ pub enum SystemdManagerError {
     Pids(Infallible),
     #[error("in pids unified controller: {0}")]
     Unified(#[from] super::unified::SystemdUnifiedError),
+    #[error("in legacy controller: {0}")]
+    Legacy(#[from] super::legacy::SystemdLegacyError),
 }
 
 impl Manager {
+    /// Creates a manager for a transient unit on a cgroup v2 (unified) hierarchy
     pub fn new(
         root_path: PathBuf,
         cgroups_path: PathBuf,
@@ -197,7 +260,22 @@ impl Manager {
         let (cgroups_path, delegation_boundary) =
             Self::construct_cgroups_path(&destructured_path, &client)?;
         let full_path = root_path.join_safely(&cgroups_path)?;
-        let fs_manager = FsManager::new(root_path.clone(), cgroups_path.clone())?;
+        let fs_manager =
+            FsManager::Unified(V2FsManager::new(root_path.clone(), cgroups_path.clone())?);
+
+        // To align with runc, youki will always add the following properties to its container units:
+        // - CPUAccounting=true
+        // - IOAccounting=true (BlockIOAccounting for cgroup v1)
+        // - MemoryAccounting=true
+        // - TasksAccounting=true
+        // see https://github.com/opencontainers/runc/blob/6023d635d725a74c6eaa11ab7f3c870c073badd2/docs/systemd.md#systemd-cgroup-driver
+        // for more details.
+        let unit_properties = HashMap::from([
+            ("MemoryAccounting".to_owned(), Variant::Bool(true)),
+            ("CPUAccounting".to_owned(), Variant::Bool(true)),
+            ("IOAccounting".to_owned(), Variant::Bool(true)),
+            ("TasksAccounting".to_owned(), Variant::Bool(true)),
+        ]);
 
         Ok(Manager {
             root_path,
@@ -206,13 +284,174 @@ impl Manager {
             container_name,
             unit_name: Self::get_unit_name(&destructured_path),
             destructured_path,
-            client,
+            client: OnceCell::from(client),
+            use_system,
             fs_manager,
             delegation_boundary,
             cgroup_wait_timeout_duration,
+            unit_properties,
+        })
+    }
+
+    /// Creates a manager for a transient unit on a cgroup v1 (legacy or hybrid) hierarchy.
+    /// systemd only creates the unit's cgroup in the hierarchies of the controllers it
+    /// manages, so the process is additionally moved into the same path of all other
+    /// v1 hierarchies and resource restrictions are applied through the v1 controllers.
+    /// Only the system instance of systemd is supported, as systemd cannot delegate
+    /// cgroup v1 controllers to unprivileged users.
+    pub fn new_legacy(
+        root_path: PathBuf,
+        cgroups_path: PathBuf,
+        container_name: String,
+        cgroup_wait_timeout_duration: Duration,
+    ) -> Result<Self, SystemdManagerError> {
+        if !nix::unistd::geteuid().is_root() {
+            return Err(SystemdManagerError::RootlessLegacy);
+        }
+
+        let mut destructured_path: CgroupsPath = cgroups_path.as_path().try_into()?;
+        ensure_parent_unit(&mut destructured_path, true);
+
+        let client = DbusConnection::new_system()?;
+
+        let (cgroups_path, delegation_boundary) =
+            Self::construct_cgroups_path(&destructured_path, &client)?;
+        let full_path = root_path
+            .join(SYSTEMD_V1_HIERARCHY)
+            .join_safely(&cgroups_path)?;
+        let fs_manager = FsManager::Legacy(V1FsManager::new(&cgroups_path)?);
+
+        let mut unit_properties = HashMap::from([
+            ("MemoryAccounting".to_owned(), Variant::Bool(true)),
+            ("CPUAccounting".to_owned(), Variant::Bool(true)),
+            ("BlockIOAccounting".to_owned(), Variant::Bool(true)),
+        ]);
+        // TasksAccounting is not known to the older versions of systemd
+        // which are still found on cgroup v1 hosts
+        if client.systemd_version()? >= 227 {
+            unit_properties.insert("TasksAccounting".to_owned(), Variant::Bool(true));
+        }
+
+        Ok(Manager {
+            root_path,
+            cgroups_path,
+            full_path,
+            container_name,
+            unit_name: Self::get_unit_name(&destructured_path),
+            destructured_path,
+            client: OnceCell::from(client),
+            use_system: true,
+            fs_manager,
+            delegation_boundary,
+            cgroup_wait_timeout_duration,
+            unit_properties,
+        })
+    }
+
+    /// Creates a manager for the existing unit of a container from what
+    /// [`Manager::cgroups_path`] returned for it, without asking systemd for the
+    /// cgroup of the unit. systemd is only connected to once it is needed, which
+    /// isn't the case for reading stats, freezing or listing the processes.
+    pub fn restore(
+        root_path: PathBuf,
+        cgroups_path: PathBuf,
+        config_path: &Path,
+        use_system: bool,
+        legacy: bool,
+    ) -> Result<Self, SystemdManagerError> {
+        let mut destructured_path: CgroupsPath = config_path.try_into()?;
+        ensure_parent_unit(&mut destructured_path, use_system);
+        let unit_name = Self::get_unit_name(&destructured_path);
+
+        // the unit is placed in its parent slice below the delegation boundary
+        let depth = Self::expand_slice(&destructured_path.parent)?
+            .components()
+            .filter(|c| c.ne(&RootDir))
+            .count()
+            + 1;
+        let delegation_boundary = cgroups_path
+            .ancestors()
+            .nth(depth)
+            .ok_or_else(|| SystemdManagerError::BadDelegationBoundary {
+                boundary: PathBuf::new(),
+                cgroup: cgroups_path.clone(),
+            })?
+            .to_path_buf();
+
+        let (full_path, fs_manager) = match legacy {
+            true => (
+                root_path
+                    .join(SYSTEMD_V1_HIERARCHY)
+                    .join_safely(&cgroups_path)?,
+                FsManager::Legacy(V1FsManager::new(&cgroups_path)?),
+            ),
+            false => (
+                root_path.join_safely(&cgroups_path)?,
+                FsManager::Unified(V2FsManager::new(root_path.clone(), cgroups_path.clone())?),
+            ),
+        };
+
+        Ok(Manager {
+            root_path,
+            cgroups_path,
+            full_path,
+            container_name: destructured_path.name.clone(),
+            unit_name,
+            destructured_path,
+            client: OnceCell::new(),
+            use_system,
+            fs_manager,
+            delegation_boundary,
+            cgroup_wait_timeout_duration: PROCESS_IN_CGROUP_TIMEOUT_DURATION,
+            unit_properties: HashMap::new(),
         })
     }
 
+    /// Path of the cgroup of the unit relative to the root path, e.g.
+    /// /system.slice/youki-569d5ce3afe1074769f67.scope
+    pub fn cgroups_path(&self) -> &Path {
+        &self.cgroups_path
+    }
+
+    /// Checks if the unit is managed by the system instance of systemd
+    pub fn uses_system(&self) -> bool {
+        self.use_system
+    }
+
+    fn client(&self) -> Result<&DbusConnection, SystemdManagerError> {
+        if let Some(client) = self.client.get() {
+            return Ok(client);
+        }
+        let client = match self.use_system {
+            true => DbusConnection::new_system()?,
+            false => DbusConnection::new_session()?,
+        };
+        Ok(self.client.get_or_init(|| client))
+    }
+
+    /// Sets additional properties for the transient unit, e.g. the ones passed through
+    /// `org.systemd.property.` annotations. These are applied when the unit is started
+    /// and take precedence over the defaults set by youki.
+    pub fn with_unit_properties(
+        mut self,
+        properties: &HashMap<String, String>,
+    ) -> Result<Self, SystemdManagerError> {
+        self.unit_properties
+            .extend(property::parse_properties(properties)?);
+        Ok(self)
+    }
+
+    /// Delegates the cgroup of the unit to the container, see [`CgroupDelegation`].
+    /// Scopes are always started with Delegate=true, which allows the container to
+    /// manage the cgroups below the one of the unit. Only supported with cgroup v2.
+    pub fn with_delegation(mut self, delegation: CgroupDelegation) -> Self {
+        self.fs_manager = match self.fs_manager {
+            FsManager::Unified(m) => FsManager::Unified(m.with_delegation(delegation)),
+            legacy => legacy,
+        };
+        self
+    }
+
     /// get_unit_name returns the unit (scope) name from the path provided by the user
     /// for example: foo:docker:bar returns in '/docker-bar.scope'
     fn get_unit_name(cgroups_path: &CgroupsPath) -> String {
@@ -321,20 +560,15 @@ impl Manager {
         let start = Instant::now();
         while start.elapsed() < self.cgroup_wait_timeout_duration {
             // If it fails, it most likely means that the cgroup hasn't been set up yet.
-            let result = self.fs_manager.get_all_pids();
-            if let Ok(pids) = result {
-                if pids.contains(&pid) {
-                    tracing::info!("Process {} successfully added to cgroup", pid);
-                    return Ok(());
-                }
-            } else if let Err(e) = result {
-                if let V2ManagerError::WrappedIo(ref wrapped_io_error) = e {
-                    if !matches!(wrapped_io_error, WrappedIoError::Read { .. }) {
-                        return Err(e.into());
+            match common::get_all_pids(&self.full_path) {
+                Ok(pids) => {
+                    if pids.contains(&pid) {
+                        tracing::info!("Process {} successfully added to cgroup", pid);
+                        return Ok(());
                     }
-                } else {
-                    return Err(e.into());
                 }
+                Err(WrappedIoError::Read { .. }) => {}
+                Err(e) => return Err(e.into()),
             }
 
             std::thread::sleep(Duration::from_millis(20));
@@ -377,6 +611,18 @@ impl Manager {
         Ok(())
     }
 
+    /// On cgroup v1 systemd does not create the unit's cgroup in the hierarchies of
+    /// controllers it does not manage (e.g. cpuset, freezer or hugetlb), so the process
+    /// is moved into these by the v1 cgroupfs manager. Writing to the hierarchies
+    /// systemd already placed the process in is a no-op.
+    fn join_legacy_hierarchies(&self, pid: Pid) -> Result<(), SystemdManagerError> {
+        if let FsManager::Legacy(m) = &self.fs_manager {
+            m.add_task(pid)?;
+        }
+
+        Ok(())
+    }
+
     pub fn any(self) -> AnyCgroupManager {
         AnyCgroupManager::Systemd(Box::new(self))
     }
@@ -390,30 +636,50 @@ impl CgroupManager for Manager {
         if pid.as_raw() == -1 {
             return Ok(());
         }
-        if self.client.transient_unit_exists(&self.unit_name) {
+        if self.client()?.transient_unit_exists(&self.unit_name) {
             tracing::debug!("Transient unit {:?} already exists", self.unit_name);
-            self.client
-                .add_process_to_unit(&self.unit_name, "", pid.as_raw() as u32)?;
-            return Ok(());
+            // the cgroup of a delegated unit has no processes of its own
+            let subcgroup = match self.fs_manager.is_delegated() {
+                true => format!("/{DELEGATED_INIT_CGROUP}"),
+                false => String::new(),
+            };
+            self.client()?
+                .add_process_to_unit(&self.unit_name, &subcgroup, pid.as_raw() as u32)?;
+            return self.join_legacy_hierarchies(pid);
         }
 
         tracing::debug!("Starting {:?}", self.unit_name);
-        self.client.start_transient_unit(
+        self.client()?.start_transient_unit(
             &self.container_name,
             pid.as_raw() as u32,
             &self.destructured_path.parent,
             &self.unit_name,
+            &self.unit_properties,
         )?;
 
         // There is a chance that the intermediate process ends before systemd gets the dbus message to add it to transit unit.
         self.wait_for_process_in_cgroup(pid)?;
+        if let FsManager::Unified(m) = &self.fs_manager {
+            m.delegate(pid)?;
+        }
 
-        Ok(())
+        self.join_legacy_hierarchies(pid)
     }
 
     fn apply(&self, controller_opt: &ControllerOpt) -> Result<(), Self::Error> {
         let mut properties: HashMap<&str, Variant> = HashMap::new();
-        let systemd_version = self.client.systemd_version()?;
+        let systemd_version = self.client()?.systemd_version()?;
+
+        if self.fs_manager.is_legacy() {
+            Legacy::apply(controller_opt, systemd_version, &mut properties)?;
+            tracing::debug!("applying properties {:?}", properties);
+            if !properties.is_empty() {
+                self.client()?
+                    .set_unit_properties(&self.unit_name, &properties)?;
+            }
+            self.fs_manager.apply(controller_opt)?;
+            return Ok(());
+        }
 
         for controller in CONTROLLER_TYPES {
             match controller {
@@ -424,6 +690,9 @@ impl CgroupManager for Manager {
                 ControllerType::CpuSet => {
                     CpuSet::apply(controller_opt, systemd_version, &mut properties)?;
                 }
+                ControllerType::Devices => {
+                    Devices::apply(controller_opt, systemd_version, &mut properties)?;
+                }
 
                 ControllerType::Pids => {
                     Pids::apply(controller_opt, systemd_version, &mut properties)
@@ -443,28 +712,39 @@ impl CgroupManager for Manager {
 
         if !properties.is_empty() {
             self.ensure_controllers_attached()?;
-            self.client
+            self.client()?
                 .set_unit_properties(&self.unit_name, &properties)?;
         }
 
+        // systemd has no properties for some of the resources (e.g. hugetlb) and
+        // only installs its own device filter, so the restrictions are also written
+        // directly to the cgroup of the unit, as runc does.
+        self.fs_manager.apply(controller_opt)?;
+
         Ok(())
     }
 
     fn remove(&self) -> Result<(), Self::Error> {
         tracing::debug!("remove {}", self.unit_name);
-        if self.client.transient_unit_exists(&self.unit_name) {
-            self.client.stop_transient_unit(&self.unit_name)?;
+        if self.client()?.transient_unit_exists(&self.unit_name) {
+            self.client()?.stop_transient_unit(&self.unit_name)?;
+        }
+
+        // the cgroups in the hierarchies not managed by systemd are not
+        // removed together with the unit
+        if let FsManager::Legacy(m) = &self.fs_manager {
+            m.remove()?;
         }
 
         Ok(())
     }
 
     fn freeze(&self, state: FreezerState) -> Result<(), Self::Error> {
-        Ok(self.fs_manager.freeze(state)?)
+        self.fs_manager.freeze(state)
     }
 
     fn stats(&self) -> Result<Stats, Self::Error> {
-        Ok(self.fs_manager.stats()?)
+        self.fs_manager.stats()
     }
 
     fn get_all_pids(&self) -> Result<Vec<Pid>, Self::Error> {
@@ -499,6 +779,7 @@ mod tests {
             _pid: u32,
             _parent: &str,
             _unit_name: &str,
+            _properties: &HashMap<String, Variant>,
         ) -> Result<(), SystemdClientError> {
             Ok(())
         }
@@ -586,6 +867,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn restore_works_without_systemd() -> Result<()> {
+        let manager = Manager::restore(
+            DEFAULT_CGROUP_ROOT.into(),
+            "/test.slice/test-a.slice/docker-foo.scope".into(),
+            Path::new("test-a.slice:docker:foo"),
+            true,
+            false,
+        )?;
+
+        assert_eq!(manager.unit_name, "docker-foo.scope");
+        assert_eq!(manager.delegation_boundary, PathBuf::from("/"));
+        assert!(manager.client.get().is_none());
+
+        Ok(())
+    }
+
     #[test]
     fn test_task_addition() {
         let manager = Manager::new(
@@ -5,10 +5,13 @@ pub mod controller_type;
 mod cpu;
 mod cpuset;
 mod dbus_native;
+mod devices;
 mod io;
+mod legacy;
 pub mod manager;
 mod memory;
 mod pids;
+pub mod property;
 mod unified;
 
 /// Checks if the system was booted with systemd
@@ -18,6 +21,19 @@ pub fn booted() -> bool {
         .unwrap_or_default()
 }
 
+/// Checks if systemd can be reached over D-Bus, through the system bus or the
+/// session bus of the user. Only the socket of the bus is connected to, the
+/// manager opens the actual connection once it needs it.
+pub fn bus_available(use_system: bool) -> bool {
+    match dbus_native::dbus::DbusConnection::probe(use_system) {
+        Ok(()) => true,
+        Err(err) => {
+            tracing::debug!(?err, use_system, "failed to connect to the bus");
+            false
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! recast {
     ($v:ident, $t:ty) => {{
@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use super::dbus_native::serialize::Variant;
+
+const MICROSECS_PER_SEC: f64 = 1_000_000.0;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SystemdPropertyError {
+    #[error("invalid systemd property name {0}")]
+    InvalidName(String),
+    #[error("unsupported value {value} for systemd property {name}")]
+    UnsupportedValue { name: String, value: String },
+}
+
+/// Parses properties given in the form of `name=value` (e.g. through the
+/// `org.systemd.property.` annotations) into values which can be passed to systemd
+/// when the transient unit is started. Only a subset of the GVariant text format is
+/// supported: booleans, unsigned integers (optionally prefixed with `uint64`) and
+/// quoted strings. Like runc, properties ending in `Sec` are interpreted as a number
+/// of seconds and converted to the corresponding `USec` property.
+pub fn parse_properties(
+    properties: &HashMap<String, String>,
+) -> Result<HashMap<String, Variant>, SystemdPropertyError> {
+    properties
+        .iter()
+        .map(|(name, value)| parse_property(name, value))
+        .collect()
+}
+
+fn parse_property(name: &str, value: &str) -> Result<(String, Variant), SystemdPropertyError> {
+    let valid_name = name.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+        && name.chars().all(|c| c.is_ascii_alphanumeric());
+    if !valid_name {
+        return Err(SystemdPropertyError::InvalidName(name.to_owned()));
+    }
+
+    let unsupported = || SystemdPropertyError::UnsupportedValue {
+        name: name.to_owned(),
+        value: value.to_owned(),
+    };
+    let value = value.trim();
+
+    if let Some(prefix) = name.strip_suffix("Sec") {
+        if !prefix.ends_with('U') {
+            let secs = value
+                .strip_prefix("uint64 ")
+                .unwrap_or(value)
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|secs| secs.is_finite() && *secs >= 0.0)
+                .ok_or_else(unsupported)?;
+            let usecs = (secs * MICROSECS_PER_SEC).round() as u64;
+            return Ok((format!("{prefix}USec"), Variant::U64(usecs)));
+        }
+    }
+
+    let variant = match value {
+        "true" => Variant::Bool(true),
+        "false" => Variant::Bool(false),
+        v if v.len() >= 2
+            && ((v.starts_with('\'') && v.ends_with('\''))
+                || (v.starts_with('"') && v.ends_with('"'))) =>
+        {
+            Variant::String(v[1..v.len() - 1].to_owned())
+        }
+        v => {
+            let number = v.strip_prefix("uint64 ").unwrap_or(v).trim();
+            Variant::U64(number.parse::<u64>().map_err(|_| unsupported())?)
+        }
+    };
+
+    Ok((name.to_owned(), variant))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_property_values() -> Result<()> {
+        let cases = [
+            ("Delegate", "true", "Delegate", Variant::Bool(true)),
+            (
+                "CPUAccounting",
+                "false",
+                "CPUAccounting",
+                Variant::Bool(false),
+            ),
+            ("CPUWeight", "uint64 500", "CPUWeight", Variant::U64(500)),
+            ("TasksMax", "1024", "TasksMax", Variant::U64(1024)),
+            (
+                "CollectMode",
+                "'inactive-or-failed'",
+                "CollectMode",
+                Variant::String("inactive-or-failed".to_owned()),
+            ),
+            (
+                "Description",
+                "\"test unit\"",
+                "Description",
+                Variant::String("test unit".to_owned()),
+            ),
+            (
+                "TimeoutStopSec",
+                "2.5",
+                "TimeoutStopUSec",
+                Variant::U64(2_500_000),
+            ),
+            (
+                "RuntimeMaxUSec",
+                "uint64 3000",
+                "RuntimeMaxUSec",
+                Variant::U64(3000),
+            ),
+        ];
+
+        for (name, value, expected_name, expected_value) in cases {
+            let (parsed_name, parsed_value) = parse_property(name, value)?;
+            assert_eq!(parsed_name, expected_name);
+            assert_eq!(parsed_value, expected_value, "{name}={value}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_invalid_properties() {
+        assert!(matches!(
+            parse_property("delegate", "true"),
+            Err(SystemdPropertyError::InvalidName(_))
+        ));
+        assert!(matches!(
+            parse_property("Cpu-Weight", "10"),
+            Err(SystemdPropertyError::InvalidName(_))
+        ));
+        assert!(matches!(
+            parse_property("CPUWeight", "-10"),
+            Err(SystemdPropertyError::UnsupportedValue { .. })
+        ));
+        assert!(matches!(
+            parse_property("TimeoutStopSec", "forever"),
+            Err(SystemdPropertyError::UnsupportedValue { .. })
+        ));
+        assert!(matches!(
+            parse_property("AllowedCPUs", "[0, 1]"),
+            Err(SystemdPropertyError::UnsupportedValue { .. })
+        ));
+    }
+}
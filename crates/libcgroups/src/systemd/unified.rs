@@ -5,7 +5,7 @@ use super::controller::Controller;
 use super::cpu::{self, convert_shares_to_cgroup2};
 use super::cpuset::{self, BitmaskError, to_bitmask};
 use super::dbus_native::serialize::Variant;
-use super::{memory, pids};
+use super::{io, memory, pids};
 use crate::common::ControllerOpt;
 
 #[derive(thiserror::Error, Debug)]
@@ -30,6 +30,10 @@ pub enum SystemdUnifiedError {
     },
     #[error("failed to to parse pids.max {value}: {err}")]
     PidsMax { err: ParseIntError, value: String },
+    #[error("failed to parse io.weight {value}: {err}")]
+    IoWeight { err: ParseIntError, value: String },
+    #[error("setting {name} requires systemd version {required} or newer")]
+    UnsupportedSystemd { name: String, required: u32 },
 }
 
 pub struct Unified {}
@@ -116,24 +120,49 @@ impl Unified {
 
                     properties.insert(systemd_cpuset, Variant::ArrayU64(bitmask));
                 }
-                memory @ ("memory.min" | "memory.low" | "memory.high" | "memory.max") => {
-                    let value =
-                        value
+                memory @ ("memory.min" | "memory.low" | "memory.high" | "memory.max"
+                | "memory.swap.max" | "memory.zswap.max") => {
+                    if memory == "memory.zswap.max" && systemd_version < 253 {
+                        return Err(SystemdUnifiedError::UnsupportedSystemd {
+                            name: memory.into(),
+                            required: 253,
+                        });
+                    }
+
+                    let value = match value.trim() {
+                        "max" => u64::MAX,
+                        v => v
                             .parse::<u64>()
                             .map_err(|err| SystemdUnifiedError::Memory {
                                 err,
                                 name: memory.into(),
                                 value: value.into(),
-                            })?;
+                            })?,
+                    };
                     let systemd_memory = match memory {
                         "memory.min" => memory::MEMORY_MIN,
                         "memory.low" => memory::MEMORY_LOW,
                         "memory.high" => memory::MEMORY_HIGH,
                         "memory.max" => memory::MEMORY_MAX,
+                        "memory.swap.max" => memory::MEMORY_SWAP,
+                        "memory.zswap.max" => memory::MEMORY_ZSWAP,
                         file_name => unreachable!("{} was not matched", file_name),
                     };
                     properties.insert(systemd_memory, Variant::U64(value));
                 }
+                "io.weight" => {
+                    // io.weight may also be given as "default <weight>"
+                    let weight = value.trim();
+                    let weight = weight.strip_prefix("default").unwrap_or(weight).trim();
+                    let weight =
+                        weight
+                            .parse::<u64>()
+                            .map_err(|err| SystemdUnifiedError::IoWeight {
+                                err,
+                                value: value.into(),
+                            })?;
+                    properties.insert(io::IO_WEIGHT, Variant::U64(weight));
+                }
                 "pids.max" => {
                     let pids = value.trim().parse::<i64>().map_err(|err| {
                         SystemdUnifiedError::PidsMax {
@@ -171,6 +200,9 @@ mod tests {
             ("memory.low", "200000"),
             ("memory.high", "300000"),
             ("memory.max", "400000"),
+            ("memory.swap.max", "max"),
+            ("memory.zswap.max", "0"),
+            ("io.weight", "default 200"),
             ("pids.max", "100"),
         ]
         .into_iter()
@@ -185,11 +217,14 @@ mod tests {
         expected.insert(memory::MEMORY_LOW, Variant::U64(200000u64));
         expected.insert(memory::MEMORY_HIGH, Variant::U64(300000u64));
         expected.insert(memory::MEMORY_MAX, Variant::U64(400000u64));
+        expected.insert(memory::MEMORY_SWAP, Variant::U64(u64::MAX));
+        expected.insert(memory::MEMORY_ZSWAP, Variant::U64(0u64));
+        expected.insert(io::IO_WEIGHT, Variant::U64(200u64));
         expected.insert(pids::TASKS_MAX, Variant::U64(100u64));
 
         // act
         let mut actual: HashMap<&str, Variant> = HashMap::new();
-        Unified::apply(&unified, 245, &mut actual).context("apply unified")?;
+        Unified::apply(&unified, 253, &mut actual).context("apply unified")?;
 
         // assert
         for (setting, value) in expected {
@@ -249,4 +284,23 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_zswap_requires_newer_systemd() {
+        // arrange
+        let unified: HashMap<String, String> = [("memory.zswap.max", "0")]
+            .into_iter()
+            .map(|(k, v)| (k.to_owned(), v.to_owned()))
+            .collect();
+        let mut actual: HashMap<&str, Variant> = HashMap::new();
+
+        // act
+        let result = Unified::apply(&unified, 252, &mut actual);
+
+        // assert
+        assert!(matches!(
+            result,
+            Err(SystemdUnifiedError::UnsupportedSystemd { required: 253, .. })
+        ));
+    }
 }
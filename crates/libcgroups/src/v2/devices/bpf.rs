@@ -20,7 +20,8 @@ pub mod prog {
     use libbpf_sys::{BPF_CGROUP_DEVICE, BPF_F_ALLOW_MULTI, BPF_PROG_TYPE_CGROUP_DEVICE, bpf_insn};
     #[cfg(not(test))]
     use libbpf_sys::{
-        bpf_prog_attach, bpf_prog_detach2, bpf_prog_get_fd_by_id, bpf_prog_load, bpf_prog_query,
+        bpf_prog_attach, bpf_prog_detach2, bpf_prog_get_fd_by_id, bpf_prog_get_info_by_fd,
+        bpf_prog_load, bpf_prog_query,
     };
     #[cfg(not(test))]
     use libc::setrlimit;
@@ -30,7 +31,8 @@ pub mod prog {
     // TODO: consider use of #[mockall_double]
     #[cfg(test)]
     use crate::v2::devices::mocks::mock_libbpf_sys::{
-        bpf_prog_attach, bpf_prog_detach2, bpf_prog_get_fd_by_id, bpf_prog_load, bpf_prog_query,
+        bpf_prog_attach, bpf_prog_detach2, bpf_prog_get_fd_by_id, bpf_prog_get_info_by_fd,
+        bpf_prog_load, bpf_prog_query,
     };
     // mocks
     // TODO: consider use of #[mockall_double]
@@ -117,6 +119,42 @@ pub mod prog {
         Ok(prog_fds)
     }
 
+    /// Returns the instructions of a loaded program as translated by the kernel
+    /// verifier. Reading them requires CAP_SYS_ADMIN, without it the kernel reports
+    /// an empty program.
+    pub fn xlated_bytecodes(prog_fd: RawFd) -> Result<Vec<u8>, super::BpfError> {
+        let info_len = std::mem::size_of::<libbpf_sys::bpf_prog_info>() as u32;
+
+        // the first call only retrieves the size of the translated program
+        let mut info = libbpf_sys::bpf_prog_info::default();
+        let mut len = info_len;
+        #[allow(unused_unsafe)]
+        let ret = unsafe { bpf_prog_get_info_by_fd(prog_fd, &mut info, &mut len) };
+        if ret != 0 {
+            return Err(errno::errno().into());
+        }
+
+        let mut insns = vec![0_u8; info.xlated_prog_len as usize];
+        if insns.is_empty() {
+            return Ok(insns);
+        }
+
+        let mut info = libbpf_sys::bpf_prog_info {
+            xlated_prog_len: insns.len() as u32,
+            xlated_prog_insns: insns.as_mut_ptr() as u64,
+            ..Default::default()
+        };
+        let mut len = info_len;
+        #[allow(unused_unsafe)]
+        let ret = unsafe { bpf_prog_get_info_by_fd(prog_fd, &mut info, &mut len) };
+        if ret != 0 {
+            return Err(errno::errno().into());
+        }
+
+        insns.truncate(info.xlated_prog_len as usize);
+        Ok(insns)
+    }
+
     pub fn detach2(prog_fd: RawFd, cgroup_fd: RawFd) -> Result<(), super::BpfError> {
         #[allow(unused_unsafe)]
         let ret = unsafe { bpf_prog_detach2(prog_fd, cgroup_fd, BPF_CGROUP_DEVICE) };
@@ -342,6 +380,56 @@ mod tests {
         assert!(error.is_err());
     }
 
+    #[test]
+    #[serial(libbpf_sys)] // mock contexts are shared
+    fn test_bpf_xlated_bytecodes() {
+        // arrange
+        let get_info = mock_libbpf_sys::bpf_prog_get_info_by_fd_context();
+
+        // expect
+        get_info.expect().times(2).returning(
+            |_prog_fd: std::os::raw::c_int,
+             info: *mut libbpf_sys::bpf_prog_info,
+             _info_len: *mut libbpf_sys::__u32|
+             -> std::os::raw::c_int {
+                unsafe {
+                    if (*info).xlated_prog_insns == 0 {
+                        (*info).xlated_prog_len = 16;
+                        return 0;
+                    }
+                    let insns = std::slice::from_raw_parts_mut(
+                        (*info).xlated_prog_insns as *mut u8,
+                        (*info).xlated_prog_len as usize,
+                    );
+                    insns.fill(0xF);
+                }
+                0
+            },
+        );
+
+        // act
+        let insns = prog::xlated_bytecodes(0).expect("successfully gets program info");
+
+        // assert
+        assert_eq!(insns, vec![0xF; 16]);
+    }
+
+    #[test]
+    #[serial(libbpf_sys)] // mock contexts are shared
+    fn test_bpf_xlated_bytecodes_error() {
+        // arrange
+        let get_info = mock_libbpf_sys::bpf_prog_get_info_by_fd_context();
+
+        // expect
+        get_info.expect().once().returning(|_, _, _| -1);
+
+        // act
+        let r = prog::xlated_bytecodes(0);
+
+        // assert
+        assert!(r.is_err());
+    }
+
     #[test]
     #[serial(libbpf_sys)] // mock contexts are shared
     fn test_bpf_detach2() {
@@ -51,27 +51,7 @@ impl Devices {
     ) -> Result<(), DevicesControllerError> {
         tracing::debug!("Apply Devices cgroup config");
 
-        // FIXME: should we start as "deny all"?
-        let mut emulator = emulator::Emulator::with_default_allow(false);
-
-        // FIXME: apply user-defined and default rules in which order?
-        if let Some(devices) = linux_devices {
-            for d in devices {
-                tracing::debug!("apply user defined rule: {:?}", d);
-                emulator.add_rule(d);
-            }
-        }
-
-        for d in [
-            default_devices().iter().map(|d| d.into()).collect(),
-            default_allow_devices(),
-        ]
-        .concat()
-        {
-            tracing::debug!("apply default rule: {:?}", d);
-            emulator.add_rule(&d);
-        }
-
+        let emulator = Self::emulator(linux_devices);
         let prog = program::Program::from_rules(&emulator.rules, emulator.default_allow)?;
 
         // Increase `ulimit -l` limit to avoid BPF_PROG_LOAD error (#2167).
@@ -110,6 +90,33 @@ impl Devices {
 
         Ok(())
     }
+
+    /// Computes the rules which are compiled into the device program, i.e. the user
+    /// defined rules followed by the default devices.
+    pub fn emulator(linux_devices: &Option<Vec<LinuxDeviceCgroup>>) -> emulator::Emulator {
+        // FIXME: should we start as "deny all"?
+        let mut emulator = emulator::Emulator::with_default_allow(false);
+
+        // FIXME: apply user-defined and default rules in which order?
+        if let Some(devices) = linux_devices {
+            for d in devices {
+                tracing::debug!("apply user defined rule: {:?}", d);
+                emulator.add_rule(d);
+            }
+        }
+
+        for d in [
+            default_devices().iter().map(|d| d.into()).collect(),
+            default_allow_devices(),
+        ]
+        .concat()
+        {
+            tracing::debug!("apply default rule: {:?}", d);
+            emulator.add_rule(&d);
+        }
+
+        emulator
+    }
 }
 
 #[cfg(test)]
@@ -49,6 +49,27 @@ impl Emulator {
 
         self.rules.push(rule.clone());
     }
+
+    /// Evaluates a device access the same way the compiled BPF program does: rules are
+    /// checked in reversed order and the first matching rule decides. `access` is
+    /// granted only if the rule allows every requested access type.
+    pub fn allows(&self, typ: LinuxDeviceType, major: u32, minor: u32, access: &str) -> bool {
+        let matched = self.rules.iter().rev().find(|rule| {
+            let rule_access = rule.access().as_deref().unwrap_or_default();
+            rule.typ().unwrap_or_default() == typ
+                && rule
+                    .major()
+                    .filter(|m| *m >= 0)
+                    .is_none_or(|m| m == major as i64)
+                && rule
+                    .minor()
+                    .filter(|m| *m >= 0)
+                    .is_none_or(|m| m == minor as i64)
+                && access.chars().all(|c| rule_access.contains(c))
+        });
+
+        matched.map_or(self.default_allow, |rule| rule.allow())
+    }
 }
 
 #[cfg(test)]
@@ -98,6 +119,35 @@ mod tests {
         assert!(!emulator.default_allow);
     }
 
+    #[test]
+    fn test_allows() {
+        // arrange
+        let mut emulator = Emulator::with_default_allow(false);
+        emulator.add_rules(&[
+            LinuxDeviceCgroupBuilder::default()
+                .allow(true)
+                .typ(LinuxDeviceType::C)
+                .minor(20)
+                .access("rw")
+                .build()
+                .unwrap(),
+            LinuxDeviceCgroupBuilder::default()
+                .allow(false)
+                .typ(LinuxDeviceType::C)
+                .major(10)
+                .access("r")
+                .build()
+                .unwrap(),
+        ]);
+
+        // assert
+        assert!(emulator.allows(LinuxDeviceType::C, 99, 20, "rw"));
+        assert!(emulator.allows(LinuxDeviceType::C, 10, 20, "w"));
+        assert!(!emulator.allows(LinuxDeviceType::C, 10, 20, "r"));
+        assert!(!emulator.allows(LinuxDeviceType::C, 99, 20, "m"));
+        assert!(!emulator.allows(LinuxDeviceType::B, 99, 20, "r"));
+    }
+
     #[test]
     fn test_add_some_rule() {
         // arrange
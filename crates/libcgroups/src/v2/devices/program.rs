@@ -201,13 +201,25 @@ impl Program {
         minor: u32,
         access: String,
     ) -> Result<u64, ProgramError> {
-        let mut mem = bpf_cgroup_dev_ctx(typ, major, minor, access)?;
-        let vm = rbpf::EbpfVmRaw::new(Some(self.prog.into_bytes()))?;
-        let result = vm.execute_program(&mut mem[..])?;
-        Ok(result)
+        execute(self.prog.into_bytes(), typ, major, minor, access)
     }
 }
 
+/// Runs a device program in the rbpf interpreter, e.g. one read back from the kernel,
+/// and returns its verdict for the given device access.
+pub fn execute(
+    bytecodes: &[u8],
+    typ: LinuxDeviceType,
+    major: u32,
+    minor: u32,
+    access: String,
+) -> Result<u64, ProgramError> {
+    let mut mem = bpf_cgroup_dev_ctx(typ, major, minor, access)?;
+    let vm = rbpf::EbpfVmRaw::new(Some(bytecodes))?;
+    let result = vm.execute_program(&mut mem[..])?;
+    Ok(result)
+}
+
 fn bpf_dev_type(typ: LinuxDeviceType) -> Result<u32, ProgramError> {
     let dev_type: u32 = match typ {
         LinuxDeviceType::C => libbpf_sys::BPF_DEVCG_DEV_CHAR,
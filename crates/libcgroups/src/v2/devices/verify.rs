@@ -0,0 +1,349 @@
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::Path;
+
+use nix::fcntl::OFlag;
+use nix::sys::stat::Mode;
+use oci_spec::runtime::{LinuxDeviceCgroup, LinuxDeviceType};
+use rbpf::disassembler::disassemble;
+
+use super::bpf::{self, BpfError};
+use super::emulator::Emulator;
+use super::program::{self, ProgramError};
+
+// Checks what the device programs attached to a cgroup actually allow. The programs are
+// read back from the kernel and run in the rbpf interpreter, so the result does not depend
+// on how the programs were generated. The same accesses are evaluated with the emulator,
+// which makes it possible to prove that the kernel enforces the rules of a container.
+
+const ACCESS_TYPES: [char; 3] = ['r', 'w', 'm'];
+// device number used for wildcard probes, it is never named by a rule
+const ANY_NUMBER: u32 = u32::MAX;
+
+#[derive(thiserror::Error, Debug)]
+pub enum VerifyError {
+    #[error("bpf error: {0}")]
+    Bpf(#[from] BpfError),
+    #[error("nix error: {0}")]
+    Nix(#[from] nix::Error),
+    #[error("program error: {0}")]
+    Program(#[from] ProgramError),
+    #[error("instructions of program {0} are not readable, CAP_SYS_ADMIN is required")]
+    NotReadable(u32),
+}
+
+/// A device program attached to a cgroup, as translated by the kernel verifier
+pub struct AttachedProgram {
+    pub id: u32,
+    bytecodes: Vec<u8>,
+}
+
+impl AttachedProgram {
+    pub fn bytecodes(&self) -> &[u8] {
+        &self.bytecodes
+    }
+
+    pub fn dump(&self) {
+        disassemble(&self.bytecodes);
+    }
+
+    /// Returns whether the program grants the access to the probed devices
+    pub fn allows(&self, probe: &DeviceProbe, access: &str) -> Result<bool, VerifyError> {
+        let ret = program::execute(
+            &self.bytecodes,
+            probe.typ,
+            probe.major.unwrap_or(ANY_NUMBER),
+            probe.minor.unwrap_or(ANY_NUMBER),
+            access.to_owned(),
+        )?;
+        Ok(ret != 0)
+    }
+}
+
+/// Reads back the device programs which are attached to the cgroup
+pub fn attached_programs(cgroup_path: &Path) -> Result<Vec<AttachedProgram>, VerifyError> {
+    let cgroup_fd = nix::dir::Dir::open(
+        cgroup_path.as_os_str(),
+        OFlag::O_RDONLY | OFlag::O_DIRECTORY,
+        Mode::from_bits(0o600).unwrap(),
+    )?;
+
+    let infos = bpf::prog::query(cgroup_fd.as_raw_fd())?;
+    // take ownership of all fds first, so that they are closed on every path
+    let infos: Vec<(u32, OwnedFd)> = infos
+        .into_iter()
+        .map(|info| (info.id, unsafe { OwnedFd::from_raw_fd(info.fd) }))
+        .collect();
+
+    let mut programs = Vec::with_capacity(infos.len());
+    for (id, fd) in infos {
+        let bytecodes = bpf::prog::xlated_bytecodes(fd.as_raw_fd())?;
+        if bytecodes.is_empty() {
+            return Err(VerifyError::NotReadable(id));
+        }
+        programs.push(AttachedProgram { id, bytecodes });
+    }
+
+    Ok(programs)
+}
+
+/// A device, or with wildcard numbers a class of devices, the device programs are
+/// evaluated for. A wildcard stands for all numbers which are not named by any rule,
+/// since the programs treat all of them in the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeviceProbe {
+    pub typ: LinuxDeviceType,
+    pub major: Option<u32>,
+    pub minor: Option<u32>,
+}
+
+impl DeviceProbe {
+    /// Returns the probe with the major number replaced by a wildcard
+    pub fn wildcard_major(&self) -> Self {
+        Self {
+            major: None,
+            ..*self
+        }
+    }
+
+    /// Returns the probe with the minor number replaced by a wildcard
+    pub fn wildcard_minor(&self) -> Self {
+        Self {
+            minor: None,
+            ..*self
+        }
+    }
+}
+
+impl Display for DeviceProbe {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let number = |n: Option<u32>| n.map_or("*".to_owned(), |n| n.to_string());
+        write!(
+            f,
+            "{} {}:{}",
+            self.typ.as_str(),
+            number(self.major),
+            number(self.minor)
+        )
+    }
+}
+
+/// Builds the probes which cover every device the rules can distinguish. Each major
+/// and minor number named by a rule is combined with each other and with wildcards, so
+/// that every possible device behaves like one of the probes. Only character and block
+/// devices are supported by the device programs.
+pub fn probes(rules: &[LinuxDeviceCgroup]) -> Vec<DeviceProbe> {
+    let mut probes = Vec::new();
+    for typ in [LinuxDeviceType::C, LinuxDeviceType::B] {
+        let typed_rules = rules
+            .iter()
+            .filter(|rule| rule.typ().unwrap_or_default() == typ);
+        let mut majors = BTreeSet::from([None]);
+        let mut minors = BTreeSet::from([None]);
+        for rule in typed_rules {
+            if let Some(major) = rule.major().filter(|m| *m >= 0) {
+                majors.insert(Some(major as u32));
+            }
+            if let Some(minor) = rule.minor().filter(|m| *m >= 0) {
+                minors.insert(Some(minor as u32));
+            }
+        }
+
+        for major in &majors {
+            for minor in &minors {
+                probes.push(DeviceProbe {
+                    typ,
+                    major: *major,
+                    minor: *minor,
+                });
+            }
+        }
+    }
+
+    probes
+}
+
+/// The access granted to a probe by the attached programs and by the emulator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceVerdict {
+    pub probe: DeviceProbe,
+    /// access granted by the programs attached to the cgroup
+    pub allowed: String,
+    /// access the emulated rules are expected to grant
+    pub expected: String,
+}
+
+impl DeviceVerdict {
+    pub fn is_consistent(&self) -> bool {
+        self.allowed == self.expected
+    }
+}
+
+/// Evaluates every probe for each access type with the attached programs and with the
+/// emulator. The kernel only grants an access if all attached programs allow it, and
+/// a cgroup without any program does not restrict device access at all.
+pub fn verify(
+    programs: &[AttachedProgram],
+    emulator: &Emulator,
+    probes: &[DeviceProbe],
+) -> Result<Vec<DeviceVerdict>, VerifyError> {
+    let mut verdicts = Vec::with_capacity(probes.len());
+    for probe in probes {
+        let mut allowed = String::new();
+        let mut expected = String::new();
+        for access in ACCESS_TYPES {
+            let access_str = access.to_string();
+
+            let mut granted = true;
+            for program in programs {
+                if !program.allows(probe, &access_str)? {
+                    granted = false;
+                    break;
+                }
+            }
+            if granted {
+                allowed.push(access);
+            }
+
+            if emulator.allows(
+                probe.typ,
+                probe.major.unwrap_or(ANY_NUMBER),
+                probe.minor.unwrap_or(ANY_NUMBER),
+                &access_str,
+            ) {
+                expected.push(access);
+            }
+        }
+
+        verdicts.push(DeviceVerdict {
+            probe: *probe,
+            allowed,
+            expected,
+        });
+    }
+
+    Ok(verdicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use oci_spec::runtime::LinuxDeviceCgroupBuilder;
+
+    use super::*;
+    use crate::v2::devices::program::Program;
+
+    fn emulator() -> Result<Emulator> {
+        let mut emulator = Emulator::with_default_allow(false);
+        emulator.add_rules(&[
+            LinuxDeviceCgroupBuilder::default()
+                .allow(true)
+                .typ(LinuxDeviceType::C)
+                .minor(20)
+                .access("rw")
+                .build()?,
+            LinuxDeviceCgroupBuilder::default()
+                .allow(false)
+                .typ(LinuxDeviceType::C)
+                .major(10)
+                .access("r")
+                .build()?,
+            LinuxDeviceCgroupBuilder::default()
+                .allow(true)
+                .typ(LinuxDeviceType::B)
+                .major(8)
+                .minor(0)
+                .access("rwm")
+                .build()?,
+        ]);
+        Ok(emulator)
+    }
+
+    fn attached(emulator: &Emulator) -> Result<AttachedProgram> {
+        let prog = Program::from_rules(&emulator.rules, emulator.default_allow)?;
+        Ok(AttachedProgram {
+            id: 1,
+            bytecodes: prog.bytecodes().to_vec(),
+        })
+    }
+
+    fn probe(typ: LinuxDeviceType, major: Option<u32>, minor: Option<u32>) -> DeviceProbe {
+        DeviceProbe { typ, major, minor }
+    }
+
+    #[test]
+    fn test_probes() -> Result<()> {
+        let probes = probes(&emulator()?.rules);
+
+        assert_eq!(
+            probes,
+            vec![
+                probe(LinuxDeviceType::C, None, None),
+                probe(LinuxDeviceType::C, None, Some(20)),
+                probe(LinuxDeviceType::C, Some(10), None),
+                probe(LinuxDeviceType::C, Some(10), Some(20)),
+                probe(LinuxDeviceType::B, None, None),
+                probe(LinuxDeviceType::B, None, Some(0)),
+                probe(LinuxDeviceType::B, Some(8), None),
+                probe(LinuxDeviceType::B, Some(8), Some(0)),
+            ]
+        );
+        assert_eq!(probes[2].to_string(), "c 10:*");
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_consistent() -> Result<()> {
+        let emulator = emulator()?;
+        let program = attached(&emulator)?;
+        let probes = probes(&emulator.rules);
+
+        let verdicts = verify(&[program], &emulator, &probes)?;
+
+        assert!(verdicts.iter().all(|v| v.is_consistent()));
+        let allowed: Vec<(String, &str)> = verdicts
+            .iter()
+            .filter(|v| !v.allowed.is_empty())
+            .map(|v| (v.probe.to_string(), v.allowed.as_str()))
+            .collect();
+        assert_eq!(
+            allowed,
+            vec![
+                ("c *:20".to_owned(), "rw"),
+                ("c 10:20".to_owned(), "w"),
+                ("b 8:0".to_owned(), "rwm"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_detects_mismatch() -> Result<()> {
+        let emulator = emulator()?;
+        let probes = probes(&emulator.rules);
+        let allow_all = attached(&Emulator::with_default_allow(true))?;
+
+        let verdicts = verify(&[allow_all], &emulator, &probes)?;
+
+        let inconsistent = verdicts.iter().find(|v| !v.is_consistent()).unwrap();
+        assert_eq!(inconsistent.probe, probe(LinuxDeviceType::C, None, None));
+        assert_eq!(inconsistent.allowed, "rwm");
+        assert_eq!(inconsistent.expected, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_combines_programs() -> Result<()> {
+        let emulator = emulator()?;
+        let probes = probes(&emulator.rules);
+        let deny_all = attached(&Emulator::with_default_allow(false))?;
+
+        let verdicts = verify(&[attached(&emulator)?, deny_all], &emulator, &probes)?;
+        assert!(verdicts.iter().all(|v| v.allowed.is_empty()));
+
+        let verdicts = verify(&[], &emulator, &probes)?;
+        assert!(verdicts.iter().all(|v| v.allowed == "rwm"));
+        Ok(())
+    }
+}
@@ -4,7 +4,7 @@ use std::path::Component::RootDir;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
-use nix::unistd::Pid;
+use nix::unistd::{Pid, chown};
 
 use super::controller::Controller;
 use super::controller_type::{
@@ -22,12 +22,17 @@ use super::pids::Pids;
 use super::unified::{Unified, V2UnifiedError};
 use super::util::{self, CGROUP_SUBTREE_CONTROL, V2UtilError};
 use crate::common::{
-    self, AnyCgroupManager, CGROUP_PROCS, CgroupManager, ControllerOpt, FreezerState,
-    JoinSafelyError, PathBufExt, WrapIoResult, WrappedIoError,
+    self, AnyCgroupManager, CGROUP_PROCS, CgroupDelegation, CgroupManager, ControllerOpt,
+    FreezerState, JoinSafelyError, PathBufExt, WrapIoResult, WrappedIoError,
 };
 use crate::stats::{PidStatsError, Stats, StatsProvider};
 
 pub const CGROUP_KILL: &str = "cgroup.kill";
+pub const CGROUP_THREADS: &str = "cgroup.threads";
+/// Leaf below a delegated container cgroup which the container process is placed in
+pub const DELEGATED_INIT_CGROUP: &str = "init";
+/// Lists the interface files of a cgroup which are handed to the delegatee
+const CGROUP_DELEGATE_FILES: &str = "/sys/kernel/cgroup/delegate";
 
 #[derive(thiserror::Error, Debug)]
 pub enum V2ManagerError {
@@ -77,6 +82,7 @@ pub struct Manager {
     root_path: PathBuf,
     cgroup_path: PathBuf,
     full_path: PathBuf,
+    delegation: Option<CgroupDelegation>,
 }
 
 impl Manager {
@@ -89,9 +95,16 @@ impl Manager {
             root_path,
             cgroup_path,
             full_path,
+            delegation: None,
         })
     }
 
+    /// Delegates the cgroup to the container when a process is added to it
+    pub fn with_delegation(mut self, delegation: CgroupDelegation) -> Self {
+        self.delegation = Some(delegation);
+        self
+    }
+
     /// Creates a unified cgroup at `self.full_path` and attaches a process to it
     fn create_unified_cgroup(&self, pid: Pid) -> Result<(), V2ManagerError> {
         let controllers: Vec<String> = util::get_available_controllers(&self.root_path)?
@@ -128,6 +141,79 @@ impl Manager {
         Ok(())
     }
 
+    #[cfg(feature = "systemd")]
+    pub(crate) fn is_delegated(&self) -> bool {
+        self.delegation.is_some()
+    }
+
+    /// Moves a process from the cgroup into its `init` leaf, enables all controllers
+    /// available in the cgroup for the leaf and hands the leaf to the owner of the
+    /// delegation. The cgroup can't have processes of its own after this.
+    pub(crate) fn delegate(&self, pid: Pid) -> Result<(), V2ManagerError> {
+        let Some(delegation) = &self.delegation else {
+            return Ok(());
+        };
+
+        let init_path = self.full_path.join(DELEGATED_INIT_CGROUP);
+        if !init_path.exists() {
+            fs::create_dir(&init_path).wrap_create_dir(&init_path)?;
+        }
+        common::write_cgroup_file(init_path.join(CGROUP_PROCS), pid)?;
+
+        let controllers: Vec<String> = util::get_available_controllers(&self.full_path)?
+            .iter()
+            .map(|c| format!("+{c}"))
+            .collect();
+        Self::write_controllers(&self.full_path, &controllers)?;
+
+        if let Some((uid, gid)) = delegation.owner {
+            tracing::debug!(?init_path, ?uid, ?gid, "delegate cgroup");
+            chown(&init_path, Some(uid), Some(gid))
+                .map_err(std::io::Error::from)
+                .wrap_other(&init_path)?;
+            for file in Self::delegate_files()? {
+                let path = init_path.join(&file);
+                if path.exists() {
+                    chown(&path, Some(uid), Some(gid))
+                        .map_err(std::io::Error::from)
+                        .wrap_other(&path)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the interface files to delegate from the kernel, which only lists them
+    /// since Linux 4.15
+    fn delegate_files() -> Result<Vec<String>, WrappedIoError> {
+        if !Path::new(CGROUP_DELEGATE_FILES).exists() {
+            return Ok(vec![
+                CGROUP_PROCS.to_owned(),
+                CGROUP_THREADS.to_owned(),
+                CGROUP_SUBTREE_CONTROL.to_owned(),
+            ]);
+        }
+        Ok(common::read_cgroup_file(CGROUP_DELEGATE_FILES)?
+            .lines()
+            .map(|file| file.trim().to_owned())
+            .filter(|file| !file.is_empty())
+            .collect())
+    }
+
+    /// Removes the cgroups below `path` bottom up, as a cgroup can only be removed
+    /// once it has no children
+    fn remove_children(path: &Path) -> Result<(), WrappedIoError> {
+        for entry in fs::read_dir(path).wrap_read(path)? {
+            let child = entry.wrap_read(path)?.path();
+            if child.is_dir() {
+                Self::remove_children(&child)?;
+                common::delete_with_retry(&child, 4, Duration::from_millis(100))?;
+            }
+        }
+        Ok(())
+    }
+
     /// Writes a list of controllers to the `{path}/cgroup.subtree_control` file
     fn write_controllers(path: &Path, controllers: &[String]) -> Result<(), WrappedIoError> {
         for controller in controllers {
@@ -146,12 +232,12 @@ impl CgroupManager for Manager {
     type Error = V2ManagerError;
 
     fn add_task(&self, pid: Pid) -> Result<(), Self::Error> {
-        if self.full_path.exists() {
+        if !self.full_path.exists() {
+            self.create_unified_cgroup(pid)?;
+        } else if self.delegation.is_none() {
             common::write_cgroup_file(self.full_path.join(CGROUP_PROCS), pid)?;
-            return Ok(());
         }
-        self.create_unified_cgroup(pid)?;
-        Ok(())
+        self.delegate(pid)
     }
 
     fn apply(&self, controller_opt: &ControllerOpt) -> Result<(), Self::Error> {
@@ -201,6 +287,7 @@ impl CgroupManager for Manager {
                 }
             }
 
+            Self::remove_children(&self.full_path)?;
             common::delete_with_retry(&self.full_path, 4, Duration::from_millis(100))?;
         }
 
@@ -240,3 +327,59 @@ impl CgroupManager for Manager {
         Ok(common::get_all_pids(&self.full_path)?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::MetadataExt;
+
+    use nix::unistd::{getgid, getuid};
+
+    use super::*;
+    use crate::test::set_fixture;
+
+    #[test]
+    fn test_add_task_delegated() {
+        let tmp = tempfile::tempdir().unwrap();
+        let container = tmp.path().join("youki");
+        let init = container.join(DELEGATED_INIT_CGROUP);
+        fs::create_dir_all(&init).unwrap();
+        set_fixture(&container, util::CGROUP_CONTROLLERS, "memory").unwrap();
+        set_fixture(&container, CGROUP_SUBTREE_CONTROL, "").unwrap();
+        for file in [CGROUP_PROCS, CGROUP_THREADS, CGROUP_SUBTREE_CONTROL] {
+            set_fixture(&init, file, "").unwrap();
+        }
+
+        let manager = Manager::new(tmp.path().to_path_buf(), PathBuf::from("youki"))
+            .unwrap()
+            .with_delegation(CgroupDelegation {
+                owner: Some((getuid(), getgid())),
+            });
+        manager.add_task(Pid::from_raw(1234)).unwrap();
+
+        assert_eq!(fs::read_to_string(init.join(CGROUP_PROCS)).unwrap(), "1234");
+        assert_eq!(
+            fs::read_to_string(container.join(CGROUP_SUBTREE_CONTROL)).unwrap(),
+            "+memory"
+        );
+        let metadata = fs::metadata(init.join(CGROUP_PROCS)).unwrap();
+        assert_eq!(metadata.uid(), getuid().as_raw());
+        assert_eq!(metadata.gid(), getgid().as_raw());
+    }
+
+    #[test]
+    fn test_add_task_not_delegated() {
+        let tmp = tempfile::tempdir().unwrap();
+        let container = tmp.path().join("youki");
+        fs::create_dir(&container).unwrap();
+        set_fixture(&container, CGROUP_PROCS, "").unwrap();
+
+        let manager = Manager::new(tmp.path().to_path_buf(), PathBuf::from("youki")).unwrap();
+        manager.add_task(Pid::from_raw(1234)).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(container.join(CGROUP_PROCS)).unwrap(),
+            "1234"
+        );
+        assert!(!container.join(DELEGATED_INIT_CGROUP).exists());
+    }
+}
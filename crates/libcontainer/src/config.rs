@@ -2,6 +2,7 @@ use std::fs;
 use std::io::{BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
 
+use nix::sys::personality::Persona;
 use oci_spec::runtime::{Hooks, Spec};
 use serde::{Deserialize, Serialize};
 
@@ -12,6 +13,35 @@ pub enum PersonalityDomain {
     Linux32 = 0x0008,
 }
 
+// The flags of personality(2), see include/uapi/linux/personality.h. Raw values
+// are used because nix doesn't define all of them for musl.
+const PERSONALITY_FLAGS: &[(&str, i32)] = &[
+    ("UNAME26", 0x0020000),
+    ("ADDR_NO_RANDOMIZE", 0x0040000),
+    ("FDPIC_FUNCPTRS", 0x0080000),
+    ("MMAP_PAGE_ZERO", 0x0100000),
+    ("ADDR_COMPAT_LAYOUT", 0x0200000),
+    ("READ_IMPLIES_EXEC", 0x0400000),
+    ("ADDR_LIMIT_32BIT", 0x0800000),
+    ("SHORT_INODE", 0x1000000),
+    ("WHOLE_SECONDS", 0x2000000),
+    ("STICKY_TIMEOUTS", 0x4000000),
+    ("ADDR_LIMIT_3GB", 0x8000000),
+];
+
+/// Combines the flags of `linux.personality.flags`, which are named like the
+/// kernel constants. Returns the first flag that isn't known as the error.
+pub fn personality_flags<S: AsRef<str>>(flags: &[S]) -> std::result::Result<Persona, String> {
+    flags.iter().try_fold(Persona::empty(), |persona, flag| {
+        let flag = flag.as_ref();
+        PERSONALITY_FLAGS
+            .iter()
+            .find(|(name, _)| *name == flag)
+            .map(|(_, bits)| persona | Persona::from_bits_retain(*bits))
+            .ok_or_else(|| flag.to_owned())
+    })
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
     #[error("failed to save config")]
@@ -107,6 +137,23 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_personality_flags() {
+        assert_eq!(personality_flags::<&str>(&[]), Ok(Persona::empty()));
+        assert_eq!(
+            personality_flags(&["ADDR_NO_RANDOMIZE", "UNAME26"]).map(|p| p.bits()),
+            Ok(0x0060000)
+        );
+        assert_eq!(
+            personality_flags(&["READ_IMPLIES_EXEC"]),
+            Ok(Persona::READ_IMPLIES_EXEC)
+        );
+        assert_eq!(
+            personality_flags(&["ADDR_LIMIT_32BIT", "NO_SUCH_FLAG"]),
+            Err("NO_SUCH_FLAG".to_owned())
+        );
+    }
+
     #[test]
     fn test_config_from_spec() -> Result<()> {
         let container_id = "sample";
@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use super::init_builder::InitContainerBuilder;
 use super::tenant_builder::TenantContainerBuilder;
 use crate::error::{ErrInvalidID, LibcontainerError};
+use crate::log_sink::LogSink;
 use crate::syscall::syscall::SyscallType;
 use crate::utils::PathBufExt;
 use crate::workload::{self, Executor};
@@ -31,6 +32,8 @@ pub struct ContainerBuilder {
     pub stdout: Option<OwnedFd>,
     // RawFd set to stderr of the container init process.
     pub stderr: Option<OwnedFd>,
+    /// Log file the stdout and stderr of the container process are relayed to
+    pub(super) log_sink: Option<LogSink>,
 }
 
 /// Builder that can be used to configure the common properties of
@@ -80,6 +83,7 @@ impl ContainerBuilder {
             stdin: None,
             stdout: None,
             stderr: None,
+            log_sink: None,
         }
     }
 
@@ -336,6 +340,44 @@ impl ContainerBuilder {
         self.stderr = Some(stderr.into());
         self
     }
+
+    /// Sets a log file the stdout and stderr of the container are written to,
+    /// by a relay process which is started when the container is built. It
+    /// takes precedence over with_stdout and with_stderr.
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use libcontainer::container::builder::ContainerBuilder;
+    /// # use libcontainer::log_sink::{LogFormat, LogSink};
+    /// # use libcontainer::syscall::syscall::SyscallType;
+    ///
+    /// ContainerBuilder::new(
+    ///     "74f1a4cb3801".to_owned(),
+    ///     SyscallType::default(),
+    /// )
+    /// .with_log_sink(Some(
+    ///     LogSink::new("/var/log/containers/74f1a4cb3801.log", LogFormat::Cri)
+    ///         .with_max_size(Some(10 * 1024 * 1024)),
+    /// ));
+    /// ```
+    pub fn with_log_sink(mut self, log_sink: Option<LogSink>) -> Self {
+        self.log_sink = log_sink;
+        self
+    }
+
+    /// Returns the stdout and stderr of the container process, which are the
+    /// pipes of a newly started log relay if a log sink is set
+    pub(super) fn take_output(
+        &mut self,
+    ) -> Result<(Option<OwnedFd>, Option<OwnedFd>), LibcontainerError> {
+        match &self.log_sink {
+            Some(log_sink) => {
+                let (stdout, stderr) = log_sink.spawn()?;
+                Ok((Some(stdout), Some(stderr)))
+            }
+            None => Ok((self.stdout.take(), self.stderr.take())),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -4,15 +4,16 @@ use std::os::fd::{AsRawFd, OwnedFd};
 use std::path::PathBuf;
 use std::rc::Rc;
 
-use libcgroups::common::CgroupManager;
+use libcgroups::common::{CgroupDelegation, CgroupManager};
 use nix::unistd::Pid;
 use oci_spec::runtime::Spec;
 
 use super::{Container, ContainerStatus};
 use crate::error::{CreateContainerError, LibcontainerError, MissingSpecError};
+use crate::landlock::LandlockRuleset;
 use crate::notify_socket::NotifyListener;
 use crate::process::args::{ContainerArgs, ContainerType};
-use crate::process::intel_rdt::delete_resctrl_subdirectory;
+use crate::process::intel_rdt::{delete_monitoring_group, delete_resctrl_subdirectory};
 use crate::process::{self};
 use crate::syscall::syscall::SyscallType;
 use crate::user_ns::UserNamespaceConfig;
@@ -59,6 +60,10 @@ pub(super) struct ContainerBuilderImpl {
     pub stderr: Option<OwnedFd>,
     // Indicate if the init process should be a sibling of the main process.
     pub as_sibling: bool,
+    /// Landlock ruleset the container process is restricted with
+    pub landlock: Option<LandlockRuleset>,
+    /// Cgroup below the container cgroup which a tenant process is moved to
+    pub sub_cgroup: Option<PathBuf>,
 }
 
 impl ContainerBuilderImpl {
@@ -88,8 +93,17 @@ impl ContainerBuilderImpl {
         let cgroups_path = utils::get_cgroup_path(linux.cgroups_path(), &self.container_id);
         let cgroup_config = libcgroups::common::CgroupConfig {
             cgroup_path: cgroups_path,
-            systemd_cgroup: self.use_systemd || self.user_ns_config.is_some(),
+            systemd_cgroup: self.use_systemd,
             container_name: self.container_id.to_owned(),
+            systemd_properties: utils::get_systemd_properties(self.spec.annotations()),
+            delegation: utils::is_cgroup_delegated(self.spec.annotations()).then(|| {
+                CgroupDelegation {
+                    owner: self
+                        .user_ns_config
+                        .as_ref()
+                        .and_then(|config| config.root_owner()),
+                }
+            }),
         };
         let process = self
             .spec
@@ -166,9 +180,11 @@ impl ContainerBuilderImpl {
             stderr: self.stderr.as_ref().map(|x| x.as_raw_fd()),
             as_sibling: self.as_sibling,
             pid_file: self.pid_file.to_owned(),
+            landlock: self.landlock.to_owned(),
+            sub_cgroup: self.sub_cgroup.to_owned(),
         };
 
-        let (init_pid, need_to_clean_up_intel_rdt_dir) =
+        let (init_pid, intel_rdt_setup, cgroup_manager) =
             process::container_main_process::container_main_process(&container_args).map_err(
                 |err| {
                     tracing::error!("failed to run container process {}", err);
@@ -177,12 +193,25 @@ impl ContainerBuilderImpl {
             )?;
 
         if let Some(container) = &mut self.container {
+            let init_process_start = procfs::process::Process::new(init_pid.as_raw())
+                .and_then(|process| process.stat())
+                .map(|stat| stat.starttime)
+                .map_err(|err| {
+                    tracing::warn!(
+                        ?err,
+                        "failed to read the start time of the container process"
+                    )
+                })
+                .ok();
             // update status and pid of the container process
             container
                 .set_status(ContainerStatus::Created)
                 .set_creator(nix::unistd::geteuid().as_raw())
                 .set_pid(init_pid.as_raw())
-                .set_clean_up_intel_rdt_directory(need_to_clean_up_intel_rdt_dir)
+                .set_init_process_start(init_process_start)
+                .set_clean_up_intel_rdt_directory(intel_rdt_setup.need_to_delete_directory)
+                .set_intel_rdt_monitoring(intel_rdt_setup.monitoring)
+                .set_cgroup_manager(cgroup_manager)
                 .save()?;
         }
 
@@ -195,8 +224,10 @@ impl ContainerBuilderImpl {
         let cmanager =
             libcgroups::common::create_cgroup_manager(libcgroups::common::CgroupConfig {
                 cgroup_path: cgroups_path,
-                systemd_cgroup: self.use_systemd || self.user_ns_config.is_some(),
+                systemd_cgroup: self.use_systemd,
                 container_name: self.container_id.to_string(),
+                systemd_properties: Default::default(),
+                delegation: None,
             })?;
 
         let mut errors = Vec::new();
@@ -207,6 +238,13 @@ impl ContainerBuilderImpl {
         }
 
         if let Some(container) = &self.container {
+            if let Some(monitoring) = container.intel_rdt_monitoring() {
+                if let Err(e) = delete_monitoring_group(monitoring) {
+                    tracing::error!(id = ?container.id(), error = ?e, "failed to delete resctrl monitoring group");
+                    errors.push(e.to_string());
+                }
+            }
+
             if let Some(true) = container.clean_up_intel_rdt_subdirectory() {
                 if let Err(e) = delete_resctrl_subdirectory(container.id()) {
                     tracing::error!(id = ?container.id(), error = ?e, "failed to delete resctrl subdirectory");
@@ -214,6 +252,27 @@ impl ContainerBuilderImpl {
                 }
             }
 
+            if let Some(bridge_network) = container.bridge_network() {
+                if let Err(e) = bridge_network.cleanup() {
+                    tracing::error!(device = ?bridge_network.host_interface, error = ?e, "failed to delete network device");
+                    errors.push(e.to_string());
+                }
+            }
+
+            if let Some(rootless_network) = container.rootless_network() {
+                if let Err(e) = rootless_network.stop(&container.root) {
+                    tracing::error!(mode = %rootless_network.mode, error = ?e, "failed to stop rootless network");
+                    errors.push(e.to_string());
+                }
+            }
+
+            if let Some(overlay_rootfs) = container.overlay_rootfs() {
+                if let Err(e) = overlay_rootfs.cleanup() {
+                    tracing::error!(error = ?e, "failed to clean up overlay rootfs");
+                    errors.push(e.to_string());
+                }
+            }
+
             if container.root.exists() {
                 if let Err(e) = fs::remove_dir_all(&container.root) {
                     tracing::error!(container_root = ?container.root, error = ?e, "failed to delete container root");
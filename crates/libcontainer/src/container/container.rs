@@ -4,12 +4,19 @@ use std::fs;
 use std::path::{Path, PathBuf};
 
 use chrono::{DateTime, Utc};
+use libcgroups::common::{AnyCgroupManager, CgroupConfig, CgroupManagerInfo};
 use nix::unistd::Pid;
 use procfs::process::Process;
 
 use crate::config::YoukiConfig;
 use crate::container::{ContainerStatus, State};
 use crate::error::LibcontainerError;
+use crate::landlock::LandlockRuleset;
+use crate::network::bridge::BridgeNetwork;
+use crate::network::rootless::RootlessNetwork;
+use crate::process::intel_rdt::IntelRdtMonitoring;
+use crate::rootfs::OverlayRootfs;
+use crate::sd_notify::{NotifyProxy, SdNotify};
 use crate::syscall::syscall::create_syscall;
 
 /// Structure representing the container data
@@ -82,6 +89,10 @@ impl Container {
         self.state.status.can_resume()
     }
 
+    pub fn can_update(&self) -> bool {
+        self.state.status.can_update()
+    }
+
     pub fn bundle(&self) -> &PathBuf {
         &self.state.bundle
     }
@@ -100,6 +111,18 @@ impl Container {
         self
     }
 
+    pub fn init_process_start(&self) -> Option<u64> {
+        self.state.init_process_start
+    }
+
+    /// Records the start time of the container process, which is read from
+    /// procfs, so that the process can be told apart from a later one which
+    /// reused its pid
+    pub fn set_init_process_start(&mut self, start: Option<u64>) -> &mut Self {
+        self.state.init_process_start = start;
+        self
+    }
+
     pub fn created(&self) -> Option<DateTime<Utc>> {
         self.state.created
     }
@@ -139,6 +162,100 @@ impl Container {
         self.state.clean_up_intel_rdt_subdirectory
     }
 
+    pub fn intel_rdt_monitoring(&self) -> Option<&IntelRdtMonitoring> {
+        self.state.intel_rdt_monitoring.as_ref()
+    }
+
+    pub fn set_intel_rdt_monitoring(
+        &mut self,
+        monitoring: Option<IntelRdtMonitoring>,
+    ) -> &mut Self {
+        self.state.intel_rdt_monitoring = monitoring;
+        self
+    }
+
+    pub fn sd_notify(&self) -> Option<&SdNotify> {
+        self.state.sd_notify.as_ref()
+    }
+
+    pub fn set_sd_notify(&mut self, sd_notify: Option<SdNotify>) -> &mut Self {
+        self.state.sd_notify = sd_notify;
+        self
+    }
+
+    pub fn bridge_network(&self) -> Option<&BridgeNetwork> {
+        self.state.bridge_network.as_ref()
+    }
+
+    pub fn set_bridge_network(&mut self, bridge_network: Option<BridgeNetwork>) -> &mut Self {
+        self.state.bridge_network = bridge_network;
+        self
+    }
+
+    pub fn rootless_network(&self) -> Option<&RootlessNetwork> {
+        self.state.rootless_network.as_ref()
+    }
+
+    pub fn set_rootless_network(&mut self, rootless_network: Option<RootlessNetwork>) -> &mut Self {
+        self.state.rootless_network = rootless_network;
+        self
+    }
+
+    pub fn overlay_rootfs(&self) -> Option<&OverlayRootfs> {
+        self.state.overlay_rootfs.as_ref()
+    }
+
+    pub fn set_overlay_rootfs(&mut self, overlay_rootfs: Option<OverlayRootfs>) -> &mut Self {
+        self.state.overlay_rootfs = overlay_rootfs;
+        self
+    }
+
+    pub fn landlock(&self) -> Option<&LandlockRuleset> {
+        self.state.landlock.as_ref()
+    }
+
+    pub fn set_landlock(&mut self, landlock: Option<LandlockRuleset>) -> &mut Self {
+        self.state.landlock = landlock;
+        self
+    }
+
+    pub fn cgroup_manager_info(&self) -> Option<&CgroupManagerInfo> {
+        self.state.cgroup_manager.as_ref()
+    }
+
+    pub fn set_cgroup_manager(&mut self, cgroup_manager: Option<CgroupManagerInfo>) -> &mut Self {
+        self.state.cgroup_manager = cgroup_manager;
+        self
+    }
+
+    /// Creates the manager of the cgroups of the container from the information
+    /// saved when it was created. Containers created by older versions don't have
+    /// this information, so their manager is worked out from the spec instead.
+    pub fn cgroup_manager(&self) -> Result<AnyCgroupManager, LibcontainerError> {
+        if let Some(info) = self.cgroup_manager_info() {
+            return Ok(libcgroups::common::create_cgroup_manager_from_info(info)?);
+        }
+
+        Ok(libcgroups::common::create_cgroup_manager(CgroupConfig {
+            cgroup_path: self.spec()?.cgroup_path,
+            systemd_cgroup: self.systemd(),
+            container_name: self.id().to_string(),
+            systemd_properties: Default::default(),
+            delegation: None,
+        })?)
+    }
+
+    /// Creates the proxy which relays the notifications of the container to the
+    /// `NOTIFY_SOCKET` of the host. It has to be created before the container is
+    /// started and is None if the container was created without one.
+    pub fn notify_proxy(&self) -> Result<Option<NotifyProxy>, LibcontainerError> {
+        let proxy = self
+            .sd_notify()
+            .map(|sd_notify| NotifyProxy::new(sd_notify, &self.root))
+            .transpose()?;
+        Ok(proxy)
+    }
+
     pub fn status(&self) -> ContainerStatus {
         self.state.status
     }
@@ -228,6 +345,7 @@ pub struct CheckpointOptions {
 #[cfg(test)]
 mod tests {
     use anyhow::{Context, Result};
+    use libcgroups::common::{CgroupManagerKind, CgroupSetup};
     use serial_test::serial;
 
     use super::*;
@@ -300,6 +418,38 @@ mod tests {
         assert_eq!(container.creator(), Some(OsString::from("youki")));
     }
 
+    #[test]
+    fn test_get_set_cgroup_manager() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let mut container = Container::new(
+            "container_id",
+            ContainerStatus::Created,
+            None,
+            &PathBuf::from("."),
+            tmp_dir.path(),
+        )?;
+        assert_eq!(container.cgroup_manager_info(), None);
+
+        let info = CgroupManagerInfo {
+            setup: CgroupSetup::Unified,
+            kind: CgroupManagerKind::Cgroupfs,
+            root_path: PathBuf::from("/sys/fs/cgroup"),
+            cgroup_path: PathBuf::from("/youki/container_id"),
+            systemd_cgroups_path: None,
+            systemd_system: false,
+        };
+        container.set_cgroup_manager(Some(info.clone())).save()?;
+
+        let container = Container::load(tmp_dir.path().to_path_buf())?;
+        assert_eq!(container.cgroup_manager_info(), Some(&info));
+        assert!(matches!(
+            container.cgroup_manager()?,
+            AnyCgroupManager::V2(_)
+        ));
+
+        Ok(())
+    }
+
     #[test]
     #[serial]
     fn test_refresh_load_save_state() -> Result<()> {
@@ -0,0 +1,247 @@
+//! Async variants of the container lifecycle operations for callers running on
+//! tokio. The blocking operations run on the blocking thread pool of tokio,
+//! while waiting for the container process is driven by its pidfd.
+use std::future::Future;
+use std::pin::Pin;
+use std::process::ExitStatus;
+use std::task::{Context, Poll, ready};
+use std::time::Duration;
+
+use futures_core::Stream;
+use nix::errno::Errno;
+use tokio::io::Interest;
+use tokio::io::unix::AsyncFd;
+use tokio::task::JoinHandle;
+use tokio::time::Interval;
+
+use super::{Container, ContainerStats, ContainerStatus};
+use crate::error::LibcontainerError;
+use crate::process::pidfd::PidFd;
+use crate::signal::Signal;
+
+fn join_error(err: tokio::task::JoinError) -> LibcontainerError {
+    LibcontainerError::Other(format!("blocking container operation failed: {err}"))
+}
+
+impl Container {
+    /// Async variant of [`Container::start`]
+    pub async fn start_async(&mut self) -> Result<(), LibcontainerError> {
+        self.run_blocking(Container::start).await
+    }
+
+    /// Async variant of [`Container::kill`]
+    pub async fn kill_async<S: Into<Signal>>(
+        &mut self,
+        signal: S,
+        all: bool,
+    ) -> Result<(), LibcontainerError> {
+        let signal = signal.into();
+        self.run_blocking(move |container| container.kill(signal, all))
+            .await
+    }
+
+    /// Async variant of [`Container::delete`]
+    pub async fn delete_async(&mut self, force: bool) -> Result<(), LibcontainerError> {
+        self.run_blocking(move |container| container.delete(force))
+            .await
+    }
+
+    /// Async variant of [`Container::pause`]
+    pub async fn pause_async(&mut self) -> Result<(), LibcontainerError> {
+        self.run_blocking(Container::pause).await
+    }
+
+    /// Async variant of [`Container::resume`]
+    pub async fn resume_async(&mut self) -> Result<(), LibcontainerError> {
+        self.run_blocking(Container::resume).await
+    }
+
+    /// Waits until the container process exited. Its exit status is only
+    /// known if the container process is a child of the caller, which it is
+    /// when the container was built by the same process.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use libcontainer::container::builder::ContainerBuilder;
+    /// use libcontainer::syscall::syscall::SyscallType;
+    ///
+    /// # async fn run() -> anyhow::Result<()> {
+    /// let mut container = ContainerBuilder::new(
+    ///     "74f1a4cb3801".to_owned(),
+    ///     SyscallType::default(),
+    /// )
+    /// .as_init("/var/run/docker/bundle")
+    /// .build()?;
+    ///
+    /// container.start_async().await?;
+    /// let status = container.wait_async().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn wait_async(&self) -> Result<Option<ExitStatus>, LibcontainerError> {
+        let Some(pid) = self.wait_pid()? else {
+            return Ok(None);
+        };
+        let pidfd = match PidFd::open(pid) {
+            Ok(Some(pidfd)) => pidfd,
+            Ok(None) => return Ok(None),
+            // without pidfds, the process is polled by the blocking wait
+            Err(Errno::ENOSYS) => {
+                let container = self.clone();
+                return tokio::task::spawn_blocking(move || container.wait(None))
+                    .await
+                    .map_err(join_error)?;
+            }
+            Err(err) => return Err(LibcontainerError::OtherSyscall(err)),
+        };
+        let pidfd = AsyncFd::with_interest(pidfd, Interest::READABLE)
+            .map_err(LibcontainerError::OtherIO)?;
+        let _ready = pidfd.readable().await.map_err(LibcontainerError::OtherIO)?;
+        pidfd
+            .get_ref()
+            .reap()
+            .map_err(LibcontainerError::OtherSyscall)
+    }
+
+    /// Returns a stream of the statistics of the container, which are
+    /// taken every interval, starting right away. The stream ends once the
+    /// container stopped. It must be called from within a tokio runtime.
+    pub fn events_stream(&self, interval: Duration) -> EventStream {
+        EventStream {
+            container: self.clone(),
+            interval: tokio::time::interval(interval),
+            pending: None,
+            done: false,
+        }
+    }
+
+    /// Runs a blocking operation on a copy of the container, which replaces
+    /// the container once the operation finished. If the future is dropped
+    /// before, the operation still completes but the container isn't updated.
+    async fn run_blocking<T, F>(&mut self, operation: F) -> Result<T, LibcontainerError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Container) -> Result<T, LibcontainerError> + Send + 'static,
+    {
+        let mut container = self.clone();
+        let (container, result) = tokio::task::spawn_blocking(move || {
+            let result = operation(&mut container);
+            (container, result)
+        })
+        .await
+        .map_err(join_error)?;
+        *self = container;
+        result
+    }
+
+    fn stats_while_alive(&mut self) -> Result<Option<ContainerStats>, LibcontainerError> {
+        self.refresh_status()?;
+        if self.status() == ContainerStatus::Stopped {
+            return Ok(None);
+        }
+        Ok(Some(self.stats()?))
+    }
+}
+
+/// Statistics of a container, see [`Container::events_stream`]
+pub struct EventStream {
+    container: Container,
+    interval: Interval,
+    pending: Option<JoinHandle<Result<Option<ContainerStats>, LibcontainerError>>>,
+    done: bool,
+}
+
+impl Stream for EventStream {
+    type Item = Result<ContainerStats, LibcontainerError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        let pending = match &mut this.pending {
+            Some(pending) => pending,
+            None => {
+                ready!(this.interval.poll_tick(cx));
+                let mut container = this.container.clone();
+                this.pending.insert(tokio::task::spawn_blocking(move || {
+                    container.stats_while_alive()
+                }))
+            }
+        };
+        let result = ready!(Pin::new(pending).poll(cx));
+        this.pending = None;
+
+        match result.map_err(join_error).and_then(|stats| stats) {
+            Ok(Some(stats)) => Poll::Ready(Some(Ok(stats))),
+            Ok(None) => {
+                this.done = true;
+                Poll::Ready(None)
+            }
+            Err(err) => {
+                this.done = true;
+                Poll::Ready(Some(Err(err)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+    use std::process::Command;
+
+    use anyhow::Result;
+
+    use super::*;
+    use crate::container::container_wait::container_of;
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn test_wait_async_exit_status() -> Result<()> {
+        let child = Command::new("sh")
+            .args(["-c", "sleep 0.1; exit 3"])
+            .spawn()?;
+        let status = block_on(container_of(child.id()).wait_async())?;
+        assert_eq!(status.and_then(|s| s.code()), Some(3));
+
+        let child = Command::new("sleep").arg("10").spawn()?;
+        let container = container_of(child.id());
+        nix::sys::signal::kill(container.pid().unwrap(), nix::sys::signal::SIGKILL)?;
+        let status = block_on(container.wait_async())?;
+        assert_eq!(status.and_then(|s| s.signal()), Some(libc::SIGKILL));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_async_without_pid() {
+        let container = Container::default();
+        assert!(matches!(
+            block_on(container.wait_async()),
+            Err(LibcontainerError::IncorrectStatus(_))
+        ));
+    }
+
+    #[test]
+    fn test_events_stream_ends_when_stopped() -> Result<()> {
+        let mut child = Command::new("true").spawn()?;
+        let container = container_of(child.id());
+        child.wait()?;
+
+        let event = block_on(async {
+            let mut stream = container.events_stream(Duration::from_millis(10));
+            std::future::poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await
+        });
+        assert!(event.is_none());
+        Ok(())
+    }
+}
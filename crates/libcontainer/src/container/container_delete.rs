@@ -8,7 +8,7 @@ use super::{Container, ContainerStatus};
 use crate::config::YoukiConfig;
 use crate::error::LibcontainerError;
 use crate::hooks;
-use crate::process::intel_rdt::delete_resctrl_subdirectory;
+use crate::process::intel_rdt::{delete_monitoring_group, delete_resctrl_subdirectory};
 
 impl Container {
     /// Deletes the container
@@ -69,6 +69,14 @@ impl Container {
         // Once reached here, the container is verified that it can be deleted.
         debug_assert!(self.status().can_delete());
 
+        if let Some(monitoring) = self.intel_rdt_monitoring() {
+            if let Err(err) = delete_monitoring_group(monitoring) {
+                tracing::warn!(
+                    "failed to delete resctrl monitoring group due to: {err:?}, continue to delete"
+                );
+            }
+        }
+
         if let Some(true) = &self.clean_up_intel_rdt_subdirectory() {
             if let Err(err) = delete_resctrl_subdirectory(self.id()) {
                 tracing::warn!(
@@ -77,6 +85,32 @@ impl Container {
             }
         }
 
+        if let Some(bridge_network) = self.bridge_network() {
+            if let Err(err) = bridge_network.cleanup() {
+                tracing::warn!(
+                    "failed to delete network device {} due to: {err:?}, continue to delete",
+                    bridge_network.host_interface
+                );
+            }
+        }
+
+        if let Some(rootless_network) = self.rootless_network() {
+            if let Err(err) = rootless_network.stop(&self.root) {
+                tracing::warn!(
+                    "failed to stop {} due to: {err:?}, continue to delete",
+                    rootless_network.mode
+                );
+            }
+        }
+
+        if let Some(overlay_rootfs) = self.overlay_rootfs() {
+            if let Err(err) = overlay_rootfs.cleanup() {
+                tracing::warn!(
+                    "failed to clean up the overlay work directory due to: {err:?}, continue to delete"
+                );
+            }
+        }
+
         if self.root.exists() {
             match YoukiConfig::load(&self.root) {
                 Ok(config) => {
@@ -85,13 +119,7 @@ impl Container {
                     // remove the cgroup created for the container
                     // check https://man7.org/linux/man-pages/man7/cgroups.7.html
                     // creating and removing cgroups section for more information on cgroups
-                    let cmanager = libcgroups::common::create_cgroup_manager(
-                        libcgroups::common::CgroupConfig {
-                            cgroup_path: config.cgroup_path.to_owned(),
-                            systemd_cgroup: self.systemd(),
-                            container_name: self.id().to_string(),
-                        },
-                    )?;
+                    let cmanager = self.cgroup_manager()?;
                     cmanager.remove().map_err(|err| {
                         tracing::error!(cgroup_path = ?config.cgroup_path, "failed to remove cgroup due to: {err:?}");
                         err
@@ -2,9 +2,23 @@ use std::thread;
 use std::time::Duration;
 
 use libcgroups::common::CgroupManager;
+use libcgroups::stats::Stats;
+use serde::Serialize;
 
 use super::{Container, ContainerStatus};
 use crate::error::LibcontainerError;
+use crate::process::intel_rdt::{self, IntelRdtStats};
+
+/// Reports the statistics of a container
+#[derive(Debug, Serialize)]
+pub struct ContainerStats {
+    /// Statistics of the cgroup of the container
+    #[serde(flatten)]
+    pub cgroup: Stats,
+    /// Intel RDT monitoring data, if CMT or MBM is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intel_rdt: Option<IntelRdtStats>,
+}
 
 impl Container {
     /// Displays container events
@@ -34,15 +48,9 @@ impl Container {
             return Err(LibcontainerError::IncorrectStatus(self.status()));
         }
 
-        let cgroup_manager =
-            libcgroups::common::create_cgroup_manager(libcgroups::common::CgroupConfig {
-                cgroup_path: self.spec()?.cgroup_path,
-                systemd_cgroup: self.systemd(),
-                container_name: self.id().to_string(),
-            })?;
         match stats {
             true => {
-                let stats = cgroup_manager.stats()?;
+                let stats = self.stats()?;
                 println!(
                     "{}",
                     serde_json::to_string_pretty(&stats)
@@ -50,7 +58,7 @@ impl Container {
                 );
             }
             false => loop {
-                let stats = cgroup_manager.stats()?;
+                let stats = self.stats()?;
                 println!(
                     "{}",
                     serde_json::to_string_pretty(&stats)
@@ -62,4 +70,19 @@ impl Container {
 
         Ok(())
     }
+
+    /// Returns the statistics of the cgroup of the container, together with
+    /// the Intel RDT monitoring data if it is enabled
+    pub fn stats(&self) -> Result<ContainerStats, LibcontainerError> {
+        let cgroup_manager = self.cgroup_manager()?;
+        let intel_rdt = self
+            .intel_rdt_monitoring()
+            .map(intel_rdt::monitoring_stats)
+            .transpose()?;
+
+        Ok(ContainerStats {
+            cgroup: cgroup_manager.stats()?,
+            intel_rdt,
+        })
+    }
 }
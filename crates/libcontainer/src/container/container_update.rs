@@ -0,0 +1,162 @@
+use libcgroups::common::CgroupManager;
+use nix::unistd::Pid;
+use oci_spec::runtime::{LinuxIntelRdt, LinuxMemoryPolicy, Spec};
+
+use super::Container;
+use crate::error::LibcontainerError;
+use crate::process::{cpu_affinity, intel_rdt, memory_policy};
+use crate::syscall::syscall::create_syscall;
+
+impl Container {
+    /// Rewrites the L3 cache and memory bandwidth schemas of the resctrl group
+    /// the container is in. Schemas which are not given are left unchanged.
+    /// A group given by closID is shared with the other containers in it.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use libcontainer::container::builder::ContainerBuilder;
+    /// use libcontainer::oci_spec::runtime::LinuxIntelRdtBuilder;
+    /// use libcontainer::syscall::syscall::SyscallType;
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut container = ContainerBuilder::new(
+    ///     "74f1a4cb3801".to_owned(),
+    ///     SyscallType::default(),
+    /// )
+    /// .as_init("/var/run/docker/bundle")
+    /// .build()?;
+    ///
+    /// let intel_rdt = LinuxIntelRdtBuilder::default()
+    ///     .mem_bw_schema("MB:0=50")
+    ///     .build()?;
+    /// container.update_intel_rdt(&intel_rdt)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_intel_rdt(&mut self, intel_rdt: &LinuxIntelRdt) -> Result<(), LibcontainerError> {
+        self.check_can_update()?;
+
+        let current = self
+            .bundle_spec()?
+            .linux()
+            .as_ref()
+            .and_then(|linux| linux.intel_rdt().clone())
+            .ok_or_else(|| {
+                tracing::error!(id = ?self.id(), "container was not created with Intel RDT");
+                LibcontainerError::InvalidInput(
+                    "container was not created with Intel RDT".to_string(),
+                )
+            })?;
+        let group = current
+            .clos_id()
+            .clone()
+            .unwrap_or_else(|| self.id().to_string());
+        if intel_rdt
+            .clos_id()
+            .as_ref()
+            .is_some_and(|clos_id| *clos_id != group)
+        {
+            tracing::error!(?group, "cannot move a running container to another closID");
+            return Err(LibcontainerError::InvalidInput(
+                "the closID of a running container can't be changed".to_string(),
+            ));
+        }
+
+        intel_rdt::update_intel_rdt(&group, intel_rdt)?;
+        Ok(())
+    }
+
+    /// Moves the memory of all processes in the container onto the nodes of
+    /// the memory policy. Only a process can change its own memory policy, so
+    /// the pages the processes allocate later still follow the policy they
+    /// were started with.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use libcontainer::container::builder::ContainerBuilder;
+    /// use libcontainer::oci_spec::runtime::{LinuxMemoryPolicyBuilder, MemoryPolicyModeType};
+    /// use libcontainer::syscall::syscall::SyscallType;
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut container = ContainerBuilder::new(
+    ///     "74f1a4cb3801".to_owned(),
+    ///     SyscallType::default(),
+    /// )
+    /// .as_init("/var/run/docker/bundle")
+    /// .build()?;
+    ///
+    /// let memory_policy = LinuxMemoryPolicyBuilder::default()
+    ///     .mode(MemoryPolicyModeType::MpolBind)
+    ///     .nodes("1")
+    ///     .flags(vec![])
+    ///     .build()?;
+    /// container.update_memory_policy(&memory_policy)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_memory_policy(
+        &mut self,
+        memory_policy: &LinuxMemoryPolicy,
+    ) -> Result<(), LibcontainerError> {
+        self.check_can_update()?;
+
+        let syscall = create_syscall();
+        let memory_policy = Some(memory_policy.clone());
+        for pid in self.container_pids()? {
+            memory_policy::migrate_memory(pid, &memory_policy, syscall.as_ref())?;
+        }
+        Ok(())
+    }
+
+    /// Sets the CPU affinity of all threads of all processes in the
+    /// container. The list of CPUs has the format of execCPUAffinity, for
+    /// example 0-3,7.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use libcontainer::container::builder::ContainerBuilder;
+    /// use libcontainer::syscall::syscall::SyscallType;
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut container = ContainerBuilder::new(
+    ///     "74f1a4cb3801".to_owned(),
+    ///     SyscallType::default(),
+    /// )
+    /// .as_init("/var/run/docker/bundle")
+    /// .build()?;
+    ///
+    /// container.update_cpu_affinity("0-3,7")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn update_cpu_affinity(&mut self, cpus: &str) -> Result<(), LibcontainerError> {
+        self.check_can_update()?;
+
+        let cpuset = cpu_affinity::to_cpuset(cpus)?;
+        for pid in self.container_pids()? {
+            cpu_affinity::set_process_affinity(pid, &cpuset)?;
+        }
+        Ok(())
+    }
+
+    fn check_can_update(&mut self) -> Result<(), LibcontainerError> {
+        self.refresh_status()?;
+        if !self.can_update() {
+            tracing::error!(status = ?self.status(), id = ?self.id(), "cannot update container");
+            return Err(LibcontainerError::IncorrectStatus(self.status()));
+        }
+        Ok(())
+    }
+
+    fn container_pids(&self) -> Result<Vec<Pid>, LibcontainerError> {
+        let cmanager = self.cgroup_manager()?;
+        Ok(cmanager.get_all_pids()?)
+    }
+
+    fn bundle_spec(&self) -> Result<Spec, LibcontainerError> {
+        Ok(Spec::load(self.bundle().join("config.json"))?)
+    }
+}
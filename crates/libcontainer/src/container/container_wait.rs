@@ -0,0 +1,220 @@
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+
+use nix::errno::Errno;
+use nix::sys::wait::{WaitPidFlag, WaitStatus, waitpid};
+use nix::unistd::Pid;
+use procfs::process::{ProcState, Process};
+
+use super::{Container, ContainerStatus};
+use crate::error::LibcontainerError;
+use crate::process::pidfd::PidFd;
+
+/// How often the container process is checked on kernels without pidfds
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+impl Container {
+    /// Blocks until the container process exited, or fails with
+    /// [`LibcontainerError::WaitTimeout`] once the timeout elapsed. Returns
+    /// the exit status of the container process if it is known, which it is
+    /// for the parent of the process, and otherwise on Linux 6.15 and later.
+    /// A container which is already stopped returns right away.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use libcontainer::container::builder::ContainerBuilder;
+    /// use libcontainer::syscall::syscall::SyscallType;
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let mut container = ContainerBuilder::new(
+    ///     "74f1a4cb3801".to_owned(),
+    ///     SyscallType::default(),
+    /// )
+    /// .as_init("/var/run/docker/bundle")
+    /// .build()?;
+    ///
+    /// container.start()?;
+    /// let status = container.wait(Some(Duration::from_secs(60)))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn wait(&self, timeout: Option<Duration>) -> Result<Option<ExitStatus>, LibcontainerError> {
+        let Some(pid) = self.wait_pid()? else {
+            return Ok(None);
+        };
+        let pidfd = match PidFd::open(pid) {
+            Ok(Some(pidfd)) => pidfd,
+            Ok(None) => return Ok(None),
+            Err(Errno::ENOSYS) => {
+                tracing::debug!("pidfd_open is not supported, polling the container process");
+                return poll_exit(pid, timeout);
+            }
+            Err(err) => return Err(LibcontainerError::OtherSyscall(err)),
+        };
+
+        if !pidfd
+            .wait_exited(timeout)
+            .map_err(LibcontainerError::OtherSyscall)?
+        {
+            return Err(LibcontainerError::WaitTimeout);
+        }
+        pidfd.reap().map_err(LibcontainerError::OtherSyscall)
+    }
+}
+
+impl Container {
+    /// Returns the pid of the container process to wait for, or None if the
+    /// process already exited. Once the container stopped, its pid may belong to
+    /// another process, which is told apart by its start time.
+    pub(super) fn wait_pid(&self) -> Result<Option<Pid>, LibcontainerError> {
+        let pid = self
+            .pid()
+            .ok_or(LibcontainerError::IncorrectStatus(self.status()))?;
+        if self.status() == ContainerStatus::Stopped {
+            return Ok(None);
+        }
+        if let Some(start) = self.init_process_start() {
+            match Process::new(pid.as_raw()).and_then(|p| p.stat()) {
+                Ok(stat) if stat.starttime == start => {}
+                _ => {
+                    tracing::debug!(?pid, "the container process already exited");
+                    return Ok(None);
+                }
+            }
+        }
+        Ok(Some(pid))
+    }
+}
+
+/// Checks the process every POLL_INTERVAL until it exited. Unlike a pidfd,
+/// this can't tell the process apart from a new one which reused its pid.
+fn poll_exit(pid: Pid, timeout: Option<Duration>) -> Result<Option<ExitStatus>, LibcontainerError> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    loop {
+        match waitpid(pid, Some(WaitPidFlag::WNOHANG)) {
+            Ok(WaitStatus::Exited(_, code)) => return Ok(Some(ExitStatus::from_raw(code << 8))),
+            Ok(WaitStatus::Signaled(_, signal, core_dumped)) => {
+                let core = if core_dumped { 0x80 } else { 0 };
+                return Ok(Some(ExitStatus::from_raw(signal as i32 | core)));
+            }
+            Ok(_) => {}
+            // not a child, so the exit status is only known while it is a zombie
+            Err(Errno::ECHILD) => match Process::new(pid.as_raw()).and_then(|p| p.stat()) {
+                Ok(stat) if matches!(stat.state(), Ok(ProcState::Zombie)) => {
+                    return Ok(stat.exit_code.map(ExitStatus::from_raw));
+                }
+                Ok(_) => {}
+                Err(_) => return Ok(None),
+            },
+            Err(err) => return Err(LibcontainerError::OtherSyscall(err)),
+        }
+
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Err(LibcontainerError::WaitTimeout);
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Returns a running container with the given process, for tests
+#[cfg(test)]
+pub(super) fn container_of(pid: u32) -> Container {
+    let mut container = Container::default();
+    container
+        .set_status(ContainerStatus::Running)
+        .set_pid(pid as i32);
+    container
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::{Command, Stdio};
+
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_wait_child() -> Result<()> {
+        let child = Command::new("sh")
+            .args(["-c", "sleep 0.1; exit 3"])
+            .spawn()?;
+        let status = container_of(child.id()).wait(None)?;
+        assert_eq!(status.and_then(|s| s.code()), Some(3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_timeout() -> Result<()> {
+        let mut child = Command::new("sleep").arg("10").spawn()?;
+        let container = container_of(child.id());
+        let result = container.wait(Some(Duration::from_millis(50)));
+        assert!(matches!(result, Err(LibcontainerError::WaitTimeout)));
+
+        child.kill()?;
+        let status = container.wait(Some(Duration::from_secs(5)))?;
+        assert_eq!(status.and_then(|s| s.signal()), Some(libc::SIGKILL));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_non_child() -> Result<()> {
+        // the background shell is reparented once its parent exited
+        let output = Command::new("sh")
+            .args(["-c", "(sleep 0.3; exit 5) >/dev/null & echo $!"])
+            .stdout(Stdio::piped())
+            .output()?;
+        let pid: u32 = String::from_utf8(output.stdout)?.trim().parse()?;
+
+        let status = container_of(pid).wait(Some(Duration::from_secs(5)))?;
+        // older kernels don't keep the exit status once the new parent reaped it
+        assert!(matches!(status.map(|s| s.code()), None | Some(Some(5))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_stopped() -> Result<()> {
+        let mut child = Command::new("sleep").arg("10").spawn()?;
+        let mut container = container_of(child.id());
+        container.set_status(ContainerStatus::Stopped);
+        assert!(container.wait(Some(Duration::from_millis(50)))?.is_none());
+
+        // a process which reused the pid has another start time
+        let start = Process::new(child.id() as i32)?.stat()?.starttime;
+        let mut container = container_of(child.id());
+        container.set_init_process_start(Some(start + 1));
+        assert!(container.wait(Some(Duration::from_millis(50)))?.is_none());
+
+        container.set_init_process_start(Some(start));
+        child.kill()?;
+        let status = container.wait(Some(Duration::from_secs(5)))?;
+        assert_eq!(status.and_then(|s| s.signal()), Some(libc::SIGKILL));
+        Ok(())
+    }
+
+    #[test]
+    fn test_wait_without_pid() {
+        let result = Container::default().wait(None);
+        assert!(matches!(result, Err(LibcontainerError::IncorrectStatus(_))));
+    }
+
+    #[test]
+    fn test_poll_exit() -> Result<()> {
+        let child = Command::new("sh").args(["-c", "exit 7"]).spawn()?;
+        let pid = Pid::from_raw(child.id() as i32);
+        let status = poll_exit(pid, Some(Duration::from_secs(5)))?;
+        assert_eq!(status.and_then(|s| s.code()), Some(7));
+
+        let child = Command::new("sleep").arg("10").spawn()?;
+        let pid = Pid::from_raw(child.id() as i32);
+        let result = poll_exit(pid, Some(Duration::from_millis(50)));
+        assert!(matches!(result, Err(LibcontainerError::WaitTimeout)));
+        nix::sys::signal::kill(pid, nix::sys::signal::SIGKILL)?;
+        poll_exit(pid, None)?;
+        Ok(())
+    }
+}
@@ -2,7 +2,8 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
-use oci_spec::runtime::Spec;
+use libcgroups::common::CgroupDriver;
+use oci_spec::runtime::{LinuxNamespaceType, MountBuilder, Spec};
 use user_ns::UserNamespaceConfig;
 
 use super::builder::ContainerBuilder;
@@ -10,8 +11,14 @@ use super::builder_impl::ContainerBuilderImpl;
 use super::{Container, ContainerStatus};
 use crate::config::YoukiConfig;
 use crate::error::{ErrInvalidSpec, LibcontainerError, MissingSpecError};
+use crate::landlock::LandlockRuleset;
+use crate::network::bridge::BridgeNetwork;
+use crate::network::rootless::RootlessNetwork;
 use crate::notify_socket::NOTIFY_FILE;
 use crate::process::args::ContainerType;
+use crate::rootfs::OverlayRootfs;
+use crate::rootfs::overlay::OverlayError;
+use crate::sd_notify::{CONTAINER_NOTIFY_SOCKET, NOTIFY_DIR, SdNotify, SdNotifyMode};
 use crate::syscall::syscall::create_syscall;
 use crate::{apparmor, tty, user_ns, utils};
 
@@ -20,9 +27,15 @@ pub struct InitContainerBuilder {
     base: ContainerBuilder,
     bundle: PathBuf,
     use_systemd: bool,
+    cgroup_driver: CgroupDriver,
     detached: bool,
     no_pivot: bool,
     as_sibling: bool,
+    sd_notify: Option<SdNotify>,
+    bridge_network: Option<BridgeNetwork>,
+    rootless_network: Option<RootlessNetwork>,
+    overlay_rootfs: Option<OverlayRootfs>,
+    landlock: Option<LandlockRuleset>,
 }
 
 impl InitContainerBuilder {
@@ -33,9 +46,15 @@ impl InitContainerBuilder {
             base: builder,
             bundle,
             use_systemd: true,
+            cgroup_driver: CgroupDriver::default(),
             detached: true,
             no_pivot: false,
             as_sibling: false,
+            sd_notify: None,
+            bridge_network: None,
+            rootless_network: None,
+            overlay_rootfs: None,
+            landlock: None,
         }
     }
 
@@ -45,6 +64,14 @@ impl InitContainerBuilder {
         self
     }
 
+    /// Sets how the manager of the cgroups is chosen. With the default auto
+    /// driver, the cgroupfs manager is used if systemd is requested but can't
+    /// be reached. The manager chosen is recorded in the state of the container.
+    pub fn with_cgroup_driver(mut self, cgroup_driver: CgroupDriver) -> Self {
+        self.cgroup_driver = cgroup_driver;
+        self
+    }
+
     /// Sets if the init process should be run as a child or a sibling of
     /// the calling process
     pub fn as_sibling(mut self, as_sibling: bool) -> Self {
@@ -62,15 +89,86 @@ impl InitContainerBuilder {
         self
     }
 
+    /// Sets how the container notifies `socket`, the `NOTIFY_SOCKET` of the host,
+    /// about its readiness. Nothing is notified without a socket. In the container
+    /// mode, the caller relays the notifications with
+    /// [`Container::notify_proxy`].
+    pub fn with_sd_notify<P: Into<PathBuf>>(
+        mut self,
+        mode: SdNotifyMode,
+        socket: Option<P>,
+    ) -> Self {
+        self.sd_notify = match (mode, socket) {
+            (SdNotifyMode::Ignore, _) | (_, None) => None,
+            (mode, Some(socket)) => Some(SdNotify {
+                mode,
+                socket: socket.into(),
+            }),
+        };
+        self
+    }
+
+    /// Connects the container to a bridge on the host by a veth pair. Without it,
+    /// the network is configured from the `org.youki.network.*` annotations of the
+    /// spec, if any.
+    pub fn with_bridge_network(mut self, bridge_network: BridgeNetwork) -> Self {
+        self.bridge_network = Some(bridge_network);
+        self
+    }
+
+    /// Connects the container to the network of the host with a user mode network
+    /// stack, which works without privileges. Without it, the network is
+    /// configured from the `org.youki.network.rootless` and
+    /// `org.youki.network.ports` annotations of the spec, if any.
+    pub fn with_rootless_network(mut self, rootless_network: RootlessNetwork) -> Self {
+        self.rootless_network = Some(rootless_network);
+        self
+    }
+
+    /// Mounts an overlay of the given layers over `root.path` of the spec, which
+    /// only has to be an empty directory then. Without it, the layers are taken from
+    /// the `org.youki.rootfs.overlay.*` annotations of the spec, if any.
+    pub fn with_overlay_rootfs(mut self, overlay_rootfs: OverlayRootfs) -> Self {
+        self.overlay_rootfs = Some(overlay_rootfs);
+        self
+    }
+
+    /// Restricts the container processes with a Landlock ruleset right before the
+    /// payload is executed. Without it, the ruleset is taken from the
+    /// `run.oci.landlock` annotation of the spec, if any.
+    pub fn with_landlock(mut self, landlock: LandlockRuleset) -> Self {
+        self.landlock = Some(landlock);
+        self
+    }
+
     /// Creates a new container
-    pub fn build(self) -> Result<Container, LibcontainerError> {
-        let spec = self.load_spec()?;
+    pub fn build(mut self) -> Result<Container, LibcontainerError> {
+        let mut spec = self.load_spec()?;
+        let bridge_network = self.load_bridge_network(&spec)?;
+        let rootless_network = self.load_rootless_network(&spec, bridge_network.as_ref())?;
+        let overlay_rootfs = self.load_overlay_rootfs(&spec)?;
+        let landlock = self.load_landlock(&spec)?;
         let container_dir = self.create_container_dir()?;
 
+        let user_ns_config = UserNamespaceConfig::new(&spec)?;
+        // rootless containers can only get a cgroup of their own through systemd
+        let use_systemd = libcgroups::common::use_systemd_driver(
+            self.cgroup_driver,
+            self.use_systemd || user_ns_config.is_some(),
+        );
+
         let mut container = self.create_container_state(&container_dir)?;
         container
-            .set_systemd(self.use_systemd)
-            .set_annotations(spec.annotations().clone());
+            .set_systemd(use_systemd)
+            .set_annotations(spec.annotations().clone())
+            .set_sd_notify(self.sd_notify.clone())
+            .set_bridge_network(bridge_network)
+            .set_rootless_network(rootless_network)
+            .set_overlay_rootfs(overlay_rootfs)
+            .set_landlock(landlock.clone());
+        if matches!(&self.sd_notify, Some(sd_notify) if sd_notify.mode == SdNotifyMode::Container) {
+            Self::setup_notify_proxy(&mut spec, &container_dir)?;
+        }
 
         let notify_path = container_dir.join(NOTIFY_FILE);
         // convert path of root file system of the container to absolute path
@@ -89,21 +187,21 @@ impl InitContainerBuilder {
             None
         };
 
-        let user_ns_config = UserNamespaceConfig::new(&spec)?;
-
         let config = YoukiConfig::from_spec(&spec, container.id())?;
         config.save(&container_dir).map_err(|err| {
             tracing::error!(?container_dir, "failed to save config: {}", err);
             err
         })?;
 
+        let (stdout, stderr) = self.base.take_output()?;
+
         let mut builder_impl = ContainerBuilderImpl {
             container_type: ContainerType::InitContainer,
             syscall: self.base.syscall,
             container_id: self.base.container_id,
             pid_file: self.base.pid_file,
             console_socket: csocketfd,
-            use_systemd: self.use_systemd,
+            use_systemd,
             spec: Rc::new(spec),
             rootfs,
             user_ns_config,
@@ -114,9 +212,11 @@ impl InitContainerBuilder {
             executor: self.base.executor,
             no_pivot: self.no_pivot,
             stdin: self.base.stdin,
-            stdout: self.base.stdout,
-            stderr: self.base.stderr,
+            stdout,
+            stderr,
             as_sibling: self.as_sibling,
+            landlock,
+            sub_cgroup: None,
         };
 
         builder_impl.create()?;
@@ -212,6 +312,24 @@ impl InitContainerBuilder {
             utils::validate_mount_options(mounts)?;
         }
 
+        // the cgroup2 mount is only writable with delegation, and without a
+        // cgroup namespace it would expose the whole cgroup hierarchy of the host
+        if utils::is_cgroup_delegated(spec.annotations()) {
+            let has_cgroup_namespace = spec
+                .linux()
+                .as_ref()
+                .and_then(|linux| linux.namespaces().as_ref())
+                .is_some_and(|namespaces| {
+                    namespaces
+                        .iter()
+                        .any(|ns| ns.typ() == LinuxNamespaceType::Cgroup)
+                });
+            if !has_cgroup_namespace {
+                tracing::error!("cgroup delegation is requested without a cgroup namespace");
+                Err(ErrInvalidSpec::CgroupDelegationWithoutCgroupNs)?;
+            }
+        }
+
         let syscall = create_syscall();
         utils::validate_spec_for_new_user_ns(spec, &*syscall)?;
         utils::validate_spec_for_net_devices(spec, &*syscall)
@@ -220,6 +338,126 @@ impl InitContainerBuilder {
         Ok(())
     }
 
+    fn load_bridge_network(&self, spec: &Spec) -> Result<Option<BridgeNetwork>, LibcontainerError> {
+        let bridge_network = match &self.bridge_network {
+            Some(bridge_network) => Some(bridge_network.clone()),
+            None => match spec.annotations() {
+                Some(annotations) => {
+                    BridgeNetwork::from_annotations(annotations, &self.base.container_id)?
+                }
+                None => None,
+            },
+        };
+
+        if let Some(bridge_network) = &bridge_network {
+            let syscall = create_syscall();
+            utils::validate_spec_for_bridge_network(spec, bridge_network, &*syscall)?;
+        }
+
+        Ok(bridge_network)
+    }
+
+    fn load_rootless_network(
+        &self,
+        spec: &Spec,
+        bridge_network: Option<&BridgeNetwork>,
+    ) -> Result<Option<RootlessNetwork>, LibcontainerError> {
+        let rootless_network = match &self.rootless_network {
+            Some(rootless_network) => Some(rootless_network.clone()),
+            None => match spec.annotations() {
+                Some(annotations) => RootlessNetwork::from_annotations(annotations)?,
+                None => None,
+            },
+        };
+
+        if rootless_network.is_some() {
+            utils::validate_spec_for_rootless_network(spec, bridge_network)?;
+        }
+
+        Ok(rootless_network)
+    }
+
+    fn load_overlay_rootfs(&self, spec: &Spec) -> Result<Option<OverlayRootfs>, LibcontainerError> {
+        let overlay_rootfs = match &self.overlay_rootfs {
+            Some(overlay_rootfs) => Some(overlay_rootfs.clone()),
+            None => match spec.annotations() {
+                Some(annotations) => {
+                    let bundle =
+                        fs::canonicalize(&self.bundle).map_err(LibcontainerError::OtherIO)?;
+                    OverlayRootfs::from_annotations(annotations, &bundle)?
+                }
+                None => None,
+            },
+        };
+
+        if let Some(overlay_rootfs) = &overlay_rootfs {
+            // the overlay is only mounted in the mount namespace of the container
+            let has_mount_namespace = spec
+                .linux()
+                .as_ref()
+                .and_then(|linux| linux.namespaces().as_ref())
+                .is_some_and(|namespaces| {
+                    namespaces
+                        .iter()
+                        .any(|ns| ns.typ() == LinuxNamespaceType::Mount)
+                });
+            if !has_mount_namespace {
+                return Err(OverlayError::NoMountNamespace.into());
+            }
+            overlay_rootfs.prepare()?;
+        }
+
+        Ok(overlay_rootfs)
+    }
+
+    fn load_landlock(&self, spec: &Spec) -> Result<Option<LandlockRuleset>, LibcontainerError> {
+        match &self.landlock {
+            Some(landlock) => Ok(Some(landlock.clone())),
+            None => match spec.annotations() {
+                Some(annotations) => Ok(LandlockRuleset::from_annotations(annotations)?),
+                None => Ok(None),
+            },
+        }
+    }
+
+    // Mounts the directory of the proxy socket into the container and points the
+    // container to it
+    fn setup_notify_proxy(spec: &mut Spec, container_dir: &Path) -> Result<(), LibcontainerError> {
+        let notify_dir = container_dir.join(NOTIFY_DIR);
+        fs::create_dir(&notify_dir).map_err(LibcontainerError::OtherIO)?;
+        let container_socket = Path::new(CONTAINER_NOTIFY_SOCKET);
+
+        let mount = MountBuilder::default()
+            .destination(container_socket.parent().expect("socket is in a directory"))
+            .typ("bind")
+            .source(&notify_dir)
+            .options(vec![
+                "bind".to_owned(),
+                "nosuid".to_owned(),
+                "noexec".to_owned(),
+                "nodev".to_owned(),
+            ])
+            .build()?;
+        let mut mounts = spec.mounts().clone().unwrap_or_default();
+        mounts.push(mount);
+        spec.set_mounts(Some(mounts));
+
+        if let Some(mut process) = spec.process().clone() {
+            let mut env: Vec<String> = process
+                .env()
+                .iter()
+                .flatten()
+                .filter(|var| !var.starts_with("NOTIFY_SOCKET="))
+                .cloned()
+                .collect();
+            env.push(format!("NOTIFY_SOCKET={CONTAINER_NOTIFY_SOCKET}"));
+            process.set_env(Some(env));
+            spec.set_process(Some(process));
+        }
+
+        Ok(())
+    }
+
     fn create_container_state(&self, container_dir: &Path) -> Result<Container, LibcontainerError> {
         let container = Container::new(
             &self.base.container_id,
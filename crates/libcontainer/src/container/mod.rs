@@ -8,6 +8,8 @@ pub mod builder;
 mod builder_impl;
 #[allow(clippy::module_inception)]
 mod container;
+#[cfg(feature = "async")]
+mod container_async;
 mod container_checkpoint;
 mod container_delete;
 mod container_events;
@@ -15,11 +17,16 @@ mod container_kill;
 mod container_pause;
 mod container_resume;
 mod container_start;
+mod container_update;
+mod container_wait;
 pub mod init_builder;
 pub mod state;
 pub mod tenant_builder;
 pub use container::{CheckpointOptions, Container};
+#[cfg(feature = "async")]
+pub use container_async::EventStream;
 pub use container_checkpoint::CheckpointError;
+pub use container_events::ContainerStats;
 #[allow(deprecated)]
 pub use state::ContainerProcessState;
 pub use state::{ContainerStatus, State, StateConversionError};
@@ -7,6 +7,7 @@ use std::io::{BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
 
 use chrono::{DateTime, Utc};
+use libcgroups::common::CgroupManagerInfo;
 use oci_spec::OciSpecError;
 use oci_spec::runtime::{
     ContainerState as OciContainerState, State as OciState, StateBuilder as OciStateBuilder,
@@ -15,6 +16,13 @@ use oci_spec::runtime::{
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
+use crate::landlock::LandlockRuleset;
+use crate::network::bridge::BridgeNetwork;
+use crate::network::rootless::RootlessNetwork;
+use crate::process::intel_rdt::IntelRdtMonitoring;
+use crate::rootfs::OverlayRootfs;
+use crate::sd_notify::SdNotify;
+
 /// Indicates status of the container
 #[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
 #[serde(rename_all = "camelCase")]
@@ -56,6 +64,14 @@ impl ContainerStatus {
     pub fn can_resume(&self) -> bool {
         matches!(self, ContainerStatus::Paused)
     }
+
+    pub fn can_update(&self) -> bool {
+        use ContainerStatus::*;
+        match self {
+            Creating | Stopped => false,
+            Created | Running | Paused => true,
+        }
+    }
 }
 
 impl Display for ContainerStatus {
@@ -106,6 +122,10 @@ pub struct State {
     // Pid is the process ID for the container process.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub pid: Option<i32>,
+    // Start time of the container process in clock ticks since boot, which
+    // tells it apart from a process which reused its pid
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub init_process_start: Option<u64>,
     // Bundle is the path to the container's bundle directory.
     pub bundle: PathBuf,
     // Annotations are key values associated with the container.
@@ -121,6 +141,27 @@ pub struct State {
     pub use_systemd: bool,
     // Specifies if the Intel RDT subdirectory needs be cleaned up.
     pub clean_up_intel_rdt_subdirectory: Option<bool>,
+    // Specifies where the Intel RDT monitoring data of the container is read from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub intel_rdt_monitoring: Option<IntelRdtMonitoring>,
+    // Specifies how notifications reach the NOTIFY_SOCKET of the host
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sd_notify: Option<SdNotify>,
+    // Specifies the veth pair which connects the container to a bridge
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bridge_network: Option<BridgeNetwork>,
+    // Specifies the user mode network stack which connects the container to the host
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rootless_network: Option<RootlessNetwork>,
+    // Specifies the layers the rootfs is assembled from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlay_rootfs: Option<OverlayRootfs>,
+    // Specifies the Landlock ruleset the container processes are restricted with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub landlock: Option<LandlockRuleset>,
+    // Specifies how the cgroups of the container are managed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cgroup_manager: Option<CgroupManagerInfo>,
 }
 
 impl State {
@@ -137,12 +178,20 @@ impl State {
             id: container_id.to_string(),
             status,
             pid,
+            init_process_start: None,
             bundle,
             annotations: Some(HashMap::default()),
             created: None,
             creator: None,
             use_systemd: false,
             clean_up_intel_rdt_subdirectory: None,
+            intel_rdt_monitoring: None,
+            sd_notify: None,
+            bridge_network: None,
+            rootless_network: None,
+            overlay_rootfs: None,
+            landlock: None,
+            cgroup_manager: None,
         }
     }
 
@@ -324,6 +373,7 @@ mod tests {
         assert!(!cstatus.can_kill());
         assert!(!cstatus.can_pause());
         assert!(!cstatus.can_resume());
+        assert!(!cstatus.can_update());
     }
 
     #[test]
@@ -334,6 +384,7 @@ mod tests {
         assert!(cstatus.can_kill());
         assert!(!cstatus.can_pause());
         assert!(!cstatus.can_resume());
+        assert!(cstatus.can_update());
     }
 
     #[test]
@@ -344,6 +395,7 @@ mod tests {
         assert!(cstatus.can_kill());
         assert!(cstatus.can_pause());
         assert!(!cstatus.can_resume());
+        assert!(cstatus.can_update());
     }
 
     #[test]
@@ -354,6 +406,7 @@ mod tests {
         assert!(!cstatus.can_kill());
         assert!(!cstatus.can_pause());
         assert!(!cstatus.can_resume());
+        assert!(!cstatus.can_update());
     }
 
     #[test]
@@ -364,5 +417,6 @@ mod tests {
         assert!(cstatus.can_kill());
         assert!(!cstatus.can_pause());
         assert!(cstatus.can_resume());
+        assert!(cstatus.can_update());
     }
 }
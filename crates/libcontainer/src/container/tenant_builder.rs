@@ -4,11 +4,12 @@ use std::ffi::{OsStr, OsString};
 use std::fs;
 use std::io::BufReader;
 use std::os::fd::{AsRawFd, OwnedFd};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::rc::Rc;
 use std::str::FromStr;
 
 use caps::Capability;
+use libcgroups::common::{CgroupManagerInfo, CgroupSetup};
 use nix::fcntl::OFlag;
 use nix::unistd::{Pid, pipe2, read};
 use oci_spec::runtime::{
@@ -18,8 +19,8 @@ use oci_spec::runtime::{
 };
 use procfs::process::Namespace;
 
-use super::Container;
 use super::builder::ContainerBuilder;
+use super::{Container, ContainerStatus};
 use crate::capabilities::CapabilityExt;
 use crate::container::builder_impl::ContainerBuilderImpl;
 use crate::error::{ErrInvalidSpec, LibcontainerError, MissingSpecError};
@@ -32,6 +33,9 @@ use crate::{tty, utils};
 const NAMESPACE_TYPES: &[&str] = &["ipc", "uts", "net", "pid", "mnt", "cgroup"];
 const TENANT_NOTIFY: &str = "tenant-notify-";
 const TENANT_TTY: &str = "tenant-tty-";
+// leaf of the processes youki executes in a delegated container, next to the
+// init leaf owned by the container
+const DELEGATED_EXEC_CGROUP: &str = "exec";
 
 fn get_path_from_spec(spec: &Spec) -> Option<String> {
     let process = match spec.process() {
@@ -68,6 +72,10 @@ pub struct TenantContainerBuilder {
     additional_gids: Vec<u32>,
     user: Option<u32>,
     group: Option<u32>,
+    apparmor_profile: Option<String>,
+    process_label: Option<String>,
+    ignore_paused: bool,
+    sub_cgroup: Option<PathBuf>,
 }
 
 /// This is a helper function to get capabilities for tenant container, based on
@@ -163,6 +171,10 @@ impl TenantContainerBuilder {
             additional_gids: vec![],
             user: None,
             group: None,
+            apparmor_profile: None,
+            process_label: None,
+            ignore_paused: false,
+            sub_cgroup: None,
         }
     }
 
@@ -226,8 +238,35 @@ impl TenantContainerBuilder {
         self
     }
 
+    /// Sets the AppArmor profile of the process, instead of the one of the
+    /// container
+    pub fn with_apparmor_profile(mut self, profile: Option<String>) -> Self {
+        self.apparmor_profile = profile;
+        self
+    }
+
+    /// Sets the SELinux label of the process, instead of the one of the container
+    pub fn with_process_label(mut self, label: Option<String>) -> Self {
+        self.process_label = label;
+        self
+    }
+
+    /// Allows to join a paused container. The process is frozen as well until
+    /// the container is resumed.
+    pub fn with_ignore_paused(mut self, ignore_paused: bool) -> Self {
+        self.ignore_paused = ignore_paused;
+        self
+    }
+
+    /// Runs the process in a sub-cgroup of the container, which is created if
+    /// it doesn't exist. Only supported with cgroup v2.
+    pub fn with_sub_cgroup<P: Into<PathBuf>>(mut self, sub_cgroup: Option<P>) -> Self {
+        self.sub_cgroup = sub_cgroup.map(|p| p.into());
+        self
+    }
+
     /// Joins an existing container
-    pub fn build(self) -> Result<Pid, LibcontainerError> {
+    pub fn build(mut self) -> Result<Pid, LibcontainerError> {
         let container_dir = self.lookup_container_dir()?;
         let container = self.load_container_state(container_dir.clone())?;
         let mut spec = self.load_init_spec(&container)?;
@@ -246,10 +285,13 @@ impl TenantContainerBuilder {
 
         let use_systemd = self.should_use_systemd(&container);
         let user_ns_config = UserNamespaceConfig::new(&spec)?;
+        let sub_cgroup = self.get_sub_cgroup(&container, &spec)?;
 
         let (read_end, write_end) =
             pipe2(OFlag::O_CLOEXEC).map_err(LibcontainerError::OtherSyscall)?;
 
+        let (stdout, stderr) = self.base.take_output()?;
+
         let mut builder_impl = ContainerBuilderImpl {
             container_type: ContainerType::TenantContainer {
                 exec_notify_fd: write_end.as_raw_fd(),
@@ -269,9 +311,11 @@ impl TenantContainerBuilder {
             executor: self.base.executor,
             no_pivot: false,
             stdin: self.base.stdin,
-            stdout: self.base.stdout,
-            stderr: self.base.stderr,
+            stdout,
+            stderr,
             as_sibling: self.as_sibling,
+            landlock: container.landlock().cloned(),
+            sub_cgroup,
         };
 
         let pid = builder_impl.create()?;
@@ -439,6 +483,9 @@ impl TenantContainerBuilder {
 
     fn load_container_state(&self, container_dir: PathBuf) -> Result<Container, LibcontainerError> {
         let container = Container::load(container_dir)?;
+        if container.status() == ContainerStatus::Paused && self.ignore_paused {
+            return Ok(container);
+        }
         if !container.can_exec() {
             tracing::error!(status = ?container.status(), "cannot exec as container");
             return Err(LibcontainerError::IncorrectStatus(container.status()));
@@ -473,6 +520,22 @@ impl TenantContainerBuilder {
                 process_builder = process_builder.no_new_privileges(no_new_priv);
             }
 
+            let init_process = spec.process().as_ref();
+            if let Some(profile) = self
+                .apparmor_profile
+                .as_ref()
+                .or(init_process.and_then(|p| p.apparmor_profile().as_ref()))
+            {
+                process_builder = process_builder.apparmor_profile(profile);
+            }
+            if let Some(label) = self
+                .process_label
+                .as_ref()
+                .or(init_process.and_then(|p| p.selinux_label().as_ref()))
+            {
+                process_builder = process_builder.selinux_label(label);
+            }
+
             let capabilities = get_capabilities(&self.capabilities, spec)?;
             process_builder = process_builder.capabilities(capabilities);
 
@@ -605,6 +668,46 @@ impl TenantContainerBuilder {
         Ok(tenant_namespaces)
     }
 
+    /// Returns the absolute path of the cgroup the process joins, below the cgroup
+    /// youki recorded for the container when it was created. The process of a
+    /// delegated container can't be placed in the container cgroup, as it is no
+    /// leaf, and must stay out of the `init` leaf handed to the container. It
+    /// joins a leaf of its own next to it instead.
+    fn get_sub_cgroup(
+        &self,
+        container: &Container,
+        spec: &Spec,
+    ) -> Result<Option<PathBuf>, LibcontainerError> {
+        let delegated = utils::is_cgroup_delegated(spec.annotations());
+        if self.sub_cgroup.is_none() && !delegated {
+            return Ok(None);
+        }
+
+        if let Some(sub_cgroup) = &self.sub_cgroup {
+            if sub_cgroup.is_absolute()
+                || sub_cgroup
+                    .components()
+                    .any(|c| !matches!(c, Component::Normal(_)))
+            {
+                tracing::error!(
+                    ?sub_cgroup,
+                    "sub-cgroup must be a relative path below the container cgroup"
+                );
+                return Err(LibcontainerError::Other(format!(
+                    "invalid sub-cgroup {}",
+                    sub_cgroup.display()
+                )));
+            }
+        }
+
+        let info = container
+            .cgroup_manager_info()
+            .ok_or(LibcontainerError::Other(
+                "the cgroup of the container was not recorded when it was created".into(),
+            ))?;
+        sub_cgroup_path(info, self.sub_cgroup.as_deref()).map(Some)
+    }
+
     fn should_use_systemd(&self, container: &Container) -> bool {
         container.systemd()
     }
@@ -642,6 +745,26 @@ impl TenantContainerBuilder {
     }
 }
 
+// Joins the sub-cgroup, or the leaf for processes of a delegated container, to
+// the recorded cgroup of the container
+fn sub_cgroup_path(
+    info: &CgroupManagerInfo,
+    sub_cgroup: Option<&Path>,
+) -> Result<PathBuf, LibcontainerError> {
+    if !matches!(info.setup, CgroupSetup::Unified) {
+        return Err(LibcontainerError::Other(
+            "sub-cgroups are only supported with cgroup v2".into(),
+        ));
+    }
+
+    let cgroup = info.root_path.join(
+        info.cgroup_path
+            .strip_prefix("/")
+            .unwrap_or(&info.cgroup_path),
+    );
+    Ok(cgroup.join(sub_cgroup.unwrap_or(Path::new(DELEGATED_EXEC_CGROUP))))
+}
+
 #[cfg(test)]
 mod test {
 
@@ -650,7 +773,11 @@ mod test {
         Capabilities, Capability as SpecCap, LinuxCapabilities, ProcessBuilder, Spec, SpecBuilder,
     };
 
-    use super::{LibcontainerError, get_capabilities};
+    use std::path::{Path, PathBuf};
+
+    use libcgroups::common::{CgroupManagerInfo, CgroupManagerKind, CgroupSetup};
+
+    use super::{LibcontainerError, get_capabilities, sub_cgroup_path};
     use crate::capabilities::CapabilityExt;
 
     fn get_spec(caps: LinuxCapabilities) -> Spec {
@@ -794,4 +921,29 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_sub_cgroup_path() -> Result<(), LibcontainerError> {
+        let mut info = CgroupManagerInfo {
+            setup: CgroupSetup::Unified,
+            kind: CgroupManagerKind::Systemd,
+            root_path: PathBuf::from("/sys/fs/cgroup"),
+            cgroup_path: PathBuf::from("/system.slice/youki-test.scope"),
+            systemd_cgroups_path: Some(PathBuf::from("system.slice:youki:test")),
+            systemd_system: true,
+        };
+        assert_eq!(
+            sub_cgroup_path(&info, Some(Path::new("tenant")))?,
+            PathBuf::from("/sys/fs/cgroup/system.slice/youki-test.scope/tenant")
+        );
+        // the processes of a delegated container stay out of the init leaf
+        assert_eq!(
+            sub_cgroup_path(&info, None)?,
+            PathBuf::from("/sys/fs/cgroup/system.slice/youki-test.scope/exec")
+        );
+
+        info.setup = CgroupSetup::Hybrid;
+        assert!(sub_cgroup_path(&info, Some(Path::new("tenant"))).is_err());
+        Ok(())
+    }
 }
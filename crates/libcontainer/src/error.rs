@@ -26,6 +26,8 @@ pub enum LibcontainerError {
     NoExecutors,
     #[error("rootless container requires valid user namespace definition")]
     NoUserNamespace,
+    #[error("timed out waiting for the container process to exit")]
+    WaitTimeout,
 
     // Invalid inputs
     #[error(transparent)]
@@ -43,6 +45,8 @@ pub enum LibcontainerError {
     #[error(transparent)]
     NotifyListener(#[from] crate::notify_socket::NotifyListenerError),
     #[error(transparent)]
+    SdNotify(#[from] crate::sd_notify::SdNotifyError),
+    #[error(transparent)]
     Config(#[from] crate::config::ConfigError),
     #[error(transparent)]
     Hook(#[from] crate::hooks::HookError),
@@ -70,6 +74,18 @@ pub enum LibcontainerError {
     NetDevicesError(#[from] crate::utils::NetDevicesError),
     #[error(transparent)]
     NetworkError(#[from] crate::network::NetworkError),
+    #[error(transparent)]
+    Overlay(#[from] crate::rootfs::overlay::OverlayError),
+    #[error(transparent)]
+    Landlock(#[from] crate::landlock::LandlockError),
+    #[error(transparent)]
+    LogSink(#[from] crate::log_sink::LogSinkError),
+    #[error(transparent)]
+    IntelRdt(#[from] crate::process::intel_rdt::IntelRdtError),
+    #[error(transparent)]
+    MemoryPolicy(#[from] crate::process::memory_policy::MemoryPolicyError),
+    #[error(transparent)]
+    CpuAffinity(#[from] crate::process::cpu_affinity::CPUAffinityError),
 
     // Catch all errors that are not covered by the above
     #[error("syscall error")]
@@ -104,6 +120,8 @@ pub enum ErrInvalidSpec {
     IoPriority,
     #[error("invalid scheduler config for process")]
     Scheduler,
+    #[error("cgroup delegation requires a cgroup namespace")]
+    CgroupDelegationWithoutCgroupNs,
 }
 
 #[derive(Debug, thiserror::Error)]
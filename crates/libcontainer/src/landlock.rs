@@ -0,0 +1,459 @@
+//! Restricts the container process with a Landlock ruleset right before seccomp
+//! is installed, in addition to seccomp, AppArmor and capabilities.
+//!
+//! Landlock denies every file system access the kernel can restrict, except for
+//! the rights granted beneath the paths of the ruleset. TCP ports are only
+//! restricted if the ruleset has port rules. Rights which the running kernel
+//! doesn't know are dropped according to its Landlock ABI version, and the
+//! ruleset isn't applied at all if the kernel doesn't support Landlock or has
+//! it disabled.
+use std::collections::HashMap;
+use std::mem::size_of;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+
+use nix::errno::Errno;
+use nix::fcntl::{OFlag, open};
+use nix::sys::stat::Mode;
+use serde::{Deserialize, Serialize};
+
+/// Annotation with the ruleset in JSON, e.g.
+/// `{"paths":[{"path":"/usr","access":["read","exec"]}],"ports":[{"port":80,"access":["bind"]}]}`
+pub const LANDLOCK_ANNOTATION: &str = "run.oci.landlock";
+
+#[derive(Debug, thiserror::Error)]
+pub enum LandlockError {
+    #[error("invalid landlock ruleset in annotation {LANDLOCK_ANNOTATION}")]
+    InvalidAnnotation(#[source] serde_json::Error),
+    #[error("failed to query the landlock ABI version")]
+    AbiVersion(#[source] Errno),
+    #[error("failed to create landlock ruleset")]
+    CreateRuleset(#[source] Errno),
+    #[error("failed to open {path:?} for a landlock rule")]
+    OpenPath {
+        path: PathBuf,
+        #[source]
+        source: Errno,
+    },
+    #[error("failed to add landlock rule for {rule}")]
+    AddRule {
+        rule: String,
+        #[source]
+        source: Errno,
+    },
+    #[error("failed to set no new privileges for landlock")]
+    NoNewPrivileges(#[source] Errno),
+    #[error("failed to restrict the process with landlock")]
+    RestrictSelf(#[source] Errno),
+}
+
+type Result<T> = std::result::Result<T, LandlockError>;
+
+// See include/uapi/linux/landlock.h
+const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1 << 0;
+const LANDLOCK_RULE_PATH_BENEATH: libc::c_int = 1;
+const LANDLOCK_RULE_NET_PORT: libc::c_int = 2;
+
+const ACCESS_FS_EXECUTE: u64 = 1 << 0;
+const ACCESS_FS_WRITE_FILE: u64 = 1 << 1;
+const ACCESS_FS_READ_FILE: u64 = 1 << 2;
+const ACCESS_FS_READ_DIR: u64 = 1 << 3;
+const ACCESS_FS_REMOVE_DIR: u64 = 1 << 4;
+const ACCESS_FS_REMOVE_FILE: u64 = 1 << 5;
+const ACCESS_FS_MAKE_CHAR: u64 = 1 << 6;
+const ACCESS_FS_MAKE_DIR: u64 = 1 << 7;
+const ACCESS_FS_MAKE_REG: u64 = 1 << 8;
+const ACCESS_FS_MAKE_SOCK: u64 = 1 << 9;
+const ACCESS_FS_MAKE_FIFO: u64 = 1 << 10;
+const ACCESS_FS_MAKE_BLOCK: u64 = 1 << 11;
+const ACCESS_FS_MAKE_SYM: u64 = 1 << 12;
+// ABI 2
+const ACCESS_FS_REFER: u64 = 1 << 13;
+// ABI 3
+const ACCESS_FS_TRUNCATE: u64 = 1 << 14;
+// ABI 5
+const ACCESS_FS_IOCTL_DEV: u64 = 1 << 15;
+
+// ABI 4
+const ACCESS_NET_BIND_TCP: u64 = 1 << 0;
+const ACCESS_NET_CONNECT_TCP: u64 = 1 << 1;
+
+// The rights which apply to files, the others only apply to directories
+const ACCESS_FS_FILE: u64 = ACCESS_FS_EXECUTE
+    | ACCESS_FS_WRITE_FILE
+    | ACCESS_FS_READ_FILE
+    | ACCESS_FS_TRUNCATE
+    | ACCESS_FS_IOCTL_DEV;
+
+/// The first ABI version with TCP port rules
+pub const NET_ABI_VERSION: u32 = 4;
+
+#[repr(C)]
+struct RulesetAttr {
+    handled_access_fs: u64,
+    handled_access_net: u64,
+}
+
+#[repr(C, packed)]
+struct PathBeneathAttr {
+    allowed_access: u64,
+    parent_fd: i32,
+}
+
+#[repr(C)]
+struct NetPortAttr {
+    allowed_access: u64,
+    port: u64,
+}
+
+/// Returns the Landlock ABI version of the kernel, or None if Landlock isn't
+/// supported (ENOSYS) or is disabled (EOPNOTSUPP). Any other error is returned.
+pub fn abi_version() -> Result<Option<u32>> {
+    // Safety: a null attribute with size 0 only queries the version
+    let version = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_create_ruleset,
+            std::ptr::null::<RulesetAttr>(),
+            0usize,
+            LANDLOCK_CREATE_RULESET_VERSION,
+        )
+    };
+    match Errno::result(version) {
+        Ok(version) => Ok(Some(version as u32)),
+        Err(err @ (Errno::ENOSYS | Errno::EOPNOTSUPP)) => {
+            tracing::debug!(?err, "landlock is not available");
+            Ok(None)
+        }
+        Err(err) => Err(LandlockError::AbiVersion(err)),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FsAccess {
+    /// Reading files and listing directories
+    Read,
+    /// Writing, truncating, creating, renaming and removing files
+    Write,
+    /// Executing files
+    Exec,
+}
+
+impl FsAccess {
+    fn bits(self) -> u64 {
+        match self {
+            Self::Read => ACCESS_FS_READ_FILE | ACCESS_FS_READ_DIR,
+            Self::Write => {
+                ACCESS_FS_WRITE_FILE
+                    | ACCESS_FS_REMOVE_DIR
+                    | ACCESS_FS_REMOVE_FILE
+                    | ACCESS_FS_MAKE_CHAR
+                    | ACCESS_FS_MAKE_DIR
+                    | ACCESS_FS_MAKE_REG
+                    | ACCESS_FS_MAKE_SOCK
+                    | ACCESS_FS_MAKE_FIFO
+                    | ACCESS_FS_MAKE_BLOCK
+                    | ACCESS_FS_MAKE_SYM
+                    | ACCESS_FS_REFER
+                    | ACCESS_FS_TRUNCATE
+                    | ACCESS_FS_IOCTL_DEV
+            }
+            Self::Exec => ACCESS_FS_EXECUTE,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetAccess {
+    /// Binding TCP sockets to the port
+    Bind,
+    /// Connecting TCP sockets to the port
+    Connect,
+}
+
+impl NetAccess {
+    fn bits(self) -> u64 {
+        match self {
+            Self::Bind => ACCESS_NET_BIND_TCP,
+            Self::Connect => ACCESS_NET_CONNECT_TCP,
+        }
+    }
+}
+
+/// Grants rights to the file hierarchy beneath a path of the container
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathRule {
+    pub path: PathBuf,
+    pub access: Vec<FsAccess>,
+}
+
+/// Grants rights to a TCP port
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortRule {
+    pub port: u16,
+    pub access: Vec<NetAccess>,
+}
+
+/// The Landlock ruleset the container process is restricted with
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LandlockRuleset {
+    #[serde(default)]
+    pub paths: Vec<PathRule>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<PortRule>,
+}
+
+impl LandlockRuleset {
+    /// Creates a ruleset which denies all file system access
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_path_rule<P: Into<PathBuf>>(mut self, path: P, access: &[FsAccess]) -> Self {
+        self.paths.push(PathRule {
+            path: path.into(),
+            access: access.to_vec(),
+        });
+        self
+    }
+
+    /// Adds a port rule, which makes the ruleset restrict TCP ports. They are
+    /// only restricted with Landlock ABI 4 or later.
+    pub fn with_port_rule(mut self, port: u16, access: &[NetAccess]) -> Self {
+        self.ports.push(PortRule {
+            port,
+            access: access.to_vec(),
+        });
+        self
+    }
+
+    /// Reads the ruleset from the `run.oci.landlock` annotation, if it is set.
+    pub fn from_annotations(annotations: &HashMap<String, String>) -> Result<Option<Self>> {
+        annotations
+            .get(LANDLOCK_ANNOTATION)
+            .map(|value| serde_json::from_str(value).map_err(LandlockError::InvalidAnnotation))
+            .transpose()
+    }
+
+    /// Restricts the calling process and its future children. Landlock requires
+    /// no new privileges, so it is set as well. Returns the ABI version the
+    /// ruleset was applied with, or None if Landlock isn't available.
+    pub fn apply(&self) -> Result<Option<u32>> {
+        let Some(abi) = abi_version()? else {
+            tracing::warn!("landlock is not supported by the kernel, the ruleset is not applied");
+            return Ok(None);
+        };
+
+        let handled_fs = handled_fs_access(abi);
+        let handled_net = if self.ports.is_empty() {
+            0
+        } else if abi < NET_ABI_VERSION {
+            tracing::warn!(
+                abi,
+                "landlock ABI doesn't support port rules, ports are not restricted"
+            );
+            0
+        } else {
+            ACCESS_NET_BIND_TCP | ACCESS_NET_CONNECT_TCP
+        };
+
+        let attr = RulesetAttr {
+            handled_access_fs: handled_fs,
+            handled_access_net: handled_net,
+        };
+        // Safety: attr is a valid ruleset attribute. Kernels before ABI 4 accept
+        // the larger struct as long as the unknown field is zero.
+        let fd = unsafe {
+            libc::syscall(
+                libc::SYS_landlock_create_ruleset,
+                &attr as *const RulesetAttr,
+                size_of::<RulesetAttr>(),
+                0u32,
+            )
+        };
+        let fd = Errno::result(fd).map_err(LandlockError::CreateRuleset)?;
+        // Safety: the syscall returned a new file descriptor
+        let ruleset = unsafe { OwnedFd::from_raw_fd(fd as i32) };
+
+        for rule in &self.paths {
+            add_path_rule(&ruleset, rule, handled_fs)?;
+        }
+        if handled_net != 0 {
+            for rule in &self.ports {
+                add_port_rule(&ruleset, rule)?;
+            }
+        }
+
+        prctl::set_no_new_privileges(true)
+            .map_err(|e| LandlockError::NoNewPrivileges(Errno::from_raw(e)))?;
+        // Safety: ruleset is a valid landlock ruleset
+        let ret =
+            unsafe { libc::syscall(libc::SYS_landlock_restrict_self, ruleset.as_raw_fd(), 0u32) };
+        Errno::result(ret).map_err(LandlockError::RestrictSelf)?;
+
+        tracing::debug!(abi, "applied landlock ruleset");
+        Ok(Some(abi))
+    }
+}
+
+// Returns the file system rights the given ABI version can restrict
+fn handled_fs_access(abi: u32) -> u64 {
+    let mut access = ACCESS_FS_REFER - 1;
+    if abi >= 2 {
+        access |= ACCESS_FS_REFER;
+    }
+    if abi >= 3 {
+        access |= ACCESS_FS_TRUNCATE;
+    }
+    if abi >= 5 {
+        access |= ACCESS_FS_IOCTL_DEV;
+    }
+    access
+}
+
+// Returns the rights of a rule, limited to the handled ones and, for anything
+// but directories, to the ones which apply to files
+fn allowed_fs_access(access: &[FsAccess], handled: u64, is_dir: bool) -> u64 {
+    let allowed = access.iter().fold(0, |bits, access| bits | access.bits()) & handled;
+    if is_dir {
+        allowed
+    } else {
+        allowed & ACCESS_FS_FILE
+    }
+}
+
+fn add_path_rule(ruleset: &OwnedFd, rule: &PathRule, handled: u64) -> Result<()> {
+    let fd = open(
+        rule.path.as_path(),
+        OFlag::O_PATH | OFlag::O_CLOEXEC,
+        Mode::empty(),
+    )
+    .map_err(|source| LandlockError::OpenPath {
+        path: rule.path.clone(),
+        source,
+    })?;
+    // Safety: open returned a new file descriptor
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    let is_dir = is_dir(&fd, &rule.path)?;
+
+    let attr = PathBeneathAttr {
+        allowed_access: allowed_fs_access(&rule.access, handled, is_dir),
+        parent_fd: fd.as_raw_fd(),
+    };
+    add_rule(ruleset, LANDLOCK_RULE_PATH_BENEATH, &attr as *const _ as _).map_err(|source| {
+        LandlockError::AddRule {
+            rule: rule.path.display().to_string(),
+            source,
+        }
+    })
+}
+
+fn add_port_rule(ruleset: &OwnedFd, rule: &PortRule) -> Result<()> {
+    let attr = NetPortAttr {
+        allowed_access: rule
+            .access
+            .iter()
+            .fold(0, |bits, access| bits | access.bits()),
+        port: rule.port.into(),
+    };
+    add_rule(ruleset, LANDLOCK_RULE_NET_PORT, &attr as *const _ as _).map_err(|source| {
+        LandlockError::AddRule {
+            rule: format!("port {}", rule.port),
+            source,
+        }
+    })
+}
+
+fn add_rule(
+    ruleset: &OwnedFd,
+    rule_type: libc::c_int,
+    attr: *const libc::c_void,
+) -> nix::Result<()> {
+    // Safety: attr points to the attribute of the rule type
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_landlock_add_rule,
+            ruleset.as_raw_fd(),
+            rule_type,
+            attr,
+            0u32,
+        )
+    };
+    Errno::result(ret).map(drop)
+}
+
+fn is_dir(fd: &OwnedFd, path: &Path) -> Result<bool> {
+    nix::sys::stat::fstat(fd.as_raw_fd())
+        .map(|stat| stat.st_mode & libc::S_IFMT == libc::S_IFDIR)
+        .map_err(|source| LandlockError::OpenPath {
+            path: path.to_owned(),
+            source,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_from_annotations() -> Result<()> {
+        let mut annotations = HashMap::new();
+        assert_eq!(LandlockRuleset::from_annotations(&annotations)?, None);
+
+        annotations.insert(
+            LANDLOCK_ANNOTATION.to_owned(),
+            r#"{"paths":[{"path":"/usr","access":["read","exec"]},{"path":"/tmp","access":["read","write"]}],"ports":[{"port":8080,"access":["bind"]}]}"#.to_owned(),
+        );
+        assert_eq!(
+            LandlockRuleset::from_annotations(&annotations)?,
+            Some(
+                LandlockRuleset::new()
+                    .with_path_rule("/usr", &[FsAccess::Read, FsAccess::Exec])
+                    .with_path_rule("/tmp", &[FsAccess::Read, FsAccess::Write])
+                    .with_port_rule(8080, &[NetAccess::Bind])
+            )
+        );
+
+        annotations.insert(
+            LANDLOCK_ANNOTATION.to_owned(),
+            r#"{"paths":[{"path":"/usr","access":["delete"]}]}"#.to_owned(),
+        );
+        assert!(LandlockRuleset::from_annotations(&annotations).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_handled_fs_access() {
+        assert_eq!(handled_fs_access(1), 0x1fff);
+        assert_eq!(handled_fs_access(2), 0x3fff);
+        assert_eq!(handled_fs_access(4), 0x7fff);
+        assert_eq!(handled_fs_access(6), 0xffff);
+    }
+
+    #[test]
+    fn test_allowed_fs_access() {
+        let access = [FsAccess::Read, FsAccess::Write];
+        assert_eq!(
+            allowed_fs_access(&access, handled_fs_access(1), true),
+            0x1fff & !ACCESS_FS_EXECUTE
+        );
+        // rights the ABI doesn't know are dropped
+        assert_eq!(
+            allowed_fs_access(&access, handled_fs_access(1), true) & ACCESS_FS_TRUNCATE,
+            0
+        );
+        // directory rights don't apply to files
+        assert_eq!(
+            allowed_fs_access(&access, handled_fs_access(3), false),
+            ACCESS_FS_READ_FILE | ACCESS_FS_WRITE_FILE | ACCESS_FS_TRUNCATE
+        );
+        assert_eq!(
+            allowed_fs_access(&[FsAccess::Exec], handled_fs_access(3), false),
+            ACCESS_FS_EXECUTE
+        );
+    }
+}
@@ -5,14 +5,19 @@ pub mod config;
 pub mod container;
 pub mod error;
 pub mod hooks;
+pub mod landlock;
+pub mod log_sink;
 pub mod namespaces;
 pub mod network;
 pub mod notify_socket;
 pub mod process;
 pub mod rootfs;
-#[cfg(feature = "libseccomp")]
+pub mod sd_notify;
+#[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
 pub mod seccomp;
+pub mod selinux;
 pub mod signal;
+pub mod subid;
 pub mod syscall;
 pub mod test_utils;
 pub mod tty;
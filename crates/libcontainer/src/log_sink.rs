@@ -0,0 +1,443 @@
+//! Relays the stdout and stderr of a container to a log file in the CRI or the
+//! docker json-file format. The relay runs in a process of its own, so the log
+//! keeps being written after the caller exited.
+use std::borrow::Cow;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::fd::{AsFd, AsRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use nix::errno::Errno;
+use nix::fcntl::OFlag;
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use nix::sys::wait::waitpid;
+use nix::unistd::{self, ForkResult};
+use serde::Serialize;
+
+use crate::utils;
+
+/// Lines longer than this are split into partial records, like the kubelet does
+const MAX_LINE_LEN: usize = 16 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum LogSinkError {
+    #[error("unknown log format {0}, expected cri or json-file")]
+    UnknownFormat(String),
+    #[error("failed to open log file {path}")]
+    Open { path: PathBuf, source: io::Error },
+    #[error("failed to create pipe for the log relay")]
+    Pipe(#[source] nix::Error),
+    #[error("failed to fork the log relay")]
+    Fork(#[source] nix::Error),
+}
+
+type Result<T> = std::result::Result<T, LogSinkError>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `<time> <stream> <P|F> <content>` lines, as read by the kubelet
+    Cri,
+    /// `{"log":...,"stream":...,"time":...}` lines of the docker json-file driver
+    JsonFile,
+}
+
+impl FromStr for LogFormat {
+    type Err = LogSinkError;
+
+    fn from_str(format: &str) -> Result<Self> {
+        match format {
+            "cri" => Ok(Self::Cri),
+            "json-file" => Ok(Self::JsonFile),
+            _ => Err(LogSinkError::UnknownFormat(format.to_owned())),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    log: Cow<'a, str>,
+    stream: &'a str,
+    time: String,
+}
+
+impl LogFormat {
+    /// Formats a record of the log. Content is a full line including its
+    /// newline, the last part of the stream without one, or part of a long line.
+    fn record(
+        &self,
+        time: DateTime<Utc>,
+        stream: Stream,
+        content: &[u8],
+        partial: bool,
+    ) -> Vec<u8> {
+        let time = time.to_rfc3339_opts(SecondsFormat::Nanos, true);
+        let mut record = match self {
+            LogFormat::Cri => {
+                let tag = if partial { "P" } else { "F" };
+                let mut record = format!("{time} {} {tag} ", stream.as_str()).into_bytes();
+                record.extend_from_slice(content.strip_suffix(b"\n").unwrap_or(content));
+                record
+            }
+            LogFormat::JsonFile => serde_json::to_vec(&JsonRecord {
+                log: String::from_utf8_lossy(content),
+                stream: stream.as_str(),
+                time,
+            })
+            .expect("log record is serializable"),
+        };
+        record.push(b'\n');
+        record
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+/// A log file the stdout and stderr of a container are written to
+#[derive(Debug, Clone)]
+pub struct LogSink {
+    path: PathBuf,
+    format: LogFormat,
+    max_size: Option<u64>,
+    max_files: u32,
+}
+
+impl LogSink {
+    pub fn new<P: Into<PathBuf>>(path: P, format: LogFormat) -> Self {
+        Self {
+            path: path.into(),
+            format,
+            max_size: None,
+            max_files: 1,
+        }
+    }
+
+    /// Rotates the log file before it would grow beyond max_size bytes
+    pub fn with_max_size(mut self, max_size: Option<u64>) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Sets the number of log files kept on rotation, including the current
+    /// one. With a single file, the log is truncated on rotation.
+    pub fn with_max_files(mut self, max_files: u32) -> Self {
+        self.max_files = max_files.max(1);
+        self
+    }
+
+    /// Starts the relay and returns the pipes for the stdout and stderr of the
+    /// container. The relay writes the log until every process closed both
+    /// pipes, no matter if the caller or the container process exited before.
+    pub fn spawn(&self) -> Result<(OwnedFd, OwnedFd)> {
+        let mut writer = LogWriter::open(self.clone())?;
+        let (stdout_read, stdout_write) =
+            unistd::pipe2(OFlag::O_CLOEXEC).map_err(LogSinkError::Pipe)?;
+        let (stderr_read, stderr_write) =
+            unistd::pipe2(OFlag::O_CLOEXEC).map_err(LogSinkError::Pipe)?;
+
+        // Safety: the child only forks once more and exits, and the grandchild
+        // relays the pipes before it exits
+        match unsafe { unistd::fork() }.map_err(LogSinkError::Fork)? {
+            ForkResult::Parent { child } => {
+                let _ = waitpid(child, None);
+                Ok((stdout_write, stderr_write))
+            }
+            ForkResult::Child => {
+                // the relay must not hold the write ends, or it never sees EOF
+                drop((stdout_write, stderr_write));
+                // the relay is reparented once the child exits, so that it
+                // doesn't show up as a process the caller has to wait for
+                if let Ok(ForkResult::Child) = unsafe { unistd::fork() } {
+                    let keep = [
+                        stdout_read.as_raw_fd(),
+                        stderr_read.as_raw_fd(),
+                        writer.file.as_raw_fd(),
+                    ];
+                    let result = utils::detach_from_caller(&keep)
+                        .and_then(|_| relay(&mut writer, stdout_read, stderr_read));
+                    if let Err(err) = result {
+                        tracing::error!(?err, path = ?self.path, "log relay failed");
+                        std::process::exit(1);
+                    }
+                }
+                std::process::exit(0);
+            }
+        }
+    }
+}
+
+fn relay(writer: &mut LogWriter, stdout: OwnedFd, stderr: OwnedFd) -> io::Result<()> {
+    let mut streams = vec![
+        (Stream::Stdout, stdout, LineBuffer::default()),
+        (Stream::Stderr, stderr, LineBuffer::default()),
+    ];
+    let mut buf = [0u8; 8192];
+    while !streams.is_empty() {
+        let mut fds: Vec<PollFd> = streams
+            .iter()
+            .map(|(_, fd, _)| PollFd::new(fd.as_fd(), PollFlags::POLLIN))
+            .collect();
+        match poll(&mut fds, PollTimeout::NONE) {
+            Err(Errno::EINTR) => continue,
+            result => result?,
+        };
+        let ready: Vec<bool> = fds
+            .iter()
+            .map(|fd| fd.revents().is_some_and(|events| !events.is_empty()))
+            .collect();
+        drop(fds);
+
+        let mut ready = ready.into_iter();
+        let mut result = Ok(());
+        streams.retain_mut(|(stream, fd, lines)| {
+            if !ready.next().unwrap_or(false) || result.is_err() {
+                return true;
+            }
+            match unistd::read(fd.as_raw_fd(), &mut buf) {
+                Ok(0) => {
+                    if let Some(rest) = lines.finish() {
+                        result = writer.write(*stream, &rest, false);
+                    }
+                    false
+                }
+                Ok(n) => {
+                    result =
+                        lines
+                            .push(&buf[..n])
+                            .into_iter()
+                            .try_for_each(|(content, partial)| {
+                                writer.write(*stream, &content, partial)
+                            });
+                    true
+                }
+                Err(Errno::EINTR | Errno::EAGAIN) => true,
+                Err(err) => {
+                    result = Err(err.into());
+                    true
+                }
+            }
+        });
+        result?;
+    }
+    Ok(())
+}
+
+/// Splits the output of a stream into the records of the log, which are
+/// complete lines or parts of lines longer than MAX_LINE_LEN
+#[derive(Debug, Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Adds output of the stream and returns the records it completed,
+    /// together with whether they are only part of a line
+    fn push(&mut self, data: &[u8]) -> Vec<(Vec<u8>, bool)> {
+        self.pending.extend_from_slice(data);
+        let mut records = Vec::new();
+        loop {
+            match self.pending.iter().position(|&b| b == b'\n') {
+                Some(pos) if pos < MAX_LINE_LEN => {
+                    records.push((self.pending.drain(..=pos).collect(), false));
+                }
+                _ if self.pending.len() >= MAX_LINE_LEN => {
+                    records.push((self.pending.drain(..MAX_LINE_LEN).collect(), true));
+                }
+                _ => return records,
+            }
+        }
+    }
+
+    /// Returns the output after the last newline once the stream is closed
+    fn finish(&mut self) -> Option<Vec<u8>> {
+        (!self.pending.is_empty()).then(|| std::mem::take(&mut self.pending))
+    }
+}
+
+struct LogWriter {
+    sink: LogSink,
+    file: File,
+    size: u64,
+}
+
+impl LogWriter {
+    fn open(sink: LogSink) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&sink.path)
+            .map_err(|err| LogSinkError::Open {
+                path: sink.path.clone(),
+                source: err,
+            })?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self { sink, file, size })
+    }
+
+    fn write(&mut self, stream: Stream, content: &[u8], partial: bool) -> io::Result<()> {
+        let record = self
+            .sink
+            .format
+            .record(Utc::now(), stream, content, partial);
+        if let Some(max_size) = self.sink.max_size
+            && self.size > 0
+            && self.size + record.len() as u64 > max_size
+        {
+            self.rotate()?;
+        }
+        self.file.write_all(&record)?;
+        self.size += record.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts log.1 to log.2 and so on, dropping the oldest file, and starts
+    /// a new log file
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..self.sink.max_files).rev() {
+            match fs::rename(
+                rotated_path(&self.sink.path, n - 1),
+                rotated_path(&self.sink.path, n),
+            ) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.sink.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, n: u32) -> PathBuf {
+    if n == 0 {
+        return path.to_owned();
+    }
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2024-05-06T07:08:09.123456789Z")
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn test_log_format_from_str() {
+        assert_eq!("cri".parse::<LogFormat>().unwrap(), LogFormat::Cri);
+        assert_eq!(
+            "json-file".parse::<LogFormat>().unwrap(),
+            LogFormat::JsonFile
+        );
+        assert!("journald".parse::<LogFormat>().is_err());
+    }
+
+    #[test]
+    fn test_cri_record() {
+        let record = LogFormat::Cri.record(time(), Stream::Stdout, b"hello\n", false);
+        assert_eq!(record, b"2024-05-06T07:08:09.123456789Z stdout F hello\n");
+        let record = LogFormat::Cri.record(time(), Stream::Stderr, b"hel", true);
+        assert_eq!(record, b"2024-05-06T07:08:09.123456789Z stderr P hel\n");
+    }
+
+    #[test]
+    fn test_json_file_record() {
+        let record = LogFormat::JsonFile.record(time(), Stream::Stderr, b"a \"b\"\n", false);
+        assert_eq!(
+            String::from_utf8(record).unwrap(),
+            "{\"log\":\"a \\\"b\\\"\\n\",\"stream\":\"stderr\",\"time\":\"2024-05-06T07:08:09.123456789Z\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_line_buffer() {
+        let mut lines = LineBuffer::default();
+        assert!(lines.push(b"par").is_empty());
+        assert_eq!(
+            lines.push(b"tial\nfull\nrest"),
+            vec![(b"partial\n".to_vec(), false), (b"full\n".to_vec(), false)]
+        );
+        assert_eq!(lines.finish(), Some(b"rest".to_vec()));
+        assert_eq!(lines.finish(), None);
+
+        let long = vec![b'x'; MAX_LINE_LEN + 10];
+        let records = lines.push(&long);
+        assert_eq!(records, vec![(vec![b'x'; MAX_LINE_LEN], true)]);
+        assert_eq!(lines.push(b"\n"), vec![(b"xxxxxxxxxx\n".to_vec(), false)]);
+    }
+
+    #[test]
+    fn test_rotation() -> anyhow::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let path = tmp.path().join("container.log");
+        let sink = LogSink::new(&path, LogFormat::Cri)
+            .with_max_size(Some(100))
+            .with_max_files(3);
+        let mut writer = LogWriter::open(sink)?;
+        for line in ["one", "two", "three", "four"] {
+            let content = format!("{line:-<40}\n");
+            writer.write(Stream::Stdout, content.as_bytes(), false)?;
+        }
+
+        // each record is 81 bytes, so every write after the first rotates
+        let read = |n| fs::read_to_string(rotated_path(&path, n));
+        assert!(read(0)?.contains("four"));
+        assert!(read(1)?.contains("three"));
+        assert!(read(2)?.contains("two"));
+        assert!(read(3).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_spawn_relay() -> anyhow::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let path = tmp.path().join("container.log");
+        let (stdout, stderr) = LogSink::new(&path, LogFormat::Cri).spawn()?;
+        File::from(stdout).write_all(b"out\npartial")?;
+        File::from(stderr).write_all(b"err\n")?;
+
+        // the relay exits once both pipes are closed, which can't be waited on
+        // since it is no child of the test
+        let mut log = String::new();
+        for _ in 0..100 {
+            log = fs::read_to_string(&path)?;
+            if log.lines().count() == 3 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        let mut records: Vec<&str> = log
+            .lines()
+            .map(|line| line.split_once(' ').unwrap().1)
+            .collect();
+        records.sort();
+        assert_eq!(
+            records,
+            vec!["stderr F err", "stdout F out", "stdout F partial"]
+        );
+        Ok(())
+    }
+}
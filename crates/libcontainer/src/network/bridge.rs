@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::net::IpAddr;
+use std::os::fd::RawFd;
+
+use serde::{Deserialize, Serialize};
+
+use super::address::AddressClient;
+use super::cidr::CidrAddress;
+use super::link::LinkClient;
+use super::route::RouteClient;
+use super::wrapper::create_network_client;
+use super::{NetworkError, Result};
+
+/// Name of the bridge the container is attached to. Setting it enables the mode.
+pub const BRIDGE_ANNOTATION: &str = "org.youki.network.bridge";
+/// Comma separated addresses of the container in CIDR notation
+pub const ADDRESSES_ANNOTATION: &str = "org.youki.network.addresses";
+/// Comma separated gateways of the default routes of the container
+pub const GATEWAYS_ANNOTATION: &str = "org.youki.network.gateways";
+/// Name of the interface in the container, eth0 by default
+pub const INTERFACE_ANNOTATION: &str = "org.youki.network.interface";
+/// MTU of the veth pair
+pub const MTU_ANNOTATION: &str = "org.youki.network.mtu";
+
+const DEFAULT_INTERFACE: &str = "eth0";
+
+/// Connects the network namespace of a container to a bridge on the host by a
+/// veth pair, for standalone containers which do not need a CNI setup.
+///
+/// The bridge is created if it doesn't exist and is left behind on deletion,
+/// while the veth pair is removed together with the container.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeNetwork {
+    pub bridge: String,
+    pub host_interface: String,
+    pub interface: String,
+    pub addresses: Vec<CidrAddress>,
+    pub gateways: Vec<IpAddr>,
+    pub mtu: Option<u32>,
+}
+
+impl BridgeNetwork {
+    /// Creates the configuration without addresses, where the name of the host end
+    /// of the veth pair is derived from the container id
+    pub fn new<S: Into<String>>(bridge: S, container_id: &str) -> Self {
+        let mut hasher = DefaultHasher::new();
+        container_id.hash(&mut hasher);
+        Self {
+            bridge: bridge.into(),
+            host_interface: format!("veth{:08x}", hasher.finish() as u32),
+            interface: DEFAULT_INTERFACE.to_owned(),
+            addresses: Vec::new(),
+            gateways: Vec::new(),
+            mtu: None,
+        }
+    }
+
+    pub fn with_address(mut self, address: CidrAddress) -> Self {
+        self.addresses.push(address);
+        self
+    }
+
+    /// Adds a default route through the gateway
+    pub fn with_gateway(mut self, gateway: IpAddr) -> Self {
+        self.gateways.push(gateway);
+        self
+    }
+
+    /// Sets the name of the interface in the container
+    pub fn with_interface<S: Into<String>>(mut self, interface: S) -> Self {
+        self.interface = interface.into();
+        self
+    }
+
+    pub fn with_mtu(mut self, mtu: u32) -> Self {
+        self.mtu = Some(mtu);
+        self
+    }
+
+    /// Reads the configuration from the annotations of the spec. Returns None if
+    /// no bridge is set.
+    pub fn from_annotations(
+        annotations: &HashMap<String, String>,
+        container_id: &str,
+    ) -> Result<Option<Self>> {
+        let Some(bridge) = annotations.get(BRIDGE_ANNOTATION) else {
+            return Ok(None);
+        };
+        let invalid = |key: &str, value: &str| NetworkError::InvalidAnnotation {
+            key: key.to_owned(),
+            value: value.to_owned(),
+        };
+        let list = |key: &str| {
+            annotations
+                .get(key)
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut network = Self::new(bridge.as_str(), container_id);
+        for address in list(ADDRESSES_ANNOTATION) {
+            let address = address
+                .parse()
+                .map_err(|_| invalid(ADDRESSES_ANNOTATION, address))?;
+            network = network.with_address(address);
+        }
+        for gateway in list(GATEWAYS_ANNOTATION) {
+            let gateway = gateway
+                .parse()
+                .map_err(|_| invalid(GATEWAYS_ANNOTATION, gateway))?;
+            network = network.with_gateway(gateway);
+        }
+        if let Some(interface) = annotations.get(INTERFACE_ANNOTATION) {
+            network = network.with_interface(interface.as_str());
+        }
+        if let Some(mtu) = annotations.get(MTU_ANNOTATION) {
+            let mtu = mtu.parse().map_err(|_| invalid(MTU_ANNOTATION, mtu))?;
+            network = network.with_mtu(mtu);
+        }
+
+        Ok(Some(network))
+    }
+
+    /// Creates the veth pair with one end in the network namespace given by
+    /// netns_fd and attaches the other end to the bridge. Runs on the host.
+    pub fn setup_host(&self, netns_fd: RawFd) -> Result<()> {
+        let mut link_client = LinkClient::new(create_network_client())?;
+        self.setup_host_links(&mut link_client, netns_fd)
+    }
+
+    /// Brings up the interface of the container and configures its addresses and
+    /// default routes. Runs in the network namespace of the container.
+    pub fn setup_container(&self) -> Result<()> {
+        let mut link_client = LinkClient::new(create_network_client())?;
+        let mut addr_client = AddressClient::new(create_network_client())?;
+        let mut route_client = RouteClient::new(create_network_client())?;
+        self.setup_container_links(&mut link_client, &mut addr_client, &mut route_client)
+    }
+
+    /// Removes the veth pair. The bridge may be shared with other containers and
+    /// is kept.
+    pub fn cleanup(&self) -> Result<()> {
+        let mut link_client = LinkClient::new(create_network_client())?;
+        self.cleanup_links(&mut link_client)
+    }
+
+    fn setup_host_links(&self, link_client: &mut LinkClient, netns_fd: RawFd) -> Result<()> {
+        let bridge = match link_client.get_by_name(&self.bridge) {
+            Ok(bridge) => bridge,
+            Err(_) => {
+                tracing::debug!("creating bridge {}", self.bridge);
+                link_client.create_bridge(&self.bridge)?;
+                link_client.get_by_name(&self.bridge)?
+            }
+        };
+        link_client.set_up(bridge.header.index)?;
+
+        tracing::debug!(
+            "creating veth pair {} and {} in network namespace fd {}",
+            self.host_interface,
+            self.interface,
+            netns_fd
+        );
+        link_client.create_veth(&self.host_interface, &self.interface, netns_fd, self.mtu)?;
+        let host_link = link_client.get_by_name(&self.host_interface)?;
+        link_client.set_controller(host_link.header.index, bridge.header.index)?;
+        link_client.set_up(host_link.header.index)?;
+
+        Ok(())
+    }
+
+    fn setup_container_links(
+        &self,
+        link_client: &mut LinkClient,
+        addr_client: &mut AddressClient,
+        route_client: &mut RouteClient,
+    ) -> Result<()> {
+        let link = link_client.get_by_name(&self.interface)?;
+        let index = link.header.index;
+        for addr in &self.addresses {
+            tracing::debug!(
+                "adding address {} to network device {}",
+                addr,
+                self.interface
+            );
+            addr_client.add(index, addr.address, addr.prefix_len)?;
+        }
+        // routes can only be added through an interface which is up
+        link_client.set_up(index)?;
+        for gateway in &self.gateways {
+            tracing::debug!("adding default route via {} to {}", gateway, self.interface);
+            route_client.add_default(index, *gateway)?;
+        }
+
+        Ok(())
+    }
+
+    fn cleanup_links(&self, link_client: &mut LinkClient) -> Result<()> {
+        // the pair is already gone if the network namespace was destroyed
+        let Ok(link) = link_client.get_by_name(&self.host_interface) else {
+            return Ok(());
+        };
+        // the namespace may also be destroyed in between, e.g. while a failed
+        // init process exits, which removes the pair before the delete
+        match link_client.delete(link.header.index) {
+            Err(_) if link_client.get_by_name(&self.host_interface).is_err() => Ok(()),
+            result => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use netlink_packet_core::NetlinkPayload;
+    use netlink_packet_route::RouteNetlinkMessage;
+    use netlink_packet_route::address::AddressMessage;
+    use netlink_packet_route::link::LinkMessage;
+    use netlink_packet_route::route::RouteMessage;
+    use serial_test::serial;
+
+    use super::*;
+    use crate::network::fake::FakeNetlinkClient;
+    use crate::network::wrapper::ClientWrapper;
+
+    fn link(index: u32) -> RouteNetlinkMessage {
+        let mut link = LinkMessage::default();
+        link.header.index = index;
+        RouteNetlinkMessage::NewLink(link)
+    }
+
+    fn annotations(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_new() {
+        let network = BridgeNetwork::new("youki0", "test");
+        assert!(network.host_interface.starts_with("veth"));
+        assert_eq!(network.host_interface.len(), 12);
+        assert_eq!(network, BridgeNetwork::new("youki0", "test"));
+        assert_ne!(
+            network.host_interface,
+            BridgeNetwork::new("youki0", "other").host_interface
+        );
+        assert_eq!(network.interface, "eth0");
+    }
+
+    #[test]
+    fn test_from_annotations() -> anyhow::Result<()> {
+        assert_eq!(
+            BridgeNetwork::from_annotations(&annotations(&[]), "test")?,
+            None
+        );
+
+        let network = BridgeNetwork::from_annotations(
+            &annotations(&[
+                (BRIDGE_ANNOTATION, "youki0"),
+                (ADDRESSES_ANNOTATION, "10.0.0.2/24, fd00::2/64"),
+                (GATEWAYS_ANNOTATION, "10.0.0.1,fd00::1"),
+                (INTERFACE_ANNOTATION, "net0"),
+                (MTU_ANNOTATION, "1400"),
+            ]),
+            "test",
+        )?;
+        let expected = BridgeNetwork::new("youki0", "test")
+            .with_address("10.0.0.2/24".parse()?)
+            .with_address("fd00::2/64".parse()?)
+            .with_gateway("10.0.0.1".parse()?)
+            .with_gateway("fd00::1".parse()?)
+            .with_interface("net0")
+            .with_mtu(1400);
+        assert_eq!(network, Some(expected));
+
+        for (key, value) in [
+            (ADDRESSES_ANNOTATION, "10.0.0.2"),
+            (GATEWAYS_ANNOTATION, "10.0.0.1/24"),
+            (MTU_ANNOTATION, "large"),
+        ] {
+            let result = BridgeNetwork::from_annotations(
+                &annotations(&[(BRIDGE_ANNOTATION, "youki0"), (key, value)]),
+                "test",
+            );
+            assert!(
+                matches!(result, Err(NetworkError::InvalidAnnotation { .. })),
+                "{key}={value}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_setup_host_links_creates_bridge() {
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client.set_failure("No such device".to_string());
+        fake_client.set_expected_responses(vec![
+            link(0), // create bridge
+            link(7), // get bridge
+            link(0), // bridge up
+            link(0), // create veth
+            link(8), // get host end
+            link(0), // set controller
+            link(0), // host end up
+        ]);
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+
+        let network = BridgeNetwork::new("youki0", "test");
+        network.setup_host_links(&mut link_client, 123).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    fn test_setup_container_links() {
+        let mut fake_link_client = FakeNetlinkClient::new();
+        fake_link_client.set_expected_responses(vec![link(2), link(0)]);
+        let mut fake_addr_client = FakeNetlinkClient::new();
+        fake_addr_client.set_expected_responses(vec![RouteNetlinkMessage::NewAddress(
+            AddressMessage::default(),
+        )]);
+        let mut fake_route_client = FakeNetlinkClient::new();
+        fake_route_client
+            .set_expected_responses(vec![RouteNetlinkMessage::NewRoute(RouteMessage::default())]);
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_link_client)).unwrap();
+        let mut addr_client = AddressClient::new(ClientWrapper::Fake(fake_addr_client)).unwrap();
+        let mut route_client = RouteClient::new(ClientWrapper::Fake(fake_route_client)).unwrap();
+
+        let network = BridgeNetwork::new("youki0", "test")
+            .with_address("10.0.0.2/24".parse().unwrap())
+            .with_gateway("10.0.0.1".parse().unwrap());
+        network
+            .setup_container_links(&mut link_client, &mut addr_client, &mut route_client)
+            .unwrap();
+
+        let send_calls = addr_client.get_send_calls().unwrap();
+        assert_eq!(send_calls.len(), 1);
+        if let NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewAddress(addr)) =
+            &send_calls[0].payload
+        {
+            assert_eq!(addr.header.index, 2);
+            assert_eq!(addr.header.prefix_len, 24);
+        } else {
+            panic!("Expected NewAddress message");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_cleanup_links() {
+        // the host end is gone
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client.set_failure("No such device".to_string());
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        let network = BridgeNetwork::new("youki0", "test");
+        assert!(network.cleanup_links(&mut link_client).is_ok());
+
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client.set_expected_responses(vec![link(8), link(0)]);
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        assert!(network.cleanup_links(&mut link_client).is_ok());
+
+        // the pair is removed between the lookup and the delete
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client.set_expected_responses(vec![link(8)]);
+        fake_client.set_failure("Netlink error: -19".to_string());
+        fake_client.set_failure("No such device".to_string());
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        assert!(network.cleanup_links(&mut link_client).is_ok());
+
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client.set_expected_responses(vec![link(8)]);
+        fake_client.set_failure("Netlink error: -1".to_string());
+        fake_client.set_expected_responses(vec![link(8)]);
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        assert!(network.cleanup_links(&mut link_client).is_err());
+    }
+
+    #[test]
+    fn test_serialize() {
+        let network = BridgeNetwork::new("youki0", "test")
+            .with_address("10.0.0.2/24".parse().unwrap())
+            .with_mtu(1400);
+        let json = serde_json::to_value(&network).unwrap();
+        assert_eq!(json["hostInterface"], network.host_interface.as_str());
+        assert_eq!(
+            serde_json::from_value::<BridgeNetwork>(json).unwrap(),
+            network
+        );
+    }
+}
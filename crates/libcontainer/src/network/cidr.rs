@@ -1,14 +1,50 @@
+use std::fmt;
 use std::net::IpAddr;
+use std::str::FromStr;
 
 use netlink_packet_route::address::{AddressAttribute, AddressMessage};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CidrAddress {
     pub prefix_len: u8,
     pub address: IpAddr,
 }
 
+#[derive(Debug, thiserror::Error)]
+#[error("invalid CIDR address {0:?}")]
+pub struct ParseCidrError(String);
+
+impl FromStr for CidrAddress {
+    type Err = ParseCidrError;
+
+    /// Parses an address in CIDR notation, e.g. 10.0.0.2/24 or fd00::2/64
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseCidrError(s.to_owned());
+        let (address, prefix_len) = s.split_once('/').ok_or_else(err)?;
+        let address: IpAddr = address.parse().map_err(|_| err())?;
+        let prefix_len: u8 = prefix_len.parse().map_err(|_| err())?;
+        let max_prefix_len = match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        if prefix_len > max_prefix_len {
+            return Err(err());
+        }
+
+        Ok(CidrAddress {
+            prefix_len,
+            address,
+        })
+    }
+}
+
+impl fmt::Display for CidrAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
 impl From<&AddressMessage> for CidrAddress {
     fn from(msg: &AddressMessage) -> Self {
         let address =
@@ -79,6 +115,28 @@ mod tests {
         assert_eq!(cidr.address, ip);
     }
 
+    #[test]
+    fn test_cidr_from_str() {
+        let cidr: CidrAddress = "10.0.0.2/24".parse().unwrap();
+        assert_eq!(cidr.address, IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)));
+        assert_eq!(cidr.prefix_len, 24);
+        assert_eq!(cidr.to_string(), "10.0.0.2/24");
+
+        let cidr: CidrAddress = "fd00::2/64".parse().unwrap();
+        assert_eq!(cidr.prefix_len, 64);
+        assert_eq!(cidr.to_string(), "fd00::2/64");
+
+        for invalid in [
+            "10.0.0.2",
+            "10.0.0.2/33",
+            "fd00::2/129",
+            "eth0/24",
+            "10.0.0.2/",
+        ] {
+            assert!(invalid.parse::<CidrAddress>().is_err(), "{invalid}");
+        }
+    }
+
     #[test]
     fn test_parse_ip_address_with_local() {
         // Test IPv4 with IFA_LOCAL (typical IPv4 case)
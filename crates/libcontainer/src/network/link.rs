@@ -4,7 +4,9 @@ use netlink_packet_core::{
     NLM_F_ACK, NLM_F_CREATE, NLM_F_EXCL, NLM_F_REQUEST, NetlinkMessage, NetlinkPayload,
 };
 use netlink_packet_route::RouteNetlinkMessage;
-use netlink_packet_route::link::{LinkAttribute, LinkFlags, LinkMessage};
+use netlink_packet_route::link::{
+    InfoData, InfoKind, InfoVeth, LinkAttribute, LinkFlags, LinkInfo, LinkMessage,
+};
 
 use super::traits::{Client, NetlinkMessageHandler};
 use super::wrapper::ClientWrapper;
@@ -145,6 +147,85 @@ impl LinkClient {
         Ok(())
     }
 
+    /// Changes the flags of a network interface selected by a mask.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the network interface to modify
+    /// * `flags` - The new values of the flags
+    /// * `mask` - The flags to change
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or failure of the operation
+    pub fn set_flags(&mut self, index: u32, flags: LinkFlags, mask: LinkFlags) -> Result<()> {
+        let mut message = LinkMessage::default();
+        message.header.index = index;
+        message.header.change_mask = mask;
+        message.header.flags = flags & mask;
+
+        let mut req = NetlinkMessage::from(RouteNetlinkMessage::SetLink(message));
+        // NLM_F_REQUEST: This is a request to the kernel
+        // NLM_F_ACK: Request an acknowledgment from the kernel
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        req.finalize();
+
+        self.client.send_and_receive(&req, LinkMessageHandler)?;
+        Ok(())
+    }
+
+    /// Sets the MTU of a network interface.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the network interface to modify
+    /// * `mtu` - The new MTU
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or failure of the operation
+    pub fn set_mtu(&mut self, index: u32, mtu: u32) -> Result<()> {
+        let mut message = LinkMessage::default();
+        message.header.index = index;
+        message.attributes.push(LinkAttribute::Mtu(mtu));
+
+        let mut req = NetlinkMessage::from(RouteNetlinkMessage::SetLink(message));
+        // NLM_F_REQUEST: This is a request to the kernel
+        // NLM_F_ACK: Request an acknowledgment from the kernel
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        req.finalize();
+
+        self.client.send_and_receive(&req, LinkMessageHandler)?;
+        Ok(())
+    }
+
+    /// Sets the hardware address of a network interface.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the network interface to modify
+    /// * `address` - The new hardware address, e.g. the 6 bytes of a MAC address
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or failure of the operation
+    pub fn set_hardware_address(&mut self, index: u32, address: &[u8]) -> Result<()> {
+        let mut message = LinkMessage::default();
+        message.header.index = index;
+        message
+            .attributes
+            .push(LinkAttribute::Address(address.to_vec()));
+
+        let mut req = NetlinkMessage::from(RouteNetlinkMessage::SetLink(message));
+        // NLM_F_REQUEST: This is a request to the kernel
+        // NLM_F_ACK: Request an acknowledgment from the kernel
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        req.finalize();
+
+        self.client.send_and_receive(&req, LinkMessageHandler)?;
+        Ok(())
+    }
+
     /// Moves a network interface to a different network namespace.
     ///
     /// # Arguments
@@ -175,6 +256,137 @@ impl LinkClient {
         self.client.send_and_receive(&req, LinkMessageHandler)?;
         Ok(())
     }
+
+    /// Creates a bridge.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the bridge
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or failure of the operation
+    pub fn create_bridge(&mut self, name: &str) -> Result<()> {
+        let mut message = LinkMessage::default();
+        message
+            .attributes
+            .push(LinkAttribute::IfName(name.to_string()));
+        message
+            .attributes
+            .push(LinkAttribute::LinkInfo(vec![LinkInfo::Kind(
+                InfoKind::Bridge,
+            )]));
+
+        let mut req = NetlinkMessage::from(RouteNetlinkMessage::NewLink(message));
+        // NLM_F_REQUEST: This is a request to the kernel
+        // NLM_F_ACK: Request an acknowledgment from the kernel
+        // NLM_F_EXCL: Fail if the interface already exists
+        // NLM_F_CREATE: Create the interface if it doesn't exist
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_EXCL | NLM_F_CREATE;
+        req.finalize();
+
+        self.client.send_and_receive(&req, LinkMessageHandler)?;
+        Ok(())
+    }
+
+    /// Creates a veth pair whose peer is created directly in another network namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the end in the current network namespace
+    /// * `peer_name` - The name of the end in the target network namespace
+    /// * `peer_ns_fd` - The file descriptor of the target network namespace
+    /// * `mtu` - The MTU of both ends, the kernel default is used if None
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or failure of the operation
+    pub fn create_veth(
+        &mut self,
+        name: &str,
+        peer_name: &str,
+        peer_ns_fd: RawFd,
+        mtu: Option<u32>,
+    ) -> Result<()> {
+        let mut peer = LinkMessage::default();
+        peer.attributes
+            .push(LinkAttribute::IfName(peer_name.to_string()));
+        peer.attributes.push(LinkAttribute::NetNsFd(peer_ns_fd));
+
+        let mut message = LinkMessage::default();
+        message
+            .attributes
+            .push(LinkAttribute::IfName(name.to_string()));
+        if let Some(mtu) = mtu {
+            peer.attributes.push(LinkAttribute::Mtu(mtu));
+            message.attributes.push(LinkAttribute::Mtu(mtu));
+        }
+        message.attributes.push(LinkAttribute::LinkInfo(vec![
+            LinkInfo::Kind(InfoKind::Veth),
+            LinkInfo::Data(InfoData::Veth(InfoVeth::Peer(peer))),
+        ]));
+
+        let mut req = NetlinkMessage::from(RouteNetlinkMessage::NewLink(message));
+        // NLM_F_REQUEST: This is a request to the kernel
+        // NLM_F_ACK: Request an acknowledgment from the kernel
+        // NLM_F_EXCL: Fail if the interface already exists
+        // NLM_F_CREATE: Create the interface if it doesn't exist
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_EXCL | NLM_F_CREATE;
+        req.finalize();
+
+        self.client.send_and_receive(&req, LinkMessageHandler)?;
+        Ok(())
+    }
+
+    /// Attaches a network interface to a controller such as a bridge.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the network interface to attach
+    /// * `controller` - The index of the controller
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or failure of the operation
+    pub fn set_controller(&mut self, index: u32, controller: u32) -> Result<()> {
+        let mut message = LinkMessage::default();
+        message.header.index = index;
+        message
+            .attributes
+            .push(LinkAttribute::Controller(controller));
+
+        let mut req = NetlinkMessage::from(RouteNetlinkMessage::SetLink(message));
+        // NLM_F_REQUEST: This is a request to the kernel
+        // NLM_F_ACK: Request an acknowledgment from the kernel
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        req.finalize();
+
+        self.client.send_and_receive(&req, LinkMessageHandler)?;
+        Ok(())
+    }
+
+    /// Deletes a network interface. Deleting one end of a veth pair deletes both.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the network interface to delete
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or failure of the operation
+    pub fn delete(&mut self, index: u32) -> Result<()> {
+        let mut message = LinkMessage::default();
+        message.header.index = index;
+
+        let mut req = NetlinkMessage::from(RouteNetlinkMessage::DelLink(message));
+        // NLM_F_REQUEST: This is a request to the kernel
+        // NLM_F_ACK: Request an acknowledgment from the kernel
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
+        req.finalize();
+
+        self.client.send_and_receive(&req, LinkMessageHandler)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -486,4 +698,213 @@ mod tests {
             panic!("Expected Fake client");
         }
     }
+
+    #[test]
+    #[serial]
+    fn test_link_client_create_bridge_success() {
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client
+            .set_expected_responses(vec![RouteNetlinkMessage::NewLink(LinkMessage::default())]);
+
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        let result = link_client.create_bridge("youki0");
+        assert!(result.is_ok());
+
+        if let ClientWrapper::Fake(fake_client) = &mut link_client.client {
+            let send_calls = fake_client.get_send_calls();
+            assert_eq!(send_calls.len(), 1);
+
+            if let NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewLink(link)) =
+                &send_calls[0].payload
+            {
+                assert!(
+                    link.attributes
+                        .contains(&LinkAttribute::IfName("youki0".to_string()))
+                );
+                assert!(
+                    link.attributes
+                        .contains(&LinkAttribute::LinkInfo(vec![LinkInfo::Kind(
+                            InfoKind::Bridge
+                        )]))
+                );
+            } else {
+                panic!("Expected NewLink message");
+            }
+
+            let expected_flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_EXCL | NLM_F_CREATE;
+            assert_eq!(send_calls[0].header.flags, expected_flags);
+        } else {
+            panic!("Expected Fake client");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_link_client_create_veth_success() {
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client
+            .set_expected_responses(vec![RouteNetlinkMessage::NewLink(LinkMessage::default())]);
+
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        let result = link_client.create_veth("veth1234", "eth0", 456, Some(1400));
+        assert!(result.is_ok());
+
+        if let ClientWrapper::Fake(fake_client) = &mut link_client.client {
+            let send_calls = fake_client.get_send_calls();
+            assert_eq!(send_calls.len(), 1);
+
+            if let NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewLink(link)) =
+                &send_calls[0].payload
+            {
+                assert!(
+                    link.attributes
+                        .contains(&LinkAttribute::IfName("veth1234".to_string()))
+                );
+                assert!(link.attributes.contains(&LinkAttribute::Mtu(1400)));
+                let peer = link
+                    .attributes
+                    .iter()
+                    .find_map(|attr| match attr {
+                        LinkAttribute::LinkInfo(infos) => {
+                            infos.iter().find_map(|info| match info {
+                                LinkInfo::Data(InfoData::Veth(InfoVeth::Peer(peer))) => Some(peer),
+                                _ => None,
+                            })
+                        }
+                        _ => None,
+                    })
+                    .expect("veth peer not found");
+                assert!(
+                    peer.attributes
+                        .contains(&LinkAttribute::IfName("eth0".to_string()))
+                );
+                assert!(peer.attributes.contains(&LinkAttribute::NetNsFd(456)));
+                assert!(peer.attributes.contains(&LinkAttribute::Mtu(1400)));
+            } else {
+                panic!("Expected NewLink message");
+            }
+        } else {
+            panic!("Expected Fake client");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_link_client_set_controller_success() {
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client
+            .set_expected_responses(vec![RouteNetlinkMessage::NewLink(LinkMessage::default())]);
+
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        let result = link_client.set_controller(42, 7);
+        assert!(result.is_ok());
+
+        if let ClientWrapper::Fake(fake_client) = &mut link_client.client {
+            let send_calls = fake_client.get_send_calls();
+            if let NetlinkPayload::InnerMessage(RouteNetlinkMessage::SetLink(link)) =
+                &send_calls[0].payload
+            {
+                assert_eq!(link.header.index, 42);
+                assert_eq!(link.attributes, vec![LinkAttribute::Controller(7)]);
+            } else {
+                panic!("Expected SetLink message");
+            }
+        } else {
+            panic!("Expected Fake client");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_link_client_delete() {
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client
+            .set_expected_responses(vec![RouteNetlinkMessage::NewLink(LinkMessage::default())]);
+        fake_client.set_failure("Delete failed".to_string());
+
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        assert!(link_client.delete(42).is_ok());
+        assert!(link_client.delete(42).is_err());
+
+        if let ClientWrapper::Fake(fake_client) = &mut link_client.client {
+            let send_calls = fake_client.get_send_calls();
+            assert_eq!(send_calls.len(), 2);
+            if let NetlinkPayload::InnerMessage(RouteNetlinkMessage::DelLink(link)) =
+                &send_calls[0].payload
+            {
+                assert_eq!(link.header.index, 42);
+            } else {
+                panic!("Expected DelLink message");
+            }
+        } else {
+            panic!("Expected Fake client");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_link_client_set_flags_success() {
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client
+            .set_expected_responses(vec![RouteNetlinkMessage::NewLink(LinkMessage::default())]);
+
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        let result = link_client.set_flags(
+            42,
+            LinkFlags::Up | LinkFlags::Promisc,
+            LinkFlags::Promisc | LinkFlags::Noarp,
+        );
+        assert!(result.is_ok());
+
+        if let ClientWrapper::Fake(fake_client) = &mut link_client.client {
+            let send_calls = fake_client.get_send_calls();
+            if let NetlinkPayload::InnerMessage(RouteNetlinkMessage::SetLink(link)) =
+                &send_calls[0].payload
+            {
+                assert_eq!(link.header.index, 42);
+                assert_eq!(link.header.flags, LinkFlags::Promisc);
+                assert_eq!(
+                    link.header.change_mask,
+                    LinkFlags::Promisc | LinkFlags::Noarp
+                );
+            } else {
+                panic!("Expected SetLink message");
+            }
+        } else {
+            panic!("Expected Fake client");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_link_client_set_mtu_and_hardware_address() {
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client.set_expected_responses(vec![
+            RouteNetlinkMessage::NewLink(LinkMessage::default()),
+            RouteNetlinkMessage::NewLink(LinkMessage::default()),
+        ]);
+
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        let mac = [0x02, 0x42, 0xac, 0x11, 0x00, 0x02];
+        assert!(link_client.set_mtu(42, 9000).is_ok());
+        assert!(link_client.set_hardware_address(42, &mac).is_ok());
+
+        if let ClientWrapper::Fake(fake_client) = &mut link_client.client {
+            let send_calls = fake_client.get_send_calls();
+            assert_eq!(send_calls.len(), 2);
+            let attributes: Vec<_> = send_calls
+                .iter()
+                .map(|call| match &call.payload {
+                    NetlinkPayload::InnerMessage(RouteNetlinkMessage::SetLink(link)) => {
+                        link.attributes.clone()
+                    }
+                    _ => panic!("Expected SetLink message"),
+                })
+                .collect();
+            assert_eq!(attributes[0], vec![LinkAttribute::Mtu(9000)]);
+            assert_eq!(attributes[1], vec![LinkAttribute::Address(mac.to_vec())]);
+        } else {
+            panic!("Expected Fake client");
+        }
+    }
 }
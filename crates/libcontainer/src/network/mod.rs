@@ -1,9 +1,12 @@
 pub mod address;
+pub mod bridge;
 pub mod cidr;
 mod client;
 mod fake;
 pub mod link;
 pub mod network_device;
+pub mod rootless;
+pub mod route;
 mod traits;
 pub mod wrapper;
 
@@ -15,6 +18,17 @@ pub enum NetworkError {
     IO(#[from] std::io::Error),
     #[error("failed to initialize NetlinkClient")]
     ClientInitializeError,
+    #[error("invalid value {value:?} of annotation {key}")]
+    InvalidAnnotation { key: String, value: String },
+    #[error("invalid rootless network mode {0:?}, expected pasta or slirp4netns")]
+    InvalidRootlessNetworkMode(String),
+    #[error("invalid port mapping {0:?}, expected [host_ip:]host_port:container_port[/tcp|/udp]")]
+    InvalidPortMapping(String),
+    #[error("{program} failed: {reason}")]
+    RootlessNetwork {
+        program: &'static str,
+        reason: String,
+    },
 }
 
 type Result<T> = std::result::Result<T, NetworkError>;
@@ -1,14 +1,57 @@
 use std::os::fd::RawFd;
 
 use netlink_packet_route::address::{AddressHeaderFlags, AddressScope};
+use netlink_packet_route::link::{LinkAttribute, LinkFlags, LinkMessage};
+use netlink_packet_route::route::{RouteProtocol, RouteType};
 use oci_spec::runtime::LinuxNetDevice;
+use serde::{Deserialize, Serialize};
 
 use super::Result;
 use super::address::AddressClient;
 use super::link::LinkClient;
+use super::route::{RouteClient, RouteConfig};
 use super::wrapper::create_network_client;
 use crate::network::cidr::CidrAddress;
 
+const LOOPBACK_DEVICE: &str = "lo";
+const RT_TABLE_LOCAL: u32 = 255;
+
+/// The link flags which are carried over to the network namespace of the container.
+/// The device is always brought up there.
+const RESTORED_FLAGS: LinkFlags = LinkFlags::Noarp
+    .union(LinkFlags::Promisc)
+    .union(LinkFlags::Allmulti)
+    .union(LinkFlags::Multicast);
+
+/// The configuration of a network device on the host, which is restored after the
+/// device has been moved to the network namespace of the container.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkDeviceState {
+    pub addresses: Vec<CidrAddress>,
+    pub routes: Vec<RouteConfig>,
+    pub mtu: Option<u32>,
+    pub hardware_address: Option<Vec<u8>>,
+    pub flags: u32,
+}
+
+impl NetworkDeviceState {
+    fn from_link(link: &LinkMessage) -> Self {
+        let mut state = Self {
+            flags: (link.header.flags & RESTORED_FLAGS).bits(),
+            ..Default::default()
+        };
+        for attr in &link.attributes {
+            match attr {
+                LinkAttribute::Mtu(mtu) => state.mtu = Some(*mtu),
+                LinkAttribute::Address(address) => state.hardware_address = Some(address.clone()),
+                _ => {}
+            }
+        }
+        state
+    }
+}
+
 /// Resolves the final name for a network device.
 /// If the device has a configured name (non-empty), use it; otherwise use the original name.
 pub fn resolve_device_name<'a>(device: &'a LinuxNetDevice, original_name: &'a str) -> &'a str {
@@ -23,12 +66,13 @@ pub fn resolve_device_name<'a>(device: &'a LinuxNetDevice, original_name: &'a st
 /// and optionally change the device name.
 /// The device name will be kept the same if device.Name is None or an empty string.
 /// This function ensures that the move and rename operations occur atomically.
-/// It preserves existing interface attributes, including IP addresses.
+/// It returns the configuration of the device, including IP addresses and routes,
+/// which has to be restored in the network namespace.
 pub fn dev_change_net_namespace(
     name: &str,
     netns_fd: RawFd,
     device: &LinuxNetDevice,
-) -> Result<Vec<CidrAddress>> {
+) -> Result<NetworkDeviceState> {
     tracing::debug!(
         "attaching network device {} to network namespace fd {}",
         name,
@@ -37,6 +81,7 @@ pub fn dev_change_net_namespace(
 
     let mut link_client = LinkClient::new(create_network_client())?;
     let mut addr_client = AddressClient::new(create_network_client())?;
+    let mut route_client = RouteClient::new(create_network_client())?;
 
     let new_name = resolve_device_name(device, name);
 
@@ -44,6 +89,21 @@ pub fn dev_change_net_namespace(
 
     let index = link.header.index;
 
+    // The routes through the interface are removed as soon as it goes down, so they
+    // have to be collected first.
+    let routes = route_client.get_by_index(index)?;
+    let routes = routes
+        .iter()
+        .filter(|route| {
+            // Routes of the kernel follow from the addresses and local ones from the
+            // interface itself, the kernel recreates both in the new namespace.
+            route.header.protocol != RouteProtocol::Kernel
+                && route.header.kind == RouteType::Unicast
+        })
+        .filter_map(RouteConfig::from_message)
+        .filter(|route| route.table != RT_TABLE_LOCAL)
+        .collect();
+
     // Set the interface link state to DOWN before modifying attributes like namespace or name.
     // This prevents potential conflicts or disruptions on the host network during the transition,
     // particularly if other host components depend on this specific interface or its properties.
@@ -93,7 +153,11 @@ pub fn dev_change_net_namespace(
         .map(CidrAddress::from)
         .collect();
 
-    Ok(cidr_addrs)
+    Ok(NetworkDeviceState {
+        addresses: cidr_addrs,
+        routes,
+        ..NetworkDeviceState::from_link(&link)
+    })
 }
 
 /// Core logic for setting up addresses in the new network namespace
@@ -122,12 +186,85 @@ pub fn setup_addresses_in_network_namespace(
     Ok(())
 }
 
+/// Restores the configuration of a network device after it was moved to the new
+/// network namespace and brings it up.
+pub fn setup_network_device_in_network_namespace(
+    state: &NetworkDeviceState,
+    link: &LinkMessage,
+    new_name: &str,
+    link_client: &mut LinkClient,
+    addr_client: &mut AddressClient,
+    route_client: &mut RouteClient,
+) -> Result<()> {
+    let index = link.header.index;
+    let current = NetworkDeviceState::from_link(link);
+
+    // Drivers may refuse to change these attributes, e.g. the hardware address of
+    // an SR-IOV VF, so they are only set when the move has changed them.
+    if let Some(mtu) = state.mtu.filter(|mtu| current.mtu != Some(*mtu)) {
+        tracing::debug!("setting mtu {} of network device {}", mtu, new_name);
+        link_client.set_mtu(index, mtu)?;
+    }
+    if let Some(address) = state
+        .hardware_address
+        .as_ref()
+        .filter(|address| current.hardware_address.as_ref() != Some(*address))
+    {
+        tracing::debug!(
+            "setting hardware address {:02x?} of network device {}",
+            address,
+            new_name
+        );
+        link_client.set_hardware_address(index, address)?;
+    }
+    if state.flags != current.flags {
+        link_client.set_flags(
+            index,
+            LinkFlags::from_bits_truncate(state.flags),
+            RESTORED_FLAGS,
+        )?;
+    }
+
+    setup_addresses_in_network_namespace(&state.addresses, index, new_name, addr_client)?;
+
+    // Routes can only be added through an interface which is up.
+    link_client.set_up(index)?;
+
+    // Gateways have to be reachable by the routes without one.
+    let (direct, via_gateway): (Vec<_>, Vec<_>) = state
+        .routes
+        .iter()
+        .partition(|route| route.gateway.is_none());
+    for route in direct.into_iter().chain(via_gateway) {
+        tracing::debug!("adding route {:?} to network device {}", route, new_name);
+        // Routes may depend on addresses which were not moved, e.g. dynamic ones,
+        // which shouldn't stop the container.
+        if let Err(err) = route_client.add(index, route) {
+            tracing::warn!(
+                ?err,
+                ?route,
+                "failed to restore route of network device {}",
+                new_name
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Brings up the loopback device of the current network namespace
+pub fn setup_loopback(link_client: &mut LinkClient) -> Result<()> {
+    let lo = link_client.get_by_name(LOOPBACK_DEVICE)?;
+    link_client.set_up(lo.header.index)
+}
+
 #[cfg(test)]
 mod tests {
     use std::net::{IpAddr, Ipv4Addr};
 
     use netlink_packet_route::RouteNetlinkMessage;
     use netlink_packet_route::address::{AddressAttribute, AddressMessage};
+    use netlink_packet_route::route::{RouteHeader, RouteMessage};
 
     use super::*;
     use crate::network::address::AddressClient;
@@ -167,6 +304,89 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_network_device_state_from_link() {
+        let mut link = LinkMessage::default();
+        link.header.flags = LinkFlags::Up | LinkFlags::Promisc | LinkFlags::Running;
+        link.attributes = vec![
+            LinkAttribute::IfName("eth1".to_string()),
+            LinkAttribute::Mtu(9000),
+            LinkAttribute::Address(vec![0x02, 0, 0, 0, 0, 1]),
+        ];
+
+        let state = NetworkDeviceState::from_link(&link);
+        assert_eq!(state.mtu, Some(9000));
+        assert_eq!(state.hardware_address, Some(vec![0x02, 0, 0, 0, 0, 1]));
+        assert_eq!(state.flags, LinkFlags::Promisc.bits());
+    }
+
+    #[test]
+    fn test_setup_network_device_in_network_namespace() {
+        let mut current = LinkMessage::default();
+        current.header.index = 5;
+        current.attributes = vec![
+            LinkAttribute::Mtu(1500),
+            LinkAttribute::Address(vec![0x02, 0, 0, 0, 0, 1]),
+        ];
+        let state = NetworkDeviceState {
+            addresses: vec!["192.168.1.2/24".parse().unwrap()],
+            routes: vec![
+                RouteConfig {
+                    destination: "0.0.0.0/0".parse().unwrap(),
+                    gateway: Some("192.168.1.1".parse().unwrap()),
+                    source: None,
+                    table: RouteHeader::RT_TABLE_MAIN.into(),
+                    protocol: RouteProtocol::Boot.into(),
+                    scope: 0,
+                    priority: None,
+                },
+                RouteConfig {
+                    destination: "192.168.1.1/32".parse().unwrap(),
+                    gateway: None,
+                    source: None,
+                    table: RouteHeader::RT_TABLE_MAIN.into(),
+                    protocol: RouteProtocol::Boot.into(),
+                    scope: 253,
+                    priority: None,
+                },
+            ],
+            mtu: Some(9000),
+            hardware_address: Some(vec![0x02, 0, 0, 0, 0, 1]),
+            flags: LinkFlags::Promisc.bits(),
+        };
+
+        let mut fake_link_client = FakeNetlinkClient::new();
+        // mtu, flags and up, the hardware address is unchanged
+        fake_link_client.set_expected_responses(vec![
+            RouteNetlinkMessage::NewLink(LinkMessage::default()),
+            RouteNetlinkMessage::NewLink(LinkMessage::default()),
+            RouteNetlinkMessage::NewLink(LinkMessage::default()),
+        ]);
+        let mut fake_addr_client = FakeNetlinkClient::new();
+        fake_addr_client.set_expected_responses(vec![RouteNetlinkMessage::NewAddress(
+            AddressMessage::default(),
+        )]);
+        let mut fake_route_client = FakeNetlinkClient::new();
+        // the route without gateway is added first and fails, which doesn't fail the setup
+        fake_route_client.set_failure("Network is unreachable".to_string());
+        fake_route_client
+            .set_expected_responses(vec![RouteNetlinkMessage::NewRoute(RouteMessage::default())]);
+        let mut link_client = LinkClient::new(ClientWrapper::Fake(fake_link_client)).unwrap();
+        let mut addr_client = AddressClient::new(ClientWrapper::Fake(fake_addr_client)).unwrap();
+        let mut route_client = RouteClient::new(ClientWrapper::Fake(fake_route_client)).unwrap();
+
+        setup_network_device_in_network_namespace(
+            &state,
+            &current,
+            "eth1",
+            &mut link_client,
+            &mut addr_client,
+            &mut route_client,
+        )
+        .unwrap();
+        assert_eq!(addr_client.get_send_calls().unwrap().len(), 1);
+    }
+
     #[test]
     fn test_resolve_device_name_with_name() {
         let device = LinuxNetDevice::default()
@@ -0,0 +1,596 @@
+//! Connects the network namespace of a container to the network of the host with a
+//! user mode network stack, `pasta` or `slirp4netns`, which doesn't need any
+//! privileges on the host.
+//!
+//! The helper is started as soon as the init process has created the network
+//! namespace and keeps running in its own session until the container is deleted.
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fmt::Display;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::IpAddr;
+use std::os::fd::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+use nix::fcntl::{FcntlArg, FdFlag, OFlag, fcntl};
+use nix::sys::signal::{self, SigSet, Signal};
+use nix::unistd::{self, Pid};
+use serde::{Deserialize, Serialize};
+
+use super::{NetworkError, Result};
+
+/// The user mode network stack, pasta or slirp4netns. Setting it enables the mode.
+pub const ROOTLESS_NETWORK_ANNOTATION: &str = "org.youki.network.rootless";
+/// Comma separated ports forwarded from the host, see [`PortMapping`]
+pub const PORTS_ANNOTATION: &str = "org.youki.network.ports";
+
+// name of the tap device slirp4netns creates in the container
+const SLIRP4NETNS_TAP: &str = "tap0";
+// the MTU slirp4netns recommends for the best throughput
+const SLIRP4NETNS_MTU: u32 = 65520;
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RootlessNetworkMode {
+    Pasta,
+    Slirp4netns,
+}
+
+impl RootlessNetworkMode {
+    fn program(&self) -> &'static str {
+        match self {
+            Self::Pasta => "pasta",
+            Self::Slirp4netns => "slirp4netns",
+        }
+    }
+}
+
+impl FromStr for RootlessNetworkMode {
+    type Err = NetworkError;
+
+    fn from_str(mode: &str) -> Result<Self> {
+        match mode {
+            "pasta" => Ok(Self::Pasta),
+            "slirp4netns" => Ok(Self::Slirp4netns),
+            _ => Err(NetworkError::InvalidRootlessNetworkMode(mode.to_owned())),
+        }
+    }
+}
+
+impl Display for RootlessNetworkMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.program())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PortProtocol {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+impl Display for PortProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp => f.write_str("tcp"),
+            Self::Udp => f.write_str("udp"),
+        }
+    }
+}
+
+/// A port of the host which is forwarded to the container, written as
+/// `[host_ip:]host_port:container_port[/tcp|/udp]`, e.g. `127.0.0.1:8080:80/tcp`.
+/// IPv6 host addresses are enclosed in brackets.
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PortMapping {
+    pub host_ip: Option<IpAddr>,
+    pub host_port: u16,
+    pub container_port: u16,
+    pub protocol: PortProtocol,
+}
+
+impl FromStr for PortMapping {
+    type Err = NetworkError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let err = || NetworkError::InvalidPortMapping(s.to_owned());
+        let (ports, protocol) = match s.rsplit_once('/') {
+            Some((ports, "tcp")) => (ports, PortProtocol::Tcp),
+            Some((ports, "udp")) => (ports, PortProtocol::Udp),
+            Some(_) => return Err(err()),
+            None => (s, PortProtocol::Tcp),
+        };
+        let (rest, container_port) = ports.rsplit_once(':').ok_or_else(err)?;
+        let (host_ip, host_port) = match rest.rsplit_once(':') {
+            Some((host_ip, host_port)) => {
+                let host_ip = host_ip
+                    .strip_prefix('[')
+                    .and_then(|ip| ip.strip_suffix(']'))
+                    .unwrap_or(host_ip);
+                (Some(host_ip.parse().map_err(|_| err())?), host_port)
+            }
+            None => (None, rest),
+        };
+
+        Ok(Self {
+            host_ip,
+            host_port: host_port.parse().map_err(|_| err())?,
+            container_port: container_port.parse().map_err(|_| err())?,
+            protocol,
+        })
+    }
+}
+
+impl Display for PortMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.host_ip {
+            Some(IpAddr::V6(ip)) => write!(f, "[{ip}]:")?,
+            Some(ip) => write!(f, "{ip}:")?,
+            None => {}
+        }
+        write!(
+            f,
+            "{}:{}/{}",
+            self.host_port, self.container_port, self.protocol
+        )
+    }
+}
+
+/// Connects a container to the network of the host with `pasta` or `slirp4netns`,
+/// which have to be in the `PATH`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct RootlessNetwork {
+    pub mode: RootlessNetworkMode,
+    pub port_mappings: Vec<PortMapping>,
+}
+
+impl RootlessNetwork {
+    pub fn new(mode: RootlessNetworkMode) -> Self {
+        Self {
+            mode,
+            port_mappings: Vec::new(),
+        }
+    }
+
+    pub fn with_port_mapping(mut self, port_mapping: PortMapping) -> Self {
+        self.port_mappings.push(port_mapping);
+        self
+    }
+
+    /// Reads the configuration from the annotations of the spec. Returns None if
+    /// no mode is set.
+    pub fn from_annotations(annotations: &HashMap<String, String>) -> Result<Option<Self>> {
+        let Some(mode) = annotations.get(ROOTLESS_NETWORK_ANNOTATION) else {
+            return Ok(None);
+        };
+        let invalid = |key: &str, value: &str| NetworkError::InvalidAnnotation {
+            key: key.to_owned(),
+            value: value.to_owned(),
+        };
+
+        let mode = mode
+            .parse()
+            .map_err(|_| invalid(ROOTLESS_NETWORK_ANNOTATION, mode))?;
+        let mut network = Self::new(mode);
+        let ports = annotations.get(PORTS_ANNOTATION).map(String::as_str);
+        for port in ports
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|port| !port.is_empty())
+        {
+            let port_mapping = port.parse().map_err(|_| invalid(PORTS_ANNOTATION, port))?;
+            network = network.with_port_mapping(port_mapping);
+        }
+
+        Ok(Some(network))
+    }
+
+    /// Starts the helper for the network namespace of the init process and
+    /// returns once the namespace is configured. The pid of the helper is kept in
+    /// the container directory.
+    pub fn start(&self, init_pid: Pid, new_user_ns: bool, container_root: &Path) -> Result<()> {
+        tracing::debug!(
+            "connecting network namespace of {} with {}",
+            init_pid,
+            self.mode
+        );
+        match self.mode {
+            RootlessNetworkMode::Pasta => self.start_pasta(init_pid, container_root),
+            RootlessNetworkMode::Slirp4netns => {
+                self.start_slirp4netns(init_pid, new_user_ns, container_root)
+            }
+        }
+    }
+
+    /// Stops the helper, if it is still running
+    pub fn stop(&self, container_root: &Path) -> Result<()> {
+        let pid_file = self.pid_file(container_root);
+        let pid = match fs::read_to_string(&pid_file) {
+            Ok(pid) => pid,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        let pid = pid
+            .trim()
+            .parse()
+            .map_err(|_| self.error(format!("invalid pid {pid:?} in {}", pid_file.display())))?;
+
+        // the pid may have been reused after the helper exited on its own
+        let is_helper = procfs::process::Process::new(pid)
+            .and_then(|process| process.stat())
+            .is_ok_and(|stat| stat.comm.starts_with(self.mode.program()));
+        if is_helper {
+            tracing::debug!("stopping {} with pid {}", self.mode, pid);
+            match signal::kill(Pid::from_raw(pid), Signal::SIGTERM) {
+                Ok(()) | Err(nix::Error::ESRCH) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        fs::remove_file(&pid_file)?;
+
+        Ok(())
+    }
+
+    fn start_pasta(&self, init_pid: Pid, container_root: &Path) -> Result<()> {
+        // pasta daemonizes once the namespace is configured
+        let status = helper_command(
+            self.mode.program(),
+            self.pasta_args(init_pid, container_root),
+        )
+        .status()
+        .map_err(|err| self.error(err))?;
+        if !status.success() {
+            return Err(self.error(status));
+        }
+
+        Ok(())
+    }
+
+    fn start_slirp4netns(
+        &self,
+        init_pid: Pid,
+        new_user_ns: bool,
+        container_root: &Path,
+    ) -> Result<()> {
+        let (ready_read, ready_write) = unistd::pipe2(OFlag::O_CLOEXEC)?;
+        let ready_fd = ready_write.as_raw_fd();
+        let args = self.slirp4netns_args(init_pid, new_user_ns, ready_fd, container_root);
+        let mut command = helper_command(self.mode.program(), args);
+        // Safety: only async signal safe functions are called
+        unsafe {
+            command.pre_exec(move || {
+                fcntl(ready_fd, FcntlArg::F_SETFD(FdFlag::empty()))?;
+                Ok(())
+            });
+        }
+        let mut child = command.spawn().map_err(|err| self.error(err))?;
+        drop(ready_write);
+
+        // slirp4netns writes "1" once it is ready and exits on errors
+        let mut ready = [0u8; 1];
+        let n = fs::File::from(ready_read)
+            .read(&mut ready)
+            .map_err(|err| self.error(err))?;
+        if n == 0 {
+            let status = child.wait().map_err(|err| self.error(err))?;
+            return Err(self.error(status));
+        }
+        fs::write(self.pid_file(container_root), child.id().to_string())?;
+
+        let api_socket = self.api_socket(container_root);
+        for port_mapping in &self.port_mappings {
+            tracing::debug!("forwarding port {}", port_mapping);
+            add_hostfwd(&api_socket, port_mapping).map_err(|reason| self.error(reason))?;
+        }
+
+        Ok(())
+    }
+
+    fn pasta_args(&self, init_pid: Pid, container_root: &Path) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![
+            "--config-net".into(),
+            "--quiet".into(),
+            "--pid".into(),
+            self.pid_file(container_root).into(),
+        ];
+        let mut forward = |option: &str, protocol: PortProtocol| {
+            let ports: Vec<String> = self
+                .port_mappings
+                .iter()
+                .filter(|port_mapping| port_mapping.protocol == protocol)
+                .map(|port_mapping| match port_mapping.host_ip {
+                    Some(host_ip) => format!(
+                        "{host_ip}/{}:{}",
+                        port_mapping.host_port, port_mapping.container_port
+                    ),
+                    None => format!("{}:{}", port_mapping.host_port, port_mapping.container_port),
+                })
+                .collect();
+            if ports.is_empty() {
+                // pasta forwards all bound ports by default
+                args.extend([option.into(), "none".into()]);
+            }
+            for port in ports {
+                args.extend([option.into(), port.into()]);
+            }
+        };
+        forward("--tcp-ports", PortProtocol::Tcp);
+        forward("--udp-ports", PortProtocol::Udp);
+        // pasta joins the user and network namespace of the process
+        args.push(init_pid.to_string().into());
+        args
+    }
+
+    fn slirp4netns_args(
+        &self,
+        init_pid: Pid,
+        new_user_ns: bool,
+        ready_fd: i32,
+        container_root: &Path,
+    ) -> Vec<OsString> {
+        let mut args: Vec<OsString> = vec![
+            "--configure".into(),
+            format!("--mtu={SLIRP4NETNS_MTU}").into(),
+            "--disable-host-loopback".into(),
+            format!("--ready-fd={ready_fd}").into(),
+        ];
+        let mut api_socket = OsString::from("--api-socket=");
+        api_socket.push(self.api_socket(container_root));
+        args.push(api_socket);
+        // joining the network namespace requires the capabilities in the user
+        // namespace of the container
+        if new_user_ns {
+            args.push(format!("--userns-path=/proc/{init_pid}/ns/user").into());
+        }
+        args.extend([
+            "--netns-type=path".into(),
+            format!("/proc/{init_pid}/ns/net").into(),
+            SLIRP4NETNS_TAP.into(),
+        ]);
+        args
+    }
+
+    fn pid_file(&self, container_root: &Path) -> PathBuf {
+        container_root.join(format!("{}.pid", self.mode))
+    }
+
+    fn api_socket(&self, container_root: &Path) -> PathBuf {
+        container_root.join(format!("{}.sock", self.mode))
+    }
+
+    fn error<E: Display>(&self, reason: E) -> NetworkError {
+        NetworkError::RootlessNetwork {
+            program: self.mode.program(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+// The helper runs in its own session, so that it neither receives the signals of
+// the terminal nor keeps the output of youki open.
+fn helper_command(program: &str, args: Vec<OsString>) -> Command {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    // Safety: only async signal safe functions are called
+    unsafe {
+        command.pre_exec(|| {
+            unistd::setsid()?;
+            // the caller may block signals while it waits for the container
+            SigSet::all().thread_unblock()?;
+            Ok(())
+        });
+    }
+    command
+}
+
+// Sends one request to the API socket of slirp4netns, which handles a single
+// request per connection
+fn add_hostfwd(api_socket: &Path, port_mapping: &PortMapping) -> std::result::Result<(), String> {
+    let request = hostfwd_request(port_mapping);
+    let mut stream = UnixStream::connect(api_socket).map_err(|err| err.to_string())?;
+    stream
+        .write_all(request.to_string().as_bytes())
+        .and_then(|_| stream.shutdown(std::net::Shutdown::Write))
+        .map_err(|err| err.to_string())?;
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(|err| err.to_string())?;
+
+    let response: serde_json::Value =
+        serde_json::from_str(&response).map_err(|err| err.to_string())?;
+    match response.get("error") {
+        Some(error) => Err(format!("failed to forward port {port_mapping}: {error}")),
+        None => Ok(()),
+    }
+}
+
+fn hostfwd_request(port_mapping: &PortMapping) -> serde_json::Value {
+    let host_addr = port_mapping
+        .host_ip
+        .map_or_else(|| "0.0.0.0".to_owned(), |ip| ip.to_string());
+    serde_json::json!({
+        "execute": "add_hostfwd",
+        "arguments": {
+            "proto": port_mapping.protocol.to_string(),
+            "host_addr": host_addr,
+            "host_port": port_mapping.host_port,
+            "guest_port": port_mapping.container_port,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn annotations(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_port_mapping() -> anyhow::Result<()> {
+        let port_mapping: PortMapping = "8080:80".parse()?;
+        assert_eq!(
+            port_mapping,
+            PortMapping {
+                host_ip: None,
+                host_port: 8080,
+                container_port: 80,
+                protocol: PortProtocol::Tcp,
+            }
+        );
+        assert_eq!(port_mapping.to_string(), "8080:80/tcp");
+
+        let port_mapping: PortMapping = "127.0.0.1:5353:53/udp".parse()?;
+        assert_eq!(port_mapping.host_ip, Some("127.0.0.1".parse()?));
+        assert_eq!(port_mapping.protocol, PortProtocol::Udp);
+        assert_eq!(port_mapping.to_string(), "127.0.0.1:5353:53/udp");
+
+        let port_mapping: PortMapping = "[::1]:8443:443/tcp".parse()?;
+        assert_eq!(port_mapping.host_ip, Some("::1".parse()?));
+        assert_eq!(port_mapping.to_string(), "[::1]:8443:443/tcp");
+
+        for invalid in ["80", "8080:80/sctp", "70000:80", "localhost:8080:80", ":80"] {
+            assert!(invalid.parse::<PortMapping>().is_err(), "{invalid}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_annotations() -> anyhow::Result<()> {
+        assert_eq!(RootlessNetwork::from_annotations(&annotations(&[]))?, None);
+
+        let network = RootlessNetwork::from_annotations(&annotations(&[
+            (ROOTLESS_NETWORK_ANNOTATION, "slirp4netns"),
+            (PORTS_ANNOTATION, "8080:80, 5353:53/udp"),
+        ]))?;
+        let expected = RootlessNetwork::new(RootlessNetworkMode::Slirp4netns)
+            .with_port_mapping("8080:80".parse()?)
+            .with_port_mapping("5353:53/udp".parse()?);
+        assert_eq!(network, Some(expected));
+
+        for (key, value) in [
+            (ROOTLESS_NETWORK_ANNOTATION, "vpnkit"),
+            (PORTS_ANNOTATION, "http"),
+        ] {
+            let mut pairs = vec![(ROOTLESS_NETWORK_ANNOTATION, "pasta")];
+            pairs.push((key, value));
+            let result = RootlessNetwork::from_annotations(&annotations(&pairs));
+            assert!(
+                matches!(result, Err(NetworkError::InvalidAnnotation { .. })),
+                "{key}={value}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pasta_args() -> anyhow::Result<()> {
+        let root = Path::new("/run/youki/test");
+        let network = RootlessNetwork::new(RootlessNetworkMode::Pasta)
+            .with_port_mapping("8080:80".parse()?)
+            .with_port_mapping("127.0.0.1:8443:443".parse()?);
+        assert_eq!(
+            network.pasta_args(Pid::from_raw(42), root),
+            [
+                "--config-net",
+                "--quiet",
+                "--pid",
+                "/run/youki/test/pasta.pid",
+                "--tcp-ports",
+                "8080:80",
+                "--tcp-ports",
+                "127.0.0.1/8443:443",
+                "--udp-ports",
+                "none",
+                "42",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_slirp4netns_args() {
+        let root = Path::new("/run/youki/test");
+        let network = RootlessNetwork::new(RootlessNetworkMode::Slirp4netns);
+        assert_eq!(
+            network.slirp4netns_args(Pid::from_raw(42), true, 5, root),
+            [
+                "--configure",
+                "--mtu=65520",
+                "--disable-host-loopback",
+                "--ready-fd=5",
+                "--api-socket=/run/youki/test/slirp4netns.sock",
+                "--userns-path=/proc/42/ns/user",
+                "--netns-type=path",
+                "/proc/42/ns/net",
+                "tap0",
+            ]
+        );
+        assert!(
+            !network
+                .slirp4netns_args(Pid::from_raw(42), false, 5, root)
+                .iter()
+                .any(|arg| arg.to_string_lossy().starts_with("--userns-path"))
+        );
+    }
+
+    #[test]
+    fn test_hostfwd_request() -> anyhow::Result<()> {
+        let request = hostfwd_request(&"5353:53/udp".parse()?);
+        assert_eq!(
+            request,
+            serde_json::json!({
+                "execute": "add_hostfwd",
+                "arguments": {
+                    "proto": "udp",
+                    "host_addr": "0.0.0.0",
+                    "host_port": 5353,
+                    "guest_port": 53,
+                },
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stop() -> anyhow::Result<()> {
+        let root = tempfile::tempdir()?;
+        let network = RootlessNetwork::new(RootlessNetworkMode::Pasta);
+        // nothing was started
+        network.stop(root.path())?;
+
+        // a process which reused the pid is left alone
+        let mut child = Command::new("sleep").arg("10").spawn()?;
+        fs::write(network.pid_file(root.path()), child.id().to_string())?;
+        network.stop(root.path())?;
+        assert!(child.try_wait()?.is_none());
+        assert!(!network.pid_file(root.path()).exists());
+        child.kill()?;
+        child.wait()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,462 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use netlink_packet_core::{
+    NLM_F_ACK, NLM_F_CREATE, NLM_F_DUMP, NLM_F_EXCL, NLM_F_REQUEST, NetlinkMessage, NetlinkPayload,
+};
+use netlink_packet_route::route::{
+    RouteAddress, RouteAttribute, RouteHeader, RouteMessage, RouteProtocol, RouteScope, RouteType,
+};
+use netlink_packet_route::{AddressFamily, RouteNetlinkMessage};
+use serde::{Deserialize, Serialize};
+
+use super::cidr::CidrAddress;
+use super::traits::{Client, NetlinkMessageHandler};
+use super::wrapper::ClientWrapper;
+use super::{NetlinkResponse, NetworkError, Result};
+
+/// A route through a network interface in a form which can be sent to another
+/// process, so that it can be re-added after the interface moved to another
+/// network namespace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteConfig {
+    pub destination: CidrAddress,
+    pub gateway: Option<IpAddr>,
+    pub source: Option<IpAddr>,
+    pub table: u32,
+    pub protocol: u8,
+    pub scope: u8,
+    pub priority: Option<u32>,
+}
+
+impl RouteConfig {
+    /// Converts the message, which fails for routes other than IPv4 and IPv6 ones
+    pub fn from_message(route: &RouteMessage) -> Option<Self> {
+        let unspecified = match route.header.address_family {
+            AddressFamily::Inet => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            AddressFamily::Inet6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+            _ => return None,
+        };
+        let ip = |address: &RouteAddress| match address {
+            RouteAddress::Inet(ip) => Some(IpAddr::V4(*ip)),
+            RouteAddress::Inet6(ip) => Some(IpAddr::V6(*ip)),
+            _ => None,
+        };
+
+        let mut config = Self {
+            destination: CidrAddress {
+                prefix_len: route.header.destination_prefix_length,
+                address: unspecified,
+            },
+            gateway: None,
+            source: None,
+            table: route.header.table.into(),
+            protocol: route.header.protocol.into(),
+            scope: route.header.scope.into(),
+            priority: None,
+        };
+        for attr in &route.attributes {
+            match attr {
+                RouteAttribute::Destination(address) => {
+                    config.destination.address = ip(address)?;
+                }
+                RouteAttribute::Gateway(address) => config.gateway = Some(ip(address)?),
+                RouteAttribute::PrefSource(address) => config.source = Some(ip(address)?),
+                RouteAttribute::Table(table) => config.table = *table,
+                RouteAttribute::Priority(priority) => config.priority = Some(*priority),
+                _ => {}
+            }
+        }
+
+        Some(config)
+    }
+
+    /// Creates the message of the route through the network interface
+    pub fn to_message(&self, index: u32) -> RouteMessage {
+        let mut message = RouteMessage::default();
+        message.header.address_family = match self.destination.address {
+            IpAddr::V4(_) => AddressFamily::Inet,
+            IpAddr::V6(_) => AddressFamily::Inet6,
+        };
+        message.header.destination_prefix_length = self.destination.prefix_len;
+        // tables beyond 255 only fit into the attribute
+        message.header.table = u8::try_from(self.table).unwrap_or(RouteHeader::RT_TABLE_UNSPEC);
+        message.header.protocol = self.protocol.into();
+        message.header.scope = self.scope.into();
+        message.header.kind = RouteType::Unicast;
+
+        if !self.destination.address.is_unspecified() {
+            message
+                .attributes
+                .push(RouteAttribute::Destination(self.destination.address.into()));
+        }
+        if let Some(gateway) = self.gateway {
+            message
+                .attributes
+                .push(RouteAttribute::Gateway(gateway.into()));
+        }
+        if let Some(source) = self.source {
+            message
+                .attributes
+                .push(RouteAttribute::PrefSource(source.into()));
+        }
+        message.attributes.push(RouteAttribute::Table(self.table));
+        if let Some(priority) = self.priority {
+            message.attributes.push(RouteAttribute::Priority(priority));
+        }
+        message.attributes.push(RouteAttribute::Oif(index));
+        message
+    }
+}
+
+/// Handler for Route messages in Netlink communication.
+///
+/// This handler processes Netlink messages related to routes
+/// and converts them into RouteMessage responses.
+pub struct RouteMessageHandler {
+    target_index: Option<u32>,
+}
+
+impl RouteMessageHandler {
+    pub fn new() -> Self {
+        Self { target_index: None }
+    }
+
+    /// Only accepts the routes through the network interface
+    pub fn with_index(index: u32) -> Self {
+        Self {
+            target_index: Some(index),
+        }
+    }
+}
+
+impl Default for RouteMessageHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NetlinkMessageHandler for RouteMessageHandler {
+    type Response = RouteMessage;
+
+    fn handle_payload(
+        &self,
+        payload: NetlinkPayload<RouteNetlinkMessage>,
+    ) -> Result<NetlinkResponse<Self::Response>> {
+        match payload {
+            NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewRoute(route)) => {
+                match self.target_index {
+                    Some(index) if !route.attributes.contains(&RouteAttribute::Oif(index)) => {
+                        Ok(NetlinkResponse::None)
+                    }
+                    _ => Ok(NetlinkResponse::Success(route)),
+                }
+            }
+            NetlinkPayload::Error(e) => match e.code {
+                None => Ok(NetlinkResponse::Success(RouteMessage::default())),
+                Some(code) => Ok(NetlinkResponse::Error(code.get())),
+            },
+            NetlinkPayload::Done(_) => Ok(NetlinkResponse::Done),
+            _ => Err(NetworkError::IO(std::io::Error::other(format!(
+                "Unexpected message type: {:?}",
+                payload
+            )))),
+        }
+    }
+}
+
+/// Client for managing routes.
+///
+/// This client provides methods for modifying the routing tables
+/// through Netlink communication.
+pub struct RouteClient {
+    client: ClientWrapper,
+}
+
+impl RouteClient {
+    /// Creates a new RouteClient instance.
+    ///
+    /// # Returns
+    ///
+    /// A Result containing either a new RouteClient or an IO error
+    pub fn new(client: ClientWrapper) -> Result<Self> {
+        Ok(Self { client })
+    }
+
+    /// Retrieves all routes through a network interface.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the network interface
+    ///
+    /// # Returns
+    ///
+    /// A Result containing either a vector of RouteMessages or an error
+    pub fn get_by_index(&mut self, index: u32) -> Result<Vec<RouteMessage>> {
+        let message = RouteMessage::default();
+        let mut req = NetlinkMessage::from(RouteNetlinkMessage::GetRoute(message));
+        // NLM_F_REQUEST: This is a request to the kernel
+        // NLM_F_DUMP: Request a dump of all matching entries
+        req.header.flags = NLM_F_REQUEST | NLM_F_DUMP;
+        req.finalize();
+
+        self.client
+            .send_and_receive_multiple(&req, RouteMessageHandler::with_index(index))
+    }
+
+    /// Adds a route through a network interface.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the network interface
+    /// * `route` - The route to add
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or failure of the operation
+    pub fn add(&mut self, index: u32, route: &RouteConfig) -> Result<()> {
+        let mut req = NetlinkMessage::from(RouteNetlinkMessage::NewRoute(route.to_message(index)));
+        // NLM_F_REQUEST: This is a request to the kernel
+        // NLM_F_ACK: Request an acknowledgment from the kernel
+        // NLM_F_EXCL: Fail if the route already exists
+        // NLM_F_CREATE: Create the route if it doesn't exist
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_EXCL | NLM_F_CREATE;
+        req.finalize();
+
+        self.client
+            .send_and_receive(&req, RouteMessageHandler::new())?;
+        Ok(())
+    }
+
+    /// Adds a default route through a gateway to the main routing table.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the network interface the gateway is reached by
+    /// * `gateway` - The address of the gateway
+    ///
+    /// # Returns
+    ///
+    /// A Result indicating success or failure of the operation
+    pub fn add_default(&mut self, index: u32, gateway: IpAddr) -> Result<()> {
+        let mut message = RouteMessage::default();
+        message.header.address_family = match gateway {
+            IpAddr::V4(_) => AddressFamily::Inet,
+            IpAddr::V6(_) => AddressFamily::Inet6,
+        };
+        message.header.destination_prefix_length = 0;
+        message.header.table = RouteHeader::RT_TABLE_MAIN;
+        message.header.protocol = RouteProtocol::Boot;
+        message.header.scope = RouteScope::Universe;
+        message.header.kind = RouteType::Unicast;
+        message
+            .attributes
+            .push(RouteAttribute::Gateway(gateway.into()));
+        message.attributes.push(RouteAttribute::Oif(index));
+
+        let mut req = NetlinkMessage::from(RouteNetlinkMessage::NewRoute(message));
+        // NLM_F_REQUEST: This is a request to the kernel
+        // NLM_F_ACK: Request an acknowledgment from the kernel
+        // NLM_F_EXCL: Fail if the route already exists
+        // NLM_F_CREATE: Create the route if it doesn't exist
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_EXCL | NLM_F_CREATE;
+        req.finalize();
+
+        self.client
+            .send_and_receive(&req, RouteMessageHandler::new())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+
+    use serial_test::serial;
+
+    use super::*;
+    use crate::network::fake::FakeNetlinkClient;
+
+    #[test]
+    #[serial]
+    fn test_route_message_handler_error() {
+        let handler = RouteMessageHandler::new();
+        let mut error_msg = netlink_packet_core::ErrorMessage::default();
+        error_msg.code = std::num::NonZeroI32::new(-17);
+        let result = handler.handle_payload(NetlinkPayload::Error(error_msg));
+
+        match result.unwrap() {
+            NetlinkResponse::Error(code) => assert_eq!(code, -17),
+            _ => panic!("Expected Error response"),
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_route_message_handler_with_index() {
+        let handler = RouteMessageHandler::with_index(2);
+        let mut route = RouteMessage::default();
+        route.attributes.push(RouteAttribute::Oif(3));
+        let payload = NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewRoute(route.clone()));
+        assert!(matches!(
+            handler.handle_payload(payload),
+            Ok(NetlinkResponse::None)
+        ));
+
+        route.attributes = vec![RouteAttribute::Oif(2)];
+        let payload = NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewRoute(route));
+        assert!(matches!(
+            handler.handle_payload(payload),
+            Ok(NetlinkResponse::Success(_))
+        ));
+    }
+
+    #[test]
+    fn test_route_config_message() {
+        let mut route = RouteMessage::default();
+        route.header.address_family = AddressFamily::Inet;
+        route.header.destination_prefix_length = 16;
+        route.header.table = RouteHeader::RT_TABLE_MAIN;
+        route.header.protocol = RouteProtocol::Static;
+        route.header.scope = RouteScope::Universe;
+        route.header.kind = RouteType::Unicast;
+        route.attributes = vec![
+            RouteAttribute::Destination("172.16.0.0".parse::<IpAddr>().unwrap().into()),
+            RouteAttribute::Gateway("10.0.0.1".parse::<IpAddr>().unwrap().into()),
+            RouteAttribute::Table(RouteHeader::RT_TABLE_MAIN.into()),
+            RouteAttribute::Priority(100),
+            RouteAttribute::Oif(5),
+        ];
+
+        let config = RouteConfig::from_message(&route).unwrap();
+        assert_eq!(config.destination, "172.16.0.0/16".parse().unwrap());
+        assert_eq!(config.gateway, Some("10.0.0.1".parse().unwrap()));
+        assert_eq!(config.source, None);
+        assert_eq!(config.table, 254);
+        assert_eq!(config.priority, Some(100));
+        assert_eq!(config.to_message(5), route);
+
+        // default routes have no destination
+        let mut route = RouteMessage::default();
+        route.header.address_family = AddressFamily::Inet6;
+        let config = RouteConfig::from_message(&route).unwrap();
+        assert_eq!(config.destination, "::/0".parse().unwrap());
+        assert!(
+            !config
+                .to_message(5)
+                .attributes
+                .iter()
+                .any(|attr| matches!(attr, RouteAttribute::Destination(_)))
+        );
+
+        route.header.address_family = AddressFamily::Mpls;
+        assert_eq!(RouteConfig::from_message(&route), None);
+    }
+
+    #[test]
+    #[serial]
+    fn test_route_client_get_by_index() {
+        let mut fake_client = FakeNetlinkClient::new();
+        let mut route = RouteMessage::default();
+        route.attributes.push(RouteAttribute::Oif(2));
+        let mut other_route = RouteMessage::default();
+        other_route.attributes.push(RouteAttribute::Oif(3));
+        fake_client.set_expected_responses(vec![
+            RouteNetlinkMessage::NewRoute(route.clone()),
+            RouteNetlinkMessage::NewRoute(other_route),
+        ]);
+
+        let mut route_client = RouteClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        let routes = route_client.get_by_index(2).unwrap();
+        assert_eq!(routes, vec![route]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_route_client_add() {
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client
+            .set_expected_responses(vec![RouteNetlinkMessage::NewRoute(RouteMessage::default())]);
+
+        let mut route_client = RouteClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        let config = RouteConfig {
+            destination: "192.168.0.0/24".parse().unwrap(),
+            gateway: None,
+            source: Some("192.168.0.2".parse().unwrap()),
+            table: 1000,
+            protocol: RouteProtocol::Static.into(),
+            scope: RouteScope::Link.into(),
+            priority: None,
+        };
+        route_client.add(4, &config).unwrap();
+
+        if let ClientWrapper::Fake(fake_client) = &route_client.client {
+            let send_calls = fake_client.get_send_calls();
+            if let NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewRoute(route)) =
+                &send_calls[0].payload
+            {
+                assert_eq!(route.header.table, RouteHeader::RT_TABLE_UNSPEC);
+                assert!(route.attributes.contains(&RouteAttribute::Table(1000)));
+                assert!(route.attributes.contains(&RouteAttribute::Oif(4)));
+                assert_eq!(RouteConfig::from_message(route), Some(config));
+            } else {
+                panic!("Expected NewRoute message");
+            }
+
+            let expected_flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_EXCL | NLM_F_CREATE;
+            assert_eq!(send_calls[0].header.flags, expected_flags);
+        } else {
+            panic!("Expected Fake client");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_route_client_add_default_failure() {
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client.set_failure("Add route failed".to_string());
+
+        let mut route_client = RouteClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        let result = route_client.add_default(2, "10.0.0.1".parse().unwrap());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn test_route_client_add_default_success() {
+        let mut fake_client = FakeNetlinkClient::new();
+        fake_client
+            .set_expected_responses(vec![RouteNetlinkMessage::NewRoute(RouteMessage::default())]);
+
+        let mut route_client = RouteClient::new(ClientWrapper::Fake(fake_client)).unwrap();
+        let gateway = IpAddr::V6(Ipv6Addr::new(0xfd00, 0, 0, 0, 0, 0, 0, 1));
+        let result = route_client.add_default(2, gateway);
+        assert!(result.is_ok());
+
+        if let ClientWrapper::Fake(fake_client) = &route_client.client {
+            let send_calls = fake_client.get_send_calls();
+            assert_eq!(send_calls.len(), 1);
+
+            if let NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewRoute(route)) =
+                &send_calls[0].payload
+            {
+                assert_eq!(route.header.address_family, AddressFamily::Inet6);
+                assert_eq!(route.header.destination_prefix_length, 0);
+                assert_eq!(route.header.table, RouteHeader::RT_TABLE_MAIN);
+                assert!(
+                    route
+                        .attributes
+                        .contains(&RouteAttribute::Gateway(gateway.into()))
+                );
+                assert!(route.attributes.contains(&RouteAttribute::Oif(2)));
+            } else {
+                panic!("Expected NewRoute message");
+            }
+
+            let expected_flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_EXCL | NLM_F_CREATE;
+            assert_eq!(send_calls[0].header.flags, expected_flags);
+        } else {
+            panic!("Expected Fake client");
+        }
+    }
+}
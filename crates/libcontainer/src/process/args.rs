@@ -6,6 +6,7 @@ use libcgroups::common::CgroupConfig;
 use oci_spec::runtime::Spec;
 
 use crate::container::Container;
+use crate::landlock::LandlockRuleset;
 use crate::notify_socket::NotifyListener;
 use crate::syscall::syscall::SyscallType;
 use crate::user_ns::UserNamespaceConfig;
@@ -55,4 +56,8 @@ pub struct ContainerArgs {
     /// File path used to communicate the PID of the
     /// container process to the higher-level runtime.
     pub pid_file: Option<PathBuf>,
+    /// Landlock ruleset the container process is restricted with
+    pub landlock: Option<LandlockRuleset>,
+    /// Cgroup below the container cgroup which a tenant process is moved to
+    pub sub_cgroup: Option<PathBuf>,
 }
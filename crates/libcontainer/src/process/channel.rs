@@ -1,10 +1,11 @@
 use std::collections::HashMap;
 use std::os::unix::prelude::{AsRawFd, RawFd};
 
+use libcgroups::common::CgroupManagerInfo;
 use nix::unistd::Pid;
 
 use crate::channel::{Receiver, Sender, channel};
-use crate::network::cidr::CidrAddress;
+use crate::network::network_device::NetworkDeviceState;
 use crate::process::message::Message;
 
 #[derive(Debug, thiserror::Error)]
@@ -74,10 +75,17 @@ impl MainSender {
         Ok(())
     }
 
-    pub fn intermediate_ready(&mut self, pid: Pid) -> Result<(), ChannelError> {
+    pub fn intermediate_ready(
+        &mut self,
+        pid: Pid,
+        cgroup_manager: Option<CgroupManagerInfo>,
+    ) -> Result<(), ChannelError> {
         // Send over the IntermediateReady follow by the pid.
         tracing::debug!("sending init pid ({:?})", pid);
-        self.sender.send(Message::IntermediateReady(pid.as_raw()))?;
+        self.sender.send(Message::IntermediateReady(
+            pid.as_raw(),
+            cgroup_manager.map(Box::new),
+        ))?;
 
         Ok(())
     }
@@ -116,8 +124,11 @@ pub struct MainReceiver {
 
 impl MainReceiver {
     /// Waits for associated intermediate process to send ready message
-    /// and return the pid of init process which is forked by intermediate process
-    pub fn wait_for_intermediate_ready(&mut self) -> Result<Pid, ChannelError> {
+    /// and return the pid of init process which is forked by intermediate process,
+    /// along with the description of the cgroup manager it placed the container with
+    pub fn wait_for_intermediate_ready(
+        &mut self,
+    ) -> Result<(Pid, Option<CgroupManagerInfo>), ChannelError> {
         let msg = self
             .receiver
             .recv()
@@ -127,11 +138,13 @@ impl MainReceiver {
             })?;
 
         match msg {
-            Message::IntermediateReady(pid) => Ok(Pid::from_raw(pid)),
+            Message::IntermediateReady(pid, cgroup_manager) => {
+                Ok((Pid::from_raw(pid), cgroup_manager.map(|info| *info)))
+            }
             Message::ExecFailed(err) => Err(ChannelError::ExecError(err)),
             Message::OtherError(err) => Err(ChannelError::OtherError(err)),
             msg => Err(ChannelError::UnexpectedMessage {
-                expected: Message::IntermediateReady(0),
+                expected: Message::IntermediateReady(0, None),
                 received: msg,
             }),
         }
@@ -328,9 +341,9 @@ impl InitSender {
 
     pub fn move_network_device(
         &mut self,
-        addrs: HashMap<String, Vec<CidrAddress>>,
+        devices: HashMap<String, NetworkDeviceState>,
     ) -> Result<(), ChannelError> {
-        self.sender.send(Message::MoveNetworkDevice(addrs))?;
+        self.sender.send(Message::MoveNetworkDevice(devices))?;
 
         Ok(())
     }
@@ -367,7 +380,7 @@ impl InitReceiver {
 
     pub fn wait_for_move_network_device(
         &mut self,
-    ) -> Result<HashMap<String, Vec<CidrAddress>>, ChannelError> {
+    ) -> Result<HashMap<String, NetworkDeviceState>, ChannelError> {
         let msg = self
             .receiver
             .recv()
@@ -376,7 +389,7 @@ impl InitReceiver {
                 source: err,
             })?;
         match msg {
-            Message::MoveNetworkDevice(addr) => Ok(addr),
+            Message::MoveNetworkDevice(devices) => Ok(devices),
             msg => Err(ChannelError::UnexpectedMessage {
                 expected: Message::WriteMapping,
                 received: msg,
@@ -431,15 +444,16 @@ mod tests {
         match unsafe { unistd::fork()? } {
             unistd::ForkResult::Parent { child } => {
                 wait::waitpid(child, None)?;
-                let pid = receiver
+                let (pid, cgroup_manager) = receiver
                     .wait_for_intermediate_ready()
                     .with_context(|| "Failed to wait for intermadiate ready")?;
                 receiver.close()?;
                 assert_eq!(pid, child);
+                assert_eq!(cgroup_manager, None);
             }
             unistd::ForkResult::Child => {
                 let pid = unistd::getpid();
-                sender.intermediate_ready(pid)?;
+                sender.intermediate_ready(pid, None)?;
                 sender.close()?;
                 std::process::exit(0);
             }
@@ -570,7 +584,13 @@ mod tests {
             address: ip,
         };
         let mut addrs = HashMap::new();
-        addrs.insert(device_name.clone(), vec![addr.clone()]);
+        addrs.insert(
+            device_name.clone(),
+            NetworkDeviceState {
+                addresses: vec![addr.clone()],
+                ..Default::default()
+            },
+        );
 
         let (sender, receiver) = &mut init_channel()?;
 
@@ -589,8 +609,8 @@ mod tests {
                 let received_addrs = receiver.wait_for_move_network_device()?;
                 receiver.close()?;
                 if let Some(received_addr) = received_addrs.get(&device_name) {
-                    if !(received_addr[0].prefix_len == addr.prefix_len
-                        && received_addr[0].address == addr.address)
+                    if !(received_addr.addresses[0].prefix_len == addr.prefix_len
+                        && received_addr.addresses[0].address == addr.address)
                     {
                         eprintln!("assertion failed in child");
                         std::process::exit(1);
@@ -1,6 +1,8 @@
+use std::fs;
 use std::os::fd::FromRawFd;
+use std::path::Path;
 
-use libcgroups::common::CgroupManager;
+use libcgroups::common::{CGROUP_PROCS, CgroupManager, write_cgroup_file};
 use nix::unistd::{Gid, Pid, Uid, close, getpid, write};
 use oci_spec::runtime::{LinuxNamespace, LinuxNamespaceType, LinuxResources};
 
@@ -50,8 +52,9 @@ pub fn container_intermediate_process(
     let spec = &args.spec;
     let linux = spec.linux().as_ref().ok_or(MissingSpecError::Linux)?;
     let namespaces = Namespaces::try_from(linux.namespaces().as_ref())?;
-    let cgroup_manager = libcgroups::common::create_cgroup_manager(args.cgroup_config.to_owned())
-        .map_err(|e| IntermediateProcessError::Cgroup(e.to_string()))?;
+    let (cgroup_manager, cgroup_manager_info) =
+        libcgroups::common::create_cgroup_manager_with_info(None, args.cgroup_config.to_owned())
+            .map_err(|e| IntermediateProcessError::Cgroup(e.to_string()))?;
 
     let current_pid = Pid::this();
     // setting CPU affinity for tenant container before cgroup move
@@ -78,11 +81,21 @@ pub fn container_intermediate_process(
     // In addition this needs to be done before we enter the cgroup namespace as
     // the cgroup of the process will form the root of the cgroup hierarchy in
     // the cgroup namespace.
-    apply_cgroups(
-        &cgroup_manager,
-        linux.resources().as_ref(),
-        matches!(args.container_type, ContainerType::InitContainer),
-    )?;
+    match &args.sub_cgroup {
+        // a delegated container cgroup is no leaf, so a tenant moves right into
+        // the cgroup of the init process, which is below it
+        Some(sub_cgroup) if args.cgroup_config.delegation.is_some() => join_sub_cgroup(sub_cgroup)?,
+        sub_cgroup => {
+            apply_cgroups(
+                &cgroup_manager,
+                linux.resources().as_ref(),
+                matches!(args.container_type, ContainerType::InitContainer),
+            )?;
+            if let Some(sub_cgroup) = sub_cgroup {
+                join_sub_cgroup(sub_cgroup)?;
+            }
+        }
+    }
 
     // setting CPU affinity for tenant container after cgroup move
     if matches!(args.container_type, ContainerType::TenantContainer { .. }) {
@@ -193,10 +206,17 @@ pub fn container_intermediate_process(
         })?;
     }
 
-    main_sender.intermediate_ready(pid).map_err(|err| {
-        tracing::error!("failed to wait on intermediate process: {}", err);
-        err
-    })?;
+    // remember how the cgroups of the container are managed, so that later
+    // commands don't have to work it out again. A tenant container uses the
+    // cgroup manager of the container it joins, so there is nothing to remember.
+    let cgroup_manager_info =
+        matches!(args.container_type, ContainerType::InitContainer).then_some(cgroup_manager_info);
+    main_sender
+        .intermediate_ready(pid, cgroup_manager_info)
+        .map_err(|err| {
+            tracing::error!("failed to wait on intermediate process: {}", err);
+            err
+        })?;
 
     // Close unused senders here so we don't have lingering socket around.
     main_sender.close().map_err(|err| {
@@ -255,6 +275,18 @@ fn setup_userns(
     Ok(())
 }
 
+// Moves the process from the container cgroup to the sub-cgroup of a tenant,
+// which is created on demand
+fn join_sub_cgroup(sub_cgroup: &Path) -> Result<()> {
+    let pid = getpid();
+    fs::create_dir_all(sub_cgroup)
+        .map_err(|err| IntermediateProcessError::Cgroup(err.to_string()))?;
+    write_cgroup_file(sub_cgroup.join(CGROUP_PROCS), pid).map_err(|err| {
+        tracing::error!(?pid, ?err, ?sub_cgroup, "failed to add task to sub-cgroup");
+        IntermediateProcessError::Cgroup(err.to_string())
+    })
+}
+
 fn apply_cgroups<
     C: CgroupManager<Error = E> + ?Sized,
     E: std::error::Error + Send + Sync + 'static,
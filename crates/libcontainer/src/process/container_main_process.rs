@@ -4,17 +4,19 @@ use std::fs::File;
 use std::os::fd::AsRawFd;
 use std::path::PathBuf;
 
+use libcgroups::common::CgroupManagerInfo;
 use nix::sys::wait::{WaitStatus, waitpid};
 use nix::unistd::Pid;
 use oci_spec::runtime::{Linux, LinuxNamespaceType};
-#[cfg(feature = "libseccomp")]
+#[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
 use oci_spec::runtime::{SECCOMP_FD_NAME, VERSION as OCI_VERSION};
 
+use crate::container::Container;
 use crate::hooks;
-use crate::network::network_device::dev_change_net_namespace;
+use crate::network::network_device::{NetworkDeviceState, dev_change_net_namespace};
 use crate::process::args::{ContainerArgs, ContainerType};
 use crate::process::fork::{self, CloneCb};
-use crate::process::intel_rdt::setup_intel_rdt;
+use crate::process::intel_rdt::{IntelRdtSetup, setup_intel_rdt};
 use crate::process::{channel, container_intermediate_process};
 use crate::syscall::SyscallError;
 use crate::user_ns::UserNamespaceConfig;
@@ -36,7 +38,7 @@ pub enum ProcessError {
     #[error("failed to create intermediate process")]
     IntermediateProcessFailed(#[source] fork::CloneError),
     #[error("failed seccomp listener")]
-    #[cfg(feature = "libseccomp")]
+    #[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
     SeccompListener(#[from] crate::process::seccomp_listener::SeccompListenerError),
     #[error("failed setup network device")]
     Network(#[from] crate::network::NetworkError),
@@ -50,7 +52,9 @@ pub enum ProcessError {
 
 type Result<T> = std::result::Result<T, ProcessError>;
 
-pub fn container_main_process(container_args: &ContainerArgs) -> Result<(Pid, bool)> {
+pub fn container_main_process(
+    container_args: &ContainerArgs,
+) -> Result<(Pid, IntelRdtSetup, Option<CgroupManagerInfo>)> {
     // We use a set of channels to communicate between parent and child process.
     // Each channel is uni-directional. Because we will pass these channel to
     // cloned process, we have to be deligent about closing any unused channel.
@@ -131,8 +135,8 @@ pub fn container_main_process(container_args: &ContainerArgs) -> Result<(Pid, bo
 
     // The intermediate process will send the init pid once it forks the init
     // process.  The intermediate process should exit after this point.
-    let init_pid = main_receiver.wait_for_intermediate_ready()?;
-    let mut need_to_clean_up_intel_rdt_subdirectory = false;
+    let (init_pid, cgroup_manager) = main_receiver.wait_for_intermediate_ready()?;
+    let mut intel_rdt_setup = IntelRdtSetup::default();
 
     if let Some(linux) = container_args.spec.linux() {
         if let Some(intel_rdt) = linux.intel_rdt() {
@@ -140,8 +144,7 @@ pub fn container_main_process(container_args: &ContainerArgs) -> Result<(Pid, bo
                 .container
                 .as_ref()
                 .map(|container| container.id());
-            need_to_clean_up_intel_rdt_subdirectory =
-                setup_intel_rdt(container_id, &init_pid, intel_rdt)?;
+            intel_rdt_setup = setup_intel_rdt(container_id, &init_pid, intel_rdt)?;
         }
     }
 
@@ -183,9 +186,15 @@ pub fn container_main_process(container_args: &ContainerArgs) -> Result<(Pid, bo
     }
 
     if let Some(linux) = container_args.spec.linux() {
-        move_network_devices_to_container(linux, init_pid, &mut main_receiver, &mut init_sender)?;
-
-        #[cfg(feature = "libseccomp")]
+        move_network_devices_to_container(
+            linux,
+            init_pid,
+            container_args.container.as_ref(),
+            &mut main_receiver,
+            &mut init_sender,
+        )?;
+
+        #[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
         if let Some(seccomp) = linux.seccomp() {
             let container = container_args
                 .container
@@ -278,7 +287,7 @@ pub fn container_main_process(container_args: &ContainerArgs) -> Result<(Pid, bo
         Err(err) => return Err(ProcessError::WaitIntermediateProcess(err)),
     };
 
-    Ok((init_pid, need_to_clean_up_intel_rdt_subdirectory))
+    Ok((init_pid, intel_rdt_setup, cgroup_manager))
 }
 
 fn setup_mapping(config: &UserNamespaceConfig, pid: Pid) -> Result<()> {
@@ -303,19 +312,27 @@ fn setup_mapping(config: &UserNamespaceConfig, pid: Pid) -> Result<()> {
 
 /// Moves configured network devices from the host to the container's network namespace.
 /// This function waits for the init process to join its namespace, then transfers each
-/// configured device while preserving network addresses. Returns early if the container
-/// runs in the host network namespace.
+/// configured device while preserving network addresses. The bridge and rootless networks
+/// of the container are connected afterwards. Returns early if the container runs in the
+/// host network namespace.
 fn move_network_devices_to_container(
     linux: &Linux,
     init_pid: Pid,
+    container: Option<&Container>,
     main_receiver: &mut channel::MainReceiver,
     init_sender: &mut channel::InitSender,
 ) -> Result<()> {
-    // Early return if there are no network devices to move
-    let devices = match linux.net_devices() {
-        Some(devs) if !devs.is_empty() => devs,
-        _ => return Ok(()),
-    };
+    // Early return if there are no network devices to move or create
+    let devices = linux.net_devices().as_ref().filter(|devs| !devs.is_empty());
+    let bridge_network = container.and_then(|container| container.bridge_network());
+    let rootless_network = container.and_then(|container| {
+        container
+            .rootless_network()
+            .map(|network| (network, &container.root))
+    });
+    if devices.is_none() && bridge_network.is_none() && rootless_network.is_none() {
+        return Ok(());
+    }
 
     if let Some(namespaces) = linux.namespaces() {
         // network devices are not moved for containers running in the host network.
@@ -351,17 +368,45 @@ fn move_network_devices_to_container(
         // The runtime spec requires that the kernel handles moving back any devices
         // that were successfully moved before the failure occurred.
         // See: https://github.com/opencontainers/runtime-spec/blob/27cb0027fd92ef81eda1ea3a8153b8337f56d94a/config-linux.md#namespace-lifecycle-and-container-termination
-        let addrs_map = devices
-            .iter()
+        let devices_map = devices
+            .into_iter()
+            .flatten()
             .map(|(name, net_dev)| {
-                let addrs = dev_change_net_namespace(name, netns_fd, net_dev).map_err(|err| {
+                let state = dev_change_net_namespace(name, netns_fd, net_dev).map_err(|err| {
                     tracing::error!("failed to dev_change_net_namespace: {}", err);
                     err
                 })?;
-                Ok((name.clone(), addrs))
+                Ok((name.clone(), state))
             })
-            .collect::<Result<HashMap<String, Vec<crate::network::cidr::CidrAddress>>>>()?;
-        init_sender.move_network_device(addrs_map)?;
+            .collect::<Result<HashMap<String, NetworkDeviceState>>>()?;
+
+        // The peer of the veth pair is created right in the network namespace, so
+        // that it never shows up on the host.
+        if let Some(bridge_network) = bridge_network {
+            bridge_network.setup_host(netns_fd).map_err(|err| {
+                tracing::error!(
+                    "failed to connect network namespace to bridge {}: {}",
+                    bridge_network.bridge,
+                    err
+                );
+                err
+            })?;
+        }
+
+        // The helper joins the namespaces of the init process, so it is started
+        // while the init process waits for its network.
+        if let Some((rootless_network, container_root)) = rootless_network {
+            let has_user_ns = namespaces
+                .iter()
+                .any(|ns| ns.typ() == LinuxNamespaceType::User);
+            rootless_network
+                .start(init_pid, has_user_ns, container_root)
+                .map_err(|err| {
+                    tracing::error!("failed to start {}: {}", rootless_network.mode, err);
+                    err
+                })?;
+        }
+        init_sender.move_network_device(devices_map)?;
     }
 
     Ok(())
@@ -1,3 +1,4 @@
+use nix::errno::Errno;
 use nix::sched::{CpuSet, sched_getaffinity, sched_setaffinity};
 use nix::unistd::Pid;
 use tracing::{Level, enabled};
@@ -18,6 +19,12 @@ pub enum CPUAffinityError {
     SetAffinity(#[source] nix::Error),
     #[error("failed to getaffinity")]
     GetAffinity(#[source] nix::Error),
+    #[error("failed to list the threads of process {pid}")]
+    ListThreads {
+        pid: Pid,
+        #[source]
+        source: procfs::ProcError,
+    },
 }
 
 type Result<T> = std::result::Result<T, CPUAffinityError>;
@@ -77,6 +84,24 @@ pub fn set_cpuset_affinity_from_string(pid: Pid, cpuset_str: &str) -> Result<()>
     sched_setaffinity(pid, &to_cpuset(cpuset_str)?).map_err(CPUAffinityError::SetAffinity)
 }
 
+/// Sets the CPU affinity of every thread of a running process, as
+/// sched_setaffinity(2) only changes the thread it is given.
+pub fn set_process_affinity(pid: Pid, cpuset: &CpuSet) -> Result<()> {
+    let list_threads = |source| CPUAffinityError::ListThreads { pid, source };
+    let tasks = procfs::process::Process::new(pid.as_raw())
+        .and_then(|process| process.tasks())
+        .map_err(list_threads)?;
+    for task in tasks {
+        let tid = Pid::from_raw(task.map_err(list_threads)?.tid);
+        match sched_setaffinity(tid, cpuset) {
+            // the thread exited in the meantime
+            Ok(()) | Err(Errno::ESRCH) => {}
+            Err(err) => return Err(CPUAffinityError::SetAffinity(err)),
+        }
+    }
+    Ok(())
+}
+
 // Logs a compact CPU affinity bitmask similar to runc's nsexec.c (see: https://github.com/opencontainers/runc/blob/main/libcontainer/nsenter/nsexec.c#L676).
 // This helps in debugging which CPUs the current process is allowed to run on.
 // Only logs when DEBUG level is enabled.
@@ -151,6 +176,27 @@ mod tests {
         assert!(cpuset.is_set(highest).unwrap());
     }
 
+    #[test]
+    fn test_set_process_affinity() -> anyhow::Result<()> {
+        let mut child = std::process::Command::new("sleep").arg("10").spawn()?;
+        let pid = Pid::from_raw(child.id() as i32);
+        let current = sched_getaffinity(Pid::this())?;
+        let cpu = (0..CpuSet::count())
+            .find(|&cpu| current.is_set(cpu).unwrap_or(false))
+            .unwrap();
+
+        let mut cpuset = CpuSet::new();
+        cpuset.set(cpu)?;
+        let result = set_process_affinity(pid, &cpuset);
+        let affinity = sched_getaffinity(pid);
+        child.kill()?;
+        child.wait()?;
+
+        result?;
+        assert_eq!(affinity?, cpuset);
+        Ok(())
+    }
+
     #[test]
     fn test_to_cpuset_exceeds_max_cpu() {
         let max = CpuSet::count();
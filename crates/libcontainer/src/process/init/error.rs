@@ -2,11 +2,11 @@ use crate::namespaces::NamespaceError;
 use crate::process::channel;
 use crate::process::memory_policy::MemoryPolicyError;
 use crate::rootfs::device::DeviceError;
-#[cfg(feature = "libseccomp")]
+#[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
 use crate::seccomp;
 use crate::syscall::SyscallError;
 use crate::workload::{ExecutorSetEnvsError, ExecutorValidationError};
-use crate::{apparmor, hooks, notify_socket, rootfs, tty, workload};
+use crate::{apparmor, hooks, landlock, notify_socket, rootfs, selinux, tty, workload};
 
 #[derive(Debug, thiserror::Error)]
 pub enum InitProcessError {
@@ -38,12 +38,16 @@ pub enum InitProcessError {
     SyscallOther(#[source] SyscallError),
     #[error("failed apparmor")]
     AppArmor(#[source] apparmor::AppArmorError),
+    #[error("failed selinux")]
+    SELinux(#[source] selinux::SELinuxError),
+    #[error("failed landlock")]
+    Landlock(#[source] landlock::LandlockError),
     #[error(transparent)]
     Pathrs(#[from] pathrs::error::Error),
     #[error("invalid umask")]
     InvalidUmask(u32),
     #[error(transparent)]
-    #[cfg(feature = "libseccomp")]
+    #[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
     Seccomp(#[from] seccomp::SeccompError),
     #[error("invalid executable: {0}")]
     InvalidExecutable(String),
@@ -77,6 +81,6 @@ pub enum InitProcessError {
     NoProcess,
     #[error("device error")]
     Device(#[source] DeviceError),
-    #[error("personality flag has not supported at this time")]
-    UnsupportedPersonalityFlag,
+    #[error("unsupported personality flag {0}")]
+    UnsupportedPersonalityFlag(String),
 }
@@ -19,24 +19,28 @@ use pathrs::procfs::{ProcfsBase, ProcfsHandle, ProcfsHandleBuilder};
 use super::Result;
 use super::context::InitContext;
 use super::error::InitProcessError;
-use crate::config::PersonalityDomain;
+use crate::config::{self, PersonalityDomain};
 use crate::error::MissingSpecError;
 use crate::namespaces::Namespaces;
 use crate::network::address::AddressClient;
+use crate::network::bridge::BridgeNetwork;
 use crate::network::link::LinkClient;
-use crate::network::network_device::{resolve_device_name, setup_addresses_in_network_namespace};
+use crate::network::network_device::{
+    resolve_device_name, setup_loopback, setup_network_device_in_network_namespace,
+};
+use crate::network::route::RouteClient;
 use crate::network::wrapper::create_network_client;
 use crate::process::args::{ContainerArgs, ContainerType};
 use crate::process::{channel, memory_policy};
 use crate::rootfs::RootFS;
 use crate::rootfs::device::{open_device_fd, verify_dev_null};
-#[cfg(feature = "libseccomp")]
+#[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
 use crate::seccomp;
 use crate::syscall::{Syscall, SyscallError};
 use crate::user_ns::UserNamespaceConfig;
-use crate::{apparmor, capabilities, hooks, tty, utils};
+use crate::{apparmor, capabilities, hooks, selinux, tty, utils};
 
-// Some variables are unused in the case where libseccomp feature is not enabled.
+// Some variables are unused in the case where no seccomp feature is enabled.
 #[allow(unused_variables)]
 pub fn container_init_process(
     args: &ContainerArgs,
@@ -81,7 +85,11 @@ pub fn container_init_process(
     if matches!(args.container_type, ContainerType::InitContainer) {
         let in_user_ns = utils::is_in_new_userns().map_err(InitProcessError::Io)?;
         let bind_service = ctx.ns.get(LinuxNamespaceType::User)?.is_some() || in_user_ns;
-        let rootfs = RootFS::new();
+        let rootfs = RootFS::new().with_overlay(
+            ctx.container
+                .and_then(|container| container.overlay_rootfs())
+                .cloned(),
+        );
         rootfs
             .prepare_rootfs(
                 ctx.spec,
@@ -137,6 +145,11 @@ pub fn container_init_process(
             err
         })?;
 
+        // Setup some operations in the network namespace.
+        // This is done here before dropping capabilities because we need to be able to add IP addresses to the device
+        // and set up the device, and before the sysctls which may refer to the devices.
+        setup_network(&ctx, main_sender, init_receiver)?;
+
         if let Some(kernel_params) = ctx.linux.sysctl() {
             sysctl(kernel_params)?;
         }
@@ -159,12 +172,11 @@ pub fn container_init_process(
     }
 
     if let Some(personality) = ctx.linux.personality() {
-        if let Some(flags) = personality.flags() {
-            if !flags.is_empty() {
-                tracing::error!("personality flag has not supported at this time");
-                return Err(InitProcessError::UnsupportedPersonalityFlag);
-            }
-        }
+        let flags = config::personality_flags(personality.flags().as_deref().unwrap_or_default())
+            .map_err(|flag| {
+            tracing::error!(?flag, "unsupported personality flag");
+            InitProcessError::UnsupportedPersonalityFlag(flag)
+        })?;
 
         let domain = match personality.domain() {
             // https://github.com/opencontainers/runtime-spec/blob/main/config-linux.md#personality
@@ -172,7 +184,7 @@ pub fn container_init_process(
             LinuxPersonalityDomain::PerLinux32 => PersonalityDomain::Linux32,
         };
 
-        ctx.syscall.personality(domain).map_err(|err| {
+        ctx.syscall.personality(domain, flags).map_err(|err| {
             tracing::error!(?err, "failed to set linux personality ");
             InitProcessError::SyscallOther(err)
         })?;
@@ -185,6 +197,13 @@ pub fn container_init_process(
         })?;
     }
 
+    if let Some(label) = ctx.process.selinux_label() {
+        selinux::set_exec_label(label).map_err(|err| {
+            tracing::error!(?err, "failed to set selinux process label");
+            InitProcessError::SELinux(err)
+        })?;
+    }
+
     if ctx.rootfs_ro {
         ctx.syscall
             .mount(
@@ -322,22 +341,20 @@ pub fn container_init_process(
         InitProcessError::SyscallOther(err)
     })?;
 
-    // Setup some operations in the network namespace.
-    // This is done here before dropping capabilities because we need to be able to add IP addresses to the device
-    // and set up the device.
-    if let Some(network_devices) = ctx.linux.net_devices() {
-        configure_container_network_devices(network_devices, main_sender, init_receiver).map_err(
-            |err| {
-                tracing::error!(?err, "failed to setup net_device");
-                err
-            },
-        )?;
+    // Landlock is applied before seccomp, so that a seccomp profile can't deny
+    // the landlock syscalls and leave the container unrestricted. It also
+    // restricts the start container hooks.
+    if let Some(landlock) = &args.landlock {
+        landlock.apply().map_err(|err| {
+            tracing::error!(?err, "failed to apply landlock ruleset");
+            InitProcessError::Landlock(err)
+        })?;
     }
 
     // Without no new privileges, seccomp is a privileged operation. We have to
     // do this before dropping capabilities. Otherwise, we should do it later,
     // as close to exec as possible.
-    #[cfg(feature = "libseccomp")]
+    #[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
     if let Some(seccomp) = ctx.linux.seccomp() {
         if ctx.process.no_new_privileges().is_none() {
             let notify_fd = seccomp::initialize_seccomp(seccomp).map_err(|err| {
@@ -350,7 +367,7 @@ pub fn container_init_process(
             })?;
         }
     }
-    #[cfg(not(feature = "libseccomp"))]
+    #[cfg(not(any(feature = "libseccomp", feature = "native_seccomp")))]
     if ctx.process.no_new_privileges().is_none() {
         tracing::warn!("seccomp not available, unable to enforce no_new_privileges!")
     }
@@ -384,7 +401,7 @@ pub fn container_init_process(
     // Initialize seccomp profile right before we are ready to execute the
     // payload so as few syscalls will happen between here and payload exec. The
     // notify socket will still need network related syscalls.
-    #[cfg(feature = "libseccomp")]
+    #[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
     if let Some(seccomp) = ctx.linux.seccomp() {
         if ctx.process.no_new_privileges().is_some() {
             let notify_fd = seccomp::initialize_seccomp(seccomp).map_err(|err| {
@@ -397,7 +414,7 @@ pub fn container_init_process(
             })?;
         }
     }
-    #[cfg(not(feature = "libseccomp"))]
+    #[cfg(not(any(feature = "libseccomp", feature = "native_seccomp")))]
     if ctx.process.no_new_privileges().is_some() {
         tracing::warn!("seccomp not available, unable to set seccomp privileges!")
     }
@@ -912,7 +929,7 @@ fn setup_scheduler(sc_op: &Option<Scheduler>) -> Result<()> {
     Ok(())
 }
 
-#[cfg(feature = "libseccomp")]
+#[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
 fn sync_seccomp(
     fd: Option<i32>,
     main_sender: &mut channel::MainSender,
@@ -939,20 +956,69 @@ fn sync_seccomp(
     Ok(())
 }
 
-fn configure_container_network_devices(
-    net_device: &HashMap<String, LinuxNetDevice>,
+fn setup_network(
+    ctx: &InitContext,
     main_sender: &mut channel::MainSender,
     init_receiver: &mut channel::InitReceiver,
 ) -> Result<()> {
-    if net_device.is_empty() {
+    // A new network namespace only has a loopback device, which is down
+    let new_net_ns = ctx
+        .ns
+        .get(LinuxNamespaceType::Network)?
+        .is_some_and(|ns| ns.path().is_none());
+    if new_net_ns {
+        let mut link_client = LinkClient::new(create_network_client())?;
+        setup_loopback(&mut link_client).map_err(|err| {
+            tracing::error!(?err, "failed to bring up loopback device");
+            err
+        })?;
+    }
+
+    let net_devices = ctx
+        .linux
+        .net_devices()
+        .as_ref()
+        .filter(|devs| !devs.is_empty());
+    let bridge_network = ctx
+        .container
+        .and_then(|container| container.bridge_network());
+    let rootless_network = ctx
+        .container
+        .and_then(|container| container.rootless_network());
+    if net_devices.is_none() && bridge_network.is_none() && rootless_network.is_none() {
         return Ok(());
     }
 
+    // The unprivileged helper of the rootless network has to open the namespaces
+    // of this process, which requires it to be dumpable
+    if rootless_network.is_some() {
+        prctl::set_dumpable(true)
+            .map_err(|e| InitProcessError::NixOther(nix::errno::Errno::from_raw(e)))?;
+    }
+    configure_container_network_devices(net_devices, bridge_network, main_sender, init_receiver)
+        .map_err(|err| {
+            tracing::error!(?err, "failed to setup net_device");
+            err
+        })?;
+    if rootless_network.is_some() {
+        prctl::set_dumpable(false)
+            .map_err(|e| InitProcessError::NixOther(nix::errno::Errno::from_raw(e)))?;
+    }
+
+    Ok(())
+}
+
+fn configure_container_network_devices(
+    net_device: Option<&HashMap<String, LinuxNetDevice>>,
+    bridge_network: Option<&BridgeNetwork>,
+    main_sender: &mut channel::MainSender,
+    init_receiver: &mut channel::InitReceiver,
+) -> Result<()> {
     main_sender.network_setup_ready()?;
 
-    let addrs_map = init_receiver.wait_for_move_network_device()?;
-    for (name, net_dev) in net_device {
-        if let Some(cidr_addrs) = addrs_map.get(name) {
+    let devices_map = init_receiver.wait_for_move_network_device()?;
+    for (name, net_dev) in net_device.into_iter().flatten() {
+        if let Some(state) = devices_map.get(name) {
             // Get the device's final name (use configured name if provided, otherwise use original name)
             let new_name = resolve_device_name(net_dev, name.as_str());
 
@@ -965,6 +1031,10 @@ fn configure_container_network_devices(
                 tracing::error!(?err, "failed to create address client");
                 err
             })?;
+            let mut route_client = RouteClient::new(create_network_client()).map_err(|err| {
+                tracing::error!(?err, "failed to create route client");
+                err
+            })?;
 
             // Get the device index
             let ns_link = link_client.get_by_name(new_name).map_err(|err| {
@@ -972,26 +1042,33 @@ fn configure_container_network_devices(
                 err
             })?;
 
-            // Assign IP addresses to the device
-            setup_addresses_in_network_namespace(
-                cidr_addrs,
-                ns_link.header.index,
+            // Restore the addresses, routes and attributes of the device and bring it up
+            setup_network_device_in_network_namespace(
+                state,
+                &ns_link,
                 new_name,
+                &mut link_client,
                 &mut addr_client,
+                &mut route_client,
             )
             .map_err(|err| {
-                tracing::error!(?err, "failed to setup addresses for device: {}", new_name);
-                err
-            })?;
-
-            // Bring the device up
-            link_client.set_up(ns_link.header.index).map_err(|err| {
-                tracing::error!(?err, "failed to bring up device: {}", new_name);
+                tracing::error!(?err, "failed to setup device: {}", new_name);
                 err
             })?;
         }
     }
 
+    if let Some(bridge_network) = bridge_network {
+        bridge_network.setup_container().map_err(|err| {
+            tracing::error!(
+                ?err,
+                "failed to setup network device: {}",
+                bridge_network.interface
+            );
+            err
+        })?;
+    }
+
     Ok(())
 }
 
@@ -1041,11 +1118,11 @@ mod tests {
     use std::path::{Path, PathBuf};
 
     use anyhow::Result;
-    #[cfg(feature = "libseccomp")]
+    #[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
     use nix::unistd;
     use nix::unistd::{Uid, User as NixUser};
     use oci_spec::runtime::{LinuxNamespaceBuilder, SpecBuilder, UserBuilder};
-    #[cfg(feature = "libseccomp")]
+    #[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
     use serial_test::serial;
 
     use super::*;
@@ -1197,7 +1274,7 @@ mod tests {
 
     #[test]
     #[serial]
-    #[cfg(feature = "libseccomp")]
+    #[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
     fn test_sync_seccomp() -> Result<()> {
         use std::os::unix::io::IntoRawFd;
         use std::thread;
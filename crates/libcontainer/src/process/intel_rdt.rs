@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::fs::{self, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 
@@ -10,6 +10,7 @@ use pathrs::flags::OpenFlags;
 use pathrs::procfs::{ProcfsBase, ProcfsHandle};
 use procfs::process::MountInfo;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 #[derive(Debug, thiserror::Error)]
 pub enum IntelRdtError {
@@ -47,6 +48,18 @@ pub enum IntelRdtError {
     CreateClosIDDirectory(#[source] std::io::Error),
     #[error("failed to canonicalize path")]
     Canonicalize(#[source] std::io::Error),
+    #[error("Intel RDT monitoring is not supported")]
+    MonitoringNotSupported,
+    #[error("failed to create resctrl monitoring group")]
+    CreateMonitoringGroup(#[source] std::io::Error),
+    #[error("failed to write to resctrl monitoring group")]
+    WriteMonitoringGroup(#[source] std::io::Error),
+    #[error("failed to remove resctrl monitoring group")]
+    RemoveMonitoringGroup(#[source] std::io::Error),
+    #[error("failed to read resctrl monitoring data")]
+    ReadMonitoringData(#[source] std::io::Error),
+    #[error("invalid resctrl monitoring data {0}")]
+    InvalidMonitoringData(String),
     #[error(transparent)]
     Pathrs(#[from] pathrs::error::Error),
     #[error(transparent)]
@@ -67,6 +80,61 @@ pub enum ParseLineError {
 
 type Result<T> = std::result::Result<T, IntelRdtError>;
 
+/// The resctrl group whose monitoring data counts for the container, which
+/// is either the group of the container, or a monitoring group created for
+/// the container within the group given by closID.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct IntelRdtMonitoring {
+    pub group: PathBuf,
+    // Specifies if the group is a monitoring group that needs to be removed
+    pub created_group: bool,
+    // Specifies if the cache occupancy (CMT) is reported
+    pub cmt: bool,
+    // Specifies if the memory bandwidth (MBM) is reported
+    pub mbm: bool,
+}
+
+/// Reports the Intel RDT monitoring data of a container per L3 cache
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct IntelRdtStats {
+    /// Cache occupancy per L3 cache, if CMT is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cmt: Option<Vec<CmtStats>>,
+    /// Memory bandwidth per L3 cache, if MBM is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mbm: Option<Vec<MbmStats>>,
+}
+
+/// Reports the cache occupancy of an L3 cache. The counters are None while
+/// the kernel reports them as unavailable.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct CmtStats {
+    /// Id of the L3 cache
+    pub cache_id: u32,
+    /// Bytes of the cache used by the container
+    pub llc_occupancy: Option<u64>,
+}
+
+/// Reports the memory bandwidth through an L3 cache. The counters are None
+/// while the kernel reports them as unavailable.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct MbmStats {
+    /// Id of the L3 cache
+    pub cache_id: u32,
+    /// Bytes transferred to and from memory
+    pub mbm_total_bytes: Option<u64>,
+    /// Bytes transferred to and from the memory local to the cache
+    pub mbm_local_bytes: Option<u64>,
+}
+
+/// The Intel RDT setup of the container process, which is needed again once
+/// the container is deleted
+#[derive(Debug, Default)]
+pub struct IntelRdtSetup {
+    pub need_to_delete_directory: bool,
+    pub monitoring: Option<IntelRdtMonitoring>,
+}
+
 pub fn delete_resctrl_subdirectory(id: &str) -> Result<()> {
     let dir = find_resctrl_mount_point().map_err(|err| {
         tracing::error!("failed to find resctrl mount point: {}", err);
@@ -372,13 +440,13 @@ fn write_resctrl_schemata(
 }
 
 /// Sets up Intel RDT configuration for the container process based on the
-/// OCI config. The result bool tells whether or not we need to clean up
-/// the created subdirectory.
+/// OCI config. The result tells whether or not we need to clean up the
+/// created subdirectory, and where the monitoring data of the container is.
 pub fn setup_intel_rdt(
     maybe_container_id: Option<&str>,
     init_pid: &Pid,
     intel_rdt: &LinuxIntelRdt,
-) -> Result<bool> {
+) -> Result<IntelRdtSetup> {
     // Find mounted resctrl filesystem, error out if it can't be found.
     let path = find_resctrl_mount_point().inspect_err(|_err| {
         tracing::error!("failed to find a mounted resctrl file system");
@@ -412,7 +480,170 @@ pub fn setup_intel_rdt(
     // the runtime MUST remove the sub-directory when the container is deleted.
     let need_to_delete_directory = !clos_id_set && created_dir;
 
-    Ok(need_to_delete_directory)
+    let cmt = intel_rdt.enable_cmt().unwrap_or(false);
+    let mbm = intel_rdt.enable_mbm().unwrap_or(false);
+    // The monitoring group of a tenant process is unknown without the id of
+    // the container, so it is only counted in the group given by closID.
+    let monitoring = match maybe_container_id {
+        Some(container_id) if cmt || mbm => Some(
+            setup_monitoring_group(&path, id, container_id, clos_id_set, *init_pid, cmt, mbm)
+                .inspect_err(|_err| {
+                    tracing::error!("failed to set up resctrl monitoring group");
+                })?,
+        ),
+        _ => None,
+    };
+
+    Ok(IntelRdtSetup {
+        need_to_delete_directory,
+        monitoring,
+    })
+}
+
+/// Creates the monitoring group of the container in a group given by
+/// closID, which may be shared with other containers. The group of the
+/// container itself monitors the container otherwise.
+fn setup_monitoring_group(
+    path: &Path,
+    id: &str,
+    container_id: &str,
+    clos_id_set: bool,
+    init_pid: Pid,
+    cmt: bool,
+    mbm: bool,
+) -> Result<IntelRdtMonitoring> {
+    if !path.join("info").join("L3_MON").exists() {
+        return Err(IntelRdtError::MonitoringNotSupported);
+    }
+    if !clos_id_set {
+        return Ok(IntelRdtMonitoring {
+            group: path.join(id),
+            created_group: false,
+            cmt,
+            mbm,
+        });
+    }
+
+    let group = path.join(id).join("mon_groups").join(container_id);
+    let created_group = match fs::create_dir(&group) {
+        Ok(()) => true,
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => false,
+        Err(err) => return Err(IntelRdtError::CreateMonitoringGroup(err)),
+    };
+    // Same as above, the file doesn't need to be created outside of tests.
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(group.join("tasks"))
+        .map_err(IntelRdtError::WriteMonitoringGroup)?;
+    write!(file, "{init_pid}").map_err(IntelRdtError::WriteMonitoringGroup)?;
+
+    Ok(IntelRdtMonitoring {
+        group,
+        created_group,
+        cmt,
+        mbm,
+    })
+}
+
+/// Removes the monitoring group if it was created for the container. Its
+/// tasks move back to the group given by closID.
+pub fn delete_monitoring_group(monitoring: &IntelRdtMonitoring) -> Result<()> {
+    if monitoring.created_group {
+        fs::remove_dir(&monitoring.group).map_err(IntelRdtError::RemoveMonitoringGroup)?;
+    }
+    Ok(())
+}
+
+/// Reads the monitoring data of the container from mon_data, which has a
+/// directory for every L3 cache.
+pub fn monitoring_stats(monitoring: &IntelRdtMonitoring) -> Result<IntelRdtStats> {
+    let mut cmt = Vec::new();
+    let mut mbm = Vec::new();
+    let entries = fs::read_dir(monitoring.group.join("mon_data"))
+        .map_err(IntelRdtError::ReadMonitoringData)?;
+    for entry in entries {
+        let entry = entry.map_err(IntelRdtError::ReadMonitoringData)?;
+        let name = entry.file_name();
+        let Some(cache_id) = name.to_str().and_then(|name| name.strip_prefix("mon_L3_")) else {
+            continue;
+        };
+        let cache_id = cache_id.parse().map_err(|_| {
+            IntelRdtError::InvalidMonitoringData(entry.path().display().to_string())
+        })?;
+
+        let domain = entry.path();
+        if monitoring.cmt {
+            cmt.push(CmtStats {
+                cache_id,
+                llc_occupancy: read_counter(&domain.join("llc_occupancy"))?,
+            });
+        }
+        if monitoring.mbm {
+            mbm.push(MbmStats {
+                cache_id,
+                mbm_total_bytes: read_counter(&domain.join("mbm_total_bytes"))?,
+                mbm_local_bytes: read_counter(&domain.join("mbm_local_bytes"))?,
+            });
+        }
+    }
+    cmt.sort_by_key(|stats| stats.cache_id);
+    mbm.sort_by_key(|stats| stats.cache_id);
+
+    Ok(IntelRdtStats {
+        cmt: monitoring.cmt.then_some(cmt),
+        mbm: monitoring.mbm.then_some(mbm),
+    })
+}
+
+/// Reads a monitoring counter, which the kernel reports as "Unavailable"
+/// until the hardware counted anything, and as "Error" if reading failed.
+fn read_counter(path: &Path) -> Result<Option<u64>> {
+    let value = match fs::read_to_string(path) {
+        Ok(value) => value,
+        // not every cache supports all the counters
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(IntelRdtError::ReadMonitoringData(err)),
+    };
+    match value.trim() {
+        "Unavailable" | "Error" => Ok(None),
+        value => value
+            .parse()
+            .map(Some)
+            .map_err(|_| IntelRdtError::InvalidMonitoringData(path.display().to_string())),
+    }
+}
+
+/// Rewrites the schemata of the resctrl group a running container is in. The
+/// kernel only changes the resources listed in the written schemata, so the
+/// L3 cache and memory bandwidth schemas can be updated separately.
+pub fn update_intel_rdt(id: &str, intel_rdt: &LinuxIntelRdt) -> Result<()> {
+    let path = find_resctrl_mount_point().inspect_err(|_err| {
+        tracing::error!("failed to find a mounted resctrl file system");
+    })?;
+    update_resctrl_schemata(
+        &path,
+        id,
+        intel_rdt.l3_cache_schema(),
+        intel_rdt.mem_bw_schema(),
+    )
+    .inspect_err(|_err| {
+        tracing::error!("failed to update the resctrl schemata file");
+    })
+}
+
+fn update_resctrl_schemata(
+    path: &Path,
+    id: &str,
+    l3_cache_schema: &Option<String>,
+    mem_bw_schema: &Option<String>,
+) -> Result<()> {
+    // Unlike on creation, the group is never created here: the tasks of the
+    // container would otherwise stay in their current group.
+    if !path.join(id).is_dir() {
+        return Err(IntelRdtError::NoResctrlSubdirectory);
+    }
+    write_resctrl_schemata(path, id, l3_cache_schema, mem_bw_schema, false, false)
 }
 
 #[cfg(test)]
@@ -619,4 +850,154 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_update_resctrl_schemata() -> Result<()> {
+        let tmp = tempfile::tempdir().unwrap();
+
+        // The group of the container must already exist.
+        let bw_1 = "MB:0=70;1=20";
+        let res = update_resctrl_schemata(tmp.path(), "foobar", &None, &Some(bw_1.to_owned()));
+        assert!(matches!(res, Err(IntelRdtError::NoResctrlSubdirectory)));
+
+        write_container_pid_to_resctrl_tasks(tmp.path(), "foobar", Pid::from_raw(1000), false)?;
+        let l3_1 = "L3:0=f;1=f0";
+        write_resctrl_schemata(
+            tmp.path(),
+            "foobar",
+            &Some(l3_1.to_owned()),
+            &None,
+            false,
+            true,
+        )?;
+
+        // Only the given schema is written.
+        update_resctrl_schemata(tmp.path(), "foobar", &None, &Some(bw_1.to_owned()))?;
+        let res = fs::read_to_string(tmp.path().join("foobar").join("schemata"))?;
+        assert!(is_same_schema(bw_1, &res)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_setup_monitoring_group() -> Result<()> {
+        let tmp = tempfile::tempdir().unwrap();
+
+        // Monitoring needs the L3_MON resource.
+        let res = setup_monitoring_group(
+            tmp.path(),
+            "foo",
+            "bar",
+            true,
+            Pid::from_raw(1000),
+            true,
+            false,
+        );
+        assert!(matches!(res, Err(IntelRdtError::MonitoringNotSupported)));
+        fs::create_dir_all(tmp.path().join("info").join("L3_MON"))?;
+
+        // Without closID, the group of the container is used.
+        let monitoring = setup_monitoring_group(
+            tmp.path(),
+            "bar",
+            "bar",
+            false,
+            Pid::from_raw(1000),
+            true,
+            false,
+        )?;
+        assert_eq!(monitoring.group, tmp.path().join("bar"));
+        assert!(!monitoring.created_group);
+
+        // With closID, a monitoring group is created for the container.
+        fs::create_dir_all(tmp.path().join("foo").join("mon_groups"))?;
+        let monitoring = setup_monitoring_group(
+            tmp.path(),
+            "foo",
+            "bar",
+            true,
+            Pid::from_raw(1000),
+            true,
+            true,
+        )?;
+        let group = tmp.path().join("foo").join("mon_groups").join("bar");
+        assert_eq!(monitoring.group, group);
+        assert!(monitoring.created_group);
+        assert_eq!(fs::read_to_string(group.join("tasks"))?, "1000");
+
+        // The tasks file has to go before the directory can be removed.
+        fs::remove_file(group.join("tasks"))?;
+        delete_monitoring_group(&monitoring)?;
+        assert!(!group.exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_monitoring_stats() -> Result<()> {
+        let tmp = tempfile::tempdir().unwrap();
+        let mon_data = tmp.path().join("mon_data");
+        for (domain, occupancy, total) in [
+            ("mon_L3_01", "4096", "Unavailable"),
+            ("mon_L3_00", "8192", "100"),
+        ] {
+            fs::create_dir_all(mon_data.join(domain))?;
+            fs::write(
+                mon_data.join(domain).join("llc_occupancy"),
+                format!("{occupancy}\n"),
+            )?;
+            fs::write(
+                mon_data.join(domain).join("mbm_total_bytes"),
+                format!("{total}\n"),
+            )?;
+        }
+
+        let mut monitoring = IntelRdtMonitoring {
+            group: tmp.path().to_path_buf(),
+            created_group: false,
+            cmt: true,
+            mbm: false,
+        };
+        let stats = monitoring_stats(&monitoring)?;
+        assert_eq!(
+            stats.cmt,
+            Some(vec![
+                CmtStats {
+                    cache_id: 0,
+                    llc_occupancy: Some(8192)
+                },
+                CmtStats {
+                    cache_id: 1,
+                    llc_occupancy: Some(4096)
+                },
+            ])
+        );
+        assert!(stats.mbm.is_none());
+
+        monitoring.mbm = true;
+        let stats = monitoring_stats(&monitoring)?;
+        assert_eq!(
+            stats.mbm,
+            Some(vec![
+                MbmStats {
+                    cache_id: 0,
+                    mbm_total_bytes: Some(100),
+                    mbm_local_bytes: None
+                },
+                MbmStats {
+                    cache_id: 1,
+                    mbm_total_bytes: None,
+                    mbm_local_bytes: None
+                },
+            ])
+        );
+
+        fs::write(mon_data.join("mon_L3_00").join("llc_occupancy"), "bogus")?;
+        assert!(matches!(
+            monitoring_stats(&monitoring),
+            Err(IntelRdtError::InvalidMonitoringData(_))
+        ));
+
+        Ok(())
+    }
 }
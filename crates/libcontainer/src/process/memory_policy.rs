@@ -1,5 +1,6 @@
-use std::fmt;
+use std::{fmt, fs};
 
+use nix::unistd::Pid;
 use oci_spec::runtime::{MemoryPolicyFlagType, MemoryPolicyModeType};
 
 use crate::syscall::{Syscall, SyscallError};
@@ -18,10 +19,15 @@ pub enum MemoryPolicyError {
     #[error("Mutually exclusive flags: {0}")]
     MutuallyExclusiveFlags(String),
 
+    #[error("Failed to read the online NUMA nodes: {0}")]
+    ReadOnlineNodes(#[source] std::io::Error),
+
     #[error("Syscall error: {0}")]
     Syscall(#[from] SyscallError),
 }
 
+const ONLINE_NODES_PATH: &str = "/sys/devices/system/node/online";
+
 type Result<T> = std::result::Result<T, MemoryPolicyError>;
 
 #[repr(i32)]
@@ -263,6 +269,64 @@ pub fn setup_memory_policy(
     Ok(())
 }
 
+/// Moves the pages of a running process onto the nodes of the memory policy
+/// using migrate_pages(2). Only the process itself can change its memory
+/// policy, so pages it allocates later still follow the policy it was
+/// started with.
+///
+/// See: https://man7.org/linux/man-pages/man2/migrate_pages.2.html
+pub fn migrate_memory(
+    pid: Pid,
+    memory_policy: &Option<oci_spec::runtime::LinuxMemoryPolicy>,
+    syscall: &dyn Syscall,
+) -> Result<()> {
+    let Some(valid) = validate_memory_policy(memory_policy)? else {
+        return Ok(());
+    };
+    // MPOL_DEFAULT, MPOL_LOCAL and MPOL_PREFERRED without nodes don't name
+    // any node to move the pages to
+    if valid.maxnode == 0 {
+        return Ok(());
+    }
+
+    let online =
+        fs::read_to_string(ONLINE_NODES_PATH).map_err(MemoryPolicyError::ReadOnlineNodes)?;
+    let (maxnode, old_nodes, new_nodes) = build_migration_nodemasks(&valid, &online)?;
+    if old_nodes.iter().all(|&word| word == 0) {
+        return Ok(());
+    }
+
+    match syscall.migrate_pages(pid, maxnode, &old_nodes, &new_nodes) {
+        // the process exited in the meantime
+        Ok(()) | Err(SyscallError::Nix(nix::errno::Errno::ESRCH)) => Ok(()),
+        Err(err) => {
+            tracing::error!(?err, ?pid, "failed to migrate pages");
+            Err(MemoryPolicyError::Syscall(err))
+        }
+    }
+}
+
+// Build the nodemasks for migrate_pages, which moves the pages from all other
+// online nodes to the nodes of the policy
+fn build_migration_nodemasks(
+    valid: &ValidatedMemoryPolicy,
+    online_nodes: &str,
+) -> Result<(u64, Vec<libc::c_ulong>, Vec<libc::c_ulong>)> {
+    let (online_mask, online_maxnode) = build_nodemask(online_nodes)?;
+    let maxnode = valid.maxnode.max(online_maxnode);
+    let num_ulongs = maxnode as usize / (std::mem::size_of::<libc::c_ulong>() * 8);
+
+    let mut new_nodes = valid.nodemask.clone();
+    new_nodes.resize(num_ulongs, 0);
+    let old_nodes = new_nodes
+        .iter()
+        .enumerate()
+        .map(|(i, new)| online_mask.get(i).copied().unwrap_or(0) & !new)
+        .collect();
+
+    Ok((maxnode, old_nodes, new_nodes))
+}
+
 // Build a proper nodemask for set_mempolicy
 fn build_nodemask(nodes: &str) -> Result<(Vec<libc::c_ulong>, u64)> {
     let node_ids = parse_node_string(nodes)?;
@@ -542,4 +606,51 @@ mod tests {
                 .is_err()
         );
     }
+
+    #[test]
+    fn test_build_migration_nodemasks() {
+        use oci_spec::runtime::{LinuxMemoryPolicyBuilder, MemoryPolicyModeType};
+
+        let policy = LinuxMemoryPolicyBuilder::default()
+            .mode(MemoryPolicyModeType::MpolBind)
+            .nodes("1".to_string())
+            .flags(vec![])
+            .build()
+            .unwrap();
+        let valid = validate_memory_policy(&Some(policy)).unwrap().unwrap();
+
+        let (maxnode, old_nodes, new_nodes) = build_migration_nodemasks(&valid, "0-3\n").unwrap();
+        assert_eq!(maxnode, 64);
+        assert_eq!(old_nodes, vec![0b1101]);
+        assert_eq!(new_nodes, vec![0b0010]);
+
+        // the online nodes beyond the policy nodemask are moved as well
+        let (maxnode, old_nodes, new_nodes) = build_migration_nodemasks(&valid, "0-1,70").unwrap();
+        assert_eq!(maxnode, 128);
+        assert_eq!(old_nodes, vec![0b01, 1 << 6]);
+        assert_eq!(new_nodes, vec![0b10, 0]);
+    }
+
+    #[test]
+    fn test_migrate_memory_without_nodes() {
+        use oci_spec::runtime::{LinuxMemoryPolicyBuilder, MemoryPolicyModeType};
+
+        let syscall = create_syscall();
+        let policy = LinuxMemoryPolicyBuilder::default()
+            .mode(MemoryPolicyModeType::MpolLocal)
+            .nodes("".to_string())
+            .flags(vec![])
+            .build()
+            .unwrap();
+
+        migrate_memory(Pid::this(), &None, syscall.as_ref()).unwrap();
+        migrate_memory(Pid::this(), &Some(policy), syscall.as_ref()).unwrap();
+
+        let got_args = syscall
+            .as_any()
+            .downcast_ref::<TestHelperSyscall>()
+            .unwrap()
+            .get_migrate_pages_args();
+        assert!(got_args.is_empty());
+    }
 }
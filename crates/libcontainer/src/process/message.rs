@@ -1,21 +1,22 @@
 use core::fmt;
 use std::collections::HashMap;
 
+use libcgroups::common::CgroupManagerInfo;
 use serde::{Deserialize, Serialize};
 
-use crate::network::cidr::CidrAddress;
+use crate::network::network_device::NetworkDeviceState;
 
 /// Used as a wrapper for messages to be sent between child and parent processes
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum Message {
-    IntermediateReady(i32),
+    IntermediateReady(i32, Option<Box<CgroupManagerInfo>>),
     InitReady,
     WriteMapping,
     MappingWritten,
     SeccompNotify,
     SeccompNotifyDone,
     SetupNetworkDeviceReady,
-    MoveNetworkDevice(HashMap<String, Vec<CidrAddress>>),
+    MoveNetworkDevice(HashMap<String, NetworkDeviceState>),
     ExecFailed(String),
     OtherError(String),
     HookRequest,
@@ -25,12 +26,12 @@ pub enum Message {
 impl fmt::Display for Message {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Message::IntermediateReady(pid) => write!(f, "IntermediateReady({})", pid),
+            Message::IntermediateReady(pid, _) => write!(f, "IntermediateReady({})", pid),
             Message::InitReady => write!(f, "InitReady"),
             Message::WriteMapping => write!(f, "WriteMapping"),
             Message::MappingWritten => write!(f, "MappingWritten"),
             Message::SetupNetworkDeviceReady => write!(f, "SetupNetworkDeviceReady"),
-            Message::MoveNetworkDevice(addr) => write!(f, "MoveNetworkDevice({:?})", addr),
+            Message::MoveNetworkDevice(devices) => write!(f, "MoveNetworkDevice({:?})", devices),
             Message::SeccompNotify => write!(f, "SeccompNotify"),
             Message::SeccompNotifyDone => write!(f, "SeccompNotifyDone"),
             Message::HookRequest => write!(f, "HookRequest"),
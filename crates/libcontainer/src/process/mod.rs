@@ -11,5 +11,6 @@ pub mod init;
 pub mod intel_rdt;
 pub mod memory_policy;
 mod message;
-#[cfg(feature = "libseccomp")]
+pub(crate) mod pidfd;
+#[cfg(any(feature = "libseccomp", feature = "native_seccomp"))]
 mod seccomp_listener;
@@ -0,0 +1,149 @@
+//! Waits for a process through a pidfd, which unlike a pid can't refer to
+//! another process once the original one exited
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+
+use nix::errno::Errno;
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use nix::unistd::Pid;
+use procfs::process::{ProcState, Process};
+
+/// Requests the exit status from PIDFD_GET_INFO, available since Linux 6.15
+const PIDFD_INFO_EXIT: u64 = 1 << 3;
+
+#[repr(C)]
+#[derive(Default)]
+struct PidfdInfo {
+    mask: u64,
+    cgroupid: u64,
+    pid: u32,
+    tgid: u32,
+    ppid: u32,
+    ruid: u32,
+    rgid: u32,
+    euid: u32,
+    egid: u32,
+    suid: u32,
+    sgid: u32,
+    fsuid: u32,
+    fsgid: u32,
+    exit_code: i32,
+}
+
+nix::ioctl_readwrite!(pidfd_get_info, 0xFF, 11, PidfdInfo);
+
+pub(crate) struct PidFd {
+    fd: OwnedFd,
+    pid: Pid,
+}
+
+impl PidFd {
+    /// Opens a pidfd of the process, or returns None if it is already gone.
+    /// Fails with ENOSYS on kernels before 5.3.
+    pub fn open(pid: Pid) -> nix::Result<Option<Self>> {
+        // Safety: pidfd_open takes no pointers
+        let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid.as_raw(), 0) };
+        match Errno::result(fd) {
+            Ok(fd) => Ok(Some(Self {
+                // Safety: pidfd_open returned a new file descriptor
+                fd: unsafe { OwnedFd::from_raw_fd(fd as RawFd) },
+                pid,
+            })),
+            Err(Errno::ESRCH) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Blocks until the process exited, which makes the pidfd readable.
+    /// Returns false if it is still running once the timeout elapsed.
+    pub fn wait_exited(&self, timeout: Option<Duration>) -> nix::Result<bool> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let timeout = match deadline {
+                Some(deadline) => {
+                    PollTimeout::try_from(deadline.saturating_duration_since(Instant::now()))
+                        .unwrap_or(PollTimeout::MAX)
+                }
+                None => PollTimeout::NONE,
+            };
+            let mut fds = [PollFd::new(self.fd.as_fd(), PollFlags::POLLIN)];
+            match poll(&mut fds, timeout) {
+                Ok(0) => return Ok(false),
+                Ok(_) => return Ok(true),
+                Err(Errno::EINTR) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Reaps the process once it exited and returns its exit status. The exit
+    /// status of a process which is not a child of the caller is only known
+    /// from the kernel since Linux 6.15, or while it is a zombie.
+    pub fn reap(&self) -> nix::Result<Option<ExitStatus>> {
+        let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+        loop {
+            // Safety: info is a valid siginfo_t
+            let ret = unsafe {
+                libc::waitid(
+                    libc::P_PIDFD,
+                    self.fd.as_raw_fd() as libc::id_t,
+                    &mut info,
+                    libc::WEXITED,
+                )
+            };
+            match Errno::result(ret) {
+                Ok(_) => break,
+                Err(Errno::EINTR) => continue,
+                Err(Errno::ECHILD) => return Ok(self.exit_status_of_non_child()),
+                Err(err) => return Err(err),
+            }
+        }
+
+        // Safety: waitid filled in the siginfo of a SIGCHLD
+        let status = unsafe { info.si_status() };
+        let raw = match info.si_code {
+            libc::CLD_EXITED => status << 8,
+            libc::CLD_DUMPED => status | 0x80,
+            _ => status,
+        };
+        Ok(Some(ExitStatus::from_raw(raw)))
+    }
+
+    fn exit_status_of_non_child(&self) -> Option<ExitStatus> {
+        if let Some(status) = self.recorded_exit_status() {
+            return Some(status);
+        }
+        // The kernel only records the exit status once the parent reaped the
+        // process. Until then, it can be read from the zombie.
+        match Process::new(self.pid.as_raw()).and_then(|process| process.stat()) {
+            Ok(stat) if matches!(stat.state(), Ok(ProcState::Zombie)) => {
+                stat.exit_code.map(ExitStatus::from_raw)
+            }
+            _ => self.recorded_exit_status(),
+        }
+    }
+
+    fn recorded_exit_status(&self) -> Option<ExitStatus> {
+        let mut info = PidfdInfo {
+            mask: PIDFD_INFO_EXIT,
+            ..Default::default()
+        };
+        // Safety: info is a valid pidfd_info
+        unsafe { pidfd_get_info(self.fd.as_raw_fd(), &mut info) }.ok()?;
+        (info.mask & PIDFD_INFO_EXIT != 0).then(|| ExitStatus::from_raw(info.exit_code))
+    }
+}
+
+impl AsFd for PidFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl AsRawFd for PidFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
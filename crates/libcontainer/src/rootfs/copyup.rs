@@ -0,0 +1,296 @@
+//! Copies the contents of a directory of the image into a tmpfs mounted over it,
+//! which is requested by the `tmpcopyup` mount option.
+//!
+//! The copy keeps ownership, modes, timestamps, extended attributes and hard
+//! links. Symlinks are copied as they are and never followed, so the image can't
+//! point the copy outside of the directory.
+use std::collections::HashMap;
+use std::ffi::{CString, OsStr};
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, OpenOptionsExt, PermissionsExt, lchown};
+use std::path::{Path, PathBuf};
+
+use nix::sys::stat::{Mode, SFlag, UtimensatFlags, mknod, utimensat};
+use nix::sys::time::TimeSpec;
+
+/// Copies the tree below `src` into `dst`, which has to be an existing, empty
+/// directory. The attributes of `src` are applied to `dst` as well, except for the
+/// mode if `keep_mode` is set because the mount options define it.
+pub fn copy_up(src: &Path, dst: &Path, keep_mode: bool) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(src)?;
+    if !metadata.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a directory", src.display()),
+        ));
+    }
+
+    let mut links = HashMap::new();
+    copy_dir_contents(src, dst, &mut links)?;
+    copy_attributes(src, dst, &metadata, !keep_mode)
+}
+
+// Copies the entries of a directory recursively. Entries on other file systems
+// are copied as well, like cp -a does.
+fn copy_dir_contents(
+    src: &Path,
+    dst: &Path,
+    links: &mut HashMap<(u64, u64), PathBuf>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let src_path = entry.path();
+        let dst_path = dst.join(entry.file_name());
+        let metadata = fs::symlink_metadata(&src_path)?;
+        let file_type = metadata.file_type();
+
+        // hard links are recreated instead of copying the file again
+        if !file_type.is_dir() && metadata.nlink() > 1 {
+            let key = (metadata.dev(), metadata.ino());
+            if let Some(first) = links.get(&key) {
+                fs::hard_link(first, &dst_path)?;
+                continue;
+            }
+            links.insert(key, dst_path.clone());
+        }
+
+        if file_type.is_dir() {
+            fs::create_dir(&dst_path)?;
+            copy_dir_contents(&src_path, &dst_path, links)?;
+        } else if file_type.is_symlink() {
+            std::os::unix::fs::symlink(fs::read_link(&src_path)?, &dst_path)?;
+        } else if file_type.is_file() {
+            copy_file(&src_path, &dst_path)?;
+        } else {
+            let kind = if file_type.is_block_device() {
+                SFlag::S_IFBLK
+            } else if file_type.is_char_device() {
+                SFlag::S_IFCHR
+            } else if file_type.is_fifo() {
+                SFlag::S_IFIFO
+            } else {
+                SFlag::S_IFSOCK
+            };
+            mknod(
+                &dst_path,
+                kind,
+                Mode::from_bits_truncate(metadata.mode()),
+                metadata.rdev(),
+            )?;
+        }
+
+        copy_attributes(&src_path, &dst_path, &metadata, true)?;
+    }
+
+    Ok(())
+}
+
+fn copy_file(src: &Path, dst: &Path) -> io::Result<()> {
+    let mut src = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(src)?;
+    let mut dst: File = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(dst)?;
+    io::copy(&mut src, &mut dst)?;
+    Ok(())
+}
+
+// The owner is set before the mode, because chown clears the setuid and setgid
+// bits. The timestamps come last, as every other change updates them.
+fn copy_attributes(
+    src: &Path,
+    dst: &Path,
+    metadata: &fs::Metadata,
+    copy_mode: bool,
+) -> io::Result<()> {
+    lchown(dst, Some(metadata.uid()), Some(metadata.gid()))?;
+    copy_xattrs(src, dst)?;
+
+    let is_symlink = metadata.file_type().is_symlink();
+    if copy_mode && !is_symlink {
+        fs::set_permissions(dst, fs::Permissions::from_mode(metadata.mode() & 0o7777))?;
+    }
+
+    utimensat(
+        None,
+        dst,
+        &TimeSpec::new(metadata.atime(), metadata.atime_nsec()),
+        &TimeSpec::new(metadata.mtime(), metadata.mtime_nsec()),
+        UtimensatFlags::NoFollowSymlink,
+    )?;
+
+    Ok(())
+}
+
+fn copy_xattrs(src: &Path, dst: &Path) -> io::Result<()> {
+    let src_c = path_to_cstring(src)?;
+    let dst_c = path_to_cstring(dst)?;
+
+    let names = read_xattr_buffer(|buf, size| unsafe {
+        // Safety: the buffer holds size bytes
+        libc::llistxattr(src_c.as_ptr(), buf as *mut libc::c_char, size)
+    });
+    let names = match names {
+        Ok(names) => names,
+        // the file system of the image doesn't support extended attributes
+        Err(err) if err.raw_os_error() == Some(libc::ENOTSUP) => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    for name in names.split(|b| *b == 0).filter(|name| !name.is_empty()) {
+        let name_c = CString::new(name)?;
+        let value = read_xattr_buffer(|buf, size| unsafe {
+            // Safety: the buffer holds size bytes
+            libc::lgetxattr(src_c.as_ptr(), name_c.as_ptr(), buf, size)
+        });
+        let value = match value {
+            Ok(value) => value,
+            // the attribute was removed in between
+            Err(err) if err.raw_os_error() == Some(libc::ENODATA) => continue,
+            Err(err) => return Err(err),
+        };
+
+        // Safety: name and value are valid for the duration of the call
+        let ret = unsafe {
+            libc::lsetxattr(
+                dst_c.as_ptr(),
+                name_c.as_ptr(),
+                value.as_ptr() as *const libc::c_void,
+                value.len(),
+                0,
+            )
+        };
+        if ret != 0 {
+            let err = io::Error::last_os_error();
+            // tmpfs only supports some namespaces, e.g. no user.* before Linux 6.6
+            if matches!(err.raw_os_error(), Some(libc::ENOTSUP | libc::EPERM)) {
+                tracing::warn!(
+                    "failed to copy extended attribute {} to {}: {}",
+                    OsStr::from_bytes(name).to_string_lossy(),
+                    dst.display(),
+                    err
+                );
+                continue;
+            }
+            return Err(err);
+        }
+    }
+
+    Ok(())
+}
+
+// Calls an xattr function first for the size and then for the data, retrying if
+// the size changed in between
+fn read_xattr_buffer<F>(f: F) -> io::Result<Vec<u8>>
+where
+    F: Fn(*mut libc::c_void, usize) -> libc::ssize_t,
+{
+    loop {
+        let size = f(std::ptr::null_mut(), 0);
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut buf = vec![0u8; size as usize];
+        let len = f(buf.as_mut_ptr() as *mut libc::c_void, buf.len());
+        if len < 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() == Some(libc::ERANGE) {
+                continue;
+            }
+            return Err(err);
+        }
+        buf.truncate(len as usize);
+        return Ok(buf);
+    }
+}
+
+fn path_to_cstring(path: &Path) -> io::Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(io::Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+    use std::os::unix::net::UnixListener;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_copy_up() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let src = tmp.path().join("src");
+        let dst = tmp.path().join("dst");
+        fs::create_dir_all(src.join("cache/sub"))?;
+        fs::create_dir(&dst)?;
+        fs::set_permissions(&src, fs::Permissions::from_mode(0o1777))?;
+        fs::write(src.join("cache/sub/file"), "content")?;
+        fs::set_permissions(
+            src.join("cache/sub/file"),
+            fs::Permissions::from_mode(0o640),
+        )?;
+        fs::hard_link(src.join("cache/sub/file"), src.join("cache/link"))?;
+        symlink("/etc/passwd", src.join("cache/passwd"))?;
+        symlink("sub", src.join("cache/dir"))?;
+        UnixListener::bind(src.join("socket"))?;
+
+        copy_up(&src, &dst, false)?;
+
+        assert_eq!(fs::read_to_string(dst.join("cache/sub/file"))?, "content");
+        let file = fs::metadata(dst.join("cache/sub/file"))?;
+        assert_eq!(file.mode() & 0o7777, 0o640);
+        assert_eq!(
+            file.mtime(),
+            fs::metadata(src.join("cache/sub/file"))?.mtime()
+        );
+        assert_eq!(file.ino(), fs::metadata(dst.join("cache/link"))?.ino());
+        // symlinks are copied, not followed
+        assert_eq!(
+            fs::read_link(dst.join("cache/passwd"))?,
+            Path::new("/etc/passwd")
+        );
+        assert_eq!(fs::read_link(dst.join("cache/dir"))?, Path::new("sub"));
+        assert!(
+            fs::symlink_metadata(dst.join("socket"))?
+                .file_type()
+                .is_socket()
+        );
+        assert_eq!(fs::metadata(&dst)?.mode() & 0o7777, 0o1777);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_up_keep_mode() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let src = tmp.path().join("src");
+        let dst = tmp.path().join("dst");
+        fs::create_dir(&src)?;
+        fs::create_dir(&dst)?;
+        fs::set_permissions(&src, fs::Permissions::from_mode(0o700))?;
+        fs::set_permissions(&dst, fs::Permissions::from_mode(0o755))?;
+
+        copy_up(&src, &dst, true)?;
+        assert_eq!(fs::metadata(&dst)?.mode() & 0o7777, 0o755);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_up_requires_directory() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let src = tmp.path().join("src");
+        symlink(tmp.path(), &src)?;
+
+        assert!(copy_up(&src, tmp.path(), false).is_err());
+
+        Ok(())
+    }
+}
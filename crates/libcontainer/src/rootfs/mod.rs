@@ -8,9 +8,14 @@ pub use rootfs::RootFS;
 pub mod device;
 pub use device::Device;
 
+pub(super) mod copyup;
+
 pub(super) mod mount;
 pub use mount::Mount;
 
+pub mod overlay;
+pub use overlay::OverlayRootfs;
+
 pub(super) mod symlink;
 
 pub mod utils;
@@ -29,6 +34,8 @@ pub enum RootfsError {
     Mount(#[from] mount::MountError),
     #[error(transparent)]
     Device(#[from] device::DeviceError),
+    #[error(transparent)]
+    Overlay(#[from] overlay::OverlayError),
 }
 
 type Result<T> = std::result::Result<T, RootfsError>;
@@ -1,6 +1,6 @@
 use std::fs::{Permissions, canonicalize};
 use std::io::{BufRead, BufReader, ErrorKind};
-use std::os::fd::{AsFd, OwnedFd};
+use std::os::fd::{AsFd, AsRawFd, OwnedFd};
 use std::os::unix::fs::{MetadataExt, PermissionsExt};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
@@ -24,6 +24,7 @@ use procfs::process::Process;
 use procfs::process::{MountInfo, MountOptFields};
 use procfs::{FromRead, ProcessCGroups};
 
+use super::copyup::copy_up;
 #[cfg(feature = "v1")]
 use super::symlink::Symlink;
 use super::symlink::SymlinkError;
@@ -107,6 +108,9 @@ pub struct MountOptions<'a> {
     pub label: Option<&'a str>,
     #[allow(dead_code)]
     pub cgroup_ns: bool,
+    /// The cgroup of the container is delegated to it, so the cgroup v2
+    /// filesystem is mounted writable
+    pub cgroup_delegate: bool,
 }
 
 pub struct Mount {
@@ -391,6 +395,7 @@ impl Mount {
             flags: MsFlags::MS_NOEXEC | MsFlags::MS_NOSUID | MsFlags::MS_NODEV,
             data: vec![data.into_owned()],
             rec_attr: None,
+            tmpcopyup: false,
         };
 
         self.mount_into_container(
@@ -477,6 +482,14 @@ impl Mount {
     ) -> Result<()> {
         tracing::debug!("Mounting cgroup v2 filesystem");
 
+        let mut mount_option_config = mount_option_config.clone();
+        // only the cgroup namespace limits the writable mount to the
+        // container's own subtree
+        if options.cgroup_delegate && options.cgroup_ns {
+            mount_option_config.flags.remove(MsFlags::MS_RDONLY);
+        }
+        let mount_option_config = &mount_option_config;
+
         let cgroup_mount = SpecMountBuilder::default()
             .typ("cgroup2")
             .source("cgroup")
@@ -745,6 +758,22 @@ impl Mount {
                 let mount_fd_owned = self.syscall.fsmount(fsfd, 0, None)?;
                 let mount_fd = mount_fd_owned.as_fd();
 
+                // The contents are copied while the new tmpfs is detached, before any
+                // read-only flag applies. The destination was resolved inside the rootfs
+                // and copy_up doesn't follow the symlinks below it.
+                if mount_option_config.tmpcopyup && typ == Some("tmpfs") {
+                    let keep_mode = data_options.iter().any(|opt| opt.starts_with("mode="));
+                    copy_up(
+                        Path::new(&format!("/proc/self/fd/{}/.", dest_fd.as_raw_fd())),
+                        Path::new(&format!("/proc/self/fd/{}/.", mount_fd.as_raw_fd())),
+                        keep_mode,
+                    )
+                    .map_err(|err| {
+                        tracing::error!("failed to copy up {:?}: {}", container_dest, err);
+                        err
+                    })?;
+                }
+
                 // mount_setattr
                 let attr_set_from_flags = self.mount_flag_to_attr(&mount_option_config.flags);
                 let mut mount_attr = linux::MountAttr {
@@ -1337,6 +1366,7 @@ mod tests {
             root: tmp.path(),
             label: None,
             cgroup_ns: true,
+            cgroup_delegate: false,
         };
 
         let subsystem_name = "cpu";
@@ -1393,6 +1423,7 @@ mod tests {
             root: tmp.path(),
             label: None,
             cgroup_ns: false,
+            cgroup_delegate: false,
         };
 
         let subsystem_name = "cpu";
@@ -1455,6 +1486,7 @@ mod tests {
             root: tmp.path(),
             label: None,
             cgroup_ns: true,
+            cgroup_delegate: false,
         };
 
         let mounter = Mount::new();
@@ -1529,6 +1561,7 @@ mod tests {
             root: tmp.path(),
             label: None,
             cgroup_ns: true,
+            cgroup_delegate: false,
         };
 
         let mounter = Mount::new();
@@ -1539,6 +1572,7 @@ mod tests {
             flags,
             data: vec![],
             rec_attr: None,
+            tmpcopyup: false,
         };
         mounter
             .mount_cgroup_v2(&spec_cgroup_mount, &mount_opts, &mount_option_config)
@@ -1678,6 +1712,7 @@ mod tests {
             root: rootfs,
             label: None,
             cgroup_ns: true,
+            cgroup_delegate: false,
         };
 
         let m = Mount::new();
@@ -1713,6 +1748,7 @@ mod tests {
             root: rootfs,
             label: None,
             cgroup_ns: true,
+            cgroup_delegate: false,
         };
 
         let m = Mount::new();
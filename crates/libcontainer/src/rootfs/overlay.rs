@@ -0,0 +1,416 @@
+//! Assembles the root file system of a container from read-only layers with
+//! overlayfs, so that containers can share their layers without a snapshotter.
+//!
+//! The overlay is mounted over `root.path` of the spec inside the mount namespace
+//! of the container, so it disappears with the container. Changes go to an upper
+//! directory or, for ephemeral containers, to a tmpfs mounted below the overlay.
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::os::fd::AsFd;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt, PermissionsExt, chown};
+use std::path::{Path, PathBuf};
+
+use nix::errno::Errno;
+use serde::{Deserialize, Serialize};
+
+use crate::syscall::{Syscall, SyscallError, linux};
+
+/// Colon separated lower directories, the topmost first like `lowerdir` of overlayfs
+pub const LOWER_DIRS_ANNOTATION: &str = "org.youki.rootfs.overlay.lowerdirs";
+/// Directory which keeps the changes. A tmpfs is used without it.
+pub const UPPER_DIR_ANNOTATION: &str = "org.youki.rootfs.overlay.upperdir";
+/// Work directory of overlayfs on the file system of the upper directory
+pub const WORK_DIR_ANNOTATION: &str = "org.youki.rootfs.overlay.workdir";
+
+// directories in the tmpfs below an ephemeral overlay
+const TMPFS_UPPER_DIR: &str = "upper";
+const TMPFS_WORK_DIR: &str = "work";
+
+#[derive(Debug, thiserror::Error)]
+pub enum OverlayError {
+    #[error("overlay rootfs requires at least one lower directory")]
+    NoLowerDirs,
+    #[error("overlay rootfs requires both an upper and a work directory")]
+    IncompleteUpper,
+    #[error("overlay rootfs requires a new mount namespace")]
+    NoMountNamespace,
+    #[error("{0} is not a directory")]
+    NotADirectory(PathBuf),
+    #[error("failed to mount {fstype} over the rootfs")]
+    Mount {
+        fstype: &'static str,
+        source: SyscallError,
+    },
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+type Result<T> = std::result::Result<T, OverlayError>;
+
+/// The directory which keeps the changes to the rootfs
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlayUpper {
+    pub upper_dir: PathBuf,
+    pub work_dir: PathBuf,
+}
+
+/// A rootfs made of read-only lower directories, the topmost first, and an upper
+/// directory, or a tmpfs if there is none.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct OverlayRootfs {
+    pub lower_dirs: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upper: Option<OverlayUpper>,
+}
+
+impl OverlayRootfs {
+    pub fn new<I, P>(lower_dirs: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        Self {
+            lower_dirs: lower_dirs.into_iter().map(Into::into).collect(),
+            upper: None,
+        }
+    }
+
+    /// Keeps the changes in `upper_dir`. The `work_dir` has to be on the same file
+    /// system.
+    pub fn with_upper<P: Into<PathBuf>>(mut self, upper_dir: P, work_dir: P) -> Self {
+        self.upper = Some(OverlayUpper {
+            upper_dir: upper_dir.into(),
+            work_dir: work_dir.into(),
+        });
+        self
+    }
+
+    /// Reads the configuration from the annotations of the spec. Relative paths are
+    /// relative to the bundle. Returns None without lower directories.
+    pub fn from_annotations(
+        annotations: &HashMap<String, String>,
+        bundle: &Path,
+    ) -> Result<Option<Self>> {
+        let Some(lower_dirs) = annotations.get(LOWER_DIRS_ANNOTATION) else {
+            return Ok(None);
+        };
+
+        let lower_dirs = lower_dirs
+            .split(':')
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| bundle.join(dir));
+        let overlay = Self::new(lower_dirs);
+        let upper_dir = annotations.get(UPPER_DIR_ANNOTATION);
+        let work_dir = annotations.get(WORK_DIR_ANNOTATION);
+        match (upper_dir, work_dir) {
+            (Some(upper_dir), Some(work_dir)) => Ok(Some(
+                overlay.with_upper(bundle.join(upper_dir), bundle.join(work_dir)),
+            )),
+            (None, None) => Ok(Some(overlay)),
+            _ => Err(OverlayError::IncompleteUpper),
+        }
+    }
+
+    /// Checks that the lower directories exist and creates the upper and work
+    /// directories if needed
+    pub fn prepare(&self) -> Result<()> {
+        if self.lower_dirs.is_empty() {
+            return Err(OverlayError::NoLowerDirs);
+        }
+        for dir in &self.lower_dirs {
+            if !dir.is_dir() {
+                return Err(OverlayError::NotADirectory(dir.clone()));
+            }
+        }
+        if let Some(upper) = &self.upper {
+            fs::create_dir_all(&upper.upper_dir)?;
+            fs::create_dir_all(&upper.work_dir)?;
+        }
+
+        Ok(())
+    }
+
+    /// Mounts the overlay over `rootfs`, which has to be in a private mount
+    /// namespace
+    pub fn mount(&self, syscall: &dyn Syscall, rootfs: &Path) -> Result<()> {
+        tracing::debug!(?rootfs, lower_dirs = ?self.lower_dirs, "mount overlay rootfs");
+        let (upper_dir, work_dir) = match &self.upper {
+            Some(upper) => (upper.upper_dir.clone(), upper.work_dir.clone()),
+            None => self.mount_tmpfs(syscall, rootfs)?,
+        };
+
+        let mount_overlay = || -> std::result::Result<(), SyscallError> {
+            let fsfd = syscall.fsopen(Some("overlay"), 0)?;
+            set_string(syscall, &fsfd, "source", "overlay")?;
+            self.set_lower_dirs(syscall, &fsfd)?;
+            set_string(syscall, &fsfd, "upperdir", &path_str(&upper_dir)?)?;
+            set_string(syscall, &fsfd, "workdir", &path_str(&work_dir)?)?;
+            syscall.fsconfig(
+                fsfd.as_fd(),
+                linux::FSCONFIG_CMD_CREATE as u32,
+                None,
+                None,
+                0,
+            )?;
+            move_onto(syscall, &syscall.fsmount(fsfd.as_fd(), 0, None)?, rootfs)
+        };
+        mount_overlay().map_err(|source| OverlayError::Mount {
+            fstype: "overlay",
+            source,
+        })
+    }
+
+    /// Removes what overlayfs left in the work directory, the changes are kept
+    pub fn cleanup(&self) -> Result<()> {
+        let Some(upper) = &self.upper else {
+            return Ok(());
+        };
+        let entries = match fs::read_dir(&upper.work_dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            // overlayfs creates its directories without any permissions
+            if path.is_dir() {
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o700))?;
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Mounts a tmpfs over the rootfs for the upper and work directories, which
+    // is hidden by the overlay afterwards
+    fn mount_tmpfs(&self, syscall: &dyn Syscall, rootfs: &Path) -> Result<(PathBuf, PathBuf)> {
+        let mount_tmpfs = || -> std::result::Result<(), SyscallError> {
+            let fsfd = syscall.fsopen(Some("tmpfs"), 0)?;
+            set_string(syscall, &fsfd, "source", "tmpfs")?;
+            syscall.fsconfig(
+                fsfd.as_fd(),
+                linux::FSCONFIG_CMD_CREATE as u32,
+                None,
+                None,
+                0,
+            )?;
+            move_onto(syscall, &syscall.fsmount(fsfd.as_fd(), 0, None)?, rootfs)
+        };
+        mount_tmpfs().map_err(|source| OverlayError::Mount {
+            fstype: "tmpfs",
+            source,
+        })?;
+
+        // the root of the overlay takes its attributes from the upper directory
+        let upper_dir = rootfs.join(TMPFS_UPPER_DIR);
+        let work_dir = rootfs.join(TMPFS_WORK_DIR);
+        let top = fs::metadata(&self.lower_dirs[0])?;
+        fs::create_dir(&upper_dir)?;
+        fs::create_dir(&work_dir)?;
+        chown(&upper_dir, Some(top.uid()), Some(top.gid()))?;
+        fs::set_permissions(&upper_dir, fs::Permissions::from_mode(top.mode() & 0o7777))?;
+
+        Ok((upper_dir, work_dir))
+    }
+
+    // The values of fsconfig are limited to 256 bytes, so the layers are added one
+    // by one where the kernel supports it (Linux 6.8)
+    fn set_lower_dirs(
+        &self,
+        syscall: &dyn Syscall,
+        fsfd: &std::os::fd::OwnedFd,
+    ) -> std::result::Result<(), SyscallError> {
+        let mut lower_dirs = self.lower_dirs.iter();
+        if let Some(first) = lower_dirs.next() {
+            match set_string(syscall, fsfd, "lowerdir+", &path_str(first)?) {
+                Ok(()) => {
+                    for dir in lower_dirs {
+                        set_string(syscall, fsfd, "lowerdir+", &path_str(dir)?)?;
+                    }
+                    return Ok(());
+                }
+                Err(SyscallError::Nix(Errno::EINVAL)) => {
+                    tracing::debug!("lowerdir+ is not supported, falling back to lowerdir");
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        set_string(syscall, fsfd, "lowerdir", &self.lowerdir_option()?)
+    }
+
+    fn lowerdir_option(&self) -> std::result::Result<String, SyscallError> {
+        let dirs = self
+            .lower_dirs
+            .iter()
+            .map(|dir| Ok(escape_lower_dir(&path_str(dir)?)))
+            .collect::<std::result::Result<Vec<_>, SyscallError>>()?;
+        Ok(dirs.join(":"))
+    }
+}
+
+fn set_string(
+    syscall: &dyn Syscall,
+    fsfd: &std::os::fd::OwnedFd,
+    key: &str,
+    value: &str,
+) -> std::result::Result<(), SyscallError> {
+    syscall.fsconfig(
+        fsfd.as_fd(),
+        linux::FSCONFIG_SET_STRING as u32,
+        Some(key),
+        Some(value),
+        0,
+    )
+}
+
+fn move_onto(
+    syscall: &dyn Syscall,
+    mount_fd: &std::os::fd::OwnedFd,
+    target: &Path,
+) -> std::result::Result<(), SyscallError> {
+    let target = OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_PATH | libc::O_DIRECTORY | libc::O_CLOEXEC)
+        .open(target)?;
+    syscall.move_mount(
+        mount_fd.as_fd(),
+        None,
+        target.as_fd(),
+        None,
+        linux::MOVE_MOUNT_T_EMPTY_PATH | linux::MOVE_MOUNT_F_EMPTY_PATH,
+    )
+}
+
+fn path_str(path: &Path) -> std::result::Result<String, SyscallError> {
+    path.to_str()
+        .map(str::to_owned)
+        .ok_or(SyscallError::Nix(Errno::EINVAL))
+}
+
+// the separators of lowerdir have to be escaped in the paths
+fn escape_lower_dir(dir: &str) -> String {
+    let mut escaped = String::with_capacity(dir.len());
+    for c in dir.chars() {
+        if matches!(c, ':' | ',' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::*;
+
+    fn annotations(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_annotations() -> Result<()> {
+        let bundle = Path::new("/bundle");
+        assert_eq!(
+            OverlayRootfs::from_annotations(&annotations(&[]), bundle)?,
+            None
+        );
+
+        let overlay = OverlayRootfs::from_annotations(
+            &annotations(&[(LOWER_DIRS_ANNOTATION, "layers/2:/layers/1")]),
+            bundle,
+        )?;
+        assert_eq!(
+            overlay,
+            Some(OverlayRootfs::new(["/bundle/layers/2", "/layers/1"]))
+        );
+
+        let overlay = OverlayRootfs::from_annotations(
+            &annotations(&[
+                (LOWER_DIRS_ANNOTATION, "/layers/1"),
+                (UPPER_DIR_ANNOTATION, "upper"),
+                (WORK_DIR_ANNOTATION, "work"),
+            ]),
+            bundle,
+        )?;
+        assert_eq!(
+            overlay,
+            Some(OverlayRootfs::new(["/layers/1"]).with_upper("/bundle/upper", "/bundle/work"))
+        );
+
+        let err = OverlayRootfs::from_annotations(
+            &annotations(&[
+                (LOWER_DIRS_ANNOTATION, "/layers/1"),
+                (UPPER_DIR_ANNOTATION, "upper"),
+            ]),
+            bundle,
+        )
+        .unwrap_err();
+        assert!(matches!(err, OverlayError::IncompleteUpper));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let lower = tmp.path().join("lower");
+        fs::create_dir(&lower)?;
+
+        assert!(matches!(
+            OverlayRootfs::new(Vec::<PathBuf>::new()).prepare(),
+            Err(OverlayError::NoLowerDirs)
+        ));
+        assert!(matches!(
+            OverlayRootfs::new([tmp.path().join("missing")]).prepare(),
+            Err(OverlayError::NotADirectory(_))
+        ));
+
+        let overlay = OverlayRootfs::new([&lower])
+            .with_upper(tmp.path().join("upper"), tmp.path().join("work"));
+        overlay.prepare()?;
+        assert!(tmp.path().join("upper").is_dir());
+        assert!(tmp.path().join("work").is_dir());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lowerdir_option() -> Result<()> {
+        let overlay = OverlayRootfs::new(["/layers/2", "/layers/a:b,c"]);
+        assert_eq!(overlay.lowerdir_option()?, r"/layers/2:/layers/a\:b\,c");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cleanup() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let upper = tmp.path().join("upper");
+        let work = tmp.path().join("work");
+        fs::create_dir_all(upper.join("etc"))?;
+        fs::create_dir_all(work.join("work"))?;
+        fs::set_permissions(work.join("work"), fs::Permissions::from_mode(0o000))?;
+
+        let overlay = OverlayRootfs::new(["/layers/1"]).with_upper(&upper, &work);
+        overlay.cleanup()?;
+        assert!(work.is_dir());
+        assert_eq!(fs::read_dir(&work)?.count(), 0);
+        assert!(upper.join("etc").is_dir());
+
+        // nothing to clean up for a tmpfs
+        OverlayRootfs::new(["/layers/1"]).cleanup()?;
+
+        Ok(())
+    }
+}
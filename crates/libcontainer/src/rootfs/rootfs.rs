@@ -6,16 +6,19 @@ use oci_spec::runtime::{Linux, Spec};
 
 use super::device::Device;
 use super::mount::{Mount, MountOptions};
+use super::overlay::OverlayRootfs;
 use super::symlink::Symlink;
 use super::utils::default_devices;
 use super::{Result, RootfsError};
 use crate::error::MissingSpecError;
 use crate::syscall::Syscall;
 use crate::syscall::syscall::create_syscall;
+use crate::utils;
 
 /// Holds information about rootfs
 pub struct RootFS {
     syscall: Box<dyn Syscall>,
+    overlay: Option<OverlayRootfs>,
 }
 
 impl Default for RootFS {
@@ -28,9 +31,17 @@ impl RootFS {
     pub fn new() -> RootFS {
         RootFS {
             syscall: create_syscall(),
+            overlay: None,
         }
     }
 
+    /// Assembles the rootfs from the layers of the overlay before anything is
+    /// mounted into it
+    pub fn with_overlay(mut self, overlay: Option<OverlayRootfs>) -> Self {
+        self.overlay = overlay;
+        self
+    }
+
     pub fn mount_to_rootfs(
         &self,
         linux: &Linux,
@@ -64,6 +75,10 @@ impl RootFS {
 
         mounter.make_parent_mount_private(rootfs)?;
 
+        if let Some(overlay) = &self.overlay {
+            overlay.mount(self.syscall.as_ref(), rootfs)?;
+        }
+
         tracing::debug!("mount root fs {:?}", rootfs);
         self.syscall
             .mount(
@@ -82,6 +97,7 @@ impl RootFS {
             root: rootfs,
             label: linux.mount_label().as_deref(),
             cgroup_ns,
+            cgroup_delegate: utils::is_cgroup_delegated(spec.annotations()),
         };
 
         if let Some(mounts) = spec.mounts() {
@@ -18,6 +18,9 @@ pub struct MountOptionConfig {
 
     /// RecAttr represents mount properties to be applied recursively.
     pub rec_attr: Option<linux::MountAttr>,
+
+    /// Copy the contents of the destination into the new tmpfs (`tmpcopyup`).
+    pub tmpcopyup: bool,
 }
 
 pub fn default_devices() -> Vec<LinuxDevice> {
@@ -86,9 +89,16 @@ pub fn parse_mount(m: &Mount) -> std::result::Result<MountOptionConfig, MountErr
     let mut flags = MsFlags::empty();
     let mut data = Vec::new();
     let mut mount_attr: Option<linux::MountAttr> = None;
+    let mut tmpcopyup = false;
 
     if let Some(options) = &m.options() {
         for option in options {
+            // an extension of the runtimes rather than a mount option
+            if option == "tmpcopyup" {
+                tmpcopyup = true;
+                continue;
+            }
+
             if let Ok(mount_attr_option) = linux::MountRecursive::from_str(option.as_str()) {
                 // Some options aren't corresponding to the mount flags.
                 // These options need `AT_RECURSIVE` options.
@@ -189,6 +199,7 @@ pub fn parse_mount(m: &Mount) -> std::result::Result<MountOptionConfig, MountErr
         flags,
         data: data.into_iter().map(|s| s.to_string()).collect(),
         rec_attr: mount_attr,
+        tmpcopyup,
     })
 }
 
@@ -226,6 +237,7 @@ mod tests {
                 flags: MsFlags::empty(),
                 data: vec![],
                 rec_attr: None,
+                tmpcopyup: false,
             },
             mount_option_config
         );
@@ -248,6 +260,7 @@ mod tests {
                 flags: MsFlags::MS_NOSUID | MsFlags::MS_STRICTATIME,
                 data: vec!["mode=755".to_string(), "size=65536k".to_string()],
                 rec_attr: None,
+                tmpcopyup: false,
             },
             mount_option_config
         );
@@ -277,7 +290,8 @@ mod tests {
                     "mode=0620".to_string(),
                     "gid=5".to_string()
                 ],
-                rec_attr: None
+                rec_attr: None,
+                tmpcopyup: false,
             },
             mount_option_config
         );
@@ -300,7 +314,8 @@ mod tests {
             MountOptionConfig {
                 flags: MsFlags::MS_NOSUID | MsFlags::MS_NOEXEC | MsFlags::MS_NODEV,
                 data: vec!["mode=1777".to_string(), "size=65536k".to_string()],
-                rec_attr: None
+                rec_attr: None,
+                tmpcopyup: false,
             },
             mount_option_config
         );
@@ -322,7 +337,8 @@ mod tests {
             MountOptionConfig {
                 flags: MsFlags::MS_NOSUID | MsFlags::MS_NOEXEC | MsFlags::MS_NODEV,
                 data: vec![],
-                rec_attr: None
+                rec_attr: None,
+                tmpcopyup: false,
             },
             mount_option_config
         );
@@ -348,6 +364,7 @@ mod tests {
                     | MsFlags::MS_RDONLY,
                 data: vec![],
                 rec_attr: None,
+                tmpcopyup: false,
             },
             mount_option_config
         );
@@ -374,7 +391,8 @@ mod tests {
                     | MsFlags::MS_RDONLY
                     | MsFlags::MS_RELATIME,
                 data: vec![],
-                rec_attr: None
+                rec_attr: None,
+                tmpcopyup: false,
             },
             mount_option_config,
         );
@@ -432,6 +450,7 @@ mod tests {
                     | MsFlags::MS_UNBINDABLE,
                 data: vec![],
                 rec_attr: None,
+                tmpcopyup: false,
             },
             mount_option_config
         );
@@ -465,7 +484,30 @@ mod tests {
             MountOptionConfig {
                 flags: MsFlags::empty(),
                 data: vec![],
-                rec_attr: Some(MountAttr::all())
+                rec_attr: Some(MountAttr::all()),
+                tmpcopyup: false,
+            },
+            mount_option_config
+        );
+
+        let mount_option_config = parse_mount(
+            &MountBuilder::default()
+                .destination(PathBuf::from("/run"))
+                .typ("tmpfs")
+                .source(PathBuf::from("tmpfs"))
+                .options(vec![
+                    "nosuid".to_string(),
+                    "tmpcopyup".to_string(),
+                    "size=65536k".to_string(),
+                ])
+                .build()?,
+        )?;
+        assert_eq!(
+            MountOptionConfig {
+                flags: MsFlags::MS_NOSUID,
+                data: vec!["size=65536k".to_string()],
+                rec_attr: None,
+                tmpcopyup: true,
             },
             mount_option_config
         );
@@ -0,0 +1,544 @@
+//! Relays the sd_notify(3) messages of a container to the `NOTIFY_SOCKET` of the
+//! host, so that a systemd unit of `Type=notify` can wrap youki.
+//!
+//! In the `container` mode, a proxy socket is bind mounted into the container at
+//! [`CONTAINER_NOTIFY_SOCKET`]. The proxy forwards the messages systemd knows to
+//! the host socket, where `MAINPID` is translated into a pid of the host.
+use std::fmt::Display;
+use std::fs;
+use std::io::{IoSlice, IoSliceMut};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use nix::sys::signal;
+use nix::sys::socket::{self, ControlMessage, ControlMessageOwned, MsgFlags, UnixAddr};
+use nix::sys::wait::waitpid;
+use nix::unistd::{self, ForkResult, Pid};
+use serde::{Deserialize, Serialize};
+
+use crate::utils;
+
+/// Directory in the container directory which holds the proxy socket
+pub const NOTIFY_DIR: &str = "notify";
+/// Path of the proxy socket in the container, which is passed as `NOTIFY_SOCKET`
+pub const CONTAINER_NOTIFY_SOCKET: &str = "/run/notify/notify.sock";
+const SOCKET_NAME: &str = "notify.sock";
+// the size systemd reserves for a notification
+const MESSAGE_SIZE: usize = 4096;
+// how often the proxy checks whether the container is still running
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, thiserror::Error)]
+pub enum SdNotifyError {
+    #[error("invalid sd_notify mode {0}, expected container, conmon or ignore")]
+    InvalidMode(String),
+    #[error("failed to create notify proxy socket {path:?}")]
+    Bind {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+    #[error("failed to create socket")]
+    Socket(#[source] std::io::Error),
+    #[error("failed to receive from notify proxy socket")]
+    Receive(#[source] nix::Error),
+    #[error("failed to send to notify socket {path:?}")]
+    Send { source: nix::Error, path: PathBuf },
+    #[error("failed to fork notify relay")]
+    Fork(#[source] nix::Error),
+}
+
+type Result<T> = std::result::Result<T, SdNotifyError>;
+
+/// Decides who tells the host's service manager that the container is ready
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SdNotifyMode {
+    /// The container sends the notifications through a proxy socket
+    #[default]
+    Container,
+    /// youki sends READY=1 as soon as the container process was started, like
+    /// conmon does for podman
+    Conmon,
+    /// Nothing is sent and `NOTIFY_SOCKET` is not passed to the container
+    Ignore,
+}
+
+impl FromStr for SdNotifyMode {
+    type Err = SdNotifyError;
+
+    fn from_str(mode: &str) -> Result<Self> {
+        match mode {
+            "container" => Ok(Self::Container),
+            "conmon" => Ok(Self::Conmon),
+            "ignore" => Ok(Self::Ignore),
+            _ => Err(SdNotifyError::InvalidMode(mode.to_owned())),
+        }
+    }
+}
+
+impl Display for SdNotifyMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let print = match self {
+            Self::Container => "container",
+            Self::Conmon => "conmon",
+            Self::Ignore => "ignore",
+        };
+
+        write!(f, "{print}")
+    }
+}
+
+/// The sd_notify setup of a container, which is stored in its state
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SdNotify {
+    pub mode: SdNotifyMode,
+    /// `NOTIFY_SOCKET` of the host, abstract sockets start with @
+    pub socket: PathBuf,
+}
+
+/// Forwards the notifications of a container to the host
+#[derive(Debug)]
+pub struct NotifyProxy {
+    mode: SdNotifyMode,
+    host_socket: PathBuf,
+    // bound in the container mode only
+    listener: Option<UnixDatagram>,
+    sender: UnixDatagram,
+}
+
+impl NotifyProxy {
+    /// Creates the proxy for a container in `container_root`. In the container mode,
+    /// the proxy socket is bound, which has to happen before the container is
+    /// started, so that no notification is lost.
+    pub fn new(sd_notify: &SdNotify, container_root: &Path) -> Result<Self> {
+        let listener = match sd_notify.mode {
+            SdNotifyMode::Container => Some(Self::bind(&container_root.join(NOTIFY_DIR))?),
+            SdNotifyMode::Conmon | SdNotifyMode::Ignore => None,
+        };
+        let sender = UnixDatagram::unbound().map_err(SdNotifyError::Socket)?;
+
+        Ok(Self {
+            mode: sd_notify.mode,
+            host_socket: sd_notify.socket.clone(),
+            listener,
+            sender,
+        })
+    }
+
+    fn bind(notify_dir: &Path) -> Result<UnixDatagram> {
+        let socket_path = notify_dir.join(SOCKET_NAME);
+        let err = |source| SdNotifyError::Bind {
+            source,
+            path: socket_path.clone(),
+        };
+        // a previous start may have left the socket behind
+        match fs::remove_file(&socket_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(err(e)),
+            _ => {}
+        }
+        // The path of the container directory may exceed the 108 bytes of a socket
+        // address, so the socket is bound relative to an fd of the directory.
+        let dir = fs::File::open(notify_dir).map_err(err)?;
+        let listener =
+            UnixDatagram::bind(format!("/proc/self/fd/{}/{SOCKET_NAME}", dir.as_raw_fd()))
+                .map_err(err)?;
+        listener
+            .set_read_timeout(Some(POLL_INTERVAL))
+            .map_err(err)?;
+        // the process of the container may run as any user
+        fs::set_permissions(&socket_path, fs::Permissions::from_mode(0o777)).map_err(err)?;
+
+        Ok(listener)
+    }
+
+    /// Relays the notifications of the container with the init process
+    /// `init_pid`. With `until_ready`, it returns after READY=1 was relayed,
+    /// otherwise it runs as long as the container may send notifications. Either
+    /// way, it returns once the init process is gone.
+    pub fn relay(&self, init_pid: Pid, until_ready: bool) -> Result<()> {
+        let listener = match (&self.listener, self.mode) {
+            (Some(listener), _) => listener,
+            (None, SdNotifyMode::Conmon) => {
+                return self.send(&format!("READY=1\nMAINPID={init_pid}"), None);
+            }
+            (None, _) => return Ok(()),
+        };
+
+        loop {
+            let mut buffer = [0u8; MESSAGE_SIZE];
+            let Some((len, fds)) = Self::receive(listener, &mut buffer)? else {
+                if signal::kill(init_pid, None).is_err() {
+                    tracing::debug!(?init_pid, "container exited, stop relaying notifications");
+                    return Ok(());
+                }
+                continue;
+            };
+            let message = String::from_utf8_lossy(&buffer[..len]);
+            let (forward, ready) = translate(&message, init_pid);
+            // only the fd of a barrier is passed on, which the host closes once
+            // it processed the messages sent before
+            let barrier = forward
+                .iter()
+                .any(|line| line == "BARRIER=1")
+                .then(|| fds.first())
+                .flatten();
+            if !forward.is_empty() {
+                self.send(&forward.join("\n"), barrier.map(|fd| fd.as_raw_fd()))?;
+            }
+            if ready && until_ready {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Relays the notifications until READY=1, so that the caller returns once
+    /// the container is ready like runc does, and hands the proxy to a relay
+    /// process in the background afterwards. It keeps relaying e.g. WATCHDOG=1
+    /// and STATUS= until the init process is gone, even after the caller exited.
+    pub fn spawn_relay(&self, init_pid: Pid) -> Result<()> {
+        self.relay(init_pid, true)?;
+        let Some(listener) = &self.listener else {
+            return Ok(());
+        };
+        if signal::kill(init_pid, None).is_err() {
+            return Ok(());
+        }
+
+        // Safety: the child only forks once more and exits, and the grandchild
+        // relays the notifications before it exits
+        match unsafe { unistd::fork() }.map_err(SdNotifyError::Fork)? {
+            ForkResult::Parent { child } => {
+                let _ = waitpid(child, None);
+                Ok(())
+            }
+            ForkResult::Child => {
+                // the relay is reparented once the child exits, so that it
+                // doesn't show up as a process the caller has to wait for
+                if let Ok(ForkResult::Child) = unsafe { unistd::fork() } {
+                    let keep = [listener.as_raw_fd(), self.sender.as_raw_fd()];
+                    if let Err(err) = utils::detach_from_caller(&keep) {
+                        tracing::error!(?err, "failed to detach notify relay");
+                        std::process::exit(1);
+                    }
+                    if let Err(err) = self.relay(init_pid, false) {
+                        tracing::error!(?err, "notify relay failed");
+                        std::process::exit(1);
+                    }
+                }
+                std::process::exit(0);
+            }
+        }
+    }
+
+    // Returns None when nothing was received within the poll interval
+    fn receive(
+        listener: &UnixDatagram,
+        buffer: &mut [u8],
+    ) -> Result<Option<(usize, Vec<OwnedFd>)>> {
+        let mut cmsg_buffer = nix::cmsg_space!([RawFd; 16]);
+        let mut iov = [IoSliceMut::new(buffer)];
+        let message = loop {
+            match socket::recvmsg::<()>(
+                listener.as_raw_fd(),
+                &mut iov,
+                Some(&mut cmsg_buffer),
+                MsgFlags::MSG_CMSG_CLOEXEC,
+            ) {
+                Err(nix::Error::EINTR) => continue,
+                Err(nix::Error::EAGAIN) => return Ok(None),
+                result => break result.map_err(SdNotifyError::Receive)?,
+            }
+        };
+
+        let mut fds = Vec::new();
+        for cmsg in message.cmsgs().map_err(SdNotifyError::Receive)? {
+            if let ControlMessageOwned::ScmRights(received) = cmsg {
+                // Safety: the fds were just received and are owned by nobody else
+                fds.extend(
+                    received
+                        .into_iter()
+                        .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) }),
+                );
+            }
+        }
+
+        Ok(Some((message.bytes, fds)))
+    }
+
+    fn send(&self, message: &str, fd: Option<RawFd>) -> Result<()> {
+        tracing::debug!(?message, socket = ?self.host_socket, "relay notification");
+        let err = |source| SdNotifyError::Send {
+            source,
+            path: self.host_socket.clone(),
+        };
+        let address = host_address(&self.host_socket).map_err(err)?;
+        let fds = fd.map(|fd| [fd]);
+        let cmsgs: Vec<ControlMessage> = fds
+            .iter()
+            .map(|fds| ControlMessage::ScmRights(fds))
+            .collect();
+        socket::sendmsg(
+            self.sender.as_raw_fd(),
+            &[IoSlice::new(message.as_bytes())],
+            &cmsgs,
+            MsgFlags::empty(),
+            Some(&address),
+        )
+        .map_err(err)?;
+
+        Ok(())
+    }
+}
+
+fn host_address(socket: &Path) -> nix::Result<UnixAddr> {
+    let bytes = socket.as_os_str().as_encoded_bytes();
+    match bytes.strip_prefix(b"@") {
+        Some(name) => UnixAddr::new_abstract(name),
+        None => UnixAddr::new(socket),
+    }
+}
+
+// Returns the lines to forward to the host and whether READY=1 is among them.
+// Unknown variables are dropped, e.g. FDSTORE=1 would hand fds of the container
+// to the service manager of the host.
+fn translate(message: &str, init_pid: Pid) -> (Vec<String>, bool) {
+    let mut forward = Vec::new();
+    let mut ready = false;
+    let mut main_pid = false;
+    for line in message.lines() {
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        match key {
+            "READY"
+            | "STATUS"
+            | "WATCHDOG"
+            | "EXTEND_TIMEOUT_USEC"
+            | "BARRIER"
+            | "STOPPING"
+            | "RELOADING" => {
+                ready |= line == "READY=1";
+                forward.push(line.to_owned());
+            }
+            "MAINPID" => match value.parse().ok().and_then(|pid| host_pid(init_pid, pid)) {
+                Some(pid) => {
+                    main_pid = true;
+                    forward.push(format!("MAINPID={pid}"));
+                }
+                None => tracing::warn!(value, "failed to translate MAINPID of the container"),
+            },
+            _ => tracing::debug!(line, "drop notification"),
+        }
+    }
+    // the container init is the main process of the unit, unless told otherwise
+    if ready && !main_pid {
+        forward.push(format!("MAINPID={init_pid}"));
+    }
+
+    (forward, ready)
+}
+
+// Translates a pid in the pid namespace of the container into a pid of the host
+fn host_pid(init_pid: Pid, pid: i32) -> Option<i32> {
+    let pid_ns = |pid: i32| fs::read_link(format!("/proc/{pid}/ns/pid")).ok();
+    let container_ns = pid_ns(init_pid.as_raw())?;
+    if pid_ns(std::process::id() as i32) == Some(container_ns.clone()) {
+        return Some(pid);
+    }
+
+    procfs::process::all_processes()
+        .ok()?
+        .filter_map(|process| process.ok())
+        .find(|process| {
+            let in_container = || {
+                process
+                    .status()
+                    .ok()
+                    .and_then(|status| status.nspid)
+                    .and_then(|nspid| nspid.last().copied())
+                    == Some(pid)
+            };
+            in_container() && pid_ns(process.pid) == Some(container_ns.clone())
+        })
+        .map(|process| process.pid)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::Result;
+
+    use super::*;
+
+    #[test]
+    fn test_mode() -> Result<()> {
+        for mode in [
+            SdNotifyMode::Container,
+            SdNotifyMode::Conmon,
+            SdNotifyMode::Ignore,
+        ] {
+            assert_eq!(mode.to_string().parse::<SdNotifyMode>()?, mode);
+        }
+        assert!("systemd".parse::<SdNotifyMode>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate() {
+        let init_pid = Pid::this();
+        let pid = std::process::id();
+        let (forward, ready) = translate("STATUS=starting\nFDSTORE=1\nWATCHDOG=1", init_pid);
+        assert_eq!(forward, vec!["STATUS=starting", "WATCHDOG=1"]);
+        assert!(!ready);
+
+        let (forward, ready) = translate("READY=1", init_pid);
+        assert_eq!(
+            forward,
+            vec!["READY=1".to_owned(), format!("MAINPID={pid}")]
+        );
+        assert!(ready);
+
+        // the test shares the pid namespace with the "container"
+        let (forward, _) = translate(&format!("READY=1\nMAINPID={pid}"), init_pid);
+        assert_eq!(
+            forward,
+            vec!["READY=1".to_owned(), format!("MAINPID={pid}")]
+        );
+    }
+
+    #[test]
+    fn test_relay() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let host_path = tmp_dir.path().join("host.sock");
+        let host = UnixDatagram::bind(&host_path)?;
+        host.set_read_timeout(Some(Duration::from_secs(5)))?;
+        fs::create_dir(tmp_dir.path().join(NOTIFY_DIR))?;
+
+        let sd_notify = SdNotify {
+            mode: SdNotifyMode::Container,
+            socket: host_path,
+        };
+        let proxy = NotifyProxy::new(&sd_notify, tmp_dir.path())?;
+        let container_socket = tmp_dir.path().join(NOTIFY_DIR).join(SOCKET_NAME);
+        let container = UnixDatagram::unbound()?;
+        container.send_to(b"STATUS=loading\nX_CUSTOM=1", &container_socket)?;
+        container.send_to(b"READY=1", &container_socket)?;
+        proxy.relay(Pid::this(), true)?;
+
+        let mut buffer = [0u8; MESSAGE_SIZE];
+        let len = host.recv(&mut buffer)?;
+        assert_eq!(&buffer[..len], b"STATUS=loading");
+        let len = host.recv(&mut buffer)?;
+        assert_eq!(
+            String::from_utf8_lossy(&buffer[..len]),
+            format!("READY=1\nMAINPID={}", std::process::id())
+        );
+
+        // the conmon mode reports the container ready on its own
+        let sd_notify = SdNotify {
+            mode: SdNotifyMode::Conmon,
+            ..sd_notify
+        };
+        NotifyProxy::new(&sd_notify, tmp_dir.path())?.relay(Pid::from_raw(42), true)?;
+        let len = host.recv(&mut buffer)?;
+        assert_eq!(&buffer[..len], b"READY=1\nMAINPID=42");
+        Ok(())
+    }
+
+    #[test]
+    fn test_spawn_relay() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let host_path = tmp_dir.path().join("host.sock");
+        let host = UnixDatagram::bind(&host_path)?;
+        host.set_read_timeout(Some(Duration::from_secs(5)))?;
+        fs::create_dir(tmp_dir.path().join(NOTIFY_DIR))?;
+        let sd_notify = SdNotify {
+            mode: SdNotifyMode::Container,
+            socket: host_path,
+        };
+        let proxy = NotifyProxy::new(&sd_notify, tmp_dir.path())?;
+        let container_socket = tmp_dir.path().join(NOTIFY_DIR).join(SOCKET_NAME);
+        let container = UnixDatagram::unbound()?;
+        let mut init = std::process::Command::new("sleep").arg("10").spawn()?;
+        let init_pid = Pid::from_raw(init.id() as i32);
+
+        container.send_to(b"READY=1", &container_socket)?;
+        proxy.spawn_relay(init_pid)?;
+        drop(proxy);
+        // the notifications after READY=1 are relayed in the background
+        container.send_to(b"WATCHDOG=1", &container_socket)?;
+
+        let mut buffer = [0u8; MESSAGE_SIZE];
+        let len = host.recv(&mut buffer)?;
+        assert_eq!(
+            String::from_utf8_lossy(&buffer[..len]),
+            format!("READY=1\nMAINPID={init_pid}")
+        );
+        let len = host.recv(&mut buffer)?;
+        assert_eq!(&buffer[..len], b"WATCHDOG=1");
+
+        init.kill()?;
+        init.wait()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_relay_barrier() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let host_path = tmp_dir.path().join("host.sock");
+        let host = UnixDatagram::bind(&host_path)?;
+        fs::create_dir(tmp_dir.path().join(NOTIFY_DIR))?;
+        let sd_notify = SdNotify {
+            mode: SdNotifyMode::Container,
+            socket: host_path,
+        };
+        let proxy = NotifyProxy::new(&sd_notify, tmp_dir.path())?;
+
+        let (read, write) = nix::unistd::pipe()?;
+        let container = UnixDatagram::unbound()?;
+        let container_socket = UnixAddr::new(&tmp_dir.path().join(NOTIFY_DIR).join(SOCKET_NAME))?;
+        socket::sendmsg(
+            container.as_raw_fd(),
+            &[IoSlice::new(b"BARRIER=1")],
+            &[ControlMessage::ScmRights(&[write.as_raw_fd()])],
+            MsgFlags::empty(),
+            Some(&container_socket),
+        )?;
+        drop(write);
+        container.send_to(
+            b"READY=1",
+            tmp_dir.path().join(NOTIFY_DIR).join(SOCKET_NAME),
+        )?;
+        proxy.relay(Pid::this(), true)?;
+
+        // the barrier completes once the host closes the forwarded fd
+        let mut buffer = [0u8; MESSAGE_SIZE];
+        let mut cmsg_buffer = nix::cmsg_space!([RawFd; 1]);
+        let mut iov = [IoSliceMut::new(&mut buffer)];
+        let message = socket::recvmsg::<()>(
+            host.as_raw_fd(),
+            &mut iov,
+            Some(&mut cmsg_buffer),
+            MsgFlags::empty(),
+        )?;
+        let fds: Vec<RawFd> = message
+            .cmsgs()?
+            .flat_map(|cmsg| match cmsg {
+                ControlMessageOwned::ScmRights(fds) => fds,
+                _ => vec![],
+            })
+            .collect();
+        assert_eq!(message.bytes, "BARRIER=1".len());
+        assert_eq!(fds.len(), 1);
+        let mut pipe_buffer = [0u8; 1];
+        nix::unistd::close(fds[0])?;
+        assert_eq!(nix::unistd::read(read.as_raw_fd(), &mut pipe_buffer)?, 0);
+        Ok(())
+    }
+}
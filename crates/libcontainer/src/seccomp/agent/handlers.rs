@@ -0,0 +1,301 @@
+// Reference handlers for common uses of seccomp notify
+use std::ffi::CString;
+use std::os::fd::{AsRawFd, OwnedFd};
+
+use nix::errno::Errno;
+use nix::fcntl::{OFlag, open};
+use nix::sys::stat::Mode;
+use nix::sys::wait::{WaitStatus, waitpid};
+use nix::unistd::{ForkResult, fork};
+use oci_spec::runtime::LinuxDeviceType;
+
+use super::{Handler, Request, Response};
+
+// audit arch of the syscalls which use the native syscall numbers
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH_NATIVE: Option<u32> = Some(0xc000_003e);
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH_NATIVE: Option<u32> = Some(0xc000_00b7);
+#[cfg(target_arch = "riscv64")]
+const AUDIT_ARCH_NATIVE: Option<u32> = Some(0xc000_00f3);
+#[cfg(all(target_arch = "powerpc64", target_endian = "little"))]
+const AUDIT_ARCH_NATIVE: Option<u32> = Some(0xc000_0015);
+#[cfg(target_arch = "s390x")]
+const AUDIT_ARCH_NATIVE: Option<u32> = Some(0x8000_0016);
+#[cfg(not(any(
+    target_arch = "x86_64",
+    target_arch = "aarch64",
+    target_arch = "riscv64",
+    all(target_arch = "powerpc64", target_endian = "little"),
+    target_arch = "s390x"
+)))]
+const AUDIT_ARCH_NATIVE: Option<u32> = None;
+
+/// Logs every notification and leaves the decision to the next handler
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AuditHandler;
+
+impl Handler for AuditHandler {
+    fn handle(&self, request: &Request<'_>) -> Option<Response> {
+        tracing::info!(
+            container = request.state.state().id(),
+            pid = request.pid(),
+            arch = format!("{:#x}", request.arch()),
+            nr = request.nr(),
+            args = ?request.args(),
+            "seccomp notification"
+        );
+        None
+    }
+}
+
+/// Creates allowed device nodes for containers which may not create them on their
+/// own, e.g. rootless ones. Other device nodes are left to the next handler, while
+/// FIFOs, sockets and regular files are created by the kernel as usual.
+#[derive(Debug, Clone, Default)]
+pub struct MknodHandler {
+    allowed: Vec<(LinuxDeviceType, u64, u64)>,
+}
+
+impl MknodHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows the device, where char devices are of type C or U
+    pub fn allow(mut self, typ: LinuxDeviceType, major: u64, minor: u64) -> Self {
+        let typ = match typ {
+            LinuxDeviceType::U => LinuxDeviceType::C,
+            typ => typ,
+        };
+        self.allowed.push((typ, major, minor));
+        self
+    }
+
+    // Creates the node in a child which takes over the root, working directory and
+    // umask of the target, so that the path is resolved the way the target would.
+    fn create(
+        &self,
+        request: &Request<'_>,
+        dirfd: i32,
+        path: u64,
+        mode: libc::mode_t,
+        dev: libc::dev_t,
+    ) -> Result<(), Errno> {
+        let pid = request.pid();
+        let path = request.read_c_string(path).map_err(|err| {
+            tracing::warn!(?err, pid, "failed to read path of mknod");
+            Errno::EFAULT
+        })?;
+        let status = procfs::process::Process::new(pid as i32)
+            .and_then(|process| process.status())
+            .map_err(|_| Errno::ESRCH)?;
+        let umask = status.umask.unwrap_or(0o022) as libc::mode_t;
+
+        let open_dir = |path: String| -> Result<OwnedFd, Errno> {
+            let fd = open(
+                path.as_str(),
+                OFlag::O_PATH | OFlag::O_DIRECTORY | OFlag::O_CLOEXEC,
+                Mode::empty(),
+            )?;
+            // Safety: the fd was just opened
+            Ok(unsafe { std::os::fd::FromRawFd::from_raw_fd(fd) })
+        };
+        let root = open_dir(format!("/proc/{pid}/root"))?;
+        let cwd = open_dir(format!("/proc/{pid}/cwd"))?;
+        let dir = match dirfd {
+            libc::AT_FDCWD => None,
+            fd => Some(open_dir(format!("/proc/{pid}/fd/{fd}"))?),
+        };
+        // the fds must belong to the target and not a process which reused its pid
+        if !request.is_valid().unwrap_or(false) {
+            return Err(Errno::ESRCH);
+        }
+
+        let dot = CString::new(".").expect("no nul byte");
+        // Safety: the child only makes syscalls before it exits
+        match unsafe { fork() }? {
+            ForkResult::Child => {
+                let result = (|| -> Result<(), Errno> {
+                    // Safety: the fds and strings outlive the calls
+                    unsafe {
+                        Errno::result(libc::fchdir(root.as_raw_fd()))?;
+                        Errno::result(libc::chroot(dot.as_ptr()))?;
+                        Errno::result(libc::fchdir(cwd.as_raw_fd()))?;
+                        libc::umask(umask);
+                        let dirfd = dir.as_ref().map_or(libc::AT_FDCWD, |dir| dir.as_raw_fd());
+                        Errno::result(libc::mknodat(dirfd, path.as_ptr(), mode, dev))?;
+                        Errno::result(libc::fchownat(
+                            dirfd,
+                            path.as_ptr(),
+                            status.fuid,
+                            status.fgid,
+                            libc::AT_SYMLINK_NOFOLLOW,
+                        ))?;
+                    }
+                    Ok(())
+                })();
+                let code = match result {
+                    Ok(()) => 0,
+                    Err(errno) => errno as i32,
+                };
+                // Safety: exits without running the destructors of the parent's state
+                unsafe { libc::_exit(code) }
+            }
+            ForkResult::Parent { child } => match waitpid(child, None)? {
+                WaitStatus::Exited(_, 0) => Ok(()),
+                WaitStatus::Exited(_, code) => Err(Errno::from_raw(code)),
+                _ => Err(Errno::EIO),
+            },
+        }
+    }
+}
+
+impl Handler for MknodHandler {
+    fn handle(&self, request: &Request<'_>) -> Option<Response> {
+        if AUDIT_ARCH_NATIVE != Some(request.arch()) {
+            return None;
+        }
+        let args = request.args();
+        let (dirfd, path, mode, dev) = match request.nr() as libc::c_long {
+            libc::SYS_mknodat => (args[0] as i32, args[1], args[2], args[3]),
+            #[cfg(any(
+                target_arch = "x86_64",
+                target_arch = "powerpc64",
+                target_arch = "s390x"
+            ))]
+            libc::SYS_mknod => (libc::AT_FDCWD, args[0], args[1], args[2]),
+            _ => return None,
+        };
+
+        let mode = mode as libc::mode_t;
+        let typ = match mode & libc::S_IFMT {
+            libc::S_IFCHR => LinuxDeviceType::C,
+            libc::S_IFBLK => LinuxDeviceType::B,
+            _ => return Some(Response::Continue),
+        };
+        // the kernel only uses the lower 32 bits of the device number
+        let dev = dev as u32 as libc::dev_t;
+        let (major, minor) = (libc::major(dev) as u64, libc::minor(dev) as u64);
+        if !self.allowed.contains(&(typ, major, minor)) {
+            return None;
+        }
+
+        match self.create(request, dirfd, path, mode, dev) {
+            Ok(()) => Some(Response::Value(0)),
+            Err(errno) => Some(Response::Errno(errno)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::fd::FromRawFd;
+    use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+    use std::sync::mpsc;
+
+    use anyhow::Result;
+    use nix::sys::stat::{self, SFlag};
+    use oci_spec::runtime::{Arch, LinuxSeccompAction, LinuxSeccompBuilder, LinuxSyscallBuilder};
+    use serial_test::serial;
+
+    use super::*;
+    use crate::seccomp::agent::{Agent, Connection, NotifyFd};
+    use crate::seccomp::initialize_seccomp;
+    use crate::test_utils::{self, TestCallbackError};
+
+    #[test]
+    #[serial]
+    fn test_mknod_handler() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let seccomp = LinuxSeccompBuilder::default()
+            .default_action(LinuxSeccompAction::ScmpActAllow)
+            .architectures(vec![Arch::ScmpArchNative])
+            .syscalls(vec![
+                LinuxSyscallBuilder::default()
+                    .names(vec!["mknod".to_owned(), "mknodat".to_owned()])
+                    .action(LinuxSeccompAction::ScmpActNotify)
+                    .build()?,
+            ])
+            .build()?;
+
+        test_utils::test_in_child_process(|| {
+            let (sender, receiver) = mpsc::channel::<OwnedFd>();
+            std::thread::spawn(move || {
+                let state = serde_json::from_value(serde_json::json!({
+                    "ociVersion": "1.0.0",
+                    "fds": [],
+                    "pid": 1,
+                    "state": {
+                        "ociVersion": "1.0.0",
+                        "id": "test",
+                        "status": "running",
+                        "bundle": "/tmp",
+                    },
+                }))
+                .unwrap();
+                let connection = Connection {
+                    state,
+                    notify_fd: NotifyFd::new(receiver.recv().unwrap()),
+                };
+                let agent = Agent::new()
+                    .with_handler(super::AuditHandler)
+                    .with_handler(MknodHandler::new().allow(LinuxDeviceType::U, 1, 3));
+                agent.serve(&connection).unwrap();
+            });
+
+            let _ = prctl::set_no_new_privileges(true);
+            let fd = initialize_seccomp(&seccomp)
+                .expect("failed to initialize seccomp")
+                .expect("no seccomp notify fd");
+            // Safety: the fd was just created by the seccomp syscall
+            sender.send(unsafe { OwnedFd::from_raw_fd(fd) }).unwrap();
+            let err = |err: &dyn std::fmt::Display| TestCallbackError::Custom(err.to_string());
+
+            // the umask of the target applies
+            stat::umask(Mode::from_bits_truncate(0o077));
+            let null = tmp_dir.path().join("null");
+            stat::mknod(
+                &null,
+                SFlag::S_IFCHR,
+                Mode::from_bits_truncate(0o666),
+                stat::makedev(1, 3),
+            )
+            .map_err(|e| err(&e))?;
+            let metadata = std::fs::metadata(&null).map_err(|e| err(&e))?;
+            if !metadata.file_type().is_char_device()
+                || metadata.rdev() != stat::makedev(1, 3)
+                || metadata.permissions().mode() & 0o777 != 0o600
+            {
+                Err(err(&format!("unexpected node {metadata:?}")))?;
+            }
+
+            let zero = tmp_dir.path().join("zero");
+            let result = stat::mknod(
+                &zero,
+                SFlag::S_IFCHR,
+                Mode::from_bits_truncate(0o666),
+                stat::makedev(1, 5),
+            );
+            if result != Err(Errno::EPERM) || zero.exists() {
+                Err(err(&format!("device 1:5 was not denied: {result:?}")))?;
+            }
+
+            // FIFOs are created by the kernel
+            let fifo = tmp_dir.path().join("fifo");
+            stat::mknod(&fifo, SFlag::S_IFIFO, Mode::from_bits_truncate(0o666), 0)
+                .map_err(|e| err(&e))?;
+            if !std::fs::metadata(&fifo)
+                .map_err(|e| err(&e))?
+                .file_type()
+                .is_fifo()
+            {
+                Err(err(&"fifo was not created"))?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,541 @@
+//! A library for seccomp agents, the processes which decide on the syscalls a
+//! container makes with the SCMP_ACT_NOTIFY action.
+//!
+//! The runtime connects to the `listenerPath` of the seccomp profile and sends the
+//! [`ContainerProcessState`] together with the seccomp notify fd. The agent accepts
+//! these connections with a [`Listener`] and answers the notifications of each of
+//! them with the first [`Handler`] which responds.
+//!
+//! ```no_run
+//! use libcontainer::seccomp::agent::{Agent, AuditHandler, Listener, MknodHandler};
+//! use oci_spec::runtime::LinuxDeviceType;
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let listener = Listener::bind("/run/seccomp-agent.sock")?;
+//! Agent::new()
+//!     .with_handler(AuditHandler)
+//!     .with_handler(MknodHandler::new().allow(LinuxDeviceType::C, 1, 3))
+//!     .run(listener)?;
+//! # Ok(())
+//! # }
+//! ```
+
+mod handlers;
+mod notify;
+
+use std::ffi::CString;
+use std::io::{IoSliceMut, Read};
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::fs::FileExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use nix::errno::Errno;
+use nix::sys::socket::{self, ControlMessageOwned, MsgFlags, UnixAddr};
+use oci_spec::runtime::ContainerProcessState;
+
+pub use self::handlers::{AuditHandler, MknodHandler};
+pub use self::notify::NotifyFd;
+
+// the state is small unless the container has many annotations
+const MAX_STATE_SIZE: usize = 1 << 20;
+const RECV_BUFFER_SIZE: usize = 4096;
+
+#[derive(Debug, thiserror::Error)]
+pub enum AgentError {
+    #[error("failed to bind seccomp listener {path:?}")]
+    Bind {
+        source: std::io::Error,
+        path: PathBuf,
+    },
+    #[error("failed to accept connection on seccomp listener")]
+    Accept(#[source] std::io::Error),
+    #[error("failed to receive container process state")]
+    Receive(#[source] nix::Error),
+    #[error("failed to read container process state")]
+    ReadState(#[source] std::io::Error),
+    #[error("expected one seccomp notify fd with the container process state, received {0}")]
+    MissingFd(usize),
+    #[error("container process state is larger than {MAX_STATE_SIZE} bytes")]
+    StateTooLarge,
+    #[error("failed to decode container process state")]
+    DecodeState(#[source] serde_json::Error),
+    #[error("seccomp notify fd failed")]
+    Notify(#[source] nix::Error),
+    #[error("failed to access memory of process {pid}")]
+    Memory { source: std::io::Error, pid: u32 },
+    #[error("notification {id} is no longer valid")]
+    InvalidId { id: u64 },
+}
+
+type Result<T> = std::result::Result<T, AgentError>;
+
+/// The socket at the listenerPath of a seccomp profile
+#[derive(Debug)]
+pub struct Listener(UnixListener);
+
+impl Listener {
+    pub fn bind<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        UnixListener::bind(path)
+            .map(Self)
+            .map_err(|err| AgentError::Bind {
+                source: err,
+                path: path.to_owned(),
+            })
+    }
+
+    /// Waits for the runtime to hand over the seccomp notify fd of a container. This
+    /// blocks until the runtime has sent the whole container process state.
+    pub fn accept(&self) -> Result<Connection> {
+        Connection::receive(self.accept_stream()?)
+    }
+
+    fn accept_stream(&self) -> Result<UnixStream> {
+        let (stream, _) = self.0.accept().map_err(AgentError::Accept)?;
+        Ok(stream)
+    }
+}
+
+/// The container process state and the seccomp notify fd sent by the runtime
+#[derive(Debug)]
+pub struct Connection {
+    pub state: ContainerProcessState,
+    pub notify_fd: NotifyFd,
+}
+
+impl Connection {
+    fn receive(mut stream: UnixStream) -> Result<Self> {
+        // The fd is attached to the first part of the message, the runtime closes
+        // the connection after sending the rest.
+        let mut buf = vec![0; RECV_BUFFER_SIZE];
+        let mut cmsg_space = nix::cmsg_space!([RawFd; 1]);
+        let (len, fds) = {
+            let mut iov = [IoSliceMut::new(&mut buf)];
+            let msg = socket::recvmsg::<UnixAddr>(
+                stream.as_raw_fd(),
+                &mut iov,
+                Some(&mut cmsg_space),
+                MsgFlags::MSG_CMSG_CLOEXEC,
+            )
+            .map_err(AgentError::Receive)?;
+            let mut fds = Vec::new();
+            for cmsg in msg.cmsgs().map_err(AgentError::Receive)? {
+                if let ControlMessageOwned::ScmRights(received) = cmsg {
+                    // Safety: the fds were just received and are owned by nobody else
+                    fds.extend(
+                        received
+                            .into_iter()
+                            .map(|fd| unsafe { OwnedFd::from_raw_fd(fd) }),
+                    );
+                }
+            }
+            (msg.bytes, fds)
+        };
+        buf.truncate(len);
+
+        let count = fds.len();
+        let Ok::<[OwnedFd; 1], _>([notify_fd]) = fds.try_into() else {
+            return Err(AgentError::MissingFd(count));
+        };
+        (&mut stream)
+            .take((MAX_STATE_SIZE - len + 1) as u64)
+            .read_to_end(&mut buf)
+            .map_err(AgentError::ReadState)?;
+        if buf.len() > MAX_STATE_SIZE {
+            return Err(AgentError::StateTooLarge);
+        }
+
+        let state = serde_json::from_slice(&buf).map_err(AgentError::DecodeState)?;
+        Ok(Self {
+            state,
+            notify_fd: NotifyFd::new(notify_fd),
+        })
+    }
+}
+
+/// A syscall of the container waiting for the decision of the agent
+#[derive(Debug)]
+pub struct Request<'a> {
+    pub notification: libc::seccomp_notif,
+    pub state: &'a ContainerProcessState,
+    notify_fd: &'a NotifyFd,
+}
+
+impl Request<'_> {
+    pub fn id(&self) -> u64 {
+        self.notification.id
+    }
+
+    /// The thread which made the syscall, in the pid namespace of the agent
+    pub fn pid(&self) -> u32 {
+        self.notification.pid
+    }
+
+    pub fn nr(&self) -> i64 {
+        self.notification.data.nr as i64
+    }
+
+    /// The audit arch of the syscall, see AUDIT_ARCH_* in <linux/audit.h>
+    pub fn arch(&self) -> u32 {
+        self.notification.data.arch
+    }
+
+    pub fn args(&self) -> [u64; 6] {
+        self.notification.data.args
+    }
+
+    /// Returns whether the target still waits for the response. The target may be
+    /// killed and its pid reused at any time, so everything read from it must only be
+    /// trusted when the notification is still valid afterwards.
+    pub fn is_valid(&self) -> Result<bool> {
+        self.notify_fd
+            .id_valid(self.id())
+            .map_err(AgentError::Notify)
+    }
+
+    /// Reads memory of the target, e.g. a buffer passed to the syscall
+    pub fn read_memory(&self, addr: u64, buf: &mut [u8]) -> Result<usize> {
+        let mem = std::fs::File::open(format!("/proc/{}/mem", self.pid())).map_err(|err| {
+            AgentError::Memory {
+                source: err,
+                pid: self.pid(),
+            }
+        })?;
+        // the process may have been replaced by another one with the same pid
+        if !self.is_valid()? {
+            return Err(AgentError::InvalidId { id: self.id() });
+        }
+        let len = mem.read_at(buf, addr).map_err(|err| AgentError::Memory {
+            source: err,
+            pid: self.pid(),
+        })?;
+        if !self.is_valid()? {
+            return Err(AgentError::InvalidId { id: self.id() });
+        }
+
+        Ok(len)
+    }
+
+    /// Reads a nul terminated string of the target, e.g. a path, of at most PATH_MAX
+    /// bytes
+    pub fn read_c_string(&self, addr: u64) -> Result<CString> {
+        let mut buf = vec![0; libc::PATH_MAX as usize];
+        let len = self.read_memory(addr, &mut buf)?;
+        buf.truncate(len);
+        match buf.iter().position(|b| *b == 0) {
+            Some(end) => {
+                buf.truncate(end);
+                // the string has no nul byte before end
+                Ok(CString::new(buf).expect("nul byte in string"))
+            }
+            None => Err(AgentError::Memory {
+                source: std::io::Error::from_raw_os_error(libc::ENAMETOOLONG),
+                pid: self.pid(),
+            }),
+        }
+    }
+
+    /// Installs a duplicate of the fd in the target and returns its number there
+    pub fn add_fd(&self, fd: BorrowedFd, cloexec: bool) -> Result<RawFd> {
+        self.notify_fd
+            .add_fd(self.id(), fd, cloexec, false)
+            .map_err(AgentError::Notify)
+    }
+}
+
+/// The decision on a syscall
+#[derive(Debug)]
+pub enum Response {
+    /// Lets the kernel run the syscall. This must not be used to allow syscalls
+    /// based on the memory of the target, which may change until the syscall runs.
+    Continue,
+    /// Returns the value from the syscall without running it
+    Value(i64),
+    /// Fails the syscall with the errno
+    Errno(Errno),
+    /// Installs the fd in the target and returns its number from the syscall
+    AddFd { fd: OwnedFd, cloexec: bool },
+}
+
+/// Decides on the notifications of a container
+pub trait Handler: Send + Sync {
+    /// Returns the response for the request, or None to leave it to the next handler
+    fn handle(&self, request: &Request<'_>) -> Option<Response>;
+}
+
+impl<F> Handler for F
+where
+    F: Fn(&Request<'_>) -> Option<Response> + Send + Sync,
+{
+    fn handle(&self, request: &Request<'_>) -> Option<Response> {
+        self(request)
+    }
+}
+
+/// Answers the notifications of containers with a chain of handlers
+pub struct Agent {
+    handlers: Vec<Box<dyn Handler>>,
+    default_errno: Errno,
+}
+
+impl Default for Agent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Agent {
+    /// Creates an agent which fails all syscalls with EPERM until handlers are added
+    pub fn new() -> Self {
+        Self {
+            handlers: Vec::new(),
+            default_errno: Errno::EPERM,
+        }
+    }
+
+    /// Adds a handler, which is asked after the ones added before
+    pub fn with_handler<H: Handler + 'static>(mut self, handler: H) -> Self {
+        self.handlers.push(Box::new(handler));
+        self
+    }
+
+    /// Sets the errno of syscalls no handler responds to
+    pub fn with_default_errno(mut self, errno: Errno) -> Self {
+        self.default_errno = errno;
+        self
+    }
+
+    /// Accepts connections and serves each of them in a thread, until accepting
+    /// fails.
+    pub fn run(self, listener: Listener) -> Result<()> {
+        let agent = Arc::new(self);
+        loop {
+            let stream = listener.accept_stream()?;
+            let agent = agent.clone();
+            // The connection is received in its thread, so that a runtime which
+            // stalls while sending doesn't hold up the other containers.
+            std::thread::spawn(move || {
+                let connection = match Connection::receive(stream) {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        tracing::warn!(?err, "failed to receive seccomp notify fd");
+                        return;
+                    }
+                };
+                let id = connection.state.state().id().clone();
+                if let Err(err) = agent.serve(&connection) {
+                    tracing::error!(?err, id, "failed to serve seccomp notifications");
+                }
+            });
+        }
+    }
+
+    /// Answers the notifications of the connection until the container's processes
+    /// using the filter are gone
+    pub fn serve(&self, connection: &Connection) -> Result<()> {
+        while let Some(notification) = connection.notify_fd.recv().map_err(AgentError::Notify)? {
+            let request = Request {
+                notification,
+                state: &connection.state,
+                notify_fd: &connection.notify_fd,
+            };
+            let response = self
+                .handlers
+                .iter()
+                .find_map(|handler| handler.handle(&request))
+                .unwrap_or(Response::Errno(self.default_errno));
+            tracing::debug!(
+                id = request.id(),
+                nr = request.nr(),
+                ?response,
+                "seccomp notification"
+            );
+
+            match self.respond(&request, response) {
+                Ok(()) => {}
+                // the target was interrupted or killed
+                Err(AgentError::Notify(Errno::ENOENT)) => {
+                    tracing::debug!(id = request.id(), "seccomp notification is gone")
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn respond(&self, request: &Request<'_>, response: Response) -> Result<()> {
+        let mut resp = libc::seccomp_notif_resp {
+            id: request.id(),
+            val: 0,
+            error: 0,
+            flags: 0,
+        };
+        match response {
+            Response::Continue => resp.flags = libc::SECCOMP_USER_NOTIF_FLAG_CONTINUE as u32,
+            Response::Value(val) => resp.val = val,
+            Response::Errno(errno) => resp.error = -(errno as i32),
+            Response::AddFd { fd, cloexec } => {
+                // the fd number is returned to the target atomically
+                request
+                    .notify_fd
+                    .add_fd(request.id(), std::os::fd::AsFd::as_fd(&fd), cloexec, true)
+                    .map_err(AgentError::Notify)?;
+                return Ok(());
+            }
+        }
+
+        request.notify_fd.send(resp).map_err(AgentError::Notify)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::IoSlice;
+    use std::os::fd::AsFd;
+    use std::sync::mpsc;
+
+    use anyhow::Result;
+    use oci_spec::runtime::{
+        Arch, ContainerProcessStateBuilder, ContainerState, LinuxSeccompAction,
+        LinuxSeccompBuilder, LinuxSyscallBuilder, StateBuilder,
+    };
+    use serial_test::serial;
+
+    use super::*;
+    use crate::seccomp::initialize_seccomp;
+    use crate::test_utils::{self, TestCallbackError};
+
+    fn state() -> ContainerProcessState {
+        ContainerProcessStateBuilder::default()
+            .version("1.0.0")
+            .fds(vec!["seccompFd".to_string()])
+            .pid(1234)
+            .metadata("test")
+            .state(
+                StateBuilder::default()
+                    .version("1.0.0")
+                    .id("test-container")
+                    .status(ContainerState::Creating)
+                    .pid(1234)
+                    .bundle("/tmp/bundle")
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_accept() -> Result<()> {
+        let tmp_dir = tempfile::tempdir()?;
+        let path = tmp_dir.path().join("agent.sock");
+        let listener = Listener::bind(&path)?;
+        let file = tempfile::tempfile()?;
+
+        // a state larger than the first read
+        let mut state = state();
+        state.set_metadata(Some("x".repeat(3 * RECV_BUFFER_SIZE)));
+        let msg = serde_json::to_vec(&state)?;
+        let stream = UnixStream::connect(&path)?;
+        socket::sendmsg::<UnixAddr>(
+            stream.as_raw_fd(),
+            &[IoSlice::new(&msg)],
+            &[socket::ControlMessage::ScmRights(&[file.as_raw_fd()])],
+            MsgFlags::empty(),
+            None,
+        )?;
+        drop(stream);
+
+        let connection = listener.accept()?;
+        assert_eq!(connection.state, state);
+
+        // a connection without an fd is rejected
+        let mut stream = UnixStream::connect(&path)?;
+        std::io::Write::write_all(&mut stream, &msg)?;
+        drop(stream);
+        assert!(matches!(listener.accept(), Err(AgentError::MissingFd(_))));
+        Ok(())
+    }
+
+    #[test]
+    #[serial]
+    fn test_serve() -> Result<()> {
+        let seccomp = LinuxSeccompBuilder::default()
+            .default_action(LinuxSeccompAction::ScmpActAllow)
+            .architectures(vec![Arch::ScmpArchNative])
+            .syscalls(vec![
+                LinuxSyscallBuilder::default()
+                    .names(vec![
+                        "getcwd".to_owned(),
+                        "dup".to_owned(),
+                        "getppid".to_owned(),
+                    ])
+                    .action(LinuxSeccompAction::ScmpActNotify)
+                    .build()?,
+            ])
+            .build()?;
+
+        test_utils::test_in_child_process(|| {
+            // The filter only applies to the thread which installs it, so the agent
+            // runs in a thread started before.
+            let (sender, receiver) = mpsc::channel::<OwnedFd>();
+            std::thread::spawn(move || {
+                let connection = Connection {
+                    state: state(),
+                    notify_fd: NotifyFd::new(receiver.recv().unwrap()),
+                };
+                let agent = Agent::new()
+                    .with_default_errno(Errno::EXDEV)
+                    .with_handler(|request: &Request<'_>| {
+                        (request.nr() == libc::SYS_getppid).then_some(Response::Value(42))
+                    })
+                    .with_handler(|request: &Request<'_>| {
+                        if request.nr() != libc::SYS_dup {
+                            return None;
+                        }
+                        let file = std::fs::File::open("/dev/null").ok()?;
+                        Some(Response::AddFd {
+                            fd: file.into(),
+                            cloexec: false,
+                        })
+                    });
+                agent.serve(&connection).unwrap();
+            });
+
+            let _ = prctl::set_no_new_privileges(true);
+            let fd = initialize_seccomp(&seccomp)
+                .expect("failed to initialize seccomp")
+                .expect("no seccomp notify fd");
+            // Safety: the fd was just created by the seccomp syscall
+            sender.send(unsafe { OwnedFd::from_raw_fd(fd) }).unwrap();
+
+            if nix::unistd::getcwd() != Err(Errno::EXDEV) {
+                Err(TestCallbackError::Custom(
+                    "getcwd didn't fail with the default errno".to_string(),
+                ))?;
+            }
+            if nix::unistd::getppid().as_raw() != 42 {
+                Err(TestCallbackError::Custom(
+                    "getppid didn't return the value of the handler".to_string(),
+                ))?;
+            }
+            let stdin = std::io::stdin();
+            let fd = nix::unistd::dup(stdin.as_fd().as_raw_fd())
+                .map_err(|err| TestCallbackError::Custom(err.to_string()))?;
+            let target = std::fs::read_link(format!("/proc/self/fd/{fd}"))
+                .map_err(|err| TestCallbackError::Custom(err.to_string()))?;
+            if target != Path::new("/dev/null") {
+                Err(TestCallbackError::Custom(format!(
+                    "dup returned {target:?} instead of the fd of the handler"
+                )))?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok(())
+    }
+}
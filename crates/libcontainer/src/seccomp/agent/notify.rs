@@ -0,0 +1,97 @@
+// The ioctls of a seccomp notify fd, see seccomp_unotify(2)
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+
+use nix::errno::Errno;
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+
+const SECCOMP_IOC_MAGIC: u8 = b'!';
+
+nix::ioctl_readwrite!(notif_recv, SECCOMP_IOC_MAGIC, 0, libc::seccomp_notif);
+nix::ioctl_readwrite!(notif_send, SECCOMP_IOC_MAGIC, 1, libc::seccomp_notif_resp);
+nix::ioctl_write_ptr!(notif_id_valid, SECCOMP_IOC_MAGIC, 2, u64);
+nix::ioctl_write_ptr!(notif_addfd, SECCOMP_IOC_MAGIC, 3, libc::seccomp_notif_addfd);
+
+/// The fd returned by the seccomp(2) syscall for a filter with SCMP_ACT_NOTIFY rules
+#[derive(Debug)]
+pub struct NotifyFd(OwnedFd);
+
+impl NotifyFd {
+    pub fn new(fd: OwnedFd) -> Self {
+        Self(fd)
+    }
+
+    /// Waits for the next notification. Returns None when no process uses the filter
+    /// anymore.
+    pub fn recv(&self) -> nix::Result<Option<libc::seccomp_notif>> {
+        loop {
+            let mut fds = [PollFd::new(self.0.as_fd(), PollFlags::POLLIN)];
+            match poll(&mut fds, PollTimeout::NONE) {
+                Ok(_) => {}
+                Err(Errno::EINTR) => continue,
+                Err(err) => return Err(err),
+            }
+            let revents = fds[0].revents().unwrap_or(PollFlags::empty());
+            if !revents.contains(PollFlags::POLLIN) {
+                if revents.intersects(PollFlags::POLLHUP | PollFlags::POLLERR) {
+                    return Ok(None);
+                }
+                continue;
+            }
+
+            // the kernel requires the struct to be zeroed
+            // Safety: seccomp_notif is plain data
+            let mut notification: libc::seccomp_notif = unsafe { std::mem::zeroed() };
+            // Safety: the pointer refers to a seccomp_notif
+            match unsafe { notif_recv(self.0.as_raw_fd(), &mut notification) } {
+                Ok(_) => return Ok(Some(notification)),
+                // the syscall was interrupted before the notification was received
+                Err(Errno::ENOENT | Errno::EINTR) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Sends the response, which fails with ENOENT when the syscall was interrupted
+    /// in the meantime
+    pub fn send(&self, mut response: libc::seccomp_notif_resp) -> nix::Result<()> {
+        // Safety: the pointer refers to a seccomp_notif_resp
+        unsafe { notif_send(self.0.as_raw_fd(), &mut response) }?;
+        Ok(())
+    }
+
+    /// Returns whether the notification is still pending, i.e. the target has not
+    /// been interrupted and still runs the same syscall.
+    pub fn id_valid(&self, id: u64) -> nix::Result<bool> {
+        // Safety: the pointer refers to a u64
+        match unsafe { notif_id_valid(self.0.as_raw_fd(), &id) } {
+            Ok(_) => Ok(true),
+            Err(Errno::ENOENT) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Installs a duplicate of the fd in the target of the notification and returns
+    /// its number there. With `send`, the number is also the result of the syscall
+    /// and no response has to be sent.
+    pub fn add_fd(&self, id: u64, fd: BorrowedFd, cloexec: bool, send: bool) -> nix::Result<RawFd> {
+        let addfd = libc::seccomp_notif_addfd {
+            id,
+            flags: if send {
+                libc::SECCOMP_ADDFD_FLAG_SEND as u32
+            } else {
+                0
+            },
+            srcfd: fd.as_raw_fd() as u32,
+            newfd: 0,
+            newfd_flags: if cloexec { libc::O_CLOEXEC as u32 } else { 0 },
+        };
+        // Safety: the pointer refers to a seccomp_notif_addfd
+        unsafe { notif_addfd(self.0.as_raw_fd(), &addfd) }
+    }
+}
+
+impl AsFd for NotifyFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
@@ -0,0 +1,272 @@
+// Classic BPF instructions as used by seccomp filters, and an interpreter which
+// evaluates a filter for a syscall the same way the kernel does.
+// See https://docs.kernel.org/networking/filter.html and
+// https://docs.kernel.org/userspace-api/seccomp_filter.html
+
+// BPF instruction classes, see <linux/bpf_common.h>
+pub const BPF_LD: u16 = 0x00;
+pub const BPF_ALU: u16 = 0x04;
+pub const BPF_JMP: u16 = 0x05;
+pub const BPF_RET: u16 = 0x06;
+
+// ld/ldx fields
+pub const BPF_W: u16 = 0x00;
+pub const BPF_ABS: u16 = 0x20;
+
+// alu fields
+pub const BPF_AND: u16 = 0x50;
+
+// jmp fields
+pub const BPF_JA: u16 = 0x00;
+pub const BPF_JEQ: u16 = 0x10;
+pub const BPF_JGT: u16 = 0x20;
+pub const BPF_JGE: u16 = 0x30;
+pub const BPF_JSET: u16 = 0x40;
+pub const BPF_K: u16 = 0x00;
+
+// maximum number of instructions the kernel accepts for a filter
+pub const BPF_MAXINSNS: usize = 4096;
+
+// Return values of seccomp filters, see <linux/seccomp.h>
+pub const SECCOMP_RET_KILL_PROCESS: u32 = 0x8000_0000;
+pub const SECCOMP_RET_KILL_THREAD: u32 = 0x0000_0000;
+pub const SECCOMP_RET_TRAP: u32 = 0x0003_0000;
+pub const SECCOMP_RET_ERRNO: u32 = 0x0005_0000;
+pub const SECCOMP_RET_USER_NOTIF: u32 = 0x7fc0_0000;
+pub const SECCOMP_RET_TRACE: u32 = 0x7ff0_0000;
+pub const SECCOMP_RET_LOG: u32 = 0x7ffc_0000;
+pub const SECCOMP_RET_ALLOW: u32 = 0x7fff_0000;
+pub const SECCOMP_RET_ACTION_FULL: u32 = 0xffff_0000;
+pub const SECCOMP_RET_DATA: u32 = 0x0000_ffff;
+
+// offsets of the fields of struct seccomp_data
+pub const SECCOMP_DATA_NR_OFFSET: u32 = 0;
+pub const SECCOMP_DATA_ARCH_OFFSET: u32 = 4;
+pub const SECCOMP_DATA_ARGS_OFFSET: u32 = 16;
+
+// set in the audit arch of little endian architectures, see <linux/audit.h>
+pub const AUDIT_ARCH_LE: u32 = 0x4000_0000;
+pub const AUDIT_ARCH_64BIT: u32 = 0x8000_0000;
+
+/// Describes the value returned by the filter, e.g. ERRNO(1)
+pub fn describe_action(action: u32) -> String {
+    let data = action & SECCOMP_RET_DATA;
+    match action & SECCOMP_RET_ACTION_FULL {
+        SECCOMP_RET_KILL_PROCESS => "KILL_PROCESS".to_owned(),
+        SECCOMP_RET_KILL_THREAD => "KILL_THREAD".to_owned(),
+        SECCOMP_RET_TRAP => "TRAP".to_owned(),
+        SECCOMP_RET_ERRNO => format!("ERRNO({data})"),
+        SECCOMP_RET_USER_NOTIF => "NOTIFY".to_owned(),
+        SECCOMP_RET_TRACE => format!("TRACE({})", data as u16 as i16),
+        SECCOMP_RET_LOG => "LOG".to_owned(),
+        SECCOMP_RET_ALLOW => "ALLOW".to_owned(),
+        _ => format!("{action:#x}"),
+    }
+}
+
+/// A single filter instruction, which has the layout of struct sock_filter
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub code: u16,
+    pub jt: u8,
+    pub jf: u8,
+    pub k: u32,
+}
+
+impl Instruction {
+    pub fn stmt(code: u16, k: u32) -> Self {
+        Self {
+            code,
+            jt: 0,
+            jf: 0,
+            k,
+        }
+    }
+
+    pub fn jump(code: u16, k: u32, jt: u8, jf: u8) -> Self {
+        Self { code, jt, jf, k }
+    }
+
+    /// Decodes an instruction from the binary format the kernel and libseccomp use
+    pub fn from_ne_bytes(bytes: [u8; 8]) -> Self {
+        Self {
+            code: u16::from_ne_bytes([bytes[0], bytes[1]]),
+            jt: bytes[2],
+            jf: bytes[3],
+            k: u32::from_ne_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+        }
+    }
+
+    pub fn to_ne_bytes(self) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        bytes[0..2].copy_from_slice(&self.code.to_ne_bytes());
+        bytes[2] = self.jt;
+        bytes[3] = self.jf;
+        bytes[4..8].copy_from_slice(&self.k.to_ne_bytes());
+        bytes
+    }
+}
+
+/// The input of a seccomp filter, see struct seccomp_data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SeccompData {
+    pub nr: i32,
+    pub arch: u32,
+    pub instruction_pointer: u64,
+    pub args: [u64; 6],
+}
+
+impl SeccompData {
+    // Loads the 32 bit word at the offset, laid out in the byte order of the audit
+    // arch, so that filters for foreign architectures can be evaluated as well.
+    fn load_word(&self, offset: u32) -> Option<u32> {
+        let big_endian = self.arch & AUDIT_ARCH_LE == 0;
+        let split = |value: u64, offset: u32| {
+            let high = (offset % 8 == 0) == big_endian;
+            if high {
+                (value >> 32) as u32
+            } else {
+                value as u32
+            }
+        };
+
+        match offset {
+            SECCOMP_DATA_NR_OFFSET => Some(self.nr as u32),
+            SECCOMP_DATA_ARCH_OFFSET => Some(self.arch),
+            8 | 12 => Some(split(self.instruction_pointer, offset)),
+            16..=60 if offset % 4 == 0 => {
+                let index = ((offset - SECCOMP_DATA_ARGS_OFFSET) / 8) as usize;
+                Some(split(self.args[index], offset))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum EvaluateError {
+    #[error("unsupported instruction {code:#06x} at {pc}")]
+    UnsupportedInstruction { pc: usize, code: u16 },
+    #[error("invalid load of offset {offset} at {pc}")]
+    InvalidLoad { pc: usize, offset: u32 },
+    #[error("filter ended without returning")]
+    MissingReturn,
+}
+
+/// Runs the filter for the syscall and returns the action it decided on
+pub fn evaluate(filter: &[Instruction], data: &SeccompData) -> Result<u32, EvaluateError> {
+    let mut accumulator: u32 = 0;
+    let mut pc = 0;
+    while let Some(inst) = filter.get(pc) {
+        pc += 1;
+        match inst.code {
+            code if code == BPF_LD | BPF_W | BPF_ABS => {
+                accumulator = data.load_word(inst.k).ok_or(EvaluateError::InvalidLoad {
+                    pc: pc - 1,
+                    offset: inst.k,
+                })?;
+            }
+            code if code == BPF_ALU | BPF_AND | BPF_K => accumulator &= inst.k,
+            code if code == BPF_JMP | BPF_JA => pc += inst.k as usize,
+            code if code & 0x07 == BPF_JMP && code & 0x08 == BPF_K => {
+                let taken = match code & 0xf0 {
+                    BPF_JEQ => accumulator == inst.k,
+                    BPF_JGT => accumulator > inst.k,
+                    BPF_JGE => accumulator >= inst.k,
+                    BPF_JSET => accumulator & inst.k != 0,
+                    _ => {
+                        return Err(EvaluateError::UnsupportedInstruction {
+                            pc: pc - 1,
+                            code: inst.code,
+                        });
+                    }
+                };
+                pc += if taken { inst.jt } else { inst.jf } as usize;
+            }
+            code if code == BPF_RET | BPF_K => return Ok(inst.k),
+            code => {
+                return Err(EvaluateError::UnsupportedInstruction { pc: pc - 1, code });
+            }
+        }
+    }
+
+    Err(EvaluateError::MissingReturn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instruction_encoding() {
+        assert_eq!(
+            Instruction::stmt(BPF_LD | BPF_W | BPF_ABS, 16),
+            Instruction {
+                code: 0x20,
+                jt: 0,
+                jf: 0,
+                k: 16,
+            }
+        );
+        let inst = Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, 10, 2, 5);
+        assert_eq!(inst.code, 0x15);
+        assert_eq!(Instruction::from_ne_bytes(inst.to_ne_bytes()), inst);
+    }
+
+    #[test]
+    fn test_evaluate() {
+        // returns ERRNO(1) for syscall 2 when the lower half of arg0 has bit 0 set
+        let filter = [
+            Instruction::stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_NR_OFFSET),
+            Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, 2, 0, 3),
+            Instruction::stmt(BPF_LD | BPF_W | BPF_ABS, SECCOMP_DATA_ARGS_OFFSET),
+            Instruction::jump(BPF_JMP | BPF_JSET | BPF_K, 1, 0, 1),
+            Instruction::stmt(BPF_RET | BPF_K, SECCOMP_RET_ERRNO | 1),
+            Instruction::stmt(BPF_RET | BPF_K, SECCOMP_RET_ALLOW),
+        ];
+        let data = |nr, arch, arg0| SeccompData {
+            nr,
+            arch,
+            args: [arg0, 0, 0, 0, 0, 0],
+            ..Default::default()
+        };
+        let little = AUDIT_ARCH_LE | AUDIT_ARCH_64BIT;
+
+        assert_eq!(
+            evaluate(&filter, &data(2, little, 1)),
+            Ok(SECCOMP_RET_ERRNO | 1)
+        );
+        assert_eq!(
+            evaluate(&filter, &data(2, little, 2)),
+            Ok(SECCOMP_RET_ALLOW)
+        );
+        assert_eq!(
+            evaluate(&filter, &data(3, little, 2)),
+            Ok(SECCOMP_RET_ALLOW)
+        );
+        // the upper half is stored first on big endian architectures
+        assert_eq!(
+            evaluate(&filter, &data(2, AUDIT_ARCH_64BIT, 1)),
+            Ok(SECCOMP_RET_ALLOW)
+        );
+        assert_eq!(
+            evaluate(&filter, &data(2, AUDIT_ARCH_64BIT, 1 << 32)),
+            Ok(SECCOMP_RET_ERRNO | 1)
+        );
+    }
+
+    #[test]
+    fn test_evaluate_errors() {
+        let data = SeccompData::default();
+        assert_eq!(evaluate(&[], &data), Err(EvaluateError::MissingReturn));
+        assert_eq!(
+            evaluate(&[Instruction::stmt(BPF_LD | BPF_W | BPF_ABS, 64)], &data),
+            Err(EvaluateError::InvalidLoad { pc: 0, offset: 64 })
+        );
+        assert_eq!(
+            evaluate(&[Instruction::stmt(0x07, 0)], &data),
+            Err(EvaluateError::UnsupportedInstruction { pc: 0, code: 0x07 })
+        );
+    }
+}
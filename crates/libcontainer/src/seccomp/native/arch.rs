@@ -0,0 +1,393 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use oci_spec::runtime;
+use syscalls::{aarch64, arm, mips, mips64, powerpc, powerpc64, riscv64, s390x, x86, x86_64};
+
+use super::bpf::{AUDIT_ARCH_64BIT, AUDIT_ARCH_LE};
+use crate::seccomp::SeccompError;
+
+// ELF machine numbers, see <linux/elf-em.h>
+const EM_386: u32 = 3;
+const EM_MIPS: u32 = 8;
+const EM_PPC: u32 = 20;
+const EM_PPC64: u32 = 21;
+const EM_S390: u32 = 22;
+const EM_ARM: u32 = 40;
+const EM_X86_64: u32 = 62;
+const EM_AARCH64: u32 = 183;
+const EM_RISCV: u32 = 243;
+
+// x32 shares the audit arch of x86_64 and marks its syscall numbers with this bit
+pub const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+// Syscalls which x32 implements with its own entry point instead of the x86_64 one
+const X32_SYSCALLS: [(&str, u32); 36] = [
+    ("rt_sigaction", 512),
+    ("rt_sigreturn", 513),
+    ("ioctl", 514),
+    ("readv", 515),
+    ("writev", 516),
+    ("recvfrom", 517),
+    ("sendmsg", 518),
+    ("recvmsg", 519),
+    ("execve", 520),
+    ("ptrace", 521),
+    ("rt_sigpending", 522),
+    ("rt_sigtimedwait", 523),
+    ("rt_sigqueueinfo", 524),
+    ("sigaltstack", 525),
+    ("timer_create", 526),
+    ("mq_notify", 527),
+    ("kexec_load", 528),
+    ("waitid", 529),
+    ("set_robust_list", 530),
+    ("get_robust_list", 531),
+    ("vmsplice", 532),
+    ("move_pages", 533),
+    ("preadv", 534),
+    ("pwritev", 535),
+    ("rt_tgsigqueueinfo", 536),
+    ("recvmmsg", 537),
+    ("sendmmsg", 538),
+    ("process_vm_readv", 539),
+    ("process_vm_writev", 540),
+    ("setsockopt", 541),
+    ("getsockopt", 542),
+    ("io_setup", 543),
+    ("io_submit", 544),
+    ("execveat", 545),
+    ("preadv2", 546),
+    ("pwritev2", 547),
+];
+
+// Syscalls which only exist on x86_64, as x32 has no 64 bit entry point for them
+const X86_64_ONLY_SYSCALLS: [&str; 12] = [
+    "uselib",
+    "_sysctl",
+    "create_module",
+    "get_kernel_syms",
+    "query_module",
+    "nfsservctl",
+    "set_thread_area",
+    "get_thread_area",
+    "epoll_ctl_old",
+    "epoll_wait_old",
+    "vserver",
+    "map_shadow_stack",
+];
+
+// The private syscalls of ARM, which are not part of the generic table
+const ARM_PRIVATE_SYSCALLS: [(&str, u32); 6] = [
+    ("breakpoint", 0xf0001),
+    ("cacheflush", 0xf0002),
+    ("usr26", 0xf0003),
+    ("usr32", 0xf0004),
+    ("set_tls", 0xf0005),
+    ("get_tls", 0xf0006),
+];
+
+// Calls of the socketcall(2) multiplexer, see <linux/net.h>
+const SOCKETCALLS: [(&str, u32); 20] = [
+    ("socket", 1),
+    ("bind", 2),
+    ("connect", 3),
+    ("listen", 4),
+    ("accept", 5),
+    ("getsockname", 6),
+    ("getpeername", 7),
+    ("socketpair", 8),
+    ("send", 9),
+    ("recv", 10),
+    ("sendto", 11),
+    ("recvfrom", 12),
+    ("shutdown", 13),
+    ("setsockopt", 14),
+    ("getsockopt", 15),
+    ("sendmsg", 16),
+    ("recvmsg", 17),
+    ("accept4", 18),
+    ("recvmmsg", 19),
+    ("sendmmsg", 20),
+];
+
+// Calls of the ipc(2) multiplexer, see <linux/ipc.h>
+const IPC_CALLS: [(&str, u32); 12] = [
+    ("semop", 1),
+    ("semget", 2),
+    ("semctl", 3),
+    ("semtimedop", 4),
+    ("msgsnd", 11),
+    ("msgrcv", 12),
+    ("msgget", 13),
+    ("msgctl", 14),
+    ("shmat", 21),
+    ("shmdt", 22),
+    ("shmget", 23),
+    ("shmctl", 24),
+];
+
+/// An architecture the native backend is able to build filters for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arch {
+    X86,
+    X86_64,
+    X32,
+    Arm,
+    Aarch64,
+    Mips,
+    Mipsel,
+    Mips64,
+    Mipsel64,
+    Ppc,
+    Ppc64,
+    Ppc64le,
+    S390x,
+    Riscv64,
+}
+
+impl Arch {
+    pub const ALL: [Self; 14] = [
+        Self::X86,
+        Self::X86_64,
+        Self::X32,
+        Self::Arm,
+        Self::Aarch64,
+        Self::Mips,
+        Self::Mipsel,
+        Self::Mips64,
+        Self::Mipsel64,
+        Self::Ppc,
+        Self::Ppc64,
+        Self::Ppc64le,
+        Self::S390x,
+        Self::Riscv64,
+    ];
+
+    /// Looks up an architecture by the name it is displayed with, e.g. x86_64
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|arch| arch.to_string() == name)
+    }
+
+    /// Returns the architecture youki is running on
+    pub fn native() -> Result<Self, SeccompError> {
+        let arch = if cfg!(all(target_arch = "x86_64", target_pointer_width = "32")) {
+            Self::X32
+        } else if cfg!(target_arch = "x86_64") {
+            Self::X86_64
+        } else if cfg!(target_arch = "x86") {
+            Self::X86
+        } else if cfg!(target_arch = "arm") {
+            Self::Arm
+        } else if cfg!(target_arch = "aarch64") {
+            Self::Aarch64
+        } else if cfg!(all(target_arch = "mips", target_endian = "big")) {
+            Self::Mips
+        } else if cfg!(target_arch = "mips") {
+            Self::Mipsel
+        } else if cfg!(all(target_arch = "mips64", target_endian = "big")) {
+            Self::Mips64
+        } else if cfg!(target_arch = "mips64") {
+            Self::Mipsel64
+        } else if cfg!(target_arch = "powerpc") {
+            Self::Ppc
+        } else if cfg!(all(target_arch = "powerpc64", target_endian = "big")) {
+            Self::Ppc64
+        } else if cfg!(target_arch = "powerpc64") {
+            Self::Ppc64le
+        } else if cfg!(target_arch = "s390x") {
+            Self::S390x
+        } else if cfg!(target_arch = "riscv64") {
+            Self::Riscv64
+        } else {
+            return Err(SeccompError::UnsupportedArch {
+                arch: runtime::Arch::ScmpArchNative,
+            });
+        };
+
+        Ok(arch)
+    }
+
+    pub fn from_spec(arch: runtime::Arch) -> Result<Self, SeccompError> {
+        let arch = match arch {
+            runtime::Arch::ScmpArchNative => return Self::native(),
+            runtime::Arch::ScmpArchX86 => Self::X86,
+            runtime::Arch::ScmpArchX86_64 => Self::X86_64,
+            runtime::Arch::ScmpArchX32 => Self::X32,
+            runtime::Arch::ScmpArchArm => Self::Arm,
+            runtime::Arch::ScmpArchAarch64 => Self::Aarch64,
+            runtime::Arch::ScmpArchMips => Self::Mips,
+            runtime::Arch::ScmpArchMipsel => Self::Mipsel,
+            runtime::Arch::ScmpArchMips64 => Self::Mips64,
+            runtime::Arch::ScmpArchMipsel64 => Self::Mipsel64,
+            runtime::Arch::ScmpArchPpc => Self::Ppc,
+            runtime::Arch::ScmpArchPpc64 => Self::Ppc64,
+            runtime::Arch::ScmpArchPpc64le => Self::Ppc64le,
+            runtime::Arch::ScmpArchS390x => Self::S390x,
+            runtime::Arch::ScmpArchRiscv64 => Self::Riscv64,
+            // there are no syscall tables for the mips n32 and 31 bit s390 ABIs
+            runtime::Arch::ScmpArchMips64n32
+            | runtime::Arch::ScmpArchMipsel64n32
+            | runtime::Arch::ScmpArchS390 => return Err(SeccompError::UnsupportedArch { arch }),
+        };
+
+        Ok(arch)
+    }
+
+    /// Returns the audit arch the kernel reports for syscalls of the architecture
+    pub fn token(self) -> u32 {
+        match self {
+            Self::X86 => EM_386 | AUDIT_ARCH_LE,
+            Self::X86_64 | Self::X32 => EM_X86_64 | AUDIT_ARCH_64BIT | AUDIT_ARCH_LE,
+            Self::Arm => EM_ARM | AUDIT_ARCH_LE,
+            Self::Aarch64 => EM_AARCH64 | AUDIT_ARCH_64BIT | AUDIT_ARCH_LE,
+            Self::Mips => EM_MIPS,
+            Self::Mipsel => EM_MIPS | AUDIT_ARCH_LE,
+            Self::Mips64 => EM_MIPS | AUDIT_ARCH_64BIT,
+            Self::Mipsel64 => EM_MIPS | AUDIT_ARCH_64BIT | AUDIT_ARCH_LE,
+            Self::Ppc => EM_PPC,
+            Self::Ppc64 => EM_PPC64 | AUDIT_ARCH_64BIT,
+            Self::Ppc64le => EM_PPC64 | AUDIT_ARCH_64BIT | AUDIT_ARCH_LE,
+            Self::S390x => EM_S390 | AUDIT_ARCH_64BIT,
+            Self::Riscv64 => EM_RISCV | AUDIT_ARCH_64BIT | AUDIT_ARCH_LE,
+        }
+    }
+
+    /// Returns whether syscall arguments are 64 bit wide. x32 passes 64 bit
+    /// arguments like x86_64.
+    pub fn is_64bit(self) -> bool {
+        self.token() & AUDIT_ARCH_64BIT != 0
+    }
+
+    pub fn is_big_endian(self) -> bool {
+        self.token() & AUDIT_ARCH_LE == 0
+    }
+
+    /// Resolves the syscall number of the architecture by name
+    pub fn syscall_number(self, name: &str) -> Option<u32> {
+        fn lookup<T: FromStr>(name: &str, id: impl Fn(T) -> i32) -> Option<u32> {
+            T::from_str(name).ok().map(|sysno| id(sysno) as u32)
+        }
+
+        match self {
+            Self::X86 => lookup(name, |s: x86::Sysno| s.id()),
+            Self::X86_64 => lookup(name, |s: x86_64::Sysno| s.id()),
+            Self::X32 => {
+                if let Some((_, nr)) = X32_SYSCALLS.iter().find(|(n, _)| *n == name) {
+                    return Some(nr | X32_SYSCALL_BIT);
+                }
+                if X86_64_ONLY_SYSCALLS.contains(&name) {
+                    return None;
+                }
+                lookup(name, |s: x86_64::Sysno| s.id()).map(|nr| nr | X32_SYSCALL_BIT)
+            }
+            Self::Arm => lookup(name, |s: arm::Sysno| s.id()).or_else(|| {
+                ARM_PRIVATE_SYSCALLS
+                    .iter()
+                    .find(|(n, _)| *n == name)
+                    .map(|(_, nr)| *nr)
+            }),
+            Self::Aarch64 => lookup(name, |s: aarch64::Sysno| s.id()),
+            Self::Mips | Self::Mipsel => lookup(name, |s: mips::Sysno| s.id()),
+            Self::Mips64 | Self::Mipsel64 => lookup(name, |s: mips64::Sysno| s.id()),
+            Self::Ppc => lookup(name, |s: powerpc::Sysno| s.id()),
+            Self::Ppc64 | Self::Ppc64le => lookup(name, |s: powerpc64::Sysno| s.id()),
+            Self::S390x => lookup(name, |s: s390x::Sysno| s.id()),
+            Self::Riscv64 => lookup(name, |s: riscv64::Sysno| s.id()),
+        }
+    }
+
+    /// Returns the multiplexer syscall, its number and the call with which the syscall
+    /// can also be invoked on the architecture, e.g. socketcall(SYS_SOCKET, ...) for socket.
+    pub fn multiplexed(self, name: &str) -> Option<(&'static str, u32, u32)> {
+        [("socketcall", &SOCKETCALLS[..]), ("ipc", &IPC_CALLS[..])]
+            .into_iter()
+            .find_map(|(multiplexer, calls)| {
+                let (_, call) = calls.iter().find(|(n, _)| *n == name)?;
+                Some((multiplexer, self.syscall_number(multiplexer)?, *call))
+            })
+    }
+}
+
+impl Display for Arch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::X86 => "x86",
+            Self::X86_64 => "x86_64",
+            Self::X32 => "x32",
+            Self::Arm => "arm",
+            Self::Aarch64 => "aarch64",
+            Self::Mips => "mips",
+            Self::Mipsel => "mipsel",
+            Self::Mips64 => "mips64",
+            Self::Mipsel64 => "mipsel64",
+            Self::Ppc => "ppc",
+            Self::Ppc64 => "ppc64",
+            Self::Ppc64le => "ppc64le",
+            Self::S390x => "s390x",
+            Self::Riscv64 => "riscv64",
+        };
+        write!(f, "{name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syscall_number() {
+        assert_eq!(Arch::X86_64.syscall_number("read"), Some(0));
+        assert_eq!(Arch::X86_64.syscall_number("socketcall"), None);
+        assert_eq!(Arch::X86.syscall_number("socket"), Some(359));
+        assert_eq!(Arch::Aarch64.syscall_number("openat"), Some(56));
+        assert_eq!(Arch::Mipsel.syscall_number("socket"), Some(4183));
+        assert_eq!(Arch::Arm.syscall_number("cacheflush"), Some(0xf0002));
+        assert_eq!(Arch::X86_64.syscall_number("no_such_syscall"), None);
+    }
+
+    #[test]
+    fn test_x32_syscall_number() {
+        assert_eq!(Arch::X32.syscall_number("read"), Some(0x4000_0000));
+        assert_eq!(Arch::X32.syscall_number("ioctl"), Some(0x4000_0202));
+        assert_eq!(Arch::X32.syscall_number("uselib"), None);
+        assert_eq!(Arch::X32.syscall_number("set_thread_area"), None);
+    }
+
+    #[test]
+    fn test_multiplexed() {
+        assert_eq!(
+            Arch::X86.multiplexed("socket"),
+            Some(("socketcall", 102, 1))
+        );
+        assert_eq!(Arch::X86.multiplexed("shmdt"), Some(("ipc", 117, 22)));
+        assert_eq!(
+            Arch::Ppc64le.multiplexed("accept4"),
+            Some(("socketcall", 102, 18))
+        );
+        assert_eq!(Arch::X86_64.multiplexed("socket"), None);
+        assert_eq!(Arch::Arm.multiplexed("socket"), None);
+        assert_eq!(Arch::X86.multiplexed("read"), None);
+    }
+
+    #[test]
+    fn test_from_spec() {
+        assert_eq!(
+            Arch::from_spec(runtime::Arch::ScmpArchPpc64le).unwrap(),
+            Arch::Ppc64le
+        );
+        assert!(Arch::from_spec(runtime::Arch::ScmpArchNative).is_ok());
+        assert!(matches!(
+            Arch::from_spec(runtime::Arch::ScmpArchMips64n32),
+            Err(SeccompError::UnsupportedArch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_from_name() {
+        for arch in Arch::ALL {
+            assert_eq!(Arch::from_name(&arch.to_string()), Some(arch));
+        }
+        assert_eq!(Arch::from_name("SCMP_ARCH_X86_64"), None);
+    }
+}
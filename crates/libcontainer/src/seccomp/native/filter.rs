@@ -0,0 +1,889 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+use oci_spec::runtime::{
+    LinuxSeccomp, LinuxSeccompAction, LinuxSeccompFilterFlag, LinuxSeccompOperator,
+};
+
+use super::arch::{Arch, X32_SYSCALL_BIT};
+use super::bpf::*;
+use crate::seccomp::{SeccompError, is_notify};
+
+type Result<T> = std::result::Result<T, SeccompError>;
+
+/// Translates an action of the spec into the value returned by the filter
+pub fn translate_action(action: LinuxSeccompAction, errno: Option<u32>) -> Result<u32> {
+    let errno = errno.map(|e| e as i32).unwrap_or(libc::EPERM);
+    let action = match action {
+        LinuxSeccompAction::ScmpActKill | LinuxSeccompAction::ScmpActKillThread => {
+            SECCOMP_RET_KILL_THREAD
+        }
+        LinuxSeccompAction::ScmpActKillProcess => SECCOMP_RET_KILL_PROCESS,
+        LinuxSeccompAction::ScmpActTrap => SECCOMP_RET_TRAP,
+        LinuxSeccompAction::ScmpActErrno => SECCOMP_RET_ERRNO | (errno as u32 & SECCOMP_RET_DATA),
+        LinuxSeccompAction::ScmpActTrace => {
+            // the message passed to the tracer is a signed 16 bit value in libseccomp
+            let msg: i16 = errno
+                .try_into()
+                .map_err(|err| SeccompError::TraceAction { source: err, errno })?;
+            SECCOMP_RET_TRACE | (msg as u16 as u32)
+        }
+        LinuxSeccompAction::ScmpActAllow => SECCOMP_RET_ALLOW,
+        LinuxSeccompAction::ScmpActNotify => SECCOMP_RET_USER_NOTIF,
+        LinuxSeccompAction::ScmpActLog => SECCOMP_RET_LOG,
+    };
+
+    Ok(action)
+}
+
+/// A comparison of a syscall argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Comparison {
+    pub index: usize,
+    pub op: LinuxSeccompOperator,
+    pub value: u64,
+    /// mask of SCMP_CMP_MASKED_EQ, the masked argument is compared to the masked value
+    pub value_two: u64,
+}
+
+impl Comparison {
+    /// Evaluates the comparison for the argument. Architectures with 32 bit
+    /// arguments only compare the lower halves.
+    pub fn matches(&self, arg: u64, is_64bit: bool) -> bool {
+        let truncate = |v: u64| if is_64bit { v } else { v as u32 as u64 };
+        let (arg, value, mask) = (truncate(arg), truncate(self.value), self.value_two);
+        match self.op {
+            LinuxSeccompOperator::ScmpCmpNe => arg != value,
+            LinuxSeccompOperator::ScmpCmpLt => arg < value,
+            LinuxSeccompOperator::ScmpCmpLe => arg <= value,
+            LinuxSeccompOperator::ScmpCmpEq => arg == value,
+            LinuxSeccompOperator::ScmpCmpGe => arg >= value,
+            LinuxSeccompOperator::ScmpCmpGt => arg > value,
+            LinuxSeccompOperator::ScmpCmpMaskedEq => arg & mask == value & mask,
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            LinuxSeccompOperator::ScmpCmpNe => "!=",
+            LinuxSeccompOperator::ScmpCmpLt => "<",
+            LinuxSeccompOperator::ScmpCmpLe => "<=",
+            LinuxSeccompOperator::ScmpCmpEq => "==",
+            LinuxSeccompOperator::ScmpCmpGe => ">=",
+            LinuxSeccompOperator::ScmpCmpGt => ">",
+            LinuxSeccompOperator::ScmpCmpMaskedEq => {
+                return write!(
+                    f,
+                    "arg{} & {:#x} == {:#x}",
+                    self.index, self.value_two, self.value
+                );
+            }
+        };
+        write!(f, "arg{} {op} {:#x}", self.index, self.value)
+    }
+}
+
+/// Comparisons which decide on the action when all of them match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    pub comparisons: Vec<Comparison>,
+    pub action: u32,
+}
+
+impl Condition {
+    pub fn matches(&self, args: &[u64; 6], is_64bit: bool) -> bool {
+        self.comparisons
+            .iter()
+            .all(|c| c.matches(args[c.index], is_64bit))
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, comparison) in self.comparisons.iter().enumerate() {
+            if i > 0 {
+                write!(f, " && ")?;
+            }
+            write!(f, "{comparison}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The rules of a syscall within a section. An unconditional action takes precedence
+/// over all conditions, otherwise the first matching condition decides.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyscallRules {
+    pub name: String,
+    pub action: Option<u32>,
+    pub conditions: Vec<Condition>,
+}
+
+/// The rules for the architectures which share an audit arch, i.e. x86_64 and x32
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub archs: Vec<Arch>,
+    pub syscalls: BTreeMap<u32, SyscallRules>,
+}
+
+impl Section {
+    pub fn token(&self) -> u32 {
+        self.archs[0].token()
+    }
+
+    /// Returns whether the arguments of the syscall are compared as 64 bit values.
+    /// libseccomp compares only the lower halves for x32.
+    pub fn is_64bit(&self, nr: u32) -> bool {
+        let x32 = self.archs.contains(&Arch::X32) && nr & X32_SYSCALL_BIT != 0;
+        self.archs[0].is_64bit() && !x32
+    }
+
+    fn add_rule(&mut self, nr: u32, name: &str, action: u32, conditions: Vec<Condition>) {
+        let rules = self.syscalls.entry(nr).or_insert_with(|| SyscallRules {
+            name: name.to_owned(),
+            action: None,
+            conditions: Vec::new(),
+        });
+        if rules.action.is_some() {
+            // the first unconditional rule wins, like in libseccomp
+            return;
+        }
+        if conditions.is_empty() {
+            rules.action = Some(action);
+            rules.conditions.clear();
+        } else {
+            rules.conditions.extend(conditions);
+        }
+    }
+}
+
+/// The reason a filter decided on an action for a syscall
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reason<'a> {
+    /// the audit arch is not one of the filter
+    UnknownArch,
+    /// the syscall belongs to the other ABI of the audit arch, i.e. x86_64 or x32
+    UnknownAbi,
+    /// the syscall has an unconditional rule
+    Unconditional(&'a SyscallRules),
+    /// the condition is the first of the syscall which matches the arguments
+    Condition(&'a SyscallRules, &'a Condition),
+    /// no rule of the filter matches
+    Default,
+}
+
+/// An action of a filter together with the reason for it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision<'a> {
+    pub action: u32,
+    pub reason: Reason<'a>,
+}
+
+/// A seccomp filter, translated from the spec into rules per architecture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    pub default_action: u32,
+    /// SECCOMP_FILTER_FLAG_* used when loading the filter
+    pub flags: u32,
+    pub sections: Vec<Section>,
+}
+
+impl Filter {
+    pub fn from_spec(seccomp: &LinuxSeccomp) -> Result<Self> {
+        let default_action =
+            translate_action(seccomp.default_action(), seccomp.default_errno_ret())?;
+
+        // the native architecture is always part of the filter, as it is in libseccomp
+        let mut sections: Vec<Section> = Vec::new();
+        let archs = seccomp.architectures().iter().flatten();
+        for arch in std::iter::once(Arch::native()).chain(archs.map(|a| Arch::from_spec(*a))) {
+            let arch = arch?;
+            match sections.iter_mut().find(|s| s.token() == arch.token()) {
+                Some(section) if !section.archs.contains(&arch) => section.archs.push(arch),
+                Some(_) => {}
+                None => sections.push(Section {
+                    archs: vec![arch],
+                    syscalls: BTreeMap::new(),
+                }),
+            }
+        }
+
+        for syscall in seccomp.syscalls().iter().flatten() {
+            let action = translate_action(syscall.action(), syscall.errno_ret())?;
+            if action == default_action {
+                tracing::warn!(
+                    "detect a seccomp action that is the same as the default action: {:?}",
+                    syscall
+                );
+                continue;
+            }
+
+            // each argument is a separate rule, the syscall matches if any of them does
+            let comparisons: Vec<Comparison> = syscall
+                .args()
+                .iter()
+                .flatten()
+                .map(|arg| Comparison {
+                    index: arg.index(),
+                    op: arg.op(),
+                    value: arg.value(),
+                    value_two: arg.value_two().unwrap_or(0),
+                })
+                .collect();
+            let conditions: Vec<Condition> = comparisons
+                .iter()
+                .map(|comparison| Condition {
+                    comparisons: vec![*comparison],
+                    action,
+                })
+                .collect();
+
+            for name in syscall.names() {
+                let mut resolved = false;
+                for section in &mut sections {
+                    for arch in section.archs.clone() {
+                        if let Some(nr) = arch.syscall_number(name) {
+                            section.add_rule(nr, name, action, conditions.clone());
+                            resolved = true;
+                        }
+                        if let Some((multiplexer, nr, call)) = arch.multiplexed(name) {
+                            let conditions = multiplexed_conditions(call, &comparisons, action);
+                            section.add_rule(nr, multiplexer, action, conditions);
+                            resolved = true;
+                        }
+                    }
+                }
+                if !resolved {
+                    tracing::warn!(
+                        "failed to resolve syscall, likely kernel doesn't support this. {:?}",
+                        name
+                    );
+                }
+            }
+        }
+
+        // conditions on later arguments are checked first, which is the order
+        // libseccomp evaluates rules with overlapping conditions in
+        for section in &mut sections {
+            for rules in section.syscalls.values_mut() {
+                rules
+                    .conditions
+                    .sort_by_key(|c| Reverse(c.comparisons.iter().map(|c| c.index).max()));
+            }
+        }
+
+        let mut flags = 0;
+        for flag in seccomp.flags().iter().flatten() {
+            flags |= match flag {
+                LinuxSeccompFilterFlag::SeccompFilterFlagLog => libc::SECCOMP_FILTER_FLAG_LOG,
+                LinuxSeccompFilterFlag::SeccompFilterFlagTsync => libc::SECCOMP_FILTER_FLAG_TSYNC,
+                LinuxSeccompFilterFlag::SeccompFilterFlagSpecAllow => {
+                    libc::SECCOMP_FILTER_FLAG_SPEC_ALLOW
+                }
+                LinuxSeccompFilterFlag::SeccompFilterFlagWaitKillableRecv => {
+                    libc::SECCOMP_FILTER_FLAG_WAIT_KILLABLE_RECV
+                }
+            } as u32;
+        }
+        if is_notify(seccomp) {
+            flags |= libc::SECCOMP_FILTER_FLAG_NEW_LISTENER as u32;
+            // TSYNC reports failures with the thread id, which is not distinguishable
+            // from the listener fd without TSYNC_ESRCH
+            if flags & libc::SECCOMP_FILTER_FLAG_TSYNC as u32 != 0 {
+                flags |= libc::SECCOMP_FILTER_FLAG_TSYNC_ESRCH as u32;
+            }
+        }
+
+        Ok(Self {
+            default_action,
+            flags,
+            sections,
+        })
+    }
+
+    /// Evaluates the rules for a syscall of the architecture identified by the audit
+    /// arch, without generating the program
+    pub fn decide(&self, token: u32, nr: u32, args: &[u64; 6]) -> u32 {
+        self.explain(token, nr, args).action
+    }
+
+    /// Like decide, but also returns the rule which decided on the action
+    pub fn explain(&self, token: u32, nr: u32, args: &[u64; 6]) -> Decision<'_> {
+        let kill = |reason| Decision {
+            action: SECCOMP_RET_KILL_THREAD,
+            reason,
+        };
+        let Some(section) = self.sections.iter().find(|s| s.token() == token) else {
+            return kill(Reason::UnknownArch);
+        };
+        if token == Arch::X86_64.token() {
+            let x86_64 = section.archs.contains(&Arch::X86_64);
+            let x32 = section.archs.contains(&Arch::X32);
+            let is_x32 = nr & X32_SYSCALL_BIT != 0;
+            let mismatch = match (x86_64, x32) {
+                (true, false) => is_x32 && nr != u32::MAX,
+                (false, true) => !is_x32,
+                _ => false,
+            };
+            if mismatch {
+                return kill(Reason::UnknownAbi);
+            }
+        }
+
+        let default = Decision {
+            action: self.default_action,
+            reason: Reason::Default,
+        };
+        let is_64bit = section.is_64bit(nr);
+        match section.syscalls.get(&nr) {
+            Some(
+                rules @ SyscallRules {
+                    action: Some(action),
+                    ..
+                },
+            ) => Decision {
+                action: *action,
+                reason: Reason::Unconditional(rules),
+            },
+            Some(rules) => rules
+                .conditions
+                .iter()
+                .find(|c| c.matches(args, is_64bit))
+                .map_or(default, |c| Decision {
+                    action: c.action,
+                    reason: Reason::Condition(rules, c),
+                }),
+            None => default,
+        }
+    }
+
+    /// Generates the BPF program of the filter
+    pub fn to_bpf(&self) -> Result<Vec<Instruction>> {
+        let mut program = vec![Instruction::stmt(
+            BPF_LD | BPF_W | BPF_ABS,
+            SECCOMP_DATA_ARCH_OFFSET,
+        )];
+        for section in &self.sections {
+            let body = self.section_bpf(section);
+            program.extend(guard(BPF_JEQ, section.token(), body.len()));
+            program.extend(body);
+        }
+        // syscalls of other architectures are never allowed
+        program.push(ret(SECCOMP_RET_KILL_THREAD));
+
+        if program.len() > BPF_MAXINSNS {
+            return Err(SeccompError::FilterTooLarge { len: program.len() });
+        }
+
+        Ok(program)
+    }
+
+    fn section_bpf(&self, section: &Section) -> Vec<Instruction> {
+        let mut body = vec![Instruction::stmt(
+            BPF_LD | BPF_W | BPF_ABS,
+            SECCOMP_DATA_NR_OFFSET,
+        )];
+
+        // x86_64 and x32 share the audit arch, so the x32 bit of the syscall number
+        // tells them apart. -1 is used by tracers to skip a syscall.
+        let x86_64 = section.archs.contains(&Arch::X86_64);
+        let x32 = section.archs.contains(&Arch::X32);
+        if x86_64 && !x32 {
+            body.push(Instruction::jump(
+                BPF_JMP | BPF_JGE | BPF_K,
+                X32_SYSCALL_BIT,
+                0,
+                2,
+            ));
+            body.push(Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, u32::MAX, 1, 0));
+            body.push(ret(SECCOMP_RET_KILL_THREAD));
+        } else if x32 && !x86_64 {
+            body.push(Instruction::jump(
+                BPF_JMP | BPF_JGE | BPF_K,
+                X32_SYSCALL_BIT,
+                1,
+                0,
+            ));
+            body.push(ret(SECCOMP_RET_KILL_THREAD));
+        }
+
+        // syscalls with the same unconditional action share the return
+        let mut groups: Vec<(u32, Vec<u32>)> = Vec::new();
+        for (nr, rules) in &section.syscalls {
+            let Some(action) = rules.action else {
+                continue;
+            };
+            match groups.iter_mut().find(|(a, _)| *a == action) {
+                Some((_, nrs)) => nrs.push(*nr),
+                None => groups.push((action, vec![*nr])),
+            }
+        }
+        for (action, nrs) in &groups {
+            for chunk in nrs.chunks(u8::MAX as usize + 1) {
+                let last = chunk.len() - 1;
+                for (i, nr) in chunk.iter().enumerate() {
+                    let inst = if i < last {
+                        Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, *nr, (last - i) as u8, 0)
+                    } else {
+                        Instruction::jump(BPF_JMP | BPF_JEQ | BPF_K, *nr, 0, 1)
+                    };
+                    body.push(inst);
+                }
+                body.push(ret(*action));
+            }
+        }
+
+        let big_endian = section.archs[0].is_big_endian();
+        for (nr, rules) in &section.syscalls {
+            if rules.action.is_some() {
+                continue;
+            }
+            let is_64bit = section.is_64bit(*nr);
+            let mut block = Vec::new();
+            for condition in &rules.conditions {
+                block.extend(condition_bpf(condition, is_64bit, big_endian));
+                block.push(ret(condition.action));
+            }
+            block.push(ret(self.default_action));
+
+            body.extend(guard(BPF_JEQ, *nr, block.len()));
+            body.extend(block);
+        }
+
+        body.push(ret(self.default_action));
+        body
+    }
+}
+
+// Translates the rules of a syscall for its multiplexer. libseccomp replaces the
+// comparison of the first argument with the one of the call, and keeps the others,
+// although the multiplexer receives the arguments of the call in memory.
+fn multiplexed_conditions(call: u32, comparisons: &[Comparison], action: u32) -> Vec<Condition> {
+    let call = Comparison {
+        index: 0,
+        op: LinuxSeccompOperator::ScmpCmpEq,
+        value: call as u64,
+        value_two: 0,
+    };
+    if comparisons.is_empty() {
+        return vec![Condition {
+            comparisons: vec![call],
+            action,
+        }];
+    }
+
+    comparisons
+        .iter()
+        .map(|comparison| Condition {
+            comparisons: if comparison.index == 0 {
+                vec![call]
+            } else {
+                vec![call, *comparison]
+            },
+            action,
+        })
+        .collect()
+}
+
+fn ret(action: u32) -> Instruction {
+    Instruction::stmt(BPF_RET | BPF_K, action)
+}
+
+// Skips the following block of instructions unless the comparison is true
+fn guard(op: u16, k: u32, block_len: usize) -> Vec<Instruction> {
+    match u8::try_from(block_len) {
+        Ok(len) => vec![Instruction::jump(BPF_JMP | op | BPF_K, k, 0, len)],
+        Err(_) => vec![
+            Instruction::jump(BPF_JMP | op | BPF_K, k, 1, 0),
+            Instruction::stmt(BPF_JMP | BPF_JA, block_len as u32),
+        ],
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    Next,
+    // the end of the comparison
+    Pass,
+    // the end of the condition, after the return of its action
+    Fail,
+}
+
+enum Step {
+    Load(u32),
+    And(u32),
+    Jump {
+        op: u16,
+        k: u32,
+        jt: Target,
+        jf: Target,
+    },
+}
+
+// Generates the comparisons of a condition, which are followed by the return of its
+// action. The instruction after the return is reached when the condition fails.
+fn condition_bpf(condition: &Condition, is_64bit: bool, big_endian: bool) -> Vec<Instruction> {
+    // each step jumps to the end of its comparison when it passes
+    let mut steps = Vec::new();
+    for comparison in &condition.comparisons {
+        let comparison_steps = comparison_steps(comparison, is_64bit, big_endian);
+        let pass = steps.len() + comparison_steps.len();
+        steps.extend(comparison_steps.into_iter().map(|step| (step, pass)));
+    }
+
+    let fail = steps.len() + 1;
+    let offset = |target: Target, pc: usize, pass: usize| {
+        let target = match target {
+            Target::Next => return 0,
+            Target::Pass => pass,
+            Target::Fail => fail,
+        };
+        (target - pc - 1) as u8
+    };
+    steps
+        .iter()
+        .enumerate()
+        .map(|(pc, (step, pass))| match *step {
+            Step::Load(offset) => Instruction::stmt(BPF_LD | BPF_W | BPF_ABS, offset),
+            Step::And(k) => Instruction::stmt(BPF_ALU | BPF_AND | BPF_K, k),
+            Step::Jump { op, k, jt, jf } => Instruction::jump(
+                BPF_JMP | op | BPF_K,
+                k,
+                offset(jt, pc, *pass),
+                offset(jf, pc, *pass),
+            ),
+        })
+        .collect()
+}
+
+fn comparison_steps(comparison: &Comparison, is_64bit: bool, big_endian: bool) -> Vec<Step> {
+    let offset = SECCOMP_DATA_ARGS_OFFSET + 8 * comparison.index as u32;
+    let (lo, hi) = if big_endian {
+        (offset + 4, offset)
+    } else {
+        (offset, offset + 4)
+    };
+    let (value_lo, value_hi) = (comparison.value as u32, (comparison.value >> 32) as u32);
+    let (mask_lo, mask_hi) = (
+        comparison.value_two as u32,
+        (comparison.value_two >> 32) as u32,
+    );
+
+    // NE, LT and LE are the inverted EQ, GE and GT comparisons
+    let (op, inverted) = match comparison.op {
+        LinuxSeccompOperator::ScmpCmpNe => (LinuxSeccompOperator::ScmpCmpEq, true),
+        LinuxSeccompOperator::ScmpCmpLt => (LinuxSeccompOperator::ScmpCmpGe, true),
+        LinuxSeccompOperator::ScmpCmpLe => (LinuxSeccompOperator::ScmpCmpGt, true),
+        op => (op, false),
+    };
+    let (pass, fail) = if inverted {
+        (Target::Fail, Target::Pass)
+    } else {
+        (Target::Pass, Target::Fail)
+    };
+    let jump = |op, k, jt, jf| Step::Jump { op, k, jt, jf };
+
+    let mut steps = Vec::new();
+    if is_64bit {
+        steps.push(Step::Load(hi));
+        match op {
+            LinuxSeccompOperator::ScmpCmpGt | LinuxSeccompOperator::ScmpCmpGe => {
+                steps.push(jump(BPF_JGT, value_hi, pass, Target::Next));
+                steps.push(jump(BPF_JEQ, value_hi, Target::Next, fail));
+            }
+            LinuxSeccompOperator::ScmpCmpMaskedEq => {
+                steps.push(Step::And(mask_hi));
+                steps.push(jump(BPF_JEQ, value_hi & mask_hi, Target::Next, fail));
+            }
+            _ => steps.push(jump(BPF_JEQ, value_hi, Target::Next, fail)),
+        }
+    }
+    steps.push(Step::Load(lo));
+    match op {
+        LinuxSeccompOperator::ScmpCmpGt => steps.push(jump(BPF_JGT, value_lo, pass, fail)),
+        LinuxSeccompOperator::ScmpCmpGe => steps.push(jump(BPF_JGE, value_lo, pass, fail)),
+        LinuxSeccompOperator::ScmpCmpMaskedEq => {
+            steps.push(Step::And(mask_lo));
+            steps.push(jump(BPF_JEQ, value_lo & mask_lo, pass, fail));
+        }
+        _ => steps.push(jump(BPF_JEQ, value_lo, pass, fail)),
+    }
+
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use oci_spec::runtime::{
+        Arch as SpecArch, LinuxSeccompArgBuilder, LinuxSeccompBuilder, LinuxSyscallBuilder,
+    };
+
+    use super::*;
+
+    fn comparison(index: usize, op: LinuxSeccompOperator, value: u64) -> Comparison {
+        Comparison {
+            index,
+            op,
+            value,
+            value_two: 0xffff_0001_0000_0001,
+        }
+    }
+
+    fn condition(comparisons: Vec<Comparison>) -> Condition {
+        Condition {
+            comparisons,
+            action: SECCOMP_RET_ALLOW,
+        }
+    }
+
+    fn evaluate_condition(condition: &Condition, args: [u64; 6], is_64bit: bool) -> bool {
+        let mut program = condition_bpf(condition, is_64bit, false);
+        program.push(ret(condition.action));
+        program.push(ret(SECCOMP_RET_KILL_THREAD));
+        let data = SeccompData {
+            arch: AUDIT_ARCH_LE,
+            args,
+            ..Default::default()
+        };
+        evaluate(&program, &data).unwrap() == SECCOMP_RET_ALLOW
+    }
+
+    #[test]
+    fn test_condition_bpf() {
+        let ops = [
+            LinuxSeccompOperator::ScmpCmpNe,
+            LinuxSeccompOperator::ScmpCmpLt,
+            LinuxSeccompOperator::ScmpCmpLe,
+            LinuxSeccompOperator::ScmpCmpEq,
+            LinuxSeccompOperator::ScmpCmpGe,
+            LinuxSeccompOperator::ScmpCmpGt,
+            LinuxSeccompOperator::ScmpCmpMaskedEq,
+        ];
+        let values = [0, 1, 0xffff_ffff, 0x1_0000_0000, 0x1_0000_0001, u64::MAX];
+        for op in ops {
+            for value in values {
+                for arg in values {
+                    for is_64bit in [true, false] {
+                        let condition = condition(vec![comparison(1, op, value)]);
+                        let args = [0, arg, 0, 0, 0, 0];
+                        assert_eq!(
+                            evaluate_condition(&condition, args, is_64bit),
+                            condition.matches(&args, is_64bit),
+                            "{op:?} {value:#x} {arg:#x} {is_64bit}"
+                        );
+                    }
+                }
+            }
+        }
+
+        // all comparisons of a condition have to match
+        for first in ops {
+            for second in ops {
+                let condition = condition(vec![
+                    comparison(0, LinuxSeccompOperator::ScmpCmpEq, 1),
+                    comparison(2, first, 1),
+                    comparison(3, second, 0x1_0000_0000),
+                ]);
+                for arg0 in [0, 1] {
+                    for arg2 in values {
+                        for arg3 in values {
+                            let args = [arg0, 0, arg2, arg3, 0, 0];
+                            assert_eq!(
+                                evaluate_condition(&condition, args, true),
+                                condition.matches(&args, true),
+                                "{first:?} {second:?} {args:x?}"
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe_action(SECCOMP_RET_ERRNO | 1), "ERRNO(1)");
+        assert_eq!(describe_action(SECCOMP_RET_TRACE | 0xffff), "TRACE(-1)");
+        assert_eq!(describe_action(SECCOMP_RET_KILL_PROCESS), "KILL_PROCESS");
+        let condition = condition(vec![
+            comparison(0, LinuxSeccompOperator::ScmpCmpEq, 1),
+            comparison(2, LinuxSeccompOperator::ScmpCmpMaskedEq, 0x10),
+        ]);
+        assert_eq!(
+            condition.to_string(),
+            "arg0 == 0x1 && arg2 & 0xffff000100000001 == 0x10"
+        );
+    }
+
+    #[test]
+    fn test_translate_action() -> Result<()> {
+        assert_eq!(
+            translate_action(LinuxSeccompAction::ScmpActErrno, None)?,
+            SECCOMP_RET_ERRNO | libc::EPERM as u32
+        );
+        assert_eq!(
+            translate_action(LinuxSeccompAction::ScmpActTrace, Some(3))?,
+            SECCOMP_RET_TRACE | 3
+        );
+        assert!(translate_action(LinuxSeccompAction::ScmpActTrace, Some(1 << 16)).is_err());
+        assert_eq!(
+            translate_action(LinuxSeccompAction::ScmpActKill, None)?,
+            SECCOMP_RET_KILL_THREAD
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_spec() -> Result<()> {
+        let seccomp = LinuxSeccompBuilder::default()
+            .default_action(LinuxSeccompAction::ScmpActErrno)
+            .architectures(vec![SpecArch::ScmpArchX86_64, SpecArch::ScmpArchX86])
+            .flags(vec![LinuxSeccompFilterFlag::SeccompFilterFlagTsync])
+            .syscalls(vec![
+                LinuxSyscallBuilder::default()
+                    .names(vec!["socket".to_owned(), "getcwd".to_owned()])
+                    .action(LinuxSeccompAction::ScmpActAllow)
+                    .args(vec![
+                        LinuxSeccompArgBuilder::default()
+                            .index(0usize)
+                            .value(1u64)
+                            .op(LinuxSeccompOperator::ScmpCmpEq)
+                            .build()?,
+                    ])
+                    .build()?,
+                LinuxSyscallBuilder::default()
+                    .names(vec!["getcwd".to_owned(), "no_such_syscall".to_owned()])
+                    .action(LinuxSeccompAction::ScmpActNotify)
+                    .build()?,
+                LinuxSyscallBuilder::default()
+                    .names(vec!["read".to_owned()])
+                    .action(LinuxSeccompAction::ScmpActErrno)
+                    .build()?,
+            ])
+            .build()?;
+
+        let filter = Filter::from_spec(&seccomp)?;
+        assert_eq!(
+            filter.flags,
+            (libc::SECCOMP_FILTER_FLAG_TSYNC
+                | libc::SECCOMP_FILTER_FLAG_NEW_LISTENER
+                | libc::SECCOMP_FILTER_FLAG_TSYNC_ESRCH) as u32
+        );
+        let x86 = filter
+            .sections
+            .iter()
+            .find(|s| s.archs == [Arch::X86])
+            .unwrap();
+        // socket is also reachable through socketcall(SYS_SOCKET, ...)
+        let socketcall = &x86.syscalls[&102];
+        assert_eq!(socketcall.name, "socketcall");
+        assert_eq!(
+            socketcall.conditions[0].comparisons,
+            [Comparison {
+                index: 0,
+                op: LinuxSeccompOperator::ScmpCmpEq,
+                value: 1,
+                value_two: 0,
+            }]
+        );
+        // the unconditional rule takes precedence over the condition
+        let getcwd = &x86.syscalls[&183];
+        assert_eq!(getcwd.action, Some(SECCOMP_RET_USER_NOTIF));
+        // rules with the default action are skipped
+        assert!(!x86.syscalls.contains_key(&3));
+
+        let program = filter.to_bpf()?;
+        let token = Arch::X86.token();
+        for (nr, args) in [
+            (359, [1, 0, 0, 0, 0, 0]),
+            (359, [2, 0, 0, 0, 0, 0]),
+            (102, [1, 0, 0, 0, 0, 0]),
+            (102, [2, 0, 0, 0, 0, 0]),
+            (183, [0; 6]),
+            (3, [0; 6]),
+        ] {
+            let data = SeccompData {
+                nr: nr as i32,
+                arch: token,
+                args,
+                ..Default::default()
+            };
+            assert_eq!(
+                evaluate(&program, &data)?,
+                filter.decide(token, nr, &args),
+                "syscall {nr}"
+            );
+        }
+        assert_eq!(
+            filter.explain(token, 359, &[2, 0, 0, 0, 0, 0]),
+            Decision {
+                action: filter.default_action,
+                reason: Reason::Default,
+            }
+        );
+        assert_eq!(
+            filter.explain(token, 183, &[0; 6]).reason,
+            Reason::Unconditional(getcwd)
+        );
+        assert!(matches!(
+            filter.explain(token, 102, &[1, 0, 0, 0, 0, 0]).reason,
+            Reason::Condition(rules, _) if rules.name == "socketcall"
+        ));
+        assert_eq!(
+            filter.explain(Arch::Arm.token(), 0, &[0; 6]).reason,
+            Reason::UnknownArch
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_jumps() -> Result<()> {
+        let seccomp = LinuxSeccompBuilder::default()
+            .default_action(LinuxSeccompAction::ScmpActErrno)
+            .build()?;
+        let mut filter = Filter::from_spec(&seccomp)?;
+        // enough syscalls to need more than one return per action and a long jump
+        // over the section
+        let section = &mut filter.sections[0];
+        for nr in 0..300 {
+            section.add_rule(
+                nr,
+                "test",
+                SECCOMP_RET_LOG,
+                vec![condition(vec![comparison(
+                    1,
+                    LinuxSeccompOperator::ScmpCmpEq,
+                    7,
+                )])],
+            );
+        }
+        for nr in 300..600 {
+            section.add_rule(nr, "test", SECCOMP_RET_ALLOW, vec![]);
+        }
+        let token = section.token();
+
+        let program = filter.to_bpf()?;
+        for nr in [0, 150, 299, 300, 450, 599, 600, u32::MAX] {
+            for arg in [0, 7] {
+                let args = [0, arg, 0, 0, 0, 0];
+                let data = SeccompData {
+                    nr: nr as i32,
+                    arch: token,
+                    args,
+                    ..Default::default()
+                };
+                assert_eq!(evaluate(&program, &data)?, filter.decide(token, nr, &args));
+            }
+        }
+        let data = SeccompData {
+            arch: 0,
+            ..Default::default()
+        };
+        assert_eq!(evaluate(&program, &data)?, SECCOMP_RET_KILL_THREAD);
+        Ok(())
+    }
+}
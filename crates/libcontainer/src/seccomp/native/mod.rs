@@ -0,0 +1,371 @@
+//! A seccomp backend which compiles the profile into a BPF program without the
+//! libseccomp C library, so that youki can be built statically, e.g. against musl.
+//! The generated programs make the same decisions as the ones of libseccomp.
+
+pub mod arch;
+pub mod filter;
+
+use std::os::unix::io::RawFd;
+
+use nix::errno::Errno;
+use oci_spec::runtime::LinuxSeccomp;
+
+pub use self::arch::Arch;
+pub use self::filter::{Comparison, Condition, Decision, Filter, Reason, Section, SyscallRules};
+pub use super::bpf::{self, EvaluateError, Instruction, SeccompData, describe_action, evaluate};
+use super::{SeccompError, check_seccomp};
+
+type Result<T> = std::result::Result<T, SeccompError>;
+
+/// Installs the program with the seccomp(2) syscall. Returns the notify fd when the
+/// filter has a listener.
+pub fn load(program: &[Instruction], flags: u32) -> Result<Option<RawFd>> {
+    let prog = libc::sock_fprog {
+        len: program.len() as u16,
+        filter: program.as_ptr() as *mut libc::sock_filter,
+    };
+    // Safety: the program outlives the call and has the layout of struct sock_filter
+    let ret = unsafe {
+        libc::syscall(
+            libc::SYS_seccomp,
+            libc::SECCOMP_SET_MODE_FILTER,
+            flags as libc::c_ulong,
+            &prog as *const libc::sock_fprog,
+        )
+    };
+    let ret = Errno::result(ret).map_err(|err| SeccompError::LoadFilter { source: err })?;
+
+    if flags & libc::SECCOMP_FILTER_FLAG_NEW_LISTENER as u32 != 0 {
+        Ok(Some(ret as RawFd))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Compiles the profile into the program which `initialize_seccomp` loads
+pub fn compile_seccomp(seccomp: &LinuxSeccomp) -> Result<Vec<Instruction>> {
+    check_seccomp(seccomp)?;
+    Filter::from_spec(seccomp)?.to_bpf()
+}
+
+/// Resolves an architecture by name, e.g. x86_64, or the native one. Returns the
+/// name and the audit arch the kernel reports for its syscalls.
+pub fn resolve_arch(name: Option<&str>) -> Result<(String, u32)> {
+    let arch = lookup_arch(name)?;
+    Ok((arch.to_string(), arch.token()))
+}
+
+/// Resolves the number of a syscall on an architecture, or the native one
+pub fn resolve_syscall(arch: Option<&str>, name: &str) -> Result<u32> {
+    let arch = lookup_arch(arch)?;
+    arch.syscall_number(name)
+        .ok_or_else(|| SeccompError::UnknownSyscall {
+            name: name.to_owned(),
+            arch: arch.to_string(),
+        })
+}
+
+fn lookup_arch(name: Option<&str>) -> Result<Arch> {
+    match name {
+        Some(name) => Arch::from_name(name).ok_or_else(|| SeccompError::UnknownArch {
+            name: name.to_owned(),
+        }),
+        None => Arch::native(),
+    }
+}
+
+#[tracing::instrument(level = "trace", skip(seccomp))]
+pub fn initialize_seccomp(seccomp: &LinuxSeccomp) -> Result<Option<RawFd>> {
+    check_seccomp(seccomp)?;
+
+    tracing::trace!(default_action = ?seccomp.default_action(), errno = ?seccomp.default_errno_ret(), "initializing seccomp");
+    let filter = Filter::from_spec(seccomp)?;
+    let program = filter.to_bpf()?;
+    tracing::trace!(
+        len = program.len(),
+        flags = filter.flags,
+        "loading seccomp filter"
+    );
+
+    // In order to use the SECCOMP_SET_MODE_FILTER operation, either the calling
+    // thread must have the CAP_SYS_ADMIN capability in its user namespace, or
+    // the thread must already have the no_new_privs bit set. The bit is not set
+    // here, since it is controlled by the spec.
+    // Ref: https://man7.org/linux/man-pages/man2/seccomp.2.html
+    load(&program, filter.flags)
+}
+
+#[cfg(all(test, feature = "libseccomp"))]
+mod tests {
+    use std::io::{Read, Seek};
+    use std::path;
+
+    use anyhow::{Context, Result};
+    use oci_spec::runtime::{
+        Arch as SpecArch, LinuxSeccompAction, LinuxSeccompArgBuilder, LinuxSeccompBuilder,
+        LinuxSeccompOperator, LinuxSyscall, LinuxSyscallBuilder,
+    };
+
+    use super::arch::X32_SYSCALL_BIT;
+    use super::*;
+    use crate::seccomp::scmp;
+
+    // The golden tests compare the decisions of the native programs with the ones
+    // of the programs generated by libseccomp for the same profile.
+
+    fn libseccomp_program(seccomp: &LinuxSeccomp) -> Result<Vec<Instruction>> {
+        let ctx = scmp::build_filter(seccomp)?;
+        let mut file = tempfile::tempfile()?;
+        ctx.export_bpf(&file)?;
+        file.rewind()?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|chunk| Instruction::from_ne_bytes(chunk.try_into().unwrap()))
+            .collect())
+    }
+
+    // The optimizer of libseccomp drops the load of the syscall number after the
+    // check of an architecture in some cases, so that the number is compared with the
+    // architecture instead. Only the chain of architecture checks at the start of the
+    // program is followed, as syscall numbers of x32 equal some architecture tokens.
+    fn loads_syscall_number(golden: &[Instruction], filter: &Filter) -> bool {
+        let load_arch = Instruction::stmt(
+            bpf::BPF_LD | bpf::BPF_W | bpf::BPF_ABS,
+            bpf::SECCOMP_DATA_ARCH_OFFSET,
+        );
+        let load_nr = Instruction::stmt(
+            bpf::BPF_LD | bpf::BPF_W | bpf::BPF_ABS,
+            bpf::SECCOMP_DATA_NR_OFFSET,
+        );
+        let tokens: Vec<u32> = filter.sections.iter().map(|s| s.token()).collect();
+        let mut pc = 0;
+        while let Some(inst) = golden.get(pc) {
+            pc += 1;
+            match inst.code {
+                _ if *inst == load_arch => {}
+                code if code == bpf::BPF_JMP | bpf::BPF_JEQ | bpf::BPF_K
+                    && tokens.contains(&inst.k) =>
+                {
+                    if golden.get(pc + inst.jt as usize) != Some(&load_nr) {
+                        return false;
+                    }
+                    pc += inst.jf as usize;
+                }
+                code if code == bpf::BPF_JMP | bpf::BPF_JA => pc += inst.k as usize,
+                _ => break,
+            }
+        }
+        true
+    }
+
+    // Interesting argument values for the conditions of the syscall
+    fn arg_values(rules: &SyscallRules, rng: &mut fastrand::Rng) -> Vec<u64> {
+        let mut values = vec![0, 1, u32::MAX as u64, u64::MAX, rng.u64(..)];
+        for comparison in rules.conditions.iter().flat_map(|c| &c.comparisons) {
+            for value in [comparison.value, comparison.value & comparison.value_two] {
+                values.extend([
+                    value,
+                    value.wrapping_add(1),
+                    value.wrapping_sub(1),
+                    value ^ (1 << 32),
+                ]);
+            }
+        }
+        values
+    }
+
+    // Returns false if the decisions could not be compared with the ones of libseccomp
+    fn assert_same_decisions(seccomp: &LinuxSeccomp, rng: &mut fastrand::Rng) -> Result<bool> {
+        let filter = Filter::from_spec(seccomp)?;
+        let native = filter.to_bpf()?;
+        // libseccomp refuses some combinations of rules, which are not compared
+        let Ok(golden) = libseccomp_program(seccomp) else {
+            return Ok(false);
+        };
+        if !loads_syscall_number(&golden, &filter) {
+            return Ok(false);
+        }
+
+        let mut probes = Vec::new();
+        for section in &filter.sections {
+            let mut nrs = vec![u32::MAX, rng.u32(..1000), rng.u32(..1000) | X32_SYSCALL_BIT];
+            nrs.extend(section.syscalls.keys());
+            for nr in nrs {
+                let values = section
+                    .syscalls
+                    .get(&nr)
+                    .map(|rules| arg_values(rules, rng))
+                    .unwrap_or_else(|| vec![0]);
+                for _ in 0..values.len() * 6 {
+                    let args = std::array::from_fn(|_| values[rng.usize(..values.len())]);
+                    probes.push((section.token(), nr, args));
+                }
+            }
+        }
+        probes.push((0, 0, [0; 6]));
+
+        for (arch, nr, args) in probes {
+            let data = SeccompData {
+                nr: nr as i32,
+                arch,
+                args,
+                ..Default::default()
+            };
+            let expected = evaluate(&golden, &data)?;
+            assert_eq!(
+                evaluate(&native, &data)?,
+                expected,
+                "arch {arch:#x} syscall {nr:#x} args {args:x?}"
+            );
+            assert_eq!(filter.decide(arch, nr, &args), expected);
+        }
+
+        Ok(true)
+    }
+
+    #[test]
+    fn test_golden_moby() -> Result<()> {
+        let fixture_path =
+            path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/seccomp/fixture/config.json");
+        let spec = oci_spec::runtime::Spec::load(fixture_path)
+            .context("Failed to load test spec for seccomp")?;
+        let mut seccomp = spec.linux().as_ref().unwrap().seccomp().clone().unwrap();
+        let mut rng = fastrand::Rng::with_seed(1);
+
+        assert!(assert_same_decisions(&seccomp, &mut rng)?);
+        if cfg!(target_arch = "x86_64") {
+            seccomp.set_architectures(Some(vec![
+                SpecArch::ScmpArchX86_64,
+                SpecArch::ScmpArchX86,
+                SpecArch::ScmpArchX32,
+            ]));
+            assert!(assert_same_decisions(&seccomp, &mut rng)?);
+        }
+        Ok(())
+    }
+
+    fn random_syscall(rng: &mut fastrand::Rng, name: &str) -> Result<LinuxSyscall> {
+        let actions = [
+            LinuxSeccompAction::ScmpActAllow,
+            LinuxSeccompAction::ScmpActErrno,
+            LinuxSeccompAction::ScmpActLog,
+            LinuxSeccompAction::ScmpActTrap,
+            LinuxSeccompAction::ScmpActTrace,
+            LinuxSeccompAction::ScmpActKillProcess,
+            LinuxSeccompAction::ScmpActNotify,
+        ];
+        let ops = [
+            LinuxSeccompOperator::ScmpCmpNe,
+            LinuxSeccompOperator::ScmpCmpLt,
+            LinuxSeccompOperator::ScmpCmpLe,
+            LinuxSeccompOperator::ScmpCmpEq,
+            LinuxSeccompOperator::ScmpCmpGe,
+            LinuxSeccompOperator::ScmpCmpGt,
+            LinuxSeccompOperator::ScmpCmpMaskedEq,
+        ];
+        let values = [
+            0,
+            1,
+            7,
+            0x8000_0000,
+            u32::MAX as u64,
+            0x1_0000_0002,
+            u64::MAX,
+        ];
+
+        // libseccomp skips loading the upper half of an argument which is compared
+        // more than once in some cases, so that the indexes of the rule are distinct
+        let first = rng.usize(..6);
+        let indexes = [first, (first + rng.usize(1..6)) % 6];
+        let mut args = Vec::new();
+        for index in indexes.into_iter().take(rng.usize(..3)) {
+            args.push(
+                LinuxSeccompArgBuilder::default()
+                    .index(index)
+                    .op(ops[rng.usize(..ops.len())])
+                    .value(values[rng.usize(..values.len())])
+                    .value_two(values[rng.usize(..values.len())])
+                    .build()?,
+            );
+        }
+
+        Ok(LinuxSyscallBuilder::default()
+            .names(vec![name.to_owned()])
+            .action(actions[rng.usize(..actions.len())])
+            .errno_ret(rng.u32(1..4))
+            .args(args)
+            .build()?)
+    }
+
+    #[test]
+    fn test_golden_random() -> Result<()> {
+        // libseccomp only combines architectures with the byte order of the native one
+        let archs = [
+            SpecArch::ScmpArchX86,
+            SpecArch::ScmpArchX86_64,
+            SpecArch::ScmpArchX32,
+            SpecArch::ScmpArchArm,
+            SpecArch::ScmpArchAarch64,
+            SpecArch::ScmpArchMipsel,
+            SpecArch::ScmpArchMipsel64,
+            SpecArch::ScmpArchPpc64le,
+            SpecArch::ScmpArchRiscv64,
+        ];
+        let names = [
+            "read",
+            "write",
+            "getcwd",
+            "personality",
+            "ioctl",
+            "clone",
+            "mmap",
+            "socket",
+            "bind",
+            "accept4",
+            "shmdt",
+            "semop",
+            "setsockopt",
+            "execve",
+            "cacheflush",
+        ];
+        let mut rng = fastrand::Rng::with_seed(29);
+
+        let mut compared = 0;
+        for _ in 0..200 {
+            let mut syscalls = Vec::new();
+            for name in names {
+                if rng.bool() {
+                    syscalls.push(random_syscall(&mut rng, name)?);
+                }
+            }
+            // an unconditional rule of a syscall with conditions takes precedence
+            if rng.bool() {
+                let name = names[rng.usize(..names.len())];
+                let mut syscall = random_syscall(&mut rng, name)?;
+                syscall.set_args(None);
+                syscalls.push(syscall);
+            }
+
+            let seccomp = LinuxSeccompBuilder::default()
+                .default_action(LinuxSeccompAction::ScmpActErrno)
+                .architectures(
+                    archs
+                        .iter()
+                        .filter(|_| rng.bool())
+                        .copied()
+                        .collect::<Vec<_>>(),
+                )
+                .syscalls(syscalls)
+                .build()?;
+            if assert_same_decisions(&seccomp, &mut rng)? {
+                compared += 1;
+            }
+        }
+        // most profiles have to be compared for the test to mean anything
+        assert!(compared >= 150, "only {compared} profiles were compared");
+        Ok(())
+    }
+}
@@ -0,0 +1,330 @@
+use std::fs::File;
+use std::io::{Read, Seek};
+use std::os::fd::{AsFd, FromRawFd};
+use std::os::unix::io;
+
+use libseccomp::{
+    ScmpAction, ScmpArch, ScmpArgCompare, ScmpCompareOp, ScmpFilterContext, ScmpSyscall,
+};
+use oci_spec::runtime::{
+    Arch, LinuxSeccomp, LinuxSeccompAction, LinuxSeccompFilterFlag, LinuxSeccompOperator,
+};
+
+use super::bpf::{AUDIT_ARCH_64BIT, AUDIT_ARCH_LE, Instruction};
+use super::{SeccompError, check_seccomp, is_notify};
+
+type Result<T> = std::result::Result<T, SeccompError>;
+
+fn translate_arch(arch: Arch) -> ScmpArch {
+    match arch {
+        Arch::ScmpArchNative => ScmpArch::Native,
+        Arch::ScmpArchX86 => ScmpArch::X86,
+        Arch::ScmpArchX86_64 => ScmpArch::X8664,
+        Arch::ScmpArchX32 => ScmpArch::X32,
+        Arch::ScmpArchArm => ScmpArch::Arm,
+        Arch::ScmpArchAarch64 => ScmpArch::Aarch64,
+        Arch::ScmpArchMips => ScmpArch::Mips,
+        Arch::ScmpArchMips64 => ScmpArch::Mips64,
+        Arch::ScmpArchMips64n32 => ScmpArch::Mips64N32,
+        Arch::ScmpArchMipsel => ScmpArch::Mipsel,
+        Arch::ScmpArchMipsel64 => ScmpArch::Mipsel64,
+        Arch::ScmpArchMipsel64n32 => ScmpArch::Mipsel64N32,
+        Arch::ScmpArchPpc => ScmpArch::Ppc,
+        Arch::ScmpArchPpc64 => ScmpArch::Ppc64,
+        Arch::ScmpArchPpc64le => ScmpArch::Ppc64Le,
+        Arch::ScmpArchS390 => ScmpArch::S390,
+        Arch::ScmpArchS390x => ScmpArch::S390X,
+        Arch::ScmpArchRiscv64 => ScmpArch::Riscv64,
+    }
+}
+
+fn translate_action(action: LinuxSeccompAction, errno: Option<u32>) -> Result<ScmpAction> {
+    tracing::trace!(?action, ?errno, "translating action");
+    let errno = errno.map(|e| e as i32).unwrap_or(libc::EPERM);
+    let action = match action {
+        LinuxSeccompAction::ScmpActKill => ScmpAction::KillThread,
+        LinuxSeccompAction::ScmpActTrap => ScmpAction::Trap,
+        LinuxSeccompAction::ScmpActErrno => ScmpAction::Errno(errno),
+        LinuxSeccompAction::ScmpActTrace => ScmpAction::Trace(
+            errno
+                .try_into()
+                .map_err(|err| SeccompError::TraceAction { source: err, errno })?,
+        ),
+        LinuxSeccompAction::ScmpActAllow => ScmpAction::Allow,
+        LinuxSeccompAction::ScmpActKillProcess => ScmpAction::KillProcess,
+        LinuxSeccompAction::ScmpActNotify => ScmpAction::Notify,
+        LinuxSeccompAction::ScmpActLog => ScmpAction::Log,
+        LinuxSeccompAction::ScmpActKillThread => ScmpAction::KillThread,
+    };
+
+    tracing::trace!(?action, "translated action");
+    Ok(action)
+}
+
+fn translate_op(op: LinuxSeccompOperator, datum_b: Option<u64>) -> ScmpCompareOp {
+    match op {
+        LinuxSeccompOperator::ScmpCmpNe => ScmpCompareOp::NotEqual,
+        LinuxSeccompOperator::ScmpCmpLt => ScmpCompareOp::Less,
+        LinuxSeccompOperator::ScmpCmpLe => ScmpCompareOp::LessOrEqual,
+        LinuxSeccompOperator::ScmpCmpEq => ScmpCompareOp::Equal,
+        LinuxSeccompOperator::ScmpCmpGe => ScmpCompareOp::GreaterEqual,
+        LinuxSeccompOperator::ScmpCmpGt => ScmpCompareOp::Greater,
+        LinuxSeccompOperator::ScmpCmpMaskedEq => ScmpCompareOp::MaskedEqual(datum_b.unwrap_or(0)),
+    }
+}
+
+/// Builds the libseccomp filter context of the profile, without loading it
+pub fn build_filter(seccomp: &LinuxSeccomp) -> Result<ScmpFilterContext> {
+    tracing::trace!(default_action = ?seccomp.default_action(), errno = ?seccomp.default_errno_ret(), "initializing seccomp");
+    let default_action = translate_action(seccomp.default_action(), seccomp.default_errno_ret())?;
+    let mut ctx =
+        ScmpFilterContext::new(default_action).map_err(|err| SeccompError::NewFilter {
+            source: err,
+            default: seccomp.default_action(),
+        })?;
+
+    if let Some(flags) = seccomp.flags() {
+        for flag in flags {
+            match flag {
+                LinuxSeccompFilterFlag::SeccompFilterFlagLog => ctx.set_ctl_log(true),
+                LinuxSeccompFilterFlag::SeccompFilterFlagTsync => ctx.set_ctl_tsync(true),
+                LinuxSeccompFilterFlag::SeccompFilterFlagSpecAllow => ctx.set_ctl_ssb(true),
+                LinuxSeccompFilterFlag::SeccompFilterFlagWaitKillableRecv => {
+                    ctx.set_ctl_waitkill(true)
+                }
+            }
+            .map_err(|err| SeccompError::SetFilterFlag {
+                source: err,
+                flag: *flag,
+            })?;
+        }
+    }
+
+    if let Some(architectures) = seccomp.architectures() {
+        for &arch in architectures {
+            tracing::trace!(?arch, "adding architecture");
+            ctx.add_arch(translate_arch(arch))
+                .map_err(|err| SeccompError::AddArch { source: err, arch })?;
+        }
+    }
+
+    // The SCMP_FLTATR_CTL_NNP controls if the seccomp load function will set
+    // the new privilege bit automatically in prctl. Normally this is a good
+    // thing, but for us we need better control. Based on the spec, if OCI
+    // runtime spec doesn't set the no new privileges in Process, we should not
+    // set it here.  If the seccomp load operation fails without enough
+    // privilege, so be it. To prevent this automatic behavior, we unset the
+    // value here.
+    ctx.set_ctl_nnp(false)
+        .map_err(|err| SeccompError::SetCtlNnp { source: err })?;
+
+    if let Some(syscalls) = seccomp.syscalls() {
+        for syscall in syscalls {
+            let action = translate_action(syscall.action(), syscall.errno_ret())?;
+            if action == default_action {
+                // When the action is the same as the default action, the rule is redundant. We can
+                // skip this here to avoid failing when we add the rules.
+                tracing::warn!(
+                    "detect a seccomp action that is the same as the default action: {:?}",
+                    syscall
+                );
+                continue;
+            }
+
+            for name in syscall.names() {
+                let sc = match ScmpSyscall::from_name(name) {
+                    Ok(x) => x,
+                    Err(_) => {
+                        // If we failed to resolve the syscall by name, likely the kernel
+                        // doeesn't support this syscall. So it is safe to skip...
+                        tracing::warn!(
+                            "failed to resolve syscall, likely kernel doesn't support this. {:?}",
+                            name
+                        );
+                        continue;
+                    }
+                };
+                match syscall.args() {
+                    // an empty list of args does not restrict the rule
+                    Some(args) if !args.is_empty() => {
+                        // The `seccomp_rule_add` requires us to break multiple
+                        // args attaching to the same rules into multiple rules.
+                        // Breaking this rule will cause `seccomp_rule_add` to
+                        // return EINVAL.
+                        //
+                        // From the man page: when adding syscall argument
+                        // comparisons to the filter it is important to remember
+                        // that while it is possible to have multiple
+                        // comparisons in a single rule, you can only compare
+                        // each argument once in a single rule.  In other words,
+                        // you can not have multiple comparisons of the 3rd
+                        // syscall argument in a single rule.
+                        for arg in args {
+                            let cmp = ScmpArgCompare::new(
+                                arg.index() as u32,
+                                translate_op(arg.op(), arg.value_two()),
+                                arg.value(),
+                            );
+                            tracing::trace!(?name, ?action, ?arg, "add seccomp conditional rule");
+                            ctx.add_rule_conditional(action, sc, &[cmp])
+                                .map_err(|err| {
+                                    tracing::error!(
+                                        "failed to add seccomp action: {:?}. Cmp: {:?} Syscall: {name}", &action, cmp,
+                                    );
+                                    SeccompError::AddRule {
+                                        source: err,
+                                    }
+                                })?;
+                        }
+                    }
+                    _ => {
+                        tracing::trace!(?name, ?action, "add seccomp rule");
+                        ctx.add_rule(action, sc).map_err(|err| {
+                            tracing::error!(
+                                "failed to add seccomp rule: {:?}. Syscall: {name}",
+                                &sc
+                            );
+                            SeccompError::AddRule { source: err }
+                        })?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(ctx)
+}
+
+// Names of the architectures as the native backend displays them, and the audit
+// arches the kernel reports for their syscalls, see <linux/audit.h>
+const ARCHES: [(&str, ScmpArch, u32); 14] = [
+    ("x86", ScmpArch::X86, 3 | AUDIT_ARCH_LE),
+    (
+        "x86_64",
+        ScmpArch::X8664,
+        62 | AUDIT_ARCH_64BIT | AUDIT_ARCH_LE,
+    ),
+    ("x32", ScmpArch::X32, 62 | AUDIT_ARCH_64BIT | AUDIT_ARCH_LE),
+    ("arm", ScmpArch::Arm, 40 | AUDIT_ARCH_LE),
+    (
+        "aarch64",
+        ScmpArch::Aarch64,
+        183 | AUDIT_ARCH_64BIT | AUDIT_ARCH_LE,
+    ),
+    ("mips", ScmpArch::Mips, 8),
+    ("mipsel", ScmpArch::Mipsel, 8 | AUDIT_ARCH_LE),
+    ("mips64", ScmpArch::Mips64, 8 | AUDIT_ARCH_64BIT),
+    (
+        "mipsel64",
+        ScmpArch::Mipsel64,
+        8 | AUDIT_ARCH_64BIT | AUDIT_ARCH_LE,
+    ),
+    ("ppc", ScmpArch::Ppc, 20),
+    ("ppc64", ScmpArch::Ppc64, 21 | AUDIT_ARCH_64BIT),
+    (
+        "ppc64le",
+        ScmpArch::Ppc64Le,
+        21 | AUDIT_ARCH_64BIT | AUDIT_ARCH_LE,
+    ),
+    ("s390x", ScmpArch::S390X, 22 | AUDIT_ARCH_64BIT),
+    (
+        "riscv64",
+        ScmpArch::Riscv64,
+        243 | AUDIT_ARCH_64BIT | AUDIT_ARCH_LE,
+    ),
+];
+
+fn lookup_arch(name: Option<&str>) -> Result<(&'static str, ScmpArch, u32)> {
+    let arch = match name {
+        Some(name) => ARCHES.iter().find(|(n, _, _)| *n == name),
+        None => {
+            let native = ScmpArch::native();
+            ARCHES.iter().find(|(_, arch, _)| *arch == native)
+        }
+    };
+    arch.copied().ok_or_else(|| SeccompError::UnknownArch {
+        name: name.unwrap_or("native").to_owned(),
+    })
+}
+
+/// Compiles the profile into the program which `initialize_seccomp` loads
+pub fn compile_seccomp(seccomp: &LinuxSeccomp) -> Result<Vec<Instruction>> {
+    check_seccomp(seccomp)?;
+    let ctx = build_filter(seccomp)?;
+
+    // Safety: the name is a nul terminated string
+    let fd = unsafe { libc::memfd_create(c"seccomp".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(SeccompError::ReadExport {
+            source: std::io::Error::last_os_error(),
+        });
+    }
+    // Safety: the fd was just created and isn't owned by anything else
+    let mut file = unsafe { File::from_raw_fd(fd) };
+    ctx.export_bpf(&file)
+        .map_err(|err| SeccompError::ExportFilter { source: err })?;
+    let mut bytes = Vec::new();
+    file.rewind()
+        .and_then(|_| file.read_to_end(&mut bytes))
+        .map_err(|err| SeccompError::ReadExport { source: err })?;
+
+    Ok(bytes
+        .chunks_exact(8)
+        .map(|chunk| Instruction::from_ne_bytes(chunk.try_into().unwrap()))
+        .collect())
+}
+
+/// Writes the pseudo filter code libseccomp generates for the profile, which lists
+/// the rules per architecture
+pub fn export_pfc(seccomp: &LinuxSeccomp, out: impl AsFd) -> Result<()> {
+    check_seccomp(seccomp)?;
+    build_filter(seccomp)?
+        .export_pfc(out)
+        .map_err(|err| SeccompError::ExportFilter { source: err })
+}
+
+/// Resolves an architecture by name, e.g. x86_64, or the native one. Returns the
+/// name and the audit arch the kernel reports for its syscalls.
+pub fn resolve_arch(name: Option<&str>) -> Result<(String, u32)> {
+    let (name, _, token) = lookup_arch(name)?;
+    Ok((name.to_owned(), token))
+}
+
+/// Resolves the number of a syscall on an architecture, or the native one
+pub fn resolve_syscall(arch: Option<&str>, name: &str) -> Result<u32> {
+    let (arch_name, arch, _) = lookup_arch(arch)?;
+    // libseccomp returns negative pseudo numbers for syscalls the architecture
+    // doesn't have, e.g. the ones multiplexed through socketcall(2)
+    ScmpSyscall::from_name_by_arch(name, arch)
+        .ok()
+        .and_then(|syscall| u32::try_from(syscall.as_raw_syscall()).ok())
+        .ok_or_else(|| SeccompError::UnknownSyscall {
+            name: name.to_owned(),
+            arch: arch_name.to_owned(),
+        })
+}
+
+#[tracing::instrument(level = "trace", skip(seccomp))]
+pub fn initialize_seccomp(seccomp: &LinuxSeccomp) -> Result<Option<io::RawFd>> {
+    check_seccomp(seccomp)?;
+
+    let ctx = build_filter(seccomp)?;
+
+    // In order to use the SECCOMP_SET_MODE_FILTER operation, either the calling
+    // thread must have the CAP_SYS_ADMIN capability in its user namespace, or
+    // the thread must already have the no_new_privs bit set.
+    // Ref: https://man7.org/linux/man-pages/man2/seccomp.2.html
+    ctx.load()
+        .map_err(|err| SeccompError::LoadContext { source: err })?;
+
+    let fd = if is_notify(seccomp) {
+        Some(
+            ctx.get_notify_fd()
+                .map_err(|err| SeccompError::GetNotifyId { source: err })?,
+        )
+    } else {
+        None
+    };
+
+    Ok(fd)
+}
@@ -0,0 +1,56 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use pathrs::flags::OpenFlags;
+use pathrs::procfs::{ProcfsBase, ProcfsHandle};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SELinuxError {
+    #[error("failed to set SELinux exec label")]
+    SetExecLabel {
+        path: PathBuf,
+        label: String,
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Pathrs(#[from] pathrs::error::Error),
+}
+
+type Result<T> = std::result::Result<T, SELinuxError>;
+
+const SELINUX_FS_PATH: &str = "/sys/fs/selinux";
+
+/// Checks if SELinux has been enabled on the system.
+pub fn is_enabled() -> bool {
+    Path::new(SELINUX_FS_PATH).join("enforce").exists()
+}
+
+/// Sets the label the next exec of the current thread transitions to, so that
+/// the payload of the container runs with it.
+pub fn set_exec_label(label: &str) -> Result<()> {
+    if label.is_empty() {
+        return Ok(());
+    }
+
+    if !is_enabled() {
+        tracing::warn!(
+            label,
+            "SELinux is not enabled, the process label is ignored"
+        );
+        return Ok(());
+    }
+
+    let subpath = Path::new("attr/exec");
+    ProcfsHandle::new()?
+        .open(
+            ProcfsBase::ProcThreadSelf,
+            subpath,
+            OpenFlags::O_WRONLY | OpenFlags::O_CLOEXEC,
+        )?
+        .write_all(label.as_bytes())
+        .map_err(|err| SELinuxError::SetExecLabel {
+            path: PathBuf::from("/proc/thread-self").join(subpath),
+            label: label.to_owned(),
+            source: err,
+        })
+}
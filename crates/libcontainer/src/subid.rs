@@ -0,0 +1,512 @@
+//! Builds the id mappings of rootless containers from the subordinate ids of the
+//! user, which are delegated in `/etc/subuid` and `/etc/subgid` or by the
+//! `getsubids` tool of shadow-utils when the ranges come from NSS.
+//!
+//! The user itself is mapped to root in the container and the subordinate ids
+//! fill the rest of the 65536 ids a container usually expects.
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::{env, fs};
+
+use nix::unistd::{Gid, Uid, User};
+use oci_spec::runtime::{LinuxIdMapping, LinuxIdMappingBuilder, Spec};
+
+/// Number of ids mapped into the container, including the id of the user
+pub const MAPPED_IDS: u32 = 65536;
+
+const SUBUID_PATH: &str = "/etc/subuid";
+const SUBGID_PATH: &str = "/etc/subgid";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SubIdError {
+    #[error("failed to read {path}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid entry {line:?} in {path}")]
+    InvalidEntry { path: PathBuf, line: String },
+    #[error("{kind} has only {available} subordinate ids for {user}, {required} are required")]
+    NotEnoughIds {
+        kind: SubIdKind,
+        user: String,
+        available: u64,
+        required: u32,
+    },
+    #[error("{kind} {id} of {what} is not mapped into the container")]
+    NotMapped {
+        kind: SubIdKind,
+        id: u32,
+        what: String,
+    },
+    #[error("invalid id in mount option {0:?}")]
+    InvalidMountOption(String),
+    #[error(transparent)]
+    Mapping(#[from] oci_spec::OciSpecError),
+}
+
+type Result<T> = std::result::Result<T, SubIdError>;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SubIdKind {
+    Uid,
+    Gid,
+}
+
+impl SubIdKind {
+    fn path(&self) -> &'static str {
+        match self {
+            Self::Uid => SUBUID_PATH,
+            Self::Gid => SUBGID_PATH,
+        }
+    }
+}
+
+impl std::fmt::Display for SubIdKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Uid => f.write_str("uid"),
+            Self::Gid => f.write_str("gid"),
+        }
+    }
+}
+
+/// A range of subordinate ids on the host
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SubIdRange {
+    pub start: u32,
+    pub count: u32,
+}
+
+/// The uid and gid mappings of a rootless container
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubIdMappings {
+    pub uid_mappings: Vec<LinuxIdMapping>,
+    pub gid_mappings: Vec<LinuxIdMapping>,
+}
+
+impl SubIdMappings {
+    /// Looks up the subordinate ids of the user with `uid` and maps `uid` and
+    /// `gid` to root in the container.
+    pub fn for_user(uid: Uid, gid: Gid) -> Result<Self> {
+        // the files may refer to the user by name or by uid
+        let name = User::from_uid(uid)
+            .ok()
+            .flatten()
+            .map(|user| user.name)
+            .unwrap_or_else(|| uid.to_string());
+        let sub_uids = subordinate_ids(SubIdKind::Uid, &name, uid)?;
+        let sub_gids = subordinate_ids(SubIdKind::Gid, &name, uid)?;
+        Self::from_ranges(&name, uid, gid, &sub_uids, &sub_gids)
+    }
+
+    /// Maps `uid` and `gid` to root and the ranges to the following ids of the
+    /// container.
+    pub fn from_ranges(
+        user: &str,
+        uid: Uid,
+        gid: Gid,
+        sub_uids: &[SubIdRange],
+        sub_gids: &[SubIdRange],
+    ) -> Result<Self> {
+        Ok(Self {
+            uid_mappings: build_mappings(SubIdKind::Uid, user, uid.as_raw(), sub_uids)?,
+            gid_mappings: build_mappings(SubIdKind::Gid, user, gid.as_raw(), sub_gids)?,
+        })
+    }
+
+    /// Checks that the user of the process and the ids in the options of the mounts
+    /// are mapped into the container.
+    pub fn validate(&self, spec: &Spec) -> Result<()> {
+        if let Some(process) = spec.process() {
+            let user = process.user();
+            self.check(SubIdKind::Uid, user.uid(), "the process user")?;
+            self.check(SubIdKind::Gid, user.gid(), "the process user")?;
+            for gid in user.additional_gids().iter().flatten() {
+                self.check(SubIdKind::Gid, *gid, "the additional groups")?;
+            }
+        }
+
+        for mount in spec.mounts().iter().flatten() {
+            for option in mount.options().iter().flatten() {
+                let (kind, id) = match option.split_once('=') {
+                    Some(("uid", id)) => (SubIdKind::Uid, id),
+                    Some(("gid", id)) => (SubIdKind::Gid, id),
+                    _ => continue,
+                };
+                let id = id
+                    .parse()
+                    .map_err(|_| SubIdError::InvalidMountOption(option.clone()))?;
+                self.check(
+                    kind,
+                    id,
+                    &format!("mount {}", mount.destination().display()),
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Validates the spec against the mappings and sets them
+    pub fn apply(self, spec: &mut Spec) -> Result<()> {
+        self.validate(spec)?;
+        if let Some(linux) = spec.linux_mut() {
+            linux
+                .set_uid_mappings(Some(self.uid_mappings))
+                .set_gid_mappings(Some(self.gid_mappings));
+        }
+        Ok(())
+    }
+
+    fn check(&self, kind: SubIdKind, id: u32, what: &str) -> Result<()> {
+        let mappings = match kind {
+            SubIdKind::Uid => &self.uid_mappings,
+            SubIdKind::Gid => &self.gid_mappings,
+        };
+        let mapped = mappings.iter().any(|m| {
+            id >= m.container_id()
+                && u64::from(id) < u64::from(m.container_id()) + u64::from(m.size())
+        });
+        if !mapped {
+            return Err(SubIdError::NotMapped {
+                kind,
+                id,
+                what: what.to_owned(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Returns the subordinate ids of the user, preferring `getsubids`, which also
+/// knows the ranges of NSS, over the files
+pub fn subordinate_ids(kind: SubIdKind, name: &str, uid: Uid) -> Result<Vec<SubIdRange>> {
+    if let Some(getsubids) = lookup_getsubids() {
+        match read_getsubids(&getsubids, kind, name) {
+            Ok(ranges) => return Ok(ranges),
+            Err(err) => {
+                tracing::debug!(?err, "getsubids failed, falling back to {}", kind.path());
+            }
+        }
+    }
+    read_subid_file(Path::new(kind.path()), name, uid)
+}
+
+/// Reads the ranges of the user, given by name or uid, from a file in the format
+/// of `/etc/subuid`, which has one `user:start:count` entry per line.
+pub fn read_subid_file(path: &Path, name: &str, uid: Uid) -> Result<Vec<SubIdRange>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(source) => {
+            return Err(SubIdError::Read {
+                path: path.to_owned(),
+                source,
+            });
+        }
+    };
+
+    let uid = uid.to_string();
+    let mut ranges = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let invalid = || SubIdError::InvalidEntry {
+            path: path.to_owned(),
+            line: line.to_owned(),
+        };
+        let mut fields = line.split(':');
+        let (Some(user), Some(start), Some(count), None) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(invalid());
+        };
+        if user != name && user != uid {
+            continue;
+        }
+        ranges.push(SubIdRange {
+            start: start.parse().map_err(|_| invalid())?,
+            count: count.parse().map_err(|_| invalid())?,
+        });
+    }
+
+    Ok(ranges)
+}
+
+fn lookup_getsubids() -> Option<PathBuf> {
+    let paths = env::var("PATH").ok()?;
+    paths
+        .split_terminator(':')
+        .map(|p| Path::new(p).join("getsubids"))
+        .find(|p| p.exists())
+}
+
+// getsubids prints one "index: user start count" line per range
+fn read_getsubids(getsubids: &Path, kind: SubIdKind, name: &str) -> Result<Vec<SubIdRange>> {
+    let mut command = Command::new(getsubids);
+    if kind == SubIdKind::Gid {
+        command.arg("-g");
+    }
+    let read_error = |source| SubIdError::Read {
+        path: getsubids.to_owned(),
+        source,
+    };
+    let output = command.arg(name).output().map_err(read_error)?;
+    if !output.status.success() {
+        return Err(read_error(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        )));
+    }
+
+    parse_getsubids(&String::from_utf8_lossy(&output.stdout), getsubids)
+}
+
+fn parse_getsubids(output: &str, getsubids: &Path) -> Result<Vec<SubIdRange>> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let invalid = || SubIdError::InvalidEntry {
+                path: getsubids.to_owned(),
+                line: line.to_owned(),
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [_, _, start, count] = fields[..] else {
+                return Err(invalid());
+            };
+            Ok(SubIdRange {
+                start: start.parse().map_err(|_| invalid())?,
+                count: count.parse().map_err(|_| invalid())?,
+            })
+        })
+        .collect()
+}
+
+fn build_mappings(
+    kind: SubIdKind,
+    user: &str,
+    id: u32,
+    ranges: &[SubIdRange],
+) -> Result<Vec<LinuxIdMapping>> {
+    let mut mappings = vec![
+        LinuxIdMappingBuilder::default()
+            .container_id(0u32)
+            .host_id(id)
+            .size(1u32)
+            .build()?,
+    ];
+
+    let mut container_id = 1;
+    for range in ranges {
+        if container_id == MAPPED_IDS {
+            break;
+        }
+        let size = range.count.min(MAPPED_IDS - container_id);
+        if size == 0 {
+            continue;
+        }
+        mappings.push(
+            LinuxIdMappingBuilder::default()
+                .container_id(container_id)
+                .host_id(range.start)
+                .size(size)
+                .build()?,
+        );
+        container_id += size;
+    }
+
+    if container_id < MAPPED_IDS {
+        return Err(SubIdError::NotEnoughIds {
+            kind,
+            user: user.to_owned(),
+            available: ranges.iter().map(|range| u64::from(range.count)).sum(),
+            required: MAPPED_IDS - 1,
+        });
+    }
+
+    Ok(mappings)
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use oci_spec::runtime::{MountBuilder, ProcessBuilder, SpecBuilder, UserBuilder};
+
+    use super::*;
+
+    fn mapping(container_id: u32, host_id: u32, size: u32) -> LinuxIdMapping {
+        LinuxIdMappingBuilder::default()
+            .container_id(container_id)
+            .host_id(host_id)
+            .size(size)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_read_subid_file() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let path = tmp.path().join("subuid");
+        fs::write(
+            &path,
+            "# comment\nalice:100000:65536\nbob:165536:65536\n1000:300000:10\n",
+        )?;
+
+        let ranges = read_subid_file(&path, "alice", Uid::from_raw(1000))?;
+        assert_eq!(
+            ranges,
+            [
+                SubIdRange {
+                    start: 100000,
+                    count: 65536
+                },
+                SubIdRange {
+                    start: 300000,
+                    count: 10
+                },
+            ]
+        );
+        assert!(read_subid_file(&path, "carol", Uid::from_raw(1001))?.is_empty());
+        assert!(
+            read_subid_file(&tmp.path().join("missing"), "alice", Uid::from_raw(1000))?.is_empty()
+        );
+
+        fs::write(&path, "alice:100000\n")?;
+        assert!(matches!(
+            read_subid_file(&path, "alice", Uid::from_raw(1000)),
+            Err(SubIdError::InvalidEntry { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_getsubids() -> Result<()> {
+        let ranges = parse_getsubids(
+            "0: alice 100000 65536\n1: alice 300000 10\n",
+            Path::new("getsubids"),
+        )?;
+        assert_eq!(
+            ranges,
+            [
+                SubIdRange {
+                    start: 100000,
+                    count: 65536
+                },
+                SubIdRange {
+                    start: 300000,
+                    count: 10
+                },
+            ]
+        );
+        assert!(parse_getsubids("alice 100000", Path::new("getsubids")).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_ranges() -> Result<()> {
+        let ranges = [
+            SubIdRange {
+                start: 100000,
+                count: 1000,
+            },
+            SubIdRange {
+                start: 200000,
+                count: 100000,
+            },
+        ];
+        let mappings = SubIdMappings::from_ranges(
+            "alice",
+            Uid::from_raw(1000),
+            Gid::from_raw(1000),
+            &ranges,
+            &ranges,
+        )?;
+        let expected = vec![
+            mapping(0, 1000, 1),
+            mapping(1, 100000, 1000),
+            mapping(1001, 200000, 64535),
+        ];
+        assert_eq!(mappings.uid_mappings, expected);
+        assert_eq!(mappings.gid_mappings, expected);
+
+        let err = SubIdMappings::from_ranges(
+            "alice",
+            Uid::from_raw(1000),
+            Gid::from_raw(1000),
+            &ranges[..1],
+            &ranges,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            SubIdError::NotEnoughIds {
+                kind: SubIdKind::Uid,
+                available: 1000,
+                ..
+            }
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() -> Result<()> {
+        let mappings = SubIdMappings {
+            uid_mappings: vec![mapping(0, 1000, 1), mapping(1, 100000, 65535)],
+            gid_mappings: vec![mapping(0, 1000, 1), mapping(1, 100000, 999)],
+        };
+        let spec = |uid: u32, gid: u32, option: &str| {
+            SpecBuilder::default()
+                .process(
+                    ProcessBuilder::default()
+                        .user(UserBuilder::default().uid(uid).gid(gid).build().unwrap())
+                        .build()
+                        .unwrap(),
+                )
+                .mounts(vec![
+                    MountBuilder::default()
+                        .destination("/dev/pts")
+                        .options(vec!["newinstance".to_owned(), option.to_owned()])
+                        .build()
+                        .unwrap(),
+                ])
+                .build()
+                .unwrap()
+        };
+
+        mappings.validate(&spec(65535, 5, "gid=5"))?;
+        assert!(matches!(
+            mappings.validate(&spec(65536, 5, "gid=5")),
+            Err(SubIdError::NotMapped {
+                kind: SubIdKind::Uid,
+                id: 65536,
+                ..
+            })
+        ));
+        assert!(matches!(
+            mappings.validate(&spec(0, 1000, "gid=5")),
+            Err(SubIdError::NotMapped {
+                kind: SubIdKind::Gid,
+                id: 1000,
+                ..
+            })
+        ));
+        assert!(matches!(
+            mappings.validate(&spec(0, 0, "gid=1000")),
+            Err(SubIdError::NotMapped {
+                kind: SubIdKind::Gid,
+                ..
+            })
+        ));
+        assert!(matches!(
+            mappings.validate(&spec(0, 0, "uid=root")),
+            Err(SubIdError::InvalidMountOption(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -17,8 +17,9 @@ use nix::fcntl;
 use nix::fcntl::{OFlag, open};
 use nix::mount::{MntFlags, MsFlags, mount, umount2};
 use nix::sched::{CloneFlags, unshare};
+use nix::sys::personality::Persona;
 use nix::sys::stat::{Mode, SFlag, mknod};
-use nix::unistd::{Gid, Uid, chown, chroot, close, fchdir, pivot_root, sethostname};
+use nix::unistd::{Gid, Pid, Uid, chown, chroot, close, fchdir, pivot_root, sethostname};
 use oci_spec::runtime::PosixRlimit;
 use pathrs::flags::OpenFlags;
 use pathrs::procfs::{ProcfsBase, ProcfsHandle};
@@ -970,6 +971,28 @@ impl Syscall for LinuxSyscall {
         }
     }
 
+    fn migrate_pages(
+        &self,
+        pid: Pid,
+        maxnode: u64,
+        old_nodes: &[libc::c_ulong],
+        new_nodes: &[libc::c_ulong],
+    ) -> Result<()> {
+        // Safety: both nodemasks hold at least maxnode bits
+        let ret = unsafe {
+            libc::syscall(
+                libc::SYS_migrate_pages,
+                pid.as_raw() as libc::c_long,
+                maxnode as libc::c_ulong,
+                old_nodes.as_ptr(),
+                new_nodes.as_ptr(),
+            )
+        };
+        // On success, the number of pages which could not be moved is returned
+        nix::errno::Errno::result(ret)?;
+        Ok(())
+    }
+
     fn umount2(&self, target: &Path, flags: MntFlags) -> Result<()> {
         umount2(target, flags)?;
         Ok(())
@@ -991,9 +1014,9 @@ impl Syscall for LinuxSyscall {
         nix::unistd::getegid()
     }
 
-    fn personality(&self, domain: PersonalityDomain) -> Result<()> {
-        let domain = nix::sys::personality::Persona::from_bits_retain(domain as i32);
-        nix::sys::personality::set(domain)
+    fn personality(&self, domain: PersonalityDomain, flags: Persona) -> Result<()> {
+        let persona = Persona::from_bits_retain(domain as i32) | flags;
+        nix::sys::personality::set(persona)
             .map(|_| ())
             .map_err(|e| e.into())
     }
@@ -12,8 +12,9 @@ use caps::{CapSet, CapsHashSet};
 use libc;
 use nix::mount::{MntFlags, MsFlags};
 use nix::sched::CloneFlags;
+use nix::sys::personality::Persona;
 use nix::sys::stat::{Mode, SFlag};
-use nix::unistd::{Gid, Uid};
+use nix::unistd::{Gid, Pid, Uid};
 use oci_spec::runtime::PosixRlimit;
 
 use crate::config::PersonalityDomain;
@@ -81,12 +82,19 @@ pub trait Syscall {
     ) -> Result<()>;
     fn set_io_priority(&self, class: i64, priority: i64) -> Result<()>;
     fn set_mempolicy(&self, mode: i32, nodemask: &[libc::c_ulong], maxnode: u64) -> Result<()>;
+    fn migrate_pages(
+        &self,
+        pid: Pid,
+        maxnode: u64,
+        old_nodes: &[libc::c_ulong],
+        new_nodes: &[libc::c_ulong],
+    ) -> Result<()>;
     fn umount2(&self, target: &Path, flags: MntFlags) -> Result<()>;
     fn get_uid(&self) -> Uid;
     fn get_gid(&self) -> Gid;
     fn get_euid(&self) -> Uid;
     fn get_egid(&self) -> Gid;
-    fn personality(&self, domain: PersonalityDomain) -> Result<()>;
+    fn personality(&self, domain: PersonalityDomain, flags: Persona) -> Result<()>;
 }
 
 #[derive(Clone, Copy)]
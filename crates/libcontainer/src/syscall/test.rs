@@ -10,8 +10,9 @@ use std::sync::Arc;
 use caps::{CapSet, CapsHashSet};
 use nix::mount::{MntFlags, MsFlags};
 use nix::sched::CloneFlags;
+use nix::sys::personality::Persona;
 use nix::sys::stat::{Mode, SFlag};
-use nix::unistd::{Gid, Uid};
+use nix::unistd::{Gid, Pid, Uid};
 use oci_spec::runtime::PosixRlimit;
 
 use super::super::config::PersonalityDomain;
@@ -75,6 +76,14 @@ pub struct MemPolicyArgs {
     pub maxnode: u64,
 }
 
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct MigratePagesArgs {
+    pub pid: Pid,
+    pub maxnode: u64,
+    pub old_nodes: Vec<libc::c_ulong>,
+    pub new_nodes: Vec<libc::c_ulong>,
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct UMount2Args {
     pub target: PathBuf,
@@ -103,6 +112,7 @@ pub enum ArgName {
     Capability,
     IoPriority,
     MemPolicy,
+    MigratePages,
     UMount2,
     MoveMount,
     Fsopen,
@@ -124,6 +134,7 @@ impl ArgName {
             ArgName::Capability,
             ArgName::IoPriority,
             ArgName::MemPolicy,
+            ArgName::MigratePages,
             ArgName::MoveMount,
         ]
         .iter()
@@ -397,6 +408,24 @@ impl Syscall for TestHelperSyscall {
         )
     }
 
+    fn migrate_pages(
+        &self,
+        pid: Pid,
+        maxnode: u64,
+        old_nodes: &[libc::c_ulong],
+        new_nodes: &[libc::c_ulong],
+    ) -> Result<()> {
+        self.mocks.act(
+            ArgName::MigratePages,
+            Box::new(MigratePagesArgs {
+                pid,
+                maxnode,
+                old_nodes: old_nodes.to_vec(),
+                new_nodes: new_nodes.to_vec(),
+            }),
+        )
+    }
+
     fn umount2(&self, target: &Path, flags: MntFlags) -> Result<()> {
         self.mocks.act(
             ArgName::UMount2,
@@ -423,7 +452,7 @@ impl Syscall for TestHelperSyscall {
         self.mock_id.borrow().egid
     }
 
-    fn personality(&self, _: PersonalityDomain) -> Result<()> {
+    fn personality(&self, _: PersonalityDomain, _: Persona) -> Result<()> {
         todo!()
     }
 }
@@ -555,6 +584,15 @@ impl TestHelperSyscall {
             .collect::<Vec<MemPolicyArgs>>()
     }
 
+    pub fn get_migrate_pages_args(&self) -> Vec<MigratePagesArgs> {
+        self.mocks
+            .fetch(ArgName::MigratePages)
+            .values
+            .iter()
+            .map(|x| x.downcast_ref::<MigratePagesArgs>().unwrap().clone())
+            .collect::<Vec<MigratePagesArgs>>()
+    }
+
     pub fn get_umount_args(&self) -> Vec<UMount2Args> {
         self.mocks
             .fetch(ArgName::UMount2)
@@ -13,10 +13,11 @@
 //! See: https://github.com/opencontainers/runc/blob/v1.4.0/libcontainer/rootfs_linux.go
 
 use std::env;
-use std::io::IoSlice;
-use std::os::fd::OwnedFd;
+use std::io::{IoSlice, IoSliceMut};
+use std::os::fd::{FromRawFd, OwnedFd};
 use std::os::unix::fs::{OpenOptionsExt, symlink};
 use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixListener;
 use std::os::unix::prelude::RawFd;
 use std::path::{Path, PathBuf};
 
@@ -81,6 +82,10 @@ pub enum TTYError {
     CreatePseudoTerminal { source: nix::Error },
     #[error("failed to send pty master")]
     SendPtyMaster { source: nix::Error },
+    #[error("failed to accept connection on console socket")]
+    AcceptConsoleSocket { source: std::io::Error },
+    #[error("failed to receive pty master")]
+    ReceivePtyMaster { source: nix::Error },
     #[error("could not close console socket")]
     CloseConsoleSocket { source: nix::Error },
     #[error("failed to create /dev/console")]
@@ -323,6 +328,41 @@ pub fn setup_console(syscall: &dyn Syscall, console_fd: RawFd, mount: bool) -> R
     Ok(())
 }
 
+/// Receives the PTY master which `setup_console` sends to the console socket,
+/// for callers which create the console socket themselves.
+pub fn receive_console(listener: &UnixListener) -> Result<OwnedFd> {
+    let (stream, _) = listener
+        .accept()
+        .map_err(|err| TTYError::AcceptConsoleSocket { source: err })?;
+
+    let mut name = [0u8; 64];
+    let mut iov = [IoSliceMut::new(&mut name)];
+    let mut cmsg_buf = nix::cmsg_space!(RawFd);
+    let msg = socket::recvmsg::<UnixAddr>(
+        stream.as_raw_fd(),
+        &mut iov,
+        Some(&mut cmsg_buf),
+        socket::MsgFlags::MSG_CMSG_CLOEXEC,
+    )
+    .map_err(|err| TTYError::ReceivePtyMaster { source: err })?;
+
+    for cmsg in msg
+        .cmsgs()
+        .map_err(|err| TTYError::ReceivePtyMaster { source: err })?
+    {
+        if let socket::ControlMessageOwned::ScmRights(fds) = cmsg {
+            if let Some(fd) = fds.first() {
+                // Safety: the fd was just received and is owned by nobody else
+                return Ok(unsafe { OwnedFd::from_raw_fd(*fd) });
+            }
+        }
+    }
+
+    Err(TTYError::ReceivePtyMaster {
+        source: nix::Error::EBADMSG,
+    })
+}
+
 /// Mount PTY slave on /dev/console.
 ///
 /// This bind-mounts the PTY slave device onto /dev/console so programs
@@ -489,6 +529,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    #[serial]
+    fn test_receive_console() -> Result<()> {
+        let testdir = tempfile::tempdir()?;
+        let socket_path = Path::join(testdir.path(), "test-socket");
+        let listener = UnixListener::bind(&socket_path)?;
+        let fd = setup_console_socket(testdir.path(), &socket_path, CONSOLE_SOCKET)?;
+
+        let openpty_result = nix::pty::openpty(None, None)?;
+        let fds = [openpty_result.master.as_raw_fd()];
+        socket::sendmsg::<UnixAddr>(
+            fd.as_raw_fd(),
+            &[IoSlice::new(PTMX_PATH)],
+            &[socket::ControlMessage::ScmRights(&fds)],
+            socket::MsgFlags::empty(),
+            None,
+        )?;
+
+        let master = receive_console(&listener)?;
+        assert!(nix::unistd::isatty(master.as_raw_fd())?);
+
+        Ok(())
+    }
+
     #[test]
     fn test_verify_ptmx_handle_with_real_pty() -> Result<()> {
         // Allocate a real PTY pair
@@ -2,7 +2,7 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::{env, fs};
 
-use nix::unistd::Pid;
+use nix::unistd::{Gid, Pid, Uid};
 use oci_spec::runtime::{Linux, LinuxIdMapping, LinuxNamespace, LinuxNamespaceType, Mount, Spec};
 
 use crate::error::MissingSpecError;
@@ -199,6 +199,13 @@ impl UserNamespaceConfig {
         Ok(())
     }
 
+    /// Returns the host user and group root in the user namespace is mapped to
+    pub fn root_owner(&self) -> Option<(Uid, Gid)> {
+        let uid = host_id(0, self.uid_mappings.as_deref()?)?;
+        let gid = host_id(0, self.gid_mappings.as_deref()?)?;
+        Some((Uid::from_raw(uid), Gid::from_raw(gid)))
+    }
+
     pub fn with_id_mapper(&mut self, mapper: UserNamespaceIDMapper) {
         self.id_mapper = mapper
     }
@@ -368,6 +375,13 @@ fn is_id_mapped(id: u32, mappings: &[LinuxIdMapping]) -> bool {
         .any(|m| id >= m.container_id() && id <= m.container_id() + m.size())
 }
 
+fn host_id(id: u32, mappings: &[LinuxIdMapping]) -> Option<u32> {
+    mappings
+        .iter()
+        .find(|m| id >= m.container_id() && id - m.container_id() < m.size())
+        .map(|m| m.host_id() + (id - m.container_id()))
+}
+
 /// Looks up the location of the newuidmap and newgidmap binaries which
 /// are required to write multiple user/group mappings
 pub fn lookup_map_binaries(
@@ -677,4 +691,28 @@ mod tests {
         );
         Ok(())
     }
+
+    #[test]
+    fn test_root_owner() -> Result<()> {
+        let mapping = |container_id: u32, host_id: u32, size: u32| {
+            LinuxIdMappingBuilder::default()
+                .container_id(container_id)
+                .host_id(host_id)
+                .size(size)
+                .build()
+        };
+        let mut config = UserNamespaceConfig {
+            uid_mappings: Some(vec![mapping(1, 200000, 65535)?, mapping(0, 1000, 1)?]),
+            gid_mappings: Some(vec![mapping(0, 100000, 65536)?]),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.root_owner(),
+            Some((Uid::from_raw(1000), Gid::from_raw(100000)))
+        );
+
+        config.uid_mappings = Some(vec![mapping(1, 200000, 65535)?]);
+        assert_eq!(config.root_owner(), None);
+        Ok(())
+    }
 }
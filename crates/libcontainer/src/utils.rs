@@ -2,19 +2,21 @@
 
 use std::collections::HashMap;
 use std::fs::{self, DirBuilder, File};
-use std::os::fd::{AsRawFd, OwnedFd};
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
 use std::os::linux::fs::MetadataExt;
 use std::os::unix::fs::DirBuilderExt;
 use std::path::{Component, Path, PathBuf};
 use std::time::Duration;
 
 use libc::IFNAMSIZ;
+use nix::fcntl::OFlag;
 use nix::sys::stat::{Mode, fstat};
 use nix::sys::statfs::{Statfs, fstatfs};
-use nix::unistd::{Uid, User};
+use nix::unistd::{self, Uid, User};
 use oci_spec::runtime::{LinuxNamespaceType, Spec};
 
 use crate::error::{LibcontainerError, MissingSpecError};
+use crate::network::bridge::BridgeNetwork;
 use crate::syscall::syscall::Syscall;
 use crate::user_ns::UserNamespaceConfig;
 
@@ -155,6 +157,39 @@ pub fn get_cgroup_path(cgroups_path: &Option<PathBuf>, container_id: &str) -> Pa
     }
 }
 
+/// Annotations with this prefix are passed to systemd as properties of the
+/// transient unit created for the container, e.g.
+/// org.systemd.property.CollectMode='inactive-or-failed'.
+pub const SYSTEMD_PROPERTY_ANNOTATION_PREFIX: &str = "org.systemd.property.";
+
+/// Collects the systemd unit properties set through annotations, keyed by the
+/// property name without the annotation prefix.
+pub fn get_systemd_properties(
+    annotations: &Option<HashMap<String, String>>,
+) -> HashMap<String, String> {
+    annotations
+        .iter()
+        .flatten()
+        .filter_map(|(key, value)| {
+            key.strip_prefix(SYSTEMD_PROPERTY_ANNOTATION_PREFIX)
+                .map(|name| (name.to_owned(), value.to_owned()))
+        })
+        .collect()
+}
+
+/// Delegates the cgroup of the container to the container when set to true,
+/// which is needed to run systemd or another cgroup aware init in it. Only
+/// supported with cgroup v2.
+pub const CGROUP_DELEGATE_ANNOTATION: &str = "run.oci.systemd.delegate";
+
+/// Checks if the cgroup of the container is delegated through annotations
+pub fn is_cgroup_delegated(annotations: &Option<HashMap<String, String>>) -> bool {
+    annotations
+        .as_ref()
+        .and_then(|annotations| annotations.get(CGROUP_DELEGATE_ANNOTATION))
+        .is_some_and(|value| value == "true")
+}
+
 pub fn write_file<P: AsRef<Path>, C: AsRef<[u8]>>(
     path: P,
     contents: C,
@@ -292,6 +327,27 @@ pub fn validate_spec_for_new_user_ns(
     Ok(())
 }
 
+/// Moves a relay process which outlives the caller into its own session and
+/// closes everything it inherited except `keep`, so that it doesn't keep pipes
+/// or sockets of the caller open
+pub fn detach_from_caller(keep: &[RawFd]) -> Result<(), std::io::Error> {
+    unistd::setsid()?;
+    let null = nix::fcntl::open("/dev/null", OFlag::O_RDWR, Mode::empty())?;
+    for fd in 0..=2 {
+        unistd::dup2(null, fd)?;
+    }
+
+    let fds: Vec<RawFd> = fs::read_dir("/proc/self/fd")?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect();
+    for fd in fds {
+        if fd > 2 && !keep.contains(&fd) {
+            let _ = unistd::close(fd);
+        }
+    }
+    Ok(())
+}
+
 // Generic retry function with delay and policy.
 // Retries the operation `op` up to `attempts` times if it fails.
 // Waits for `delay` duration between retries.
@@ -327,6 +383,8 @@ pub enum NetDevicesError {
     RootlessNotSupported,
     #[error("invalid network device name: {0}")]
     InvalidDeviceName(String),
+    #[error("the bridge and the rootless network can't be used together")]
+    ConflictingNetworks,
     #[error(transparent)]
     IO(#[from] std::io::Error),
     #[error(transparent)]
@@ -380,6 +438,65 @@ pub fn validate_spec_for_net_devices(
     Ok(())
 }
 
+// check if given spec is valid for the bridge network mode
+pub fn validate_spec_for_bridge_network(
+    spec: &Spec,
+    network: &BridgeNetwork,
+    syscall: &dyn Syscall,
+) -> Result<(), NetDevicesError> {
+    let linux = spec
+        .linux()
+        .as_ref()
+        .ok_or(NetDevicesError::Spec(MissingSpecError::Linux))?;
+
+    let has_net_namespace = linux.namespaces().as_ref().is_some_and(|namespaces| {
+        namespaces
+            .iter()
+            .any(|ns| ns.typ() == LinuxNamespaceType::Network)
+    });
+    if !has_net_namespace {
+        return Err(NetDevicesError::NoNetNamespace);
+    }
+
+    if rootless_required(syscall)? {
+        return Err(NetDevicesError::RootlessNotSupported);
+    }
+
+    for name in [&network.bridge, &network.host_interface, &network.interface] {
+        if !dev_valid_name(name) {
+            return Err(NetDevicesError::InvalidDeviceName(name.clone()));
+        }
+    }
+
+    Ok(())
+}
+
+// check if given spec is valid for the rootless network mode, which can't be
+// combined with the bridge network
+pub fn validate_spec_for_rootless_network(
+    spec: &Spec,
+    bridge_network: Option<&BridgeNetwork>,
+) -> Result<(), NetDevicesError> {
+    if bridge_network.is_some() {
+        return Err(NetDevicesError::ConflictingNetworks);
+    }
+
+    let linux = spec
+        .linux()
+        .as_ref()
+        .ok_or(NetDevicesError::Spec(MissingSpecError::Linux))?;
+    let has_net_namespace = linux.namespaces().as_ref().is_some_and(|namespaces| {
+        namespaces
+            .iter()
+            .any(|ns| ns.typ() == LinuxNamespaceType::Network)
+    });
+    if !has_net_namespace {
+        return Err(NetDevicesError::NoNetNamespace);
+    }
+
+    Ok(())
+}
+
 /// Validates mount destinations and warns about deprecated relative paths.
 /// Follows the OCI Runtime Spec requirement that mount destinations SHOULD be absolute.
 /// Relative paths are deprecated but still accepted for backward compatibility.
@@ -465,6 +582,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_get_systemd_properties() {
+        let annotations: HashMap<String, String> = [
+            ("org.systemd.property.CollectMode", "'inactive-or-failed'"),
+            ("org.systemd.property.TimeoutStopSec", "10"),
+            ("org.opencontainers.image.title", "test"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect();
+
+        let properties = get_systemd_properties(&Some(annotations));
+
+        assert_eq!(properties.len(), 2);
+        assert_eq!(properties["CollectMode"], "'inactive-or-failed'");
+        assert_eq!(properties["TimeoutStopSec"], "10");
+        assert!(get_systemd_properties(&None).is_empty());
+    }
+
+    #[test]
+    fn test_is_cgroup_delegated() {
+        let annotations = |value: &str| {
+            Some(HashMap::from([(
+                CGROUP_DELEGATE_ANNOTATION.to_owned(),
+                value.to_owned(),
+            )]))
+        };
+
+        assert!(is_cgroup_delegated(&annotations("true")));
+        assert!(!is_cgroup_delegated(&annotations("false")));
+        assert!(!is_cgroup_delegated(&Some(HashMap::new())));
+        assert!(!is_cgroup_delegated(&None));
+    }
+
     #[test]
     fn test_parse_env() -> Result<()> {
         let key = "key".into();
@@ -658,4 +809,36 @@ mod tests {
         let result = validate_spec_for_net_devices(&spec, &*syscall);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_bridge_network() {
+        let network = BridgeNetwork::new("youki0", "test");
+        let syscall = create_syscall();
+        syscall.set_id(Uid::from_raw(0), Gid::from_raw(0)).unwrap();
+
+        let spec = build_spec_with_ns_and_devices(false, vec![]);
+        let err = validate_spec_for_bridge_network(&spec, &network, &*syscall).unwrap_err();
+        assert!(matches!(err, NetDevicesError::NoNetNamespace));
+
+        let spec = build_spec_with_ns_and_devices(true, vec![]);
+        assert!(validate_spec_for_bridge_network(&spec, &network, &*syscall).is_ok());
+
+        let network = network.with_interface("/:invalid");
+        let err = validate_spec_for_bridge_network(&spec, &network, &*syscall).unwrap_err();
+        assert!(matches!(err, NetDevicesError::InvalidDeviceName(_)));
+    }
+
+    #[test]
+    fn test_rootless_network() {
+        let spec = build_spec_with_ns_and_devices(false, vec![]);
+        let err = validate_spec_for_rootless_network(&spec, None).unwrap_err();
+        assert!(matches!(err, NetDevicesError::NoNetNamespace));
+
+        let spec = build_spec_with_ns_and_devices(true, vec![]);
+        assert!(validate_spec_for_rootless_network(&spec, None).is_ok());
+
+        let bridge_network = BridgeNetwork::new("youki0", "test");
+        let err = validate_spec_for_rootless_network(&spec, Some(&bridge_network)).unwrap_err();
+        assert!(matches!(err, NetDevicesError::ConflictingNetworks));
+    }
 }
@@ -12,4 +12,9 @@ pub struct Spec {
     /// Generate a configuration for a rootless container
     #[clap(long)]
     pub rootless: bool,
+
+    /// Map the subordinate ids of the user from /etc/subuid and /etc/subgid into
+    /// the rootless container
+    #[clap(long, requires = "rootless")]
+    pub subids: bool,
 }
@@ -6,7 +6,7 @@ use clap::Parser;
 #[derive(Parser, Debug)]
 pub struct Update {
     /// Read the new resource limits from the given json file. Use - to read from stdin.
-    /// If this option is used, all other options are ignored.
+    /// If this option is used, all other cgroup options are ignored.
     #[clap(short, long)]
     pub resources: Option<PathBuf>,
 
@@ -66,6 +66,20 @@ pub struct Update {
     #[clap(long)]
     pub mem_bw_schema: Option<String>,
 
+    /// Set the memory policy mode, for example MPOL_BIND. The memory of the
+    /// processes in the container is moved to the nodes of the policy.
+    #[clap(long)]
+    pub memory_policy: Option<String>,
+
+    /// Set the memory policy nodes. The list format is the same as for --cpuset-cpus.
+    #[clap(long, requires = "memory_policy")]
+    pub memory_policy_nodes: Option<String>,
+
+    /// Set the CPU affinity of the processes in the container. The list format is the
+    /// same as for --cpuset-cpus.
+    #[clap(long)]
+    pub exec_cpu_affinity: Option<String>,
+
     #[clap(value_parser = clap::builder::NonEmptyStringValueParser::new(), required = true)]
     pub container_id: String,
 }
@@ -0,0 +1,44 @@
+//! Contains functionality of the attach command, which connects to the
+//! terminal of a container created with a terminal but without a console socket
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, bail};
+use clap::Parser;
+use libcontainer::container::ContainerStatus;
+
+use crate::commands::load_container;
+use crate::console::{self, ATTACH_SOCKET, DetachKeys, RawTerminal};
+
+/// Attach to the terminal of a container
+#[derive(Parser, Debug)]
+pub struct Attach {
+    /// Key sequence which detaches from the container, as comma separated
+    /// characters or ctrl-<key>
+    #[clap(long, default_value = "ctrl-p,ctrl-q")]
+    pub detach_keys: String,
+    #[clap(value_parser = clap::builder::NonEmptyStringValueParser::new(), required = true)]
+    pub container_id: String,
+}
+
+pub fn attach(args: Attach, root_path: PathBuf) -> Result<()> {
+    let detach_keys = DetachKeys::parse(&args.detach_keys)?;
+    let container = load_container(root_path, &args.container_id)?;
+    if container.status() == ContainerStatus::Stopped {
+        bail!("cannot attach to stopped container {}", args.container_id);
+    }
+
+    let socket = container.root.join(ATTACH_SOCKET);
+    if !socket.exists() {
+        bail!(
+            "container {} has no terminal to attach to, it was not created with a terminal by youki",
+            args.container_id
+        );
+    }
+    let stream = UnixStream::connect(&socket)
+        .with_context(|| format!("failed to connect to {}", socket.display()))?;
+
+    let _raw = RawTerminal::new()?;
+    console::attach(stream, detach_keys)?;
+    Ok(())
+}
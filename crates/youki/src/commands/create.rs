@@ -2,10 +2,14 @@
 use std::path::PathBuf;
 
 use anyhow::Result;
+use libcgroups::common::CgroupDriver;
 use libcontainer::container::builder::ContainerBuilder;
+use libcontainer::sd_notify::SdNotifyMode;
 use libcontainer::syscall::syscall::SyscallType;
 use liboci_cli::Create;
 
+use crate::commands::ContainerLogOpts;
+use crate::console::{self, ConsoleSocket};
 use crate::workload::executor::default_executor;
 
 // One thing to note is that in the end, container is just another process in Linux
@@ -13,19 +17,45 @@ use crate::workload::executor::default_executor;
 // can be given impression that is is running on a complete system, but on the system which
 // it is running, it is just another process, and has attributes such as pid, file descriptors, etc.
 // associated with it like any other process.
-pub fn create(args: Create, root_path: PathBuf, systemd_cgroup: bool) -> Result<()> {
-    ContainerBuilder::new(args.container_id.clone(), SyscallType::default())
+pub fn create(
+    args: Create,
+    root_path: PathBuf,
+    systemd_cgroup: bool,
+    cgroup_driver: CgroupDriver,
+    sdnotify: SdNotifyMode,
+    container_log: &ContainerLogOpts,
+) -> Result<()> {
+    // Without a console socket, the terminal is handed to a relay in the
+    // background, which `youki attach` connects to
+    let console = if args.console_socket.is_none() && console::has_terminal(&args.bundle)? {
+        Some(ConsoleSocket::new(&root_path)?)
+    } else {
+        None
+    };
+    let console_socket = args
+        .console_socket
+        .clone()
+        .or_else(|| console.as_ref().map(|c| c.path().to_owned()));
+
+    let container = ContainerBuilder::new(args.container_id.clone(), SyscallType::default())
         .with_executor(default_executor())
         .with_pid_file(args.pid_file.as_ref())?
-        .with_console_socket(args.console_socket.as_ref())
+        .with_console_socket(console_socket.as_ref())
         .with_root_path(root_path)?
         .with_preserved_fds(args.preserve_fds)
+        .with_log_sink(container_log.sink()?)
         .validate_id()?
         .as_init(&args.bundle)
         .with_systemd(systemd_cgroup)
+        .with_cgroup_driver(cgroup_driver)
         .with_detach(true)
         .with_no_pivot(args.no_pivot)
+        .with_sd_notify(sdnotify, std::env::var_os("NOTIFY_SOCKET"))
         .build()?;
 
+    if let Some(console) = console {
+        console::spawn_relay(console, &container.root)?;
+    }
+
     Ok(())
 }
@@ -0,0 +1,135 @@
+//! Contains functionality of the devices command, which shows the device access enforced
+//! by the cgroup v2 device programs of a container
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+
+/// Show the device access enforced for a container
+#[derive(Parser, Debug)]
+pub struct Devices {
+    /// Print the instructions of the device programs attached to the container's cgroup
+    #[clap(long)]
+    pub dump: bool,
+    #[clap(value_parser = clap::builder::NonEmptyStringValueParser::new(), required = true)]
+    pub container_id: String,
+}
+
+#[cfg(not(feature = "cgroupsv2_devices"))]
+pub fn devices(_: Devices, _: PathBuf) -> Result<()> {
+    anyhow::bail!("youki was built without the cgroupsv2_devices feature")
+}
+
+#[cfg(feature = "cgroupsv2_devices")]
+pub fn devices(args: Devices, root_path: PathBuf) -> Result<()> {
+    use std::fmt::Write as _;
+    use std::io::{self, Write};
+    use std::path::Path;
+
+    use anyhow::{Context, bail};
+    use libcgroups::common::{CgroupSetup, DEFAULT_CGROUP_ROOT};
+    use libcgroups::v2::devices::controller::Devices as DevicesController;
+    use libcgroups::v2::devices::verify;
+    use oci_spec::runtime::Spec;
+    use tabwriter::TabWriter;
+
+    use crate::commands::load_container;
+
+    if !matches!(
+        libcgroups::common::get_cgroup_setup()?,
+        CgroupSetup::Unified
+    ) {
+        bail!("device programs are only used on a cgroup v2 unified hierarchy");
+    }
+
+    let container = load_container(root_path, &args.container_id)?;
+    let Some(pid) = container.pid() else {
+        bail!("container {} is not running", args.container_id);
+    };
+
+    // the programs are attached to the cgroup the container's init process lives in
+    let cgroup_path = procfs::process::Process::new(pid.as_raw())?
+        .cgroups()?
+        .into_iter()
+        .find(|cgroup| cgroup.hierarchy == 0)
+        .map(|cgroup| {
+            let relative = cgroup.pathname.trim_start_matches('/');
+            Path::new(DEFAULT_CGROUP_ROOT).join(relative)
+        })
+        .with_context(|| {
+            format!(
+                "failed to find the cgroup of container {}",
+                args.container_id
+            )
+        })?;
+
+    let config = container.bundle().join("config.json");
+    let spec =
+        Spec::load(&config).with_context(|| format!("failed to load {}", config.display()))?;
+    let rules = spec
+        .linux()
+        .as_ref()
+        .and_then(|linux| linux.resources().as_ref())
+        .and_then(|resources| resources.devices().clone());
+    let emulator = DevicesController::emulator(&rules);
+
+    let programs = verify::attached_programs(&cgroup_path).with_context(|| {
+        format!(
+            "failed to read the device programs of {}",
+            cgroup_path.display()
+        )
+    })?;
+    if args.dump {
+        for program in &programs {
+            println!("program {}:", program.id);
+            program.dump();
+        }
+    }
+
+    let verdicts = verify::verify(&programs, &emulator, &verify::probes(&emulator.rules))?;
+    let mut content = String::new();
+    for verdict in &verdicts {
+        // skip devices which are covered by a more general entry with the same access
+        let covered = [
+            verdict.probe.wildcard_major(),
+            verdict.probe.wildcard_minor(),
+        ]
+        .iter()
+        .filter(|general| **general != verdict.probe)
+        .any(|general| {
+            verdicts.iter().any(|v| {
+                v.probe == *general
+                    && v.allowed == verdict.allowed
+                    && v.expected == verdict.expected
+            })
+        });
+        if covered || (verdict.allowed.is_empty() && verdict.is_consistent()) {
+            continue;
+        }
+
+        let status = if verdict.is_consistent() {
+            "ok"
+        } else {
+            "MISMATCH"
+        };
+        let _ = writeln!(
+            content,
+            "{}\t{}\t{}\t{}",
+            verdict.probe, verdict.allowed, verdict.expected, status
+        );
+    }
+
+    let mut tab_writer = TabWriter::new(io::stdout());
+    writeln!(&mut tab_writer, "DEVICE\tALLOWED\tEXPECTED\tSTATUS")?;
+    write!(&mut tab_writer, "{content}")?;
+    tab_writer.flush()?;
+
+    if verdicts.iter().any(|v| !v.is_consistent()) {
+        bail!(
+            "device programs of container {} do not match its rules",
+            args.container_id
+        );
+    }
+
+    Ok(())
+}
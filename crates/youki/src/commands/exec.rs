@@ -1,24 +1,39 @@
 use std::path::PathBuf;
 
-use anyhow::Result;
+use anyhow::{Result, bail};
 use libcontainer::container::builder::ContainerBuilder;
 use libcontainer::syscall::syscall::SyscallType;
 use liboci_cli::Exec;
 use nix::sys::wait::{WaitStatus, waitpid};
 
+use crate::console::{self, ConsoleSocket, RawTerminal};
 use crate::workload::executor::default_executor;
 
 pub fn exec(args: Exec, root_path: PathBuf) -> Result<i32> {
-    // TODO: not all values from exec are used here. We need to support
-    // the remaining ones.
     let user = args.user.map(|(u, _)| u);
     let group = args.user.and_then(|(_, g)| g);
 
+    // Without a console socket, the terminal is relayed by youki itself, which
+    // isn't possible once it has detached
+    let console = if args.tty && args.console_socket.is_none() {
+        if args.detach {
+            bail!("cannot allocate a tty for a detached process without --console-socket");
+        }
+        Some(ConsoleSocket::new(&root_path.join(&args.container_id))?)
+    } else {
+        None
+    };
+    let console_socket = args
+        .console_socket
+        .clone()
+        .or_else(|| console.as_ref().map(|c| c.path().to_owned()));
+
     let pid = ContainerBuilder::new(args.container_id.clone(), SyscallType::default())
         .with_executor(default_executor())
         .with_root_path(root_path)?
-        .with_console_socket(args.console_socket.as_ref())
+        .with_console_socket(console_socket.as_ref())
         .with_pid_file(args.pid_file.as_ref())?
+        .with_preserved_fds(args.preserve_fds)
         .validate_id()?
         .as_tenant()
         .with_detach(args.detach)
@@ -30,6 +45,11 @@ pub fn exec(args: Exec, root_path: PathBuf) -> Result<i32> {
         .with_additional_gids(args.additional_gids)
         .with_user(user)
         .with_group(group)
+        .with_capabilities(args.cap)
+        .with_apparmor_profile(args.apparmor)
+        .with_process_label(args.process_label)
+        .with_ignore_paused(args.ignore_paused)
+        .with_sub_cgroup(args.cgroup.as_ref())
         .build()?;
 
     // See https://github.com/youki-dev/youki/pull/1252 for a detailed explanation
@@ -40,9 +60,16 @@ pub fn exec(args: Exec, root_path: PathBuf) -> Result<i32> {
         return Ok(0);
     }
 
+    if let Some(console) = console {
+        let master = console.receive()?;
+        let _raw = RawTerminal::new()?;
+        console::relay(&master)?;
+    }
+
     match waitpid(pid, None)? {
         WaitStatus::Exited(_, status) => Ok(status),
-        WaitStatus::Signaled(_, sig, _) => Ok(sig as i32),
+        // like a shell, report a process killed by a signal as 128 + signal
+        WaitStatus::Signaled(_, sig, _) => Ok(128 + sig as i32),
         _ => Ok(0),
     }
 }
@@ -1,5 +1,8 @@
 //! Contains Functionality of `features` container command
+use std::collections::HashMap;
+
 use anyhow::Result;
+use libcontainer::landlock;
 use libcontainer::oci_spec::runtime::{
     ApparmorBuilder, CgroupBuilder, FeaturesBuilder, IDMapBuilder, IntelRdtBuilder,
     LinuxFeatureBuilder, LinuxNamespaceType, MountExtensionsBuilder, SelinuxBuilder, VERSION,
@@ -41,6 +44,17 @@ fn known_hooks() -> Vec<String> {
     .collect()
 }
 
+// Return the youki specific annotations describing the host
+fn youki_annotations() -> Result<HashMap<String, String>> {
+    let mut annotations = HashMap::new();
+    // 0 means that landlock is not available
+    annotations.insert(
+        "org.youki.landlock.abi".to_owned(),
+        landlock::abi_version()?.unwrap_or(0).to_string(),
+    );
+    Ok(annotations)
+}
+
 /// lists all existing containers
 pub fn features(_: Features) -> Result<()> {
     // Query supported namespaces
@@ -96,6 +110,7 @@ pub fn features(_: Features) -> Result<()> {
         .hooks(known_hooks())
         .mount_options(MountOption::known_options())
         .linux(linux)
+        .annotations(youki_annotations()?)
         .build()
         .unwrap();
 
@@ -8,7 +8,7 @@ use anyhow::Result;
 use clap::Parser;
 #[cfg(feature = "v2")]
 use libcgroups::{common::CgroupSetup, v2::controller_type::ControllerType};
-use libcontainer::user_ns;
+use libcontainer::{landlock, user_ns};
 use procfs::{CpuInfo, Current, Meminfo};
 /// Show information about the system
 #[derive(Parser, Debug)]
@@ -22,6 +22,7 @@ pub fn info(_: Info) -> Result<()> {
     print_cgroups();
     print_namespaces();
     print_capabilities();
+    print_landlock();
 
     Ok(())
 }
@@ -43,11 +44,13 @@ pub fn print_youki() {
         "rustc: {}",
         option_env!("VERGEN_RUSTC_SEMVER").unwrap_or("unknown")
     );
-    #[cfg(feature = "seccomp")]
+    #[cfg(all(feature = "seccomp", not(feature = "native_seccomp")))]
     println!(
         "libseccomp: {}",
         option_env!("LIBSECCOMP_VERSION").unwrap_or("unknown")
     );
+    #[cfg(feature = "native_seccomp")]
+    println!("seccomp: native");
 }
 
 /// Print Kernel Release, Version and Architecture
@@ -277,6 +280,14 @@ pub fn print_capabilities() {
     }
 }
 
+pub fn print_landlock() {
+    match landlock::abi_version() {
+        Ok(Some(abi)) => println!("{:<18}enabled (ABI {abi})", "Landlock"),
+        Ok(None) => println!("{:<18}disabled", "Landlock"),
+        Err(err) => println!("{:<18}<cannot query landlock: {err}>", "Landlock"),
+    }
+}
+
 fn print_feature_status(config: &str, feature: &str, display: FeatureDisplay) {
     if let Some(status_flag) = find_parameter(config, feature) {
         let status = if status_flag == "y" {
@@ -4,11 +4,14 @@ use std::path::{Path, PathBuf};
 use anyhow::{Context, Result, bail};
 use libcgroups::common::AnyCgroupManager;
 use libcontainer::container::Container;
+use libcontainer::log_sink::{LogFormat, LogSink};
 
+pub mod attach;
 pub mod checkpoint;
 pub mod completion;
 pub mod create;
 pub mod delete;
+pub mod devices;
 pub mod events;
 pub mod exec;
 pub mod features;
@@ -19,10 +22,13 @@ pub mod pause;
 pub mod ps;
 pub mod resume;
 pub mod run;
+#[cfg(any(feature = "seccomp", feature = "native_seccomp"))]
+pub mod seccomp;
 pub mod spec_json;
 pub mod start;
 pub mod state;
 pub mod update;
+pub mod wait;
 
 fn construct_container_root<P: AsRef<Path>>(root_path: P, container_id: &str) -> Result<PathBuf> {
     // resolves relative paths, symbolic links etc. and get complete path
@@ -57,11 +63,44 @@ fn create_cgroup_manager<P: AsRef<Path>>(
     container_id: &str,
 ) -> Result<AnyCgroupManager> {
     let container = load_container(root_path, container_id)?;
-    Ok(libcgroups::common::create_cgroup_manager(
-        libcgroups::common::CgroupConfig {
-            cgroup_path: container.spec()?.cgroup_path,
-            systemd_cgroup: container.systemd(),
-            container_name: container.id().to_string(),
-        },
-    )?)
+    Ok(container.cgroup_manager()?)
+}
+
+/// Options of the container log, which youki adds to create and run
+#[derive(clap::Args, Debug, Default)]
+pub struct ContainerLogOpts {
+    /// Write the stdout and stderr of the container to this file, from a relay
+    /// process which keeps running after youki exited
+    #[clap(long)]
+    pub container_log_path: Option<PathBuf>,
+    /// Format of the container log, 'cri' (default) or 'json-file'
+    #[clap(long, requires = "container_log_path")]
+    pub container_log_format: Option<String>,
+    /// Rotate the container log before it grows beyond this many bytes
+    #[clap(long, requires = "container_log_path")]
+    pub container_log_max_size: Option<u64>,
+    /// Number of container log files kept on rotation, including the current one
+    #[clap(long, requires = "container_log_max_size")]
+    pub container_log_max_files: Option<u32>,
+}
+
+impl ContainerLogOpts {
+    /// Builds the sink of the container output, if a container log is set
+    fn sink(&self) -> Result<Option<LogSink>> {
+        let Some(path) = &self.container_log_path else {
+            return Ok(None);
+        };
+        let format: LogFormat = self
+            .container_log_format
+            .as_deref()
+            .unwrap_or("cri")
+            .parse()?;
+        let path = std::path::absolute(path)
+            .with_context(|| format!("invalid log path {}", path.display()))?;
+        Ok(Some(
+            LogSink::new(path, format)
+                .with_max_size(self.container_log_max_size)
+                .with_max_files(self.container_log_max_files.unwrap_or(1)),
+        ))
+    }
 }
@@ -1,7 +1,9 @@
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
+use libcgroups::common::CgroupDriver;
 use libcontainer::container::builder::ContainerBuilder;
+use libcontainer::sd_notify::SdNotifyMode;
 use libcontainer::syscall::syscall::SyscallType;
 use liboci_cli::Run;
 use nix::sys::signal::{self, kill};
@@ -9,27 +11,62 @@ use nix::sys::signalfd::SigSet;
 use nix::sys::wait::{WaitPidFlag, WaitStatus, waitpid};
 use nix::unistd::Pid;
 
+use crate::commands::ContainerLogOpts;
+use crate::console::{self, ConsoleSocket};
 use crate::workload::executor::default_executor;
 
-pub fn run(args: Run, root_path: PathBuf, systemd_cgroup: bool) -> Result<i32> {
+pub fn run(
+    args: Run,
+    root_path: PathBuf,
+    systemd_cgroup: bool,
+    cgroup_driver: CgroupDriver,
+    sdnotify: SdNotifyMode,
+    container_log: &ContainerLogOpts,
+) -> Result<i32> {
+    // A detached container without a console socket hands its terminal to a
+    // relay in the background, which `youki attach` connects to
+    let console =
+        if args.detach && args.console_socket.is_none() && console::has_terminal(&args.bundle)? {
+            Some(ConsoleSocket::new(&root_path)?)
+        } else {
+            None
+        };
+    let console_socket = args
+        .console_socket
+        .clone()
+        .or_else(|| console.as_ref().map(|c| c.path().to_owned()));
+
     let mut container = ContainerBuilder::new(args.container_id.clone(), SyscallType::default())
         .with_executor(default_executor())
         .with_pid_file(args.pid_file.as_ref())?
-        .with_console_socket(args.console_socket.as_ref())
+        .with_console_socket(console_socket.as_ref())
         .with_root_path(root_path)?
         .with_preserved_fds(args.preserve_fds)
+        .with_log_sink(container_log.sink()?)
         .validate_id()?
         .as_init(&args.bundle)
         .with_systemd(systemd_cgroup)
+        .with_cgroup_driver(cgroup_driver)
         .with_detach(args.detach)
         .with_no_pivot(args.no_pivot)
+        .with_sd_notify(sdnotify, std::env::var_os("NOTIFY_SOCKET"))
         .build()?;
 
+    if let Some(console) = console {
+        console::spawn_relay(console, &container.root)?;
+    }
+
+    let notify_proxy = container.notify_proxy()?;
     container
         .start()
         .with_context(|| format!("failed to start container {}", args.container_id))?;
 
     if args.detach {
+        if let (Some(notify_proxy), Some(pid)) = (notify_proxy, container.pid()) {
+            notify_proxy
+                .spawn_relay(pid)
+                .context("failed to relay notifications of the container")?;
+        }
         return Ok(0);
     }
 
@@ -40,7 +77,20 @@ pub fn run(args: Run, root_path: PathBuf, systemd_cgroup: bool) -> Result<i32> {
         container.pid().is_some(),
         "expects a container init pid in the container state"
     );
-    let foreground_result = handle_foreground(container.pid().unwrap());
+    let init_pid = container.pid().unwrap();
+    if let Some(notify_proxy) = notify_proxy {
+        // The signals are blocked before the relay thread is spawned, so that they
+        // are only delivered to the thread waiting for them in handle_foreground.
+        SigSet::all()
+            .thread_block()
+            .with_context(|| "failed to call pthread_sigmask")?;
+        std::thread::spawn(move || {
+            if let Err(err) = notify_proxy.relay(init_pid, false) {
+                tracing::warn!(?err, "failed to relay notifications of the container");
+            }
+        });
+    }
+    let foreground_result = handle_foreground(init_pid);
     // execute the destruction action after the container finishes running
     container.delete(true)?;
     // return result
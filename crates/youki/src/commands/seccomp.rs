@@ -0,0 +1,354 @@
+//! Contains functionality of the seccomp command, which shows how the seccomp profile
+//! of a bundle is translated into a filter
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use libcontainer::seccomp::bpf::{SeccompData, describe_action, evaluate};
+#[cfg(feature = "native_seccomp")]
+use libcontainer::seccomp::native::{Filter, Reason};
+use libcontainer::seccomp::{compile_seccomp, resolve_arch, resolve_syscall};
+use oci_spec::runtime::LinuxSeccomp;
+
+/// Inspect the seccomp profile of a bundle
+#[derive(Parser, Debug)]
+pub struct Seccomp {
+    /// Path to the bundle or its config.json
+    #[clap(short, long, default_value = ".")]
+    pub bundle: PathBuf,
+    #[clap(subcommand)]
+    pub cmd: SeccompCmd,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SeccompCmd {
+    /// Print the rules of the filter per architecture
+    Dump,
+    /// Show the action the filter takes for a syscall
+    Check {
+        /// Architecture of the syscall, e.g. x86_64 (defaults to the native one)
+        #[clap(long)]
+        arch: Option<String>,
+        /// Name or number of the syscall
+        syscall: String,
+        /// Arguments of the syscall, decimal or hexadecimal with a 0x prefix
+        #[clap(num_args = 0..=6, value_parser = parse_arg)]
+        args: Vec<u64>,
+    },
+    /// Write the compiled filter as raw BPF instructions, e.g. for `seccomp-tools disasm`
+    Export {
+        /// File to write the filter to (defaults to stdout)
+        #[clap(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+fn parse_arg(arg: &str) -> Result<u64, std::num::ParseIntError> {
+    match arg.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => arg.parse(),
+    }
+}
+
+pub fn seccomp(args: Seccomp) -> Result<()> {
+    use std::{fs, io};
+
+    let seccomp = load_seccomp(&args.bundle)?;
+    match args.cmd {
+        SeccompCmd::Dump => {
+            #[cfg(feature = "native_seccomp")]
+            dump(&Filter::from_spec(&seccomp)?, &mut io::stdout())?;
+            #[cfg(not(feature = "native_seccomp"))]
+            libcontainer::seccomp::export_pfc(&seccomp, io::stdout())?;
+        }
+        SeccompCmd::Check {
+            arch,
+            syscall,
+            args,
+        } => {
+            let (arch_name, token) = resolve_arch(arch.as_deref())?;
+            let nr = match parse_arg(&syscall) {
+                Ok(nr) => u32::try_from(nr).context("syscall number is out of range")?,
+                Err(_) => resolve_syscall(arch.as_deref(), &syscall)?,
+            };
+            let mut syscall_args = [0; 6];
+            syscall_args[..args.len()].copy_from_slice(&args);
+
+            // the compiled program is what the kernel runs
+            let program = compile_seccomp(&seccomp)?;
+            let data = SeccompData {
+                nr: nr as i32,
+                arch: token,
+                args: syscall_args,
+                ..Default::default()
+            };
+            let action = evaluate(&program, &data)?;
+
+            let mut stdout = io::stdout();
+            check(&arch_name, nr, &syscall_args, action, &mut stdout)?;
+            // the rules of the native backend tell why
+            #[cfg(feature = "native_seccomp")]
+            explain(
+                &Filter::from_spec(&seccomp)?,
+                token,
+                nr,
+                &syscall_args,
+                action,
+                &mut stdout,
+            )?;
+        }
+        SeccompCmd::Export { output } => {
+            let bytes: Vec<u8> = compile_seccomp(&seccomp)?
+                .iter()
+                .flat_map(|instruction| instruction.to_ne_bytes())
+                .collect();
+            match output {
+                Some(path) => fs::write(&path, bytes)
+                    .with_context(|| format!("failed to write {}", path.display()))?,
+                None => io::stdout().write_all(&bytes)?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn load_seccomp(bundle: &Path) -> Result<LinuxSeccomp> {
+    use oci_spec::runtime::Spec;
+
+    let config = if bundle.is_dir() {
+        bundle.join("config.json")
+    } else {
+        bundle.to_path_buf()
+    };
+    let spec =
+        Spec::load(&config).with_context(|| format!("failed to load {}", config.display()))?;
+    spec.linux()
+        .as_ref()
+        .and_then(|linux| linux.seccomp().clone())
+        .with_context(|| format!("{} has no seccomp profile", config.display()))
+}
+
+#[cfg(feature = "native_seccomp")]
+fn dump(filter: &Filter, out: &mut impl Write) -> Result<()> {
+    use nix::libc;
+    use tabwriter::TabWriter;
+
+    let flags: Vec<&str> = [
+        (libc::SECCOMP_FILTER_FLAG_TSYNC, "TSYNC"),
+        (libc::SECCOMP_FILTER_FLAG_LOG, "LOG"),
+        (libc::SECCOMP_FILTER_FLAG_SPEC_ALLOW, "SPEC_ALLOW"),
+        (libc::SECCOMP_FILTER_FLAG_NEW_LISTENER, "NEW_LISTENER"),
+        (libc::SECCOMP_FILTER_FLAG_TSYNC_ESRCH, "TSYNC_ESRCH"),
+        (
+            libc::SECCOMP_FILTER_FLAG_WAIT_KILLABLE_RECV,
+            "WAIT_KILLABLE_RECV",
+        ),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| filter.flags & *flag as u32 != 0)
+    .map(|(_, name)| name)
+    .collect();
+    writeln!(
+        out,
+        "default action: {}",
+        describe_action(filter.default_action)
+    )?;
+    writeln!(out, "flags: {}", flags.join(" "))?;
+
+    for section in &filter.sections {
+        let archs: Vec<String> = section.archs.iter().map(|arch| arch.to_string()).collect();
+        writeln!(out)?;
+        writeln!(
+            out,
+            "arch: {} (audit arch {:#x})",
+            archs.join(" "),
+            section.token()
+        )?;
+
+        let mut tab_writer = TabWriter::new(&mut *out);
+        writeln!(&mut tab_writer, "NR\tSYSCALL\tACTION\tCONDITION")?;
+        for (nr, rules) in &section.syscalls {
+            if let Some(action) = rules.action {
+                writeln!(
+                    &mut tab_writer,
+                    "{nr}\t{}\t{}\t-",
+                    rules.name,
+                    describe_action(action)
+                )?;
+            }
+            for condition in &rules.conditions {
+                writeln!(
+                    &mut tab_writer,
+                    "{nr}\t{}\t{}\t{condition}",
+                    rules.name,
+                    describe_action(condition.action)
+                )?;
+            }
+        }
+        tab_writer.flush()?;
+    }
+
+    Ok(())
+}
+
+fn check(arch: &str, nr: u32, args: &[u64; 6], action: u32, out: &mut impl Write) -> Result<()> {
+    let args: Vec<String> = args.iter().map(|arg| format!("{arg:#x}")).collect();
+    writeln!(
+        out,
+        "{arch} syscall {nr}({}): {}",
+        args.join(", "),
+        describe_action(action)
+    )?;
+
+    Ok(())
+}
+
+#[cfg(feature = "native_seccomp")]
+fn explain(
+    filter: &Filter,
+    token: u32,
+    nr: u32,
+    args: &[u64; 6],
+    action: u32,
+    out: &mut impl Write,
+) -> Result<()> {
+    let decision = filter.explain(token, nr, args);
+    if decision.action != action {
+        anyhow::bail!(
+            "the compiled filter returns {action:#x}, but the rules {:#x}",
+            decision.action
+        );
+    }
+    let reason = match decision.reason {
+        Reason::UnknownArch => format!("{token:#x} is not an architecture of the profile"),
+        Reason::UnknownAbi => format!("{nr} is a syscall of the other x86_64 ABI"),
+        Reason::Unconditional(rules) => format!("unconditional rule of {}", rules.name),
+        Reason::Condition(rules, condition) => {
+            format!("rule of {} with {condition}", rules.name)
+        }
+        Reason::Default => "default action, no rule matches".to_owned(),
+    };
+    writeln!(out, "reason: {reason}")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use oci_spec::runtime::{
+        LinuxSeccompAction, LinuxSeccompArgBuilder, LinuxSeccompBuilder, LinuxSeccompOperator,
+        LinuxSyscallBuilder,
+    };
+
+    use super::*;
+
+    // an architecture the profile has no rules for, which can't be the one of
+    // the host, as the native architecture is always added to the filter
+    #[cfg(not(target_arch = "aarch64"))]
+    const FOREIGN_ARCH: &str = "aarch64";
+    #[cfg(target_arch = "aarch64")]
+    const FOREIGN_ARCH: &str = "s390x";
+
+    fn profile() -> Result<LinuxSeccomp> {
+        Ok(LinuxSeccompBuilder::default()
+            .default_action(LinuxSeccompAction::ScmpActErrno)
+            .architectures(vec![oci_spec::runtime::Arch::ScmpArchX86_64])
+            .syscalls(vec![
+                LinuxSyscallBuilder::default()
+                    .names(vec!["read".to_owned()])
+                    .action(LinuxSeccompAction::ScmpActAllow)
+                    .build()?,
+                LinuxSyscallBuilder::default()
+                    .names(vec!["personality".to_owned()])
+                    .action(LinuxSeccompAction::ScmpActAllow)
+                    .args(vec![
+                        LinuxSeccompArgBuilder::default()
+                            .index(0usize)
+                            .value(8u64)
+                            .op(LinuxSeccompOperator::ScmpCmpEq)
+                            .build()?,
+                    ])
+                    .build()?,
+            ])
+            .build()?)
+    }
+
+    fn decide(arch: &str, syscall: &str, args: [u64; 6]) -> Result<u32> {
+        let (_, token) = resolve_arch(Some(arch))?;
+        let data = SeccompData {
+            nr: resolve_syscall(Some(arch), syscall)? as i32,
+            arch: token,
+            args,
+            ..Default::default()
+        };
+        Ok(evaluate(&compile_seccomp(&profile()?)?, &data)?)
+    }
+
+    #[test]
+    fn test_parse_arg() {
+        assert_eq!(parse_arg("10"), Ok(10));
+        assert_eq!(parse_arg("0x10"), Ok(16));
+        assert!(parse_arg("x").is_err());
+    }
+
+    #[test]
+    fn test_check() -> Result<()> {
+        let action = decide("x86_64", "personality", [8, 0, 0, 0, 0, 0])?;
+        let mut out = Vec::new();
+        check("x86_64", 135, &[8, 0, 0, 0, 0, 0], action, &mut out)?;
+        let out = String::from_utf8(out)?;
+        assert!(out.contains("x86_64 syscall 135(0x8"), "{out}");
+        assert!(out.contains(": ALLOW"), "{out}");
+
+        let action = decide("x86_64", "personality", [0; 6])?;
+        assert_eq!(describe_action(action), "ERRNO(1)");
+        // the profile has no rules for other architectures
+        assert_ne!(
+            describe_action(decide(FOREIGN_ARCH, "read", [0; 6])?),
+            "ALLOW"
+        );
+        assert!(resolve_arch(Some("vax")).is_err());
+        assert!(resolve_syscall(Some("x86_64"), "no_such_syscall").is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "native_seccomp")]
+    #[test]
+    fn test_explain() -> Result<()> {
+        let filter = Filter::from_spec(&profile()?)?;
+        let (_, token) = resolve_arch(Some("x86_64"))?;
+        let mut out = Vec::new();
+        let action = decide("x86_64", "personality", [8, 0, 0, 0, 0, 0])?;
+        explain(&filter, token, 135, &[8, 0, 0, 0, 0, 0], action, &mut out)?;
+        let out = String::from_utf8(out)?;
+        assert!(
+            out.contains("rule of personality with arg0 == 0x8"),
+            "{out}"
+        );
+
+        let mut out = Vec::new();
+        let action = decide("x86_64", "personality", [0; 6])?;
+        explain(&filter, token, 135, &[0; 6], action, &mut out)?;
+        let out = String::from_utf8(out)?;
+        assert!(out.contains("default action"), "{out}");
+        Ok(())
+    }
+
+    #[cfg(feature = "native_seccomp")]
+    #[test]
+    fn test_dump() -> Result<()> {
+        let mut out = Vec::new();
+        dump(&Filter::from_spec(&profile()?)?, &mut out)?;
+        let out = String::from_utf8(out)?;
+        assert!(out.contains("default action: ERRNO(1)"), "{out}");
+        assert!(
+            out.contains("arch: x86_64 (audit arch 0xc000003e)"),
+            "{out}"
+        );
+        assert!(out.contains("read"), "{out}");
+        assert!(out.contains("arg0 == 0x8"), "{out}");
+        Ok(())
+    }
+}
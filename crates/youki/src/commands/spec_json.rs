@@ -7,6 +7,7 @@ use libcontainer::oci_spec::runtime::{
     LinuxBuilder, LinuxIdMappingBuilder, LinuxNamespace, LinuxNamespaceBuilder, LinuxNamespaceType,
     Mount, Spec,
 };
+use libcontainer::subid::SubIdMappings;
 use libcontainer::syscall::syscall::Syscall;
 use serde_json::to_writer_pretty;
 
@@ -85,9 +86,34 @@ pub fn get_rootless(syscall: &dyn Syscall) -> Result<Spec> {
     Ok(spec)
 }
 
+/// Generates a rootless spec which maps the subordinate ids of the user, so that
+/// the container has the usual 65536 users and groups.
+pub fn get_rootless_with_subids(syscall: &dyn Syscall) -> Result<Spec> {
+    let mut spec = get_rootless(syscall)?;
+    let mappings = SubIdMappings::for_user(syscall.get_euid(), syscall.get_egid())?;
+
+    // the ids of the default mounts are mapped again
+    let default_mounts = libcontainer::oci_spec::runtime::get_default_mounts();
+    if let Some(mounts) = spec.mounts_mut() {
+        for mount in mounts.iter_mut() {
+            let default = default_mounts.iter().find(|default| {
+                default.destination() == mount.destination() && default.typ() == mount.typ()
+            });
+            if let Some(default) = default {
+                mount.set_options(default.options().clone());
+            }
+        }
+    }
+
+    mappings.apply(&mut spec)?;
+    Ok(spec)
+}
+
 /// spec Cli command
 pub fn spec(args: liboci_cli::Spec, syscall: &dyn Syscall) -> Result<()> {
-    let spec = if args.rootless {
+    let spec = if args.subids {
+        get_rootless_with_subids(syscall)?
+    } else if args.rootless {
         get_rootless(syscall)?
     } else {
         get_default()?
@@ -9,7 +9,18 @@ use crate::commands::load_container;
 
 pub fn start(args: Start, root_path: PathBuf) -> Result<()> {
     let mut container = load_container(root_path, &args.container_id)?;
+    let notify_proxy = container.notify_proxy()?;
     container
         .start()
-        .with_context(|| format!("failed to start container {}", args.container_id))
+        .with_context(|| format!("failed to start container {}", args.container_id))?;
+
+    // like runc, youki start returns once the container reported that it is
+    // ready, and the notifications after that are relayed in the background
+    if let (Some(notify_proxy), Some(pid)) = (notify_proxy, container.pid()) {
+        notify_proxy
+            .spawn_relay(pid)
+            .context("failed to relay notifications of the container")?;
+    }
+
+    Ok(())
 }
@@ -1,16 +1,20 @@
 use std::path::PathBuf;
 use std::{fs, io};
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use libcgroups::common::{CgroupManager, ControllerOpt};
 use libcgroups::{self};
-use libcontainer::oci_spec::runtime::{LinuxPidsBuilder, LinuxResources, LinuxResourcesBuilder};
+use libcontainer::container::Container;
+use libcontainer::oci_spec::runtime::{
+    LinuxIntelRdt, LinuxMemoryPolicy, LinuxPidsBuilder, LinuxResources, LinuxResourcesBuilder,
+    MemoryPolicyModeType, Spec,
+};
 use liboci_cli::Update;
 
-use crate::commands::create_cgroup_manager;
+use crate::commands::{create_cgroup_manager, load_container};
 
 pub fn update(args: Update, root_path: PathBuf) -> Result<()> {
-    let cmanager = create_cgroup_manager(root_path, &args.container_id)?;
+    let cmanager = create_cgroup_manager(&root_path, &args.container_id)?;
 
     let linux_res: LinuxResources;
     if let Some(resources_path) = args.resources {
@@ -35,5 +39,58 @@ pub fn update(args: Update, root_path: PathBuf) -> Result<()> {
         oom_score_adj: None,
         freezer_state: None,
     })?;
+
+    let mut container = load_container(&root_path, &args.container_id)?;
+    if args.l3_cache_schema.is_some() || args.mem_bw_schema.is_some() {
+        let mut intel_rdt = LinuxIntelRdt::default();
+        intel_rdt
+            .set_l3_cache_schema(args.l3_cache_schema)
+            .set_mem_bw_schema(args.mem_bw_schema);
+        container
+            .update_intel_rdt(&intel_rdt)
+            .context("failed to update Intel RDT")?;
+    }
+
+    if let Some(mode) = args.memory_policy {
+        let mode: MemoryPolicyModeType = mode
+            .parse()
+            .with_context(|| format!("invalid memory policy mode {mode}"))?;
+        let mut memory_policy = LinuxMemoryPolicy::default();
+        memory_policy
+            .set_mode(mode)
+            .set_nodes(args.memory_policy_nodes);
+        container
+            .update_memory_policy(&memory_policy)
+            .context("failed to update the memory policy")?;
+    }
+
+    let cpu_affinity = match args.exec_cpu_affinity {
+        Some(cpus) => Some(cpus),
+        // Changing the cpuset resets the CPU affinity of the processes, so
+        // the configured one is applied again.
+        None if linux_res
+            .cpu()
+            .as_ref()
+            .is_some_and(|cpu| cpu.cpus().is_some()) =>
+        {
+            configured_cpu_affinity(&container)?
+        }
+        None => None,
+    };
+    if let Some(cpus) = cpu_affinity {
+        container
+            .update_cpu_affinity(&cpus)
+            .context("failed to update the CPU affinity")?;
+    }
+
     Ok(())
 }
+
+fn configured_cpu_affinity(container: &Container) -> Result<Option<String>> {
+    let spec = Spec::load(container.bundle().join("config.json"))?;
+    Ok(spec
+        .process()
+        .as_ref()
+        .and_then(|process| process.exec_cpu_affinity().as_ref())
+        .and_then(|affinity| affinity.cpu_affinity_final().clone()))
+}
@@ -0,0 +1,35 @@
+//! Contains functionality of the wait command, which blocks until the process
+//! of a container exited and prints its exit code
+use std::os::unix::process::ExitStatusExt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{Result, bail};
+use clap::Parser;
+
+use crate::commands::load_container;
+
+/// Wait for the process of a container to exit and print its exit code
+#[derive(Parser, Debug)]
+pub struct Wait {
+    /// Give up after this many seconds
+    #[clap(long)]
+    pub timeout: Option<u64>,
+    #[clap(value_parser = clap::builder::NonEmptyStringValueParser::new(), required = true)]
+    pub container_id: String,
+}
+
+pub fn wait(args: Wait, root_path: PathBuf) -> Result<()> {
+    let container = load_container(root_path, &args.container_id)?;
+    let status = container.wait(args.timeout.map(Duration::from_secs))?;
+
+    // like a shell, report a process killed by a signal as 128 + signal
+    match status.and_then(|status| status.code().or(status.signal().map(|sig| 128 + sig))) {
+        Some(code) => println!("{code}"),
+        None => bail!(
+            "container {} exited, but its exit status is not available",
+            args.container_id
+        ),
+    }
+    Ok(())
+}
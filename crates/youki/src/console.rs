@@ -0,0 +1,669 @@
+//! Relays the terminal of a container process to the terminal youki runs in,
+//! for processes started with a terminal but without a console socket. A
+//! process youki doesn't wait for is relayed by a process in the background,
+//! which `youki attach` connects to.
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use nix::errno::Errno;
+use nix::libc;
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use nix::sys::signal::{SigSet, Signal};
+use nix::sys::signalfd::{SfdFlags, SignalFd};
+use nix::sys::termios::{self, SetArg, Termios};
+use nix::unistd::{self, ForkResult};
+use oci_spec::runtime::Spec;
+
+nix::ioctl_read_bad!(get_window_size, libc::TIOCGWINSZ, libc::winsize);
+nix::ioctl_write_ptr_bad!(set_window_size, libc::TIOCSWINSZ, libc::winsize);
+
+/// Socket in the container directory the console relay accepts `youki attach` on
+pub const ATTACH_SOCKET: &str = "attach.sock";
+/// File in the container directory the console relay logs the terminal output to
+pub const CONSOLE_LOG: &str = "console.log";
+/// Size the console log may grow to before it is moved to `console.log.1`,
+/// replacing the previous one
+const CONSOLE_LOG_MAX_SIZE: u64 = 1024 * 1024;
+
+/// A console socket in the directory of the container, which receives the pty
+/// master of the container process. It is removed when dropped.
+pub struct ConsoleSocket {
+    listener: UnixListener,
+    path: PathBuf,
+}
+
+impl ConsoleSocket {
+    pub fn new(dir: &Path) -> Result<Self> {
+        // the socket is linked from the container directory, so it must be absolute
+        let dir = std::fs::canonicalize(dir)
+            .with_context(|| format!("failed to canonicalize {}", dir.display()))?;
+        let path = dir.join(format!("console-{}.sock", std::process::id()));
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("failed to create console socket {}", path.display()))?;
+        Ok(Self { listener, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Waits for the pty master, which the container process sends once it has
+    /// set up its terminal
+    pub fn receive(&self) -> Result<OwnedFd> {
+        libcontainer::tty::receive_console(&self.listener).context("failed to receive pty master")
+    }
+}
+
+impl Drop for ConsoleSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Puts the terminal on stdin into raw mode, so that keys like ^C reach the
+/// container, and restores it when dropped
+pub struct RawTerminal {
+    original: Option<Termios>,
+}
+
+impl RawTerminal {
+    pub fn new() -> Result<Self> {
+        let stdin = std::io::stdin();
+        if !nix::unistd::isatty(stdin.as_raw_fd()).unwrap_or(false) {
+            return Ok(Self { original: None });
+        }
+
+        let original = termios::tcgetattr(&stdin).context("failed to get terminal attributes")?;
+        let mut raw = original.clone();
+        termios::cfmakeraw(&mut raw);
+        termios::tcsetattr(&stdin, SetArg::TCSANOW, &raw)
+            .context("failed to set terminal to raw mode")?;
+        Ok(Self {
+            original: Some(original),
+        })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        if let Some(original) = &self.original
+            && let Err(err) = termios::tcsetattr(std::io::stdin(), SetArg::TCSANOW, original)
+        {
+            tracing::warn!(?err, "failed to restore terminal");
+        }
+    }
+}
+
+/// Returns the window size of the terminal on stdin, if stdin is a terminal
+fn window_size() -> Option<libc::winsize> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    // Safety: size is a valid winsize
+    unsafe { get_window_size(std::io::stdin().as_raw_fd(), &mut size) }.ok()?;
+    Some(size)
+}
+
+fn set_pty_size(master: BorrowedFd, size: &libc::winsize) -> Result<()> {
+    // Safety: size is a valid winsize
+    unsafe { set_window_size(master.as_raw_fd(), size) }.context("failed to resize pty")?;
+    Ok(())
+}
+
+/// Copies the window size of the terminal on stdin to the pty
+pub fn resize(master: BorrowedFd) -> Result<()> {
+    // if stdin is not a terminal, the pty keeps its default size
+    match window_size() {
+        Some(size) => set_pty_size(master, &size),
+        None => Ok(()),
+    }
+}
+
+/// Blocks SIGWINCH and returns a signalfd it can be read from instead
+fn window_change_signal() -> Result<SignalFd> {
+    let mut mask = SigSet::empty();
+    mask.add(Signal::SIGWINCH);
+    mask.thread_block().context("failed to block SIGWINCH")?;
+    SignalFd::with_flags(&mask, SfdFlags::SFD_CLOEXEC).context("failed to create signalfd")
+}
+
+fn is_ready(fd: &PollFd) -> bool {
+    fd.revents().is_some_and(|events| !events.is_empty())
+}
+
+/// Relays stdin to the pty and the pty to stdout until all processes of the
+/// container closed the terminal. Window size changes are forwarded as well.
+pub fn relay(master: &OwnedFd) -> Result<()> {
+    let winch = window_change_signal()?;
+    resize(master.as_fd())?;
+
+    let stdin = std::io::stdin();
+    let mut stdin_open = true;
+    let mut buf = [0u8; 4096];
+    loop {
+        let mut fds = vec![
+            PollFd::new(master.as_fd(), PollFlags::POLLIN),
+            PollFd::new(winch.as_fd(), PollFlags::POLLIN),
+        ];
+        if stdin_open {
+            fds.push(PollFd::new(stdin.as_fd(), PollFlags::POLLIN));
+        }
+        match poll(&mut fds, PollTimeout::NONE) {
+            Err(Errno::EINTR) => continue,
+            result => result.context("failed to poll terminal")?,
+        };
+        let (master_ready, winch_ready) = (is_ready(&fds[0]), is_ready(&fds[1]));
+        let stdin_ready = stdin_open && is_ready(&fds[2]);
+        drop(fds);
+
+        if master_ready {
+            // the pty returns EIO once the last process closed the terminal
+            match nix::unistd::read(master.as_raw_fd(), &mut buf) {
+                Ok(0) | Err(Errno::EIO) => return Ok(()),
+                Ok(n) => {
+                    let mut stdout = std::io::stdout().lock();
+                    stdout.write_all(&buf[..n])?;
+                    stdout.flush()?;
+                }
+                Err(Errno::EINTR | Errno::EAGAIN) => {}
+                Err(err) => return Err(err).context("failed to read from pty"),
+            }
+        }
+        if winch_ready {
+            let _ = winch.read_signal();
+            resize(master.as_fd())?;
+        }
+        if stdin_ready {
+            // std::io::Stdin buffers, which would hide input from poll
+            match nix::unistd::read(stdin.as_raw_fd(), &mut buf) {
+                // keep relaying the output after the input was closed
+                Ok(0) => stdin_open = false,
+                Ok(n) => write_all(master, &buf[..n])?,
+                Err(Errno::EINTR | Errno::EAGAIN) => {}
+                Err(err) => return Err(err).context("failed to read from stdin"),
+            }
+        }
+    }
+}
+
+fn write_all(fd: &OwnedFd, mut data: &[u8]) -> Result<()> {
+    while !data.is_empty() {
+        match nix::unistd::write(fd, data) {
+            Ok(n) => data = &data[n..],
+            Err(Errno::EINTR) => {}
+            Err(err) => return Err(err).context("failed to write to pty"),
+        }
+    }
+    Ok(())
+}
+
+/// Returns if the process of the container in the bundle requests a terminal
+pub fn has_terminal(bundle: &Path) -> Result<bool> {
+    let spec = Spec::load(bundle.join("config.json")).context("failed to load spec")?;
+    Ok(spec
+        .process()
+        .as_ref()
+        .and_then(|process| process.terminal())
+        .unwrap_or(false))
+}
+
+/// Hands the pty master sent to the console socket to a relay process in the
+/// background, which logs the terminal output to the container directory and
+/// serves `youki attach` until all processes of the container closed the
+/// terminal.
+pub fn spawn_relay(console: ConsoleSocket, container_dir: &Path) -> Result<()> {
+    let attach_path = container_dir.join(ATTACH_SOCKET);
+    let attach = UnixListener::bind(&attach_path)
+        .with_context(|| format!("failed to create attach socket {}", attach_path.display()))?;
+    let log = ConsoleLog::open(container_dir.join(CONSOLE_LOG), CONSOLE_LOG_MAX_SIZE)?;
+
+    // Safety: youki is single threaded at this point, and the child only
+    // relays the terminal before it exits
+    match unsafe { unistd::fork() }.context("failed to fork console relay")? {
+        // the relay process already accepts on the sockets, so the console
+        // socket can be removed once the parent is done with it
+        ForkResult::Parent { .. } => Ok(()),
+        ForkResult::Child => {
+            let keep = [
+                console.listener.as_raw_fd(),
+                attach.as_raw_fd(),
+                log.file.as_raw_fd(),
+            ];
+            let result = libcontainer::utils::detach_from_caller(&keep)
+                .context("failed to detach console relay")
+                .and_then(|_| serve(&console, &attach, log));
+            let _ = std::fs::remove_file(&attach_path);
+            if let Err(err) = result {
+                tracing::error!(?err, "console relay failed");
+                std::process::exit(1);
+            }
+            std::process::exit(0);
+        }
+    }
+}
+
+/// The terminal output of the container, kept below max_size bytes by moving
+/// it aside once it is full
+struct ConsoleLog {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+}
+
+impl ConsoleLog {
+    fn open(path: PathBuf, max_size: u64) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("failed to open console log {}", path.display()))?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(Self {
+            path,
+            file,
+            size,
+            max_size,
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        if self.size > 0 && self.size + data.len() as u64 > self.max_size {
+            let mut rotated = self.path.as_os_str().to_owned();
+            rotated.push(".1");
+            std::fs::rename(&self.path, rotated)?;
+            self.file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)?;
+            self.size = 0;
+        }
+        self.file.write_all(data)?;
+        self.size += data.len() as u64;
+        Ok(())
+    }
+}
+
+fn serve(console: &ConsoleSocket, attach: &UnixListener, mut log: ConsoleLog) -> Result<()> {
+    let master = console.receive()?;
+    let mut clients: Vec<AttachClient> = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let mut fds = vec![
+            PollFd::new(master.as_fd(), PollFlags::POLLIN),
+            PollFd::new(attach.as_fd(), PollFlags::POLLIN),
+        ];
+        fds.extend(
+            clients
+                .iter()
+                .map(|client| PollFd::new(client.stream.as_fd(), PollFlags::POLLIN)),
+        );
+        match poll(&mut fds, PollTimeout::NONE) {
+            Err(Errno::EINTR) => continue,
+            result => result.context("failed to poll console relay")?,
+        };
+        let ready: Vec<bool> = fds.iter().map(is_ready).collect();
+        drop(fds);
+
+        if ready[0] {
+            // the pty returns EIO once the last process closed the terminal
+            match unistd::read(master.as_raw_fd(), &mut buf) {
+                Ok(0) | Err(Errno::EIO) => return Ok(()),
+                Ok(n) => {
+                    // the output is logged even if nobody is attached, so that
+                    // the container never blocks on a full pty
+                    if let Err(err) = log.write(&buf[..n]) {
+                        tracing::warn!(?err, "failed to write console log");
+                    }
+                    clients.retain_mut(|client| client.send(&buf[..n]));
+                }
+                Err(Errno::EINTR | Errno::EAGAIN) => {}
+                Err(err) => return Err(err).context("failed to read from pty"),
+            }
+        }
+
+        let mut client_ready = ready[2..].iter();
+        clients.retain_mut(|client| match client_ready.next() {
+            Some(true) => client.receive(&master),
+            _ => true,
+        });
+
+        if ready[1] {
+            match attach.accept() {
+                Ok((stream, _)) => {
+                    stream.set_nonblocking(true)?;
+                    clients.push(AttachClient {
+                        stream,
+                        pending: Vec::new(),
+                    });
+                }
+                Err(err) => tracing::warn!(?err, "failed to accept attach connection"),
+            }
+        }
+    }
+}
+
+/// A `youki attach` connected to the console relay
+struct AttachClient {
+    stream: UnixStream,
+    /// Received bytes which don't make up a complete frame yet
+    pending: Vec<u8>,
+}
+
+impl AttachClient {
+    /// Sends terminal output to the client, which misses it rather than
+    /// blocking the terminal if it doesn't keep up. Returns false once the
+    /// client is gone.
+    fn send(&mut self, data: &[u8]) -> bool {
+        match (&self.stream).write_all(data) {
+            Ok(()) => true,
+            Err(err) => err.kind() == ErrorKind::WouldBlock,
+        }
+    }
+
+    /// Applies the frames received from the client to the pty. Returns false
+    /// once the client is gone.
+    fn receive(&mut self, master: &OwnedFd) -> bool {
+        let mut buf = [0u8; 4096];
+        match (&self.stream).read(&mut buf) {
+            Ok(0) => return false,
+            Ok(n) => self.pending.extend_from_slice(&buf[..n]),
+            Err(err) => {
+                return matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted);
+            }
+        }
+
+        let mut consumed = 0;
+        loop {
+            let (frame, len) = match Frame::decode(&self.pending[consumed..]) {
+                Ok(Some(decoded)) => decoded,
+                Ok(None) => break,
+                Err(err) => {
+                    tracing::warn!(?err, "dropping attach connection");
+                    return false;
+                }
+            };
+            let result = match frame {
+                Frame::Input(data) => write_all(master, data),
+                Frame::Resize { rows, cols } => set_pty_size(
+                    master.as_fd(),
+                    &libc::winsize {
+                        ws_row: rows,
+                        ws_col: cols,
+                        ws_xpixel: 0,
+                        ws_ypixel: 0,
+                    },
+                ),
+            };
+            if let Err(err) = result {
+                tracing::warn!(?err, "failed to apply attach input");
+            }
+            consumed += len;
+        }
+        self.pending.drain(..consumed);
+        true
+    }
+}
+
+/// Messages `youki attach` sends to the console relay, each a type byte and a
+/// big endian u16 length followed by the payload
+#[derive(Debug, PartialEq)]
+enum Frame<'a> {
+    Input(&'a [u8]),
+    Resize { rows: u16, cols: u16 },
+}
+
+const FRAME_HEADER_LEN: usize = 3;
+const FRAME_INPUT: u8 = 0;
+const FRAME_RESIZE: u8 = 1;
+
+impl<'a> Frame<'a> {
+    fn encode(&self) -> Vec<u8> {
+        let (kind, payload) = match self {
+            Frame::Input(data) => (FRAME_INPUT, data.to_vec()),
+            Frame::Resize { rows, cols } => (
+                FRAME_RESIZE,
+                [rows.to_be_bytes(), cols.to_be_bytes()].concat(),
+            ),
+        };
+        let len = u16::try_from(payload.len()).expect("frame payload exceeds u16::MAX");
+        let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+        frame.push(kind);
+        frame.extend_from_slice(&len.to_be_bytes());
+        frame.extend_from_slice(&payload);
+        frame
+    }
+
+    /// Decodes the frame at the start of buf, together with its length in
+    /// bytes, or None if buf doesn't hold a complete frame yet
+    fn decode(buf: &'a [u8]) -> Result<Option<(Self, usize)>> {
+        if buf.len() < FRAME_HEADER_LEN {
+            return Ok(None);
+        }
+        let len = FRAME_HEADER_LEN + u16::from_be_bytes([buf[1], buf[2]]) as usize;
+        if buf.len() < len {
+            return Ok(None);
+        }
+        let payload = &buf[FRAME_HEADER_LEN..len];
+        let frame = match (buf[0], payload) {
+            (FRAME_INPUT, data) => Frame::Input(data),
+            (FRAME_RESIZE, [r0, r1, c0, c1]) => Frame::Resize {
+                rows: u16::from_be_bytes([*r0, *r1]),
+                cols: u16::from_be_bytes([*c0, *c1]),
+            },
+            (kind, _) => bail!("invalid frame of type {kind} with {} bytes", payload.len()),
+        };
+        Ok(Some((frame, len)))
+    }
+}
+
+/// A key sequence which detaches `youki attach` from the container
+#[derive(Debug)]
+pub struct DetachKeys {
+    keys: Vec<u8>,
+    /// Number of keys of the sequence typed so far
+    matched: usize,
+}
+
+impl DetachKeys {
+    /// Parses comma separated keys, each either a single character or ctrl-
+    /// followed by a letter or one of @[\]^_
+    pub fn parse(keys: &str) -> Result<Self> {
+        let keys = keys
+            .split(',')
+            .map(|key| {
+                let ctrl = key.strip_prefix("ctrl-");
+                match ctrl.unwrap_or(key).as_bytes() {
+                    [c] if ctrl.is_none() && c.is_ascii() => Ok(*c),
+                    [c] if c.is_ascii_alphabetic() || b"@[\\]^_".contains(c) => {
+                        Ok(c.to_ascii_uppercase() & 0x1f)
+                    }
+                    _ => bail!("invalid detach key {key:?}"),
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { keys, matched: 0 })
+    }
+
+    /// Returns the input to forward to the container and whether the detach
+    /// sequence was completed. Keys which may start the sequence are held back
+    /// until it is clear whether they do.
+    fn filter(&mut self, input: &[u8]) -> (Vec<u8>, bool) {
+        let mut forward = Vec::with_capacity(input.len());
+        for &byte in input {
+            if self.matched > 0 && byte != self.keys[self.matched] {
+                forward.extend_from_slice(&self.keys[..self.matched]);
+                self.matched = 0;
+            }
+            if byte == self.keys[self.matched] {
+                self.matched += 1;
+                if self.matched == self.keys.len() {
+                    self.matched = 0;
+                    return (forward, true);
+                }
+            } else {
+                forward.push(byte);
+            }
+        }
+        (forward, false)
+    }
+}
+
+/// Connects the terminal youki runs in to the console relay of a container,
+/// until the container closed its terminal or the detach keys were typed.
+/// Returns true if detached.
+pub fn attach(stream: UnixStream, mut detach_keys: DetachKeys) -> Result<bool> {
+    let winch = window_change_signal()?;
+    let send_size = || -> Result<()> {
+        if let Some(size) = window_size() {
+            let frame = Frame::Resize {
+                rows: size.ws_row,
+                cols: size.ws_col,
+            };
+            (&stream)
+                .write_all(&frame.encode())
+                .context("failed to send window size")?;
+        }
+        Ok(())
+    };
+    send_size()?;
+
+    let stdin = std::io::stdin();
+    let mut stdin_open = true;
+    let mut buf = [0u8; 4096];
+    loop {
+        let mut fds = vec![
+            PollFd::new(stream.as_fd(), PollFlags::POLLIN),
+            PollFd::new(winch.as_fd(), PollFlags::POLLIN),
+        ];
+        if stdin_open {
+            fds.push(PollFd::new(stdin.as_fd(), PollFlags::POLLIN));
+        }
+        match poll(&mut fds, PollTimeout::NONE) {
+            Err(Errno::EINTR) => continue,
+            result => result.context("failed to poll terminal")?,
+        };
+        let (stream_ready, winch_ready) = (is_ready(&fds[0]), is_ready(&fds[1]));
+        let stdin_ready = stdin_open && is_ready(&fds[2]);
+        drop(fds);
+
+        if stream_ready {
+            // the relay closes the connection once the container closed the terminal
+            match (&stream).read(&mut buf) {
+                Ok(0) => return Ok(false),
+                Ok(n) => {
+                    let mut stdout = std::io::stdout().lock();
+                    stdout.write_all(&buf[..n])?;
+                    stdout.flush()?;
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err).context("failed to read from console relay"),
+            }
+        }
+        if winch_ready {
+            let _ = winch.read_signal();
+            send_size()?;
+        }
+        if stdin_ready {
+            match unistd::read(stdin.as_raw_fd(), &mut buf) {
+                Ok(0) => stdin_open = false,
+                Ok(n) => {
+                    let (input, detach) = detach_keys.filter(&buf[..n]);
+                    if !input.is_empty() {
+                        (&stream)
+                            .write_all(&Frame::Input(&input).encode())
+                            .context("failed to send input to console relay")?;
+                    }
+                    if detach {
+                        return Ok(true);
+                    }
+                }
+                Err(Errno::EINTR | Errno::EAGAIN) => {}
+                Err(err) => return Err(err).context("failed to read from stdin"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let frames = [
+            Frame::Input(b"echo hello\r"),
+            Frame::Input(b""),
+            Frame::Resize { rows: 24, cols: 80 },
+        ];
+        let encoded: Vec<u8> = frames.iter().flat_map(Frame::encode).collect();
+
+        let mut offset = 0;
+        for frame in frames {
+            let (decoded, len) = Frame::decode(&encoded[offset..]).unwrap().unwrap();
+            assert_eq!(decoded, frame);
+            offset += len;
+        }
+        assert_eq!(offset, encoded.len());
+        assert!(Frame::decode(&encoded[offset..]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_frame_decode_incomplete_and_invalid() {
+        let encoded = Frame::Input(b"ls").encode();
+        for len in 0..encoded.len() {
+            assert!(Frame::decode(&encoded[..len]).unwrap().is_none());
+        }
+        assert!(Frame::decode(&[7, 0, 0]).is_err());
+        assert!(Frame::decode(&[FRAME_RESIZE, 0, 2, 0, 24]).is_err());
+    }
+
+    #[test]
+    fn test_console_log_rotation() -> Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let path = tmp.path().join(CONSOLE_LOG);
+        let mut log = ConsoleLog::open(path.clone(), 10)?;
+        log.write(b"0123456")?;
+        log.write(b"789")?;
+        log.write(b"abc")?;
+        log.write(b"defghijklmno")?;
+
+        assert_eq!(std::fs::read_to_string(&path)?, "defghijklmno");
+        assert_eq!(
+            std::fs::read_to_string(tmp.path().join("console.log.1"))?,
+            "abc"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_detach_keys_parse() {
+        assert_eq!(
+            DetachKeys::parse("ctrl-p,ctrl-q").unwrap().keys,
+            [0x10, 0x11]
+        );
+        assert_eq!(
+            DetachKeys::parse("ctrl-@,ctrl-[,q").unwrap().keys,
+            [0, 0x1b, b'q']
+        );
+        for keys in ["", "ctrl-", "ctrl-1", "ab", "q,,q"] {
+            assert!(
+                DetachKeys::parse(keys).is_err(),
+                "{keys:?} should be invalid"
+            );
+        }
+    }
+
+    #[test]
+    fn test_detach_keys_filter() {
+        let mut keys = DetachKeys::parse("ctrl-p,ctrl-q").unwrap();
+        assert_eq!(keys.filter(b"ls\r"), (b"ls\r".to_vec(), false));
+        // a partial sequence is held back until the next key
+        assert_eq!(keys.filter(b"a\x10"), (b"a".to_vec(), false));
+        assert_eq!(keys.filter(b"b"), (b"\x10b".to_vec(), false));
+        assert_eq!(keys.filter(b"\x10\x10"), (b"\x10".to_vec(), false));
+        assert_eq!(keys.filter(b"\x11rest"), (b"".to_vec(), true));
+    }
+}
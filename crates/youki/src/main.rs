@@ -2,12 +2,15 @@
 //! Container Runtime written in Rust, inspired by [railcar](https://github.com/oracle/railcar)
 //! This crate provides a container runtime which can be used by a high-level container runtime to run containers.
 mod commands;
+mod console;
 mod observability;
 mod rootpath;
 mod workload;
 
 use anyhow::{Context, Result};
 use clap::{CommandFactory, Parser};
+use libcgroups::common::CgroupDriver;
+use libcontainer::sd_notify::SdNotifyMode;
 use libcontainer::syscall::syscall::create_syscall;
 use liboci_cli::{CommonCmd, GlobalOpts, StandardCmd};
 
@@ -22,6 +25,18 @@ struct YoukiExtendOpts {
     /// set the log level (default is 'error')
     #[clap(long)]
     pub log_level: Option<String>,
+    /// How the container notifies the NOTIFY_SOCKET of the host: container relays
+    /// its own notifications, conmon sends READY=1 once it was started, ignore
+    /// sends nothing
+    #[clap(long, default_value = "container")]
+    pub sdnotify: SdNotifyMode,
+    /// How the cgroup manager is chosen: auto falls back to cgroupfs if
+    /// --systemd-cgroup is given but systemd can't be reached, systemd and
+    /// cgroupfs always use the respective manager
+    #[clap(long, default_value = "auto")]
+    pub cgroup_manager: CgroupDriver,
+    #[clap(flatten)]
+    pub container_log: commands::ContainerLogOpts,
 }
 
 // High-level commandline option definition
@@ -56,8 +71,13 @@ enum SubCommand {
     Common(Box<liboci_cli::CommonCmd>),
 
     // Youki specific extensions
+    Attach(commands::attach::Attach),
     Info(info::Info),
     Completion(commands::completion::Completion),
+    Devices(commands::devices::Devices),
+    #[cfg(any(feature = "seccomp", feature = "native_seccomp"))]
+    Seccomp(commands::seccomp::Seccomp),
+    Wait(commands::wait::Wait),
 }
 
 /// This is the entry point in the container runtime. The binary is run by a high-level container runtime,
@@ -97,12 +117,19 @@ fn main() -> Result<()> {
 
     let root_path = rootpath::determine(opts.global.root, &*syscall)?;
     let systemd_cgroup = opts.global.systemd_cgroup;
+    let sdnotify = opts.youki_extend.sdnotify;
+    let cgroup_driver = opts.youki_extend.cgroup_manager;
 
     let cmd_result = match opts.subcmd {
         Some(SubCommand::Standard(cmd)) => match *cmd {
-            StandardCmd::Create(create) => {
-                commands::create::create(create, root_path, systemd_cgroup)
-            }
+            StandardCmd::Create(create) => commands::create::create(
+                create,
+                root_path,
+                systemd_cgroup,
+                cgroup_driver,
+                sdnotify,
+                &opts.youki_extend.container_log,
+            ),
             StandardCmd::Start(start) => commands::start::start(start, root_path),
             StandardCmd::Kill(kill) => commands::kill::kill(kill, root_path),
             StandardCmd::Delete(delete) => commands::delete::delete(delete, root_path),
@@ -126,22 +153,36 @@ fn main() -> Result<()> {
             CommonCmd::Pause(pause) => commands::pause::pause(pause, root_path),
             CommonCmd::Ps(ps) => commands::ps::ps(ps, root_path),
             CommonCmd::Resume(resume) => commands::resume::resume(resume, root_path),
-            CommonCmd::Run(run) => match commands::run::run(run, root_path, systemd_cgroup) {
-                Ok(exit_code) => std::process::exit(exit_code),
-                Err(e) => {
-                    tracing::error!("error in executing command: {:?}", e);
-                    eprintln!("run failed : {e}");
-                    std::process::exit(-1);
+            CommonCmd::Run(run) => {
+                match commands::run::run(
+                    run,
+                    root_path,
+                    systemd_cgroup,
+                    cgroup_driver,
+                    sdnotify,
+                    &opts.youki_extend.container_log,
+                ) {
+                    Ok(exit_code) => std::process::exit(exit_code),
+                    Err(e) => {
+                        tracing::error!("error in executing command: {:?}", e);
+                        eprintln!("run failed : {e}");
+                        std::process::exit(-1);
+                    }
                 }
-            },
+            }
             CommonCmd::Spec(spec) => commands::spec_json::spec(spec, &*syscall),
             CommonCmd::Update(update) => commands::update::update(update, root_path),
         },
 
+        Some(SubCommand::Attach(attach)) => commands::attach::attach(attach, root_path),
         Some(SubCommand::Info(info)) => commands::info::info(info),
         Some(SubCommand::Completion(completion)) => {
             commands::completion::completion(completion, &mut app)
         }
+        Some(SubCommand::Devices(devices)) => commands::devices::devices(devices, root_path),
+        #[cfg(any(feature = "seccomp", feature = "native_seccomp"))]
+        Some(SubCommand::Seccomp(seccomp)) => commands::seccomp::seccomp(seccomp),
+        Some(SubCommand::Wait(wait)) => commands::wait::wait(wait, root_path),
         None => app
             .print_help()
             .map_err(|e| anyhow::anyhow!("failed to print help: {e}")),
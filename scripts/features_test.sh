@@ -18,6 +18,9 @@ test_package_features "libcontainer" "v2 libseccomp"
 test_package_features "libcontainer" "systemd libseccomp"
 test_package_features "libcontainer" "v2 cgroupsv2_devices libseccomp"
 test_package_features "libcontainer" "systemd cgroupsv2_devices libseccomp"
+test_package_features "libcontainer" "v2 native_seccomp"
+test_package_features "libcontainer" "systemd native_seccomp"
+test_package_features "libcontainer" "v2 async"
 
 test_package_features "libcgroups" "v1"
 test_package_features "libcgroups" "v2"
@@ -41,5 +44,7 @@ test_features "v2 seccomp"
 test_features "systemd seccomp"
 test_features "v2 cgroupsv2_devices seccomp"
 test_features "systemd cgroupsv2_devices seccomp"
+test_features "v2 native_seccomp"
+test_features "systemd native_seccomp"
 
 exit 0
@@ -9,7 +9,7 @@ use crate::utils::test_utils::{
     check_container_created, exec_container, start_container, test_outside_container,
 };
 
-fn create_spec(domain: LinuxPersonalityDomain) -> Result<Spec> {
+fn create_spec(domain: LinuxPersonalityDomain, flags: &[&str]) -> Result<Spec> {
     SpecBuilder::default()
         .process(
             ProcessBuilder::default()
@@ -27,6 +27,7 @@ fn create_spec(domain: LinuxPersonalityDomain) -> Result<Spec> {
                 .personality(
                     LinuxPersonalityBuilder::default()
                         .domain(domain)
+                        .flags(flags.iter().map(|f| f.to_string()).collect::<Vec<_>>())
                         .build()
                         .context("failed to create personality")?,
                 )
@@ -37,8 +38,13 @@ fn create_spec(domain: LinuxPersonalityDomain) -> Result<Spec> {
         .context("failed to create spec")
 }
 
-fn personality_for_linux(domain: LinuxPersonalityDomain, expect: &str) -> TestResult {
-    let spec = test_result!(create_spec(domain));
+fn check_personality(
+    domain: LinuxPersonalityDomain,
+    flags: &[&str],
+    cmd: &[&str],
+    expect: &str,
+) -> TestResult {
+    let spec = test_result!(create_spec(domain, flags));
 
     test_outside_container(&spec, &|data| {
         test_result!(check_container_created(&data));
@@ -51,7 +57,7 @@ fn personality_for_linux(domain: LinuxPersonalityDomain, expect: &str) -> TestRe
             return TestResult::Failed(anyhow!("container start failed"));
         }
 
-        let (stdout, _) = exec_container(id, dir, &["uname", "-m"], None).expect("exec failed");
+        let (stdout, _) = exec_container(id, dir, cmd, None).expect("exec failed");
 
         if !stdout.contains(expect) {
             return TestResult::Failed(anyhow!("unexpected personality: {}", stdout));
@@ -61,6 +67,10 @@ fn personality_for_linux(domain: LinuxPersonalityDomain, expect: &str) -> TestRe
     })
 }
 
+fn personality_for_linux(domain: LinuxPersonalityDomain, expect: &str) -> TestResult {
+    check_personality(domain, &[], &["uname", "-m"], expect)
+}
+
 fn personality_for_linux32() -> TestResult {
     personality_for_linux(LinuxPersonalityDomain::PerLinux32, "i686")
 }
@@ -69,6 +79,34 @@ fn personality_for_linux64() -> TestResult {
     personality_for_linux(LinuxPersonalityDomain::PerLinux, "x86_64")
 }
 
+// /proc/self/personality shows the whole persona as 8 hex digits. READ_IMPLIES_EXEC
+// isn't checked, as the kernel clears it when a 64 bit program is executed.
+fn personality_flag(flag: &str, expect: &str) -> TestResult {
+    check_personality(
+        LinuxPersonalityDomain::PerLinux,
+        &[flag],
+        &["cat", "/proc/self/personality"],
+        expect,
+    )
+}
+
+fn personality_addr_no_randomize() -> TestResult {
+    personality_flag("ADDR_NO_RANDOMIZE", "00040000")
+}
+
+fn personality_addr_limit_32bit() -> TestResult {
+    personality_flag("ADDR_LIMIT_32BIT", "00800000")
+}
+
+fn personality_uname26() -> TestResult {
+    check_personality(
+        LinuxPersonalityDomain::PerLinux,
+        &["UNAME26"],
+        &["uname", "-r"],
+        "2.6.",
+    )
+}
+
 pub fn get_personality_test() -> TestGroup {
     let mut test_group = TestGroup::new("personality");
     let personality_for_linux32 =
@@ -79,5 +117,20 @@ pub fn get_personality_test() -> TestGroup {
         Test::new("personality_for_linux64", Box::new(personality_for_linux64));
     test_group.add(vec![Box::new(personality_for_linux64)]);
 
+    let personality_addr_no_randomize = Test::new(
+        "personality_addr_no_randomize",
+        Box::new(personality_addr_no_randomize),
+    );
+    test_group.add(vec![Box::new(personality_addr_no_randomize)]);
+
+    let personality_addr_limit_32bit = Test::new(
+        "personality_addr_limit_32bit",
+        Box::new(personality_addr_limit_32bit),
+    );
+    test_group.add(vec![Box::new(personality_addr_limit_32bit)]);
+
+    let personality_uname26 = Test::new("personality_uname26", Box::new(personality_uname26));
+    test_group.add(vec![Box::new(personality_uname26)]);
+
     test_group
 }